@@ -305,6 +305,9 @@ export class RuntimeController {
                this.runtimes[RuntimeType.V8Isolate] ||
                null;
       
+      case Language.Ebpf:
+        return this.runtimes[RuntimeType.Ebpf] || null;
+      
       case Language.Rust:
       case Language.C:
       case Language.Cpp:
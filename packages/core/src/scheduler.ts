@@ -87,7 +87,7 @@ export class IntelligentScheduler {
   }> = {
     [RuntimeType.Ebpf]: {
       coldStart: 100, // nanoseconds
-      languages: [Language.C],
+      languages: [Language.C, Language.Ebpf],
       maxMemory: 512 * 1024, // 512KB
       strengths: ['ultra-low-latency', 'packet-filtering', 'security'],
       weaknesses: ['complex-computation', 'high-memory']
@@ -175,6 +175,13 @@ export class CapabilityManager {
       },
       requiresAudit: true,
     });
+
+    // Vector Search
+    this.policies.set(Capability.VectorSearch, {
+      capability: Capability.VectorSearch,
+      // Read-only queries served by the host's vector store
+      requiresAudit: false,
+    });
   }
 
   async enforceCapabilities(capabilities: Set<Capability>): Promise<void> {
@@ -111,6 +111,7 @@ export class SecurityManager {
         // Some capabilities
         capabilities.add(Capability.SystemTime);
         capabilities.add(Capability.FileSystemRead);
+        capabilities.add(Capability.VectorSearch);
         break;
 
       case TrustLevel.High:
@@ -121,6 +122,7 @@ export class SecurityManager {
         capabilities.add(Capability.SystemTime);
         capabilities.add(Capability.EnvironmentVariables);
         capabilities.add(Capability.SharedMemory);
+        capabilities.add(Capability.VectorSearch);
         break;
     }
 
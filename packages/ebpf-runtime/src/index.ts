@@ -6,7 +6,8 @@ import {
   ExecutionConfig, 
   ExecutionResult,
   RuntimeError,
-  Capability
+  Capability,
+  OutputEncoding
 } from '@rizome/next-rc-types';
 import { 
   EbpfRuntimeBridge, 
@@ -80,13 +81,14 @@ class EbpfRuntime implements Runtime {
         networkAccess: config.permissions.capabilities.has(Capability.NetworkAccess),
         filesystemAccess: config.permissions.capabilities.has(Capability.FileSystemRead) || 
                          config.permissions.capabilities.has(Capability.FileSystemWrite),
+        outputEncoding: this.mapOutputEncoding(config.outputEncoding),
       };
 
       const result = await this.bridge.execute({ id: instanceId.id }, nativeConfig);
       
       return {
         success: result.success,
-        output: result.output ? Array.from(result.output) : undefined,
+        output: result.outputBytes ? Array.from(result.outputBytes) : result.output || undefined,
         error: result.exitCode !== 0 ? `Exit code: ${result.exitCode}` : undefined,
         executionTime: result.executionTimeMs,
         memoryUsed: 0, // eBPF doesn't report memory usage
@@ -99,15 +101,28 @@ class EbpfRuntime implements Runtime {
     }
   }
 
-  async executeFilter(instanceId: InstanceId, inputData: Buffer): Promise<ExecutionResult> {
+  async executeFilter(
+    instanceId: InstanceId,
+    inputData: Buffer,
+    outputEncoding: OutputEncoding = OutputEncoding.Utf8
+  ): Promise<ExecutionResult> {
     await this.ensureInitialized();
     
     try {
-      const result = await this.bridge.executeFilter({ id: instanceId.id }, inputData);
+      const result = await this.bridge.executeFilter(
+        { id: instanceId.id },
+        inputData,
+        this.mapOutputEncoding(outputEncoding)
+      );
       
       return {
         success: result.success,
-        output: result.output ? Array.from(result.output) : undefined,
+        // Text encodings carry the verdict as decimal text
+        output: result.outputBytes
+          ? Array.from(result.outputBytes)
+          : outputEncoding === OutputEncoding.Base64
+            ? result.output
+            : result.output ? Number(result.output) : undefined,
         error: result.exitCode !== 0 ? `Exit code: ${result.exitCode}` : undefined,
         executionTime: result.executionTimeMs,
         memoryUsed: 0, // eBPF doesn't report memory usage
@@ -221,6 +236,7 @@ class EbpfRuntime implements Runtime {
       case Language.C: return 5;
       case Language.Cpp: return 6;
       case Language.Wasm: return 7;
+      case Language.Ebpf: return 8;
       default: 
         throw new RuntimeError(
           `Unsupported language for eBPF: ${language}`,
@@ -237,6 +253,16 @@ class EbpfRuntime implements Runtime {
       default: return 1;       // Default to Medium
     }
   }
+
+  private mapOutputEncoding(encoding?: OutputEncoding): number {
+    switch (encoding) {
+      case OutputEncoding.Base64: return 1; // OutputEncoding.Base64
+      case OutputEncoding.Json: return 2;   // OutputEncoding.Json
+      case OutputEncoding.Arrow: return 3;  // OutputEncoding.Arrow
+      case OutputEncoding.Bytes: return 4;  // OutputEncoding.Bytes
+      default: return 0;                    // Default to Utf8
+    }
+  }
 }
 
 export { EbpfRuntime };
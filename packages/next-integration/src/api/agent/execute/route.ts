@@ -1,5 +1,5 @@
 import { NextRequest, NextResponse } from 'next/server';
-import { RuntimeController, Language, TrustLevel, Capability } from '@rizome/next-rc-core';
+import { RuntimeController, Language, TrustLevel, Capability, OutputEncoding } from '@rizome/next-rc-core';
 import { getRuntimeConfig } from '../../../config';
 
 export const runtime = 'nodejs'; // Use Node.js runtime for full capabilities
@@ -17,6 +17,7 @@ export async function POST(request: NextRequest) {
       timeout = 30000,
       memory = 128 * 1024 * 1024,
       hints = {},
+      outputEncoding = OutputEncoding.Utf8,
     } = body;
 
     // Validate input
@@ -27,6 +28,13 @@ export async function POST(request: NextRequest) {
       );
     }
 
+    if (!Object.values(OutputEncoding).includes(outputEncoding)) {
+      return NextResponse.json(
+        { error: `Unsupported output encoding: ${outputEncoding}` },
+        { status: 400 }
+      );
+    }
+
     // Get trust level from headers or use default
     const trustLevel = request.headers.get('X-Trust-Level') as TrustLevel || TrustLevel.Low;
 
@@ -38,6 +46,7 @@ export async function POST(request: NextRequest) {
         capabilities: new Set<Capability>(permissions.capabilities || []),
         trustLevel,
       },
+      outputEncoding,
     };
 
     // Get latency requirement from headers
@@ -54,10 +63,14 @@ export async function POST(request: NextRequest) {
       hints
     );
 
+    // Binary output can't travel in JSON as-is, so it goes as base64
+    const output = Buffer.isBuffer(result.output) ? result.output.toString('base64') : result.output;
+
     // Add execution metadata to response headers
     const response = NextResponse.json({
       success: result.success,
-      output: result.output,
+      output,
+      outputEncoding,
       error: result.error,
       executionTime: result.executionTime,
       memoryUsed: result.memoryUsed,
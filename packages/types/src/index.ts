@@ -15,6 +15,7 @@ export enum Language {
   C = 'c',
   Cpp = 'cpp',
   Wasm = 'wasm',
+  Ebpf = 'ebpf',
 }
 
 export enum RuntimeType {
@@ -29,6 +30,15 @@ export interface ExecutionConfig {
   timeout: number; // milliseconds
   memoryLimit: number; // bytes
   permissions: Permissions;
+  outputEncoding?: OutputEncoding; // utf8 when unset
+}
+
+export enum OutputEncoding {
+  Utf8 = 'utf8',
+  Base64 = 'base64',
+  Json = 'json',
+  Arrow = 'arrow',
+  Bytes = 'bytes',
 }
 
 export interface ExecutionResult {
@@ -54,6 +64,7 @@ export enum Capability {
   SharedMemory = 'shared_memory',
   CpuIntensive = 'cpu_intensive',
   GpuAccess = 'gpu_access',
+  VectorSearch = 'vector_search',
 }
 
 export enum TrustLevel {
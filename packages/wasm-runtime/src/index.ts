@@ -6,7 +6,8 @@ import {
   ExecutionConfig, 
   ExecutionResult,
   RuntimeError,
-  Capability
+  Capability,
+  OutputEncoding
 } from '@rizome/next-rc-types';
 import { 
   WasmRuntimeBridge, 
@@ -82,13 +83,14 @@ export class WasmRuntime implements Runtime {
         networkAccess: config.permissions.capabilities.has(Capability.NetworkAccess),
         filesystemAccess: config.permissions.capabilities.has(Capability.FileSystemRead) || 
                          config.permissions.capabilities.has(Capability.FileSystemWrite),
+        outputEncoding: this.mapOutputEncoding(config.outputEncoding),
       };
 
       const result = await this.bridge.execute({ id: instanceId.id }, nativeConfig);
       
       return {
         success: result.success,
-        output: result.output ? this.parseOutput(result.output) : undefined,
+        output: this.decodeOutput(result.output, result.outputBytes, config.outputEncoding),
         error: result.error || undefined,
         executionTime: result.executionTimeMs,
         memoryUsed: result.memoryUsedBytes,
@@ -204,6 +206,27 @@ export class WasmRuntime implements Runtime {
     }
   }
 
+  private mapOutputEncoding(encoding?: OutputEncoding): number {
+    switch (encoding) {
+      case OutputEncoding.Base64: return 1; // OutputEncoding.Base64
+      case OutputEncoding.Json: return 2;   // OutputEncoding.Json
+      case OutputEncoding.Arrow: return 3;  // OutputEncoding.Arrow
+      case OutputEncoding.Bytes: return 4;  // OutputEncoding.Bytes
+      default: return 0;                    // Default to Utf8
+    }
+  }
+
+  private decodeOutput(output: string, outputBytes: Buffer | undefined, encoding?: OutputEncoding): any {
+    if (outputBytes) {
+      return outputBytes;
+    }
+    switch (encoding) {
+      case OutputEncoding.Json: return JSON.parse(output);
+      case OutputEncoding.Base64: return output;
+      default: return output ? this.parseOutput(output) : undefined;
+    }
+  }
+
   private parseOutput(output: string): any {
     try {
       // Try to parse as JSON first
@@ -0,0 +1,81 @@
+use anyhow::{anyhow, bail, Result};
+use next_rc_shared::{sha256_hex, ModuleId};
+use serde::{Deserialize, Serialize};
+
+use crate::program::{EbpfProgram, ProgramMetadata, ProgramType};
+
+/// Leads every exported program.
+const MAGIC: &[u8; 8] = b"NRCEBPF\0";
+
+/// Bumped whenever the header or layout changes incompatibly.
+pub const EXPORT_FORMAT_VERSION: u16 = 1;
+
+/// Describes the bytecode that follows it in an export.
+#[derive(Debug, Serialize, Deserialize)]
+struct ExportHeader {
+    module_id: ModuleId,
+    prog_type: ProgramType,
+    metadata: ProgramMetadata,
+    bytecode_sha256: String,
+}
+
+/// Encodes a program as the magic, the format version (u16 LE), the length
+/// of a JSON header (u32 LE), the header and then the bytecode.
+pub fn encode(program: &EbpfProgram) -> Result<Vec<u8>> {
+    let header = serde_json::to_vec(&ExportHeader {
+        module_id: program.id.clone(),
+        prog_type: program.prog_type,
+        metadata: program.metadata.clone(),
+        bytecode_sha256: sha256_hex(&program.bytecode),
+    })?;
+    let header_len = u32::try_from(header.len()).map_err(|_| anyhow!("Program metadata too large to export"))?;
+
+    let mut bytes = Vec::with_capacity(MAGIC.len() + 6 + header.len() + program.bytecode.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&EXPORT_FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&header_len.to_le_bytes());
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(&program.bytecode);
+    Ok(bytes)
+}
+
+/// Reads back an exported program, checking its version and that the
+/// bytecode matches the digest in the header. The program still has to be
+/// verified before it runs.
+pub fn decode(bytes: &[u8]) -> Result<EbpfProgram> {
+    let rest = bytes
+        .strip_prefix(MAGIC.as_slice())
+        .ok_or_else(|| anyhow!("Not an exported eBPF program"))?;
+    let (version, rest) = split(rest, 2)?;
+    let version = u16::from_le_bytes([version[0], version[1]]);
+    if version != EXPORT_FORMAT_VERSION {
+        bail!(
+            "Unsupported eBPF export format version {} (expected {})",
+            version,
+            EXPORT_FORMAT_VERSION
+        );
+    }
+    let (header_len, rest) = split(rest, 4)?;
+    let header_len = u32::from_le_bytes([header_len[0], header_len[1], header_len[2], header_len[3]]);
+    let (header, bytecode) = split(rest, header_len as usize)?;
+    let header: ExportHeader =
+        serde_json::from_slice(header).map_err(|e| anyhow!("Malformed eBPF export header: {}", e))?;
+
+    if sha256_hex(bytecode) != header.bytecode_sha256 {
+        bail!("eBPF export bytecode does not match its digest");
+    }
+
+    Ok(EbpfProgram {
+        id: header.module_id,
+        bytecode: bytecode.to_vec(),
+        prog_type: header.prog_type,
+        metadata: header.metadata,
+    })
+}
+
+fn split(bytes: &[u8], at: usize) -> Result<(&[u8], &[u8])> {
+    if bytes.len() < at {
+        bail!("Truncated eBPF export");
+    }
+    Ok(bytes.split_at(at))
+}
@@ -64,6 +64,13 @@ impl JitCompiler {
         Ok(program)
     }
     
+    /// Runs rbpf's load-time checks without populating the cache.
+    pub fn check(&self, bytecode: &[u8]) -> Result<()> {
+        let mut vm = rbpf::EbpfVmMbuff::new(Some(bytecode))
+            .map_err(|e| anyhow!("Failed to create VM: {}", e))?;
+        self.register_helpers(&mut vm)
+    }
+    
     pub fn execute(&self, program: &JitProgram, data: &[u8]) -> Result<u64> {
         trace!("Executing JIT compiled eBPF program on {} bytes", data.len());
         
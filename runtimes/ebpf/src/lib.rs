@@ -1,10 +1,14 @@
+pub mod export;
 pub mod jit;
+pub mod maps;
 pub mod memory_pool;
 pub mod program;
 pub mod runtime;
+pub mod stats;
 pub mod verifier;
 
 pub use runtime::EbpfRuntime;
+pub use stats::{LatencyHistogram, ProgramStats};
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
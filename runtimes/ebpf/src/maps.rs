@@ -0,0 +1,215 @@
+use anyhow::{anyhow, bail, Result};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::program::{MapDefinition, MapType};
+
+/// Userspace backing store for a single eBPF map.
+pub struct EbpfMap {
+    definition: MapDefinition,
+    entries: RwLock<HashMap<Vec<u8>, Vec<u8>>>,
+}
+
+impl EbpfMap {
+    pub fn new(definition: MapDefinition) -> Self {
+        Self {
+            definition,
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn definition(&self) -> &MapDefinition {
+        &self.definition
+    }
+
+    pub fn lookup(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.entries.read().get(key).cloned()
+    }
+
+    pub fn update(&self, key: &[u8], value: &[u8]) -> Result<()> {
+        if key.len() != self.definition.key_size as usize {
+            bail!("Key size mismatch for map {}: expected {} bytes", self.definition.name, self.definition.key_size);
+        }
+        if value.len() != self.definition.value_size as usize {
+            bail!("Value size mismatch for map {}: expected {} bytes", self.definition.name, self.definition.value_size);
+        }
+        if matches!(self.definition.map_type, MapType::Array | MapType::PercpuArray) {
+            let index = u32::from_le_bytes(key.try_into().map_err(|_| anyhow!("Array maps use 4-byte keys"))?);
+            if index >= self.definition.max_entries {
+                bail!("Index {} out of bounds for map {}", index, self.definition.name);
+            }
+        }
+
+        let mut entries = self.entries.write();
+        if !entries.contains_key(key) && entries.len() >= self.definition.max_entries as usize {
+            bail!("Map {} is full ({} entries)", self.definition.name, self.definition.max_entries);
+        }
+        entries.insert(key.to_vec(), value.to_vec());
+        Ok(())
+    }
+
+    pub fn delete(&self, key: &[u8]) -> bool {
+        self.entries.write().remove(key).is_some()
+    }
+
+    pub fn snapshot(&self) -> MapSnapshot {
+        let mut entries: Vec<MapEntry> = self
+            .entries
+            .read()
+            .iter()
+            .map(|(key, value)| MapEntry {
+                key: key.clone(),
+                value: value.clone(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.key.cmp(&b.key));
+
+        MapSnapshot {
+            name: self.definition.name.clone(),
+            taken_at: SystemTime::now(),
+            entries,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapEntry {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+impl MapEntry {
+    /// Interprets 4- or 8-byte little-endian values as counters.
+    pub fn counter_value(&self) -> Option<u64> {
+        match self.value.len() {
+            4 => Some(u32::from_le_bytes(self.value[..].try_into().ok()?) as u64),
+            8 => Some(u64::from_le_bytes(self.value[..].try_into().ok()?)),
+            _ => None,
+        }
+    }
+
+    pub fn key_hex(&self) -> String {
+        self.key.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapSnapshot {
+    pub name: String,
+    pub taken_at: SystemTime,
+    pub entries: Vec<MapEntry>,
+}
+
+impl MapSnapshot {
+    /// Publishes counter-sized entries as `ebpf_map_value` counters labelled
+    /// by map name and hex-encoded key.
+    pub fn export_metrics(&self) {
+        for entry in &self.entries {
+            if let Some(value) = entry.counter_value() {
+                metrics::counter!("ebpf_map_value", "map" => self.name.clone(), "key" => entry.key_hex())
+                    .absolute(value);
+            }
+        }
+        metrics::gauge!("ebpf_map_entries", "map" => self.name.clone()).set(self.entries.len() as f64);
+    }
+}
+
+pub struct MapRegistry {
+    maps: RwLock<HashMap<String, Arc<EbpfMap>>>,
+}
+
+impl MapRegistry {
+    pub fn new() -> Self {
+        Self {
+            maps: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Creates the map if it doesn't exist yet; programs sharing a map name
+    /// share the same backing store.
+    pub fn create(&self, definition: MapDefinition) -> Arc<EbpfMap> {
+        self.maps
+            .write()
+            .entry(definition.name.clone())
+            .or_insert_with(|| Arc::new(EbpfMap::new(definition)))
+            .clone()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<EbpfMap>> {
+        self.maps.read().get(name).cloned()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.maps.read().keys().cloned().collect()
+    }
+
+    pub fn dump_map(&self, name: &str) -> Result<MapSnapshot> {
+        self.get(name)
+            .map(|map| map.snapshot())
+            .ok_or_else(|| anyhow!("Map not found: {}", name))
+    }
+
+    /// Periodically snapshots the named maps into the metrics pipeline until
+    /// the returned task is aborted.
+    pub fn spawn_exporter(self: &Arc<Self>, names: Vec<String>, interval: Duration) -> JoinHandle<()> {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                for name in &names {
+                    match registry.dump_map(name) {
+                        Ok(snapshot) => {
+                            debug!("Exporting {} entries from eBPF map {}", snapshot.entries.len(), name);
+                            snapshot.export_metrics();
+                        }
+                        Err(e) => warn!("Skipping map export: {}", e),
+                    }
+                }
+            }
+        })
+    }
+}
+
+impl Default for MapRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter_map() -> MapDefinition {
+        MapDefinition {
+            name: "packets".to_string(),
+            map_type: MapType::Hash,
+            key_size: 4,
+            value_size: 8,
+            max_entries: 2,
+        }
+    }
+
+    #[test]
+    fn test_map_update_and_dump() {
+        let registry = MapRegistry::new();
+        let map = registry.create(counter_map());
+
+        map.update(&6u32.to_le_bytes(), &42u64.to_le_bytes()).unwrap();
+        map.update(&17u32.to_le_bytes(), &7u64.to_le_bytes()).unwrap();
+        assert!(map.update(&1u32.to_le_bytes(), &1u64.to_le_bytes()).is_err());
+        assert!(map.update(&[0; 2], &1u64.to_le_bytes()).is_err());
+
+        let snapshot = registry.dump_map("packets").unwrap();
+        assert_eq!(snapshot.entries.len(), 2);
+        assert_eq!(snapshot.entries[0].key_hex(), "06000000");
+        assert_eq!(snapshot.entries[0].counter_value(), Some(42));
+        assert!(registry.dump_map("missing").is_err());
+    }
+}
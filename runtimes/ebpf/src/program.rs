@@ -2,6 +2,7 @@ use anyhow::{anyhow, Result};
 use goblin::elf::Elf;
 use next_rc_shared::ModuleId;
 use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
 // use rbpf::ebpf; // Unused
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -14,7 +15,7 @@ pub struct EbpfProgram {
     pub metadata: ProgramMetadata,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ProgramMetadata {
     pub name: String,
     pub section: String,
@@ -22,7 +23,7 @@ pub struct ProgramMetadata {
     pub maps: Vec<MapDefinition>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct MapDefinition {
     pub name: String,
     pub map_type: MapType,
@@ -31,7 +32,7 @@ pub struct MapDefinition {
     pub max_entries: u32,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ProgramType {
     Filter,
     XdpAction,
@@ -41,7 +42,7 @@ pub enum ProgramType {
     UProbe,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MapType {
     Hash,
     Array,
@@ -1,19 +1,27 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use goblin::elf::Elf;
 use next_rc_shared::{
-    ExecutionConfig, ExecutionResult, InstanceId, Language, ModuleId, Runtime as RuntimeTrait,
+    Diagnostic, ExecutionConfig, ExecutionResult, InstanceId, JobPhase, Language, LoadReport, LoadTracker, ModuleId, OutputEncoding,
+    Runtime as RuntimeTrait, RuntimeCapabilities, RuntimeEnvironment, RuntimeError, TimingBreakdown, ToolchainStatus,
+    TrustLevel, ValidationReport,
 };
+#[cfg(feature = "fault-injection")]
+use next_rc_shared::FaultInjector;
 use parking_lot::RwLock;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 use tracing::{debug, info, trace};
 use uuid::Uuid;
 
 use crate::{
+    export,
     jit::{JitCompiler, JitProgram},
+    maps::{MapRegistry, MapSnapshot},
     memory_pool::EbpfMemoryPool,
     program::{EbpfProgram, ProgramCache, ProgramType},
+    stats::{ProgramOutcome, ProgramStats, ProgramStatsRegistry},
     verifier::Verifier,
 };
 
@@ -22,7 +30,13 @@ pub struct EbpfRuntime {
     verifier: Arc<Verifier>,
     program_cache: Arc<ProgramCache>,
     memory_pool: Arc<EbpfMemoryPool>,
+    maps: Arc<MapRegistry>,
     instances: Arc<RwLock<HashMap<InstanceId, EbpfInstance>>>,
+    stats: Arc<ProgramStatsRegistry>,
+    clang: &'static ToolchainStatus,
+    load: LoadTracker,
+    #[cfg(feature = "fault-injection")]
+    faults: RwLock<Option<Arc<FaultInjector>>>,
 }
 
 struct EbpfInstance {
@@ -41,7 +55,13 @@ impl EbpfRuntime {
             verifier: Arc::new(Verifier::new()),
             program_cache: Arc::new(ProgramCache::new()),
             memory_pool: Arc::new(EbpfMemoryPool::with_defaults()?),
+            maps: Arc::new(MapRegistry::new()),
             instances: Arc::new(RwLock::new(HashMap::new())),
+            stats: Arc::new(ProgramStatsRegistry::new()),
+            clang: clang(),
+            load: LoadTracker::new(),
+            #[cfg(feature = "fault-injection")]
+            faults: RwLock::new(None),
         })
     }
     
@@ -56,10 +76,85 @@ impl EbpfRuntime {
             verifier: Arc::new(Verifier::with_config(max_instructions, allow_unsafe)),
             program_cache: Arc::new(ProgramCache::new()),
             memory_pool: Arc::new(EbpfMemoryPool::with_defaults()?),
+            maps: Arc::new(MapRegistry::new()),
             instances: Arc::new(RwLock::new(HashMap::new())),
+            stats: Arc::new(ProgramStatsRegistry::new()),
+            clang: clang(),
+            load: LoadTracker::new(),
+            #[cfg(feature = "fault-injection")]
+            faults: RwLock::new(None),
         })
     }
     
+    pub fn maps(&self) -> Arc<MapRegistry> {
+        self.maps.clone()
+    }
+    
+    pub fn dump_map(&self, map_name: &str) -> Result<MapSnapshot> {
+        self.maps.dump_map(map_name)
+    }
+    
+    /// Snapshots the named maps into the metrics pipeline every `interval`.
+    pub fn start_map_export(&self, map_names: Vec<String>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        self.maps.spawn_exporter(map_names, interval)
+    }
+    
+    /// Execution counts, verdicts and latency of a compiled program.
+    pub fn program_stats(&self, module_id: &ModuleId) -> Result<ProgramStats> {
+        match self.stats.get(module_id) {
+            Some(stats) => Ok(stats),
+            None if self.program_cache.get(module_id).is_some() => Ok(ProgramStats::default()),
+            None => Err(anyhow!("Module not found: {}", module_id.0)),
+        }
+    }
+    
+    /// Serializes a compiled program with a versioned header, for loading
+    /// on another node with `import_program`.
+    pub fn export_program(&self, module_id: &ModuleId) -> Result<Vec<u8>> {
+        let program = self.program_cache
+            .get(module_id)
+            .ok_or_else(|| anyhow!("Module not found: {}", module_id.0))?;
+        export::encode(&program)
+    }
+    
+    /// Loads a program from `export_program`, keeping its module ID. The
+    /// program is verified against this runtime's verifier and JIT-compiled
+    /// before it is cached, so a program the verifier rejects never loads.
+    pub fn import_program(&self, bytes: &[u8]) -> Result<ModuleId> {
+        let program = export::decode(bytes)?;
+        self.verifier.verify(&program.bytecode)?;
+        self.jit_compiler.compile(&program.bytecode)?;
+        
+        for definition in &program.metadata.maps {
+            self.maps.create(definition.clone());
+        }
+        
+        let module_id = self.program_cache.insert(program);
+        info!("Imported eBPF module {}", module_id.0);
+        Ok(module_id)
+    }
+    
+    /// Injects failures and latency into compilations, instantiations and
+    /// executions, for resilience testing; `None` turns injection off.
+    #[cfg(feature = "fault-injection")]
+    pub fn set_fault_injector(&self, faults: Option<Arc<FaultInjector>>) {
+        *self.faults.write() = faults;
+    }
+    
+    /// Delays or fails the start of `phase` as the fault injector decides.
+    async fn inject_fault(&self, phase: JobPhase) -> Result<()> {
+        #[cfg(feature = "fault-injection")]
+        {
+            let faults = self.faults.read().clone();
+            if let Some(faults) = faults {
+                faults.inject(phase).await?;
+            }
+        }
+        #[cfg(not(feature = "fault-injection"))]
+        let _ = phase;
+        Ok(())
+    }
+    
     pub fn execute_filter(&self, program: &EbpfProgram, data: &[u8]) -> Result<FilterResult> {
         let start = Instant::now();
         
@@ -70,9 +165,11 @@ impl EbpfRuntime {
         let jit_program = self.jit_compiler.compile(&program.bytecode)?;
         
         // Execute with ~100ns overhead
-        let result = self.jit_compiler.execute(&jit_program, data)?;
+        let result = self.jit_compiler.execute(&jit_program, data);
         
         let elapsed = start.elapsed();
+        self.stats.record(&program.id, Self::outcome(&result), elapsed);
+        let result = result?;
         trace!("eBPF filter executed in {:?}", elapsed);
         
         Ok(FilterResult {
@@ -81,35 +178,55 @@ impl EbpfRuntime {
         })
     }
     
-    fn compile_to_ebpf(&self, _code: &[u8], language: Language) -> Result<Vec<u8>> {
+    fn outcome(result: &Result<u64>) -> ProgramOutcome {
+        match result {
+            Ok(verdict) if *verdict > 0 => ProgramOutcome::Accept,
+            Ok(_) => ProgramOutcome::Drop,
+            Err(_) => ProgramOutcome::Error,
+        }
+    }
+    
+    /// Bytecode of `code`: raw eBPF bytecode as is, and C built with clang.
+    fn to_bytecode(&self, code: &[u8], language: Language) -> Result<Vec<u8>> {
         match language {
-            Language::C => {
-                // In a real implementation, this would use clang with BPF target
-                // For now, return a simple test program
-                Ok(vec![
-                    // BPF_MOV64_IMM(BPF_REG_0, 1)
-                    0xb7, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
-                    // BPF_EXIT_INSN()
-                    0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
-                ])
-            }
-            _ => Err(anyhow!("Unsupported language for eBPF: {:?}", language)),
+            Language::Ebpf => Ok(code.to_vec()),
+            Language::C => self.compile_to_ebpf(code),
+            _ => Err(RuntimeError::InvalidLanguage(format!("{:?} cannot be compiled to eBPF", language)).into()),
         }
     }
+    
+    /// Builds C with clang's BPF target and takes the program from the first
+    /// executable section of the object.
+    fn compile_to_ebpf(&self, code: &[u8]) -> Result<Vec<u8>> {
+        let args = ["-target", "bpf", "-O2", "-c", "-o", "program.o", "program.c"];
+        let object = self.clang.run(code, "program.c", "program.o", &args)?;
+        
+        let elf = Elf::parse(&object)
+            .map_err(|e| RuntimeError::CompilationError(format!("clang produced an unreadable object: {}", e)))?;
+        elf.section_headers
+            .iter()
+            .find(|section| section.is_executable() && section.sh_size > 0)
+            .and_then(|section| object.get(section.file_range()?))
+            .map(<[u8]>::to_vec)
+            .ok_or_else(|| RuntimeError::CompilationError("clang produced no eBPF program".to_string()).into())
+    }
+}
+
+/// Compiles C programs; probed once per process, when the first runtime is
+/// created.
+fn clang() -> &'static ToolchainStatus {
+    static CLANG: OnceLock<ToolchainStatus> = OnceLock::new();
+    CLANG.get_or_init(|| ToolchainStatus::probe("clang", "install clang with the BPF target or disable C support"))
 }
 
 #[async_trait]
 impl RuntimeTrait for EbpfRuntime {
     async fn compile(&self, code: &[u8], language: Language) -> Result<ModuleId> {
         debug!("Compiling {:?} code to eBPF ({} bytes)", language, code.len());
+        self.inject_fault(JobPhase::Compile).await?;
         let start = Instant::now();
         
-        let bytecode = if language == Language::C {
-            self.compile_to_ebpf(code, language)?
-        } else {
-            // Assume raw eBPF bytecode
-            code.to_vec()
-        };
+        let bytecode = self.to_bytecode(code, language)?;
         
         // Create program
         let program = EbpfProgram::from_bytecode(bytecode, ProgramType::Filter);
@@ -117,6 +234,10 @@ impl RuntimeTrait for EbpfRuntime {
         // Verify the program
         self.verifier.verify(&program.bytecode)?;
         
+        for definition in &program.metadata.maps {
+            self.maps.create(definition.clone());
+        }
+        
         // Cache the program
         let module_id = self.program_cache.insert(program);
         
@@ -128,6 +249,7 @@ impl RuntimeTrait for EbpfRuntime {
     
     async fn instantiate(&self, module_id: ModuleId) -> Result<InstanceId> {
         debug!("Instantiating eBPF module {}", module_id.0);
+        self.inject_fault(JobPhase::Instantiate).await?;
         let start = Instant::now();
         
         // Get program from cache
@@ -159,9 +281,11 @@ impl RuntimeTrait for EbpfRuntime {
     async fn execute(
         &self,
         instance_id: InstanceId,
-        _config: ExecutionConfig,
+        config: ExecutionConfig,
     ) -> Result<ExecutionResult> {
         debug!("Executing eBPF instance {}", instance_id.0);
+        self.inject_fault(JobPhase::Execute).await?;
+        let _running = self.load.run();
         let start = Instant::now();
         
         let instances = self.instances.read();
@@ -169,21 +293,40 @@ impl RuntimeTrait for EbpfRuntime {
             .get(&instance_id)
             .ok_or_else(|| anyhow!("Instance not found: {}", instance_id.0))?;
         
-        // For eBPF, we expect the input data to be passed through config
-        // In a real implementation, this would come from the execution context
-        let test_data = b"test packet data";
+        // The program filters the execution's input, or a placeholder packet
+        // when there is none
+        let data = config.input.as_deref().unwrap_or(b"test packet data");
+        let setup_done = Instant::now();
         
         // Execute the JIT compiled program
-        let result = self.jit_compiler.execute(&instance.jit_program, test_data)?;
-        
-        let execution_time = start.elapsed();
+        let result = self.jit_compiler.execute(&instance.jit_program, data);
+        
+        let executed = Instant::now();
+        let execution_time = executed - start;
+        self.stats.record(&instance.module_id, Self::outcome(&result), execution_time);
+        let result = result?;
+        
+        // Text encodings get the verdict as a decimal number rather than
+        // its raw little-endian bytes
+        let output = match config.output_encoding {
+            OutputEncoding::Utf8 | OutputEncoding::Json => result.to_string().into_bytes(),
+            encoding => encoding.encode(result.to_le_bytes().to_vec())?,
+        };
         
         Ok(ExecutionResult {
             success: true,
-            output: Some(result.to_le_bytes().to_vec()),
+            output: Some(output),
             error: None,
             execution_time,
             memory_used: 0, // eBPF uses minimal memory
+            logs: Default::default(),
+            attestation: None,
+            environment_fingerprint: None,
+            timing: Some(TimingBreakdown {
+                sandbox_setup: setup_done - start,
+                execute: executed - setup_done,
+                ..Default::default()
+            }),
         })
     }
     
@@ -198,6 +341,63 @@ impl RuntimeTrait for EbpfRuntime {
             Err(anyhow!("Instance not found: {}", instance_id.0))
         }
     }
+    
+    /// Memory access is only permitted for high-trust submissions, even when
+    /// the runtime verifier itself allows unsafe programs.
+    async fn validate(
+        &self,
+        code: &[u8],
+        language: Language,
+        trust_level: TrustLevel,
+    ) -> Result<ValidationReport> {
+        debug!("Validating {:?} code as eBPF ({} bytes)", language, code.len());
+        
+        let bytecode = match self.to_bytecode(code, language) {
+            Ok(bytecode) => bytecode,
+            Err(e) => return Ok(ValidationReport::from_diagnostics(vec![Diagnostic::error(e.to_string())])),
+        };
+        
+        let verifier = Verifier::with_config(
+            self.verifier.max_instructions(),
+            self.verifier.allows_unsafe() && trust_level == TrustLevel::High,
+        );
+        
+        let mut diagnostics = Vec::new();
+        if let Err(e) = verifier.verify(&bytecode) {
+            diagnostics.push(Diagnostic::error(e.to_string()));
+        } else if let Err(e) = self.jit_compiler.check(&bytecode) {
+            diagnostics.push(Diagnostic::error(e.to_string()));
+        }
+        
+        Ok(ValidationReport::from_diagnostics(diagnostics))
+    }
+    
+    fn environment(&self) -> RuntimeEnvironment {
+        RuntimeEnvironment {
+            engine: "rbpf 0.2".to_string(),
+            engine_config: [
+                ("max_instructions", self.verifier.max_instructions().to_string()),
+                ("allow_unsafe", self.verifier.allows_unsafe().to_string()),
+            ]
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+            packages: Default::default(),
+        }
+    }
+    
+    /// Raw bytecode, and C when clang was found.
+    fn capabilities(&self) -> RuntimeCapabilities {
+        RuntimeCapabilities {
+            languages: if self.clang.available() { vec![Language::Ebpf, Language::C] } else { vec![Language::Ebpf] },
+            toolchains: vec![self.clang.clone()],
+        }
+    }
+    
+    /// Programs run to completion on the caller's thread, so nothing queues.
+    fn current_load(&self) -> LoadReport {
+        self.load.report(None, None)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -229,13 +429,14 @@ mod tests {
             0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
         ];
         
-        let module_id = runtime.compile(&bytecode, Language::C).await.unwrap();
+        let module_id = runtime.compile(&bytecode, Language::Ebpf).await.unwrap();
         let instance_id = runtime.instantiate(module_id).await.unwrap();
         
         let config = ExecutionConfig {
             timeout: Duration::from_millis(1),
             memory_limit: 1024,
             permissions: Permissions::new(TrustLevel::Low),
+            ..Default::default()
         };
         
         let result = runtime.execute(instance_id.clone(), config).await.unwrap();
@@ -245,6 +446,73 @@ mod tests {
         runtime.destroy(instance_id).await.unwrap();
     }
     
+    #[tokio::test]
+    async fn test_output_encodings() {
+        let runtime = EbpfRuntime::new().unwrap();
+        let bytecode = vec![
+            0xb7, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let module_id = runtime.compile(&bytecode, Language::Ebpf).await.unwrap();
+        let instance_id = runtime.instantiate(module_id).await.unwrap();
+        let execute = |output_encoding| {
+            runtime.execute(instance_id.clone(), ExecutionConfig { output_encoding, ..Default::default() })
+        };
+        
+        assert_eq!(execute(OutputEncoding::Utf8).await.unwrap().output, Some(b"1".to_vec()));
+        assert_eq!(execute(OutputEncoding::Base64).await.unwrap().output, Some(b"AQAAAAAAAAA=".to_vec()));
+        let error = execute(OutputEncoding::Arrow).await.unwrap_err().to_string();
+        assert!(error.contains("Arrow"), "{}", error);
+    }
+    
+    #[tokio::test]
+    async fn test_program_export_import() {
+        let source = EbpfRuntime::new().unwrap();
+        let bytecode = vec![
+            0xb7, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        let module_id = source.compile(&bytecode, Language::Ebpf).await.unwrap();
+        let exported = source.export_program(&module_id).unwrap();
+        
+        let target = EbpfRuntime::new().unwrap();
+        assert_eq!(target.import_program(&exported).unwrap(), module_id);
+        let instance_id = target.instantiate(module_id).await.unwrap();
+        let result = target.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+        assert_eq!(result.output, Some(1u64.to_le_bytes().to_vec()));
+        
+        let mut tampered = exported.clone();
+        *tampered.last_mut().unwrap() ^= 0xff;
+        assert!(target.import_program(&tampered).is_err());
+        
+        let mut future = exported;
+        future[8] = 2;
+        let error = target.import_program(&future).unwrap_err().to_string();
+        assert!(error.contains("version 2"), "{}", error);
+        
+        // Intact exports still go through this node's verifier
+        let unverified = EbpfProgram::from_bytecode(vec![0xb7; 7], ProgramType::Filter);
+        assert!(target.import_program(&export::encode(&unverified).unwrap()).is_err());
+        assert!(target.program_stats(&unverified.id).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_unsupported_languages_are_rejected() {
+        let runtime = EbpfRuntime::new().unwrap();
+        let bytecode = vec![
+            0xb7, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00,
+            0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        
+        for language in [Language::Wasm, Language::Python, Language::Rust] {
+            let error = runtime.compile(&bytecode, language).await.unwrap_err();
+            assert!(matches!(error.downcast_ref::<RuntimeError>(), Some(RuntimeError::InvalidLanguage(_))), "{}", error);
+            assert!(!runtime.validate(&bytecode, language, TrustLevel::Low).await.unwrap().valid);
+        }
+        assert!(runtime.validate(&bytecode, Language::Ebpf, TrustLevel::Low).await.unwrap().valid);
+        assert!(runtime.capabilities().languages.contains(&Language::Ebpf));
+    }
+    
     #[test]
     fn test_filter_execution() {
         let runtime = EbpfRuntime::new().unwrap();
@@ -0,0 +1,214 @@
+use next_rc_shared::ModuleId;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Latency buckets with power-of-two upper bounds from 1ns to ~1s; the last
+/// bucket takes everything slower.
+const LATENCY_BUCKETS: usize = 32;
+
+/// How a single run of a program ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgramOutcome {
+    Accept,
+    Drop,
+    Error,
+}
+
+/// Lock-free counters for one program, updated on every run.
+struct ProgramCounters {
+    accepted: AtomicU64,
+    dropped: AtomicU64,
+    errors: AtomicU64,
+    latency_sum_ns: AtomicU64,
+    latency_max_ns: AtomicU64,
+    buckets: [AtomicU64; LATENCY_BUCKETS],
+}
+
+impl ProgramCounters {
+    fn new() -> Self {
+        Self {
+            accepted: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+            latency_sum_ns: AtomicU64::new(0),
+            latency_max_ns: AtomicU64::new(0),
+            buckets: std::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    fn record(&self, outcome: ProgramOutcome, elapsed: Duration) {
+        let counter = match outcome {
+            ProgramOutcome::Accept => &self.accepted,
+            ProgramOutcome::Drop => &self.dropped,
+            ProgramOutcome::Error => &self.errors,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        let nanos = elapsed.as_nanos().min(u64::MAX as u128) as u64;
+        self.latency_sum_ns.fetch_add(nanos, Ordering::Relaxed);
+        self.latency_max_ns.fetch_max(nanos, Ordering::Relaxed);
+        self.buckets[bucket_index(nanos)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> ProgramStats {
+        let accepted = self.accepted.load(Ordering::Relaxed);
+        let dropped = self.dropped.load(Ordering::Relaxed);
+        let errors = self.errors.load(Ordering::Relaxed);
+        ProgramStats {
+            executions: accepted + dropped + errors,
+            accepted,
+            dropped,
+            errors,
+            latency: LatencyHistogram {
+                buckets: self
+                    .buckets
+                    .iter()
+                    .enumerate()
+                    .map(|(index, count)| (bucket_upper_bound(index), count.load(Ordering::Relaxed)))
+                    .collect(),
+                sum_ns: self.latency_sum_ns.load(Ordering::Relaxed),
+                max_ns: self.latency_max_ns.load(Ordering::Relaxed),
+            },
+        }
+    }
+}
+
+/// Smallest bucket whose upper bound is at least `nanos`.
+fn bucket_index(nanos: u64) -> usize {
+    let index = match nanos {
+        0 | 1 => 0,
+        _ => (64 - (nanos - 1).leading_zeros()) as usize,
+    };
+    index.min(LATENCY_BUCKETS - 1)
+}
+
+fn bucket_upper_bound(index: usize) -> u64 {
+    if index == LATENCY_BUCKETS - 1 {
+        u64::MAX
+    } else {
+        1 << index
+    }
+}
+
+/// Execution counts and latency of one program since it was compiled.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ProgramStats {
+    pub executions: u64,
+    pub accepted: u64,
+    pub dropped: u64,
+    /// Runs that failed before the program returned a verdict.
+    pub errors: u64,
+    pub latency: LatencyHistogram,
+}
+
+impl ProgramStats {
+    /// Share of verdicts that accepted the input.
+    pub fn accept_ratio(&self) -> f64 {
+        match self.accepted + self.dropped {
+            0 => 0.0,
+            verdicts => self.accepted as f64 / verdicts as f64,
+        }
+    }
+
+    pub fn drop_ratio(&self) -> f64 {
+        match self.accepted + self.dropped {
+            0 => 0.0,
+            verdicts => self.dropped as f64 / verdicts as f64,
+        }
+    }
+}
+
+/// Nanosecond latencies as `(upper bound, count)` buckets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    pub buckets: Vec<(u64, u64)>,
+    pub sum_ns: u64,
+    pub max_ns: u64,
+}
+
+impl LatencyHistogram {
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().map(|(_, count)| count).sum()
+    }
+
+    pub fn mean_ns(&self) -> f64 {
+        match self.count() {
+            0 => 0.0,
+            count => self.sum_ns as f64 / count as f64,
+        }
+    }
+
+    /// Upper bound of the bucket holding the `quantile` (0.0 to 1.0) sample,
+    /// capped at the slowest run seen.
+    pub fn quantile_ns(&self, quantile: f64) -> u64 {
+        let rank = (quantile.clamp(0.0, 1.0) * self.count() as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (upper_bound, count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                return (*upper_bound).min(self.max_ns);
+            }
+        }
+        self.max_ns
+    }
+}
+
+/// Per-program counters, keyed by the program's module id.
+#[derive(Default)]
+pub struct ProgramStatsRegistry {
+    programs: RwLock<HashMap<ModuleId, Arc<ProgramCounters>>>,
+}
+
+impl ProgramStatsRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, module_id: &ModuleId, outcome: ProgramOutcome, elapsed: Duration) {
+        if let Some(counters) = self.programs.read().get(module_id) {
+            counters.record(outcome, elapsed);
+            return;
+        }
+        let counters = self
+            .programs
+            .write()
+            .entry(module_id.clone())
+            .or_insert_with(|| Arc::new(ProgramCounters::new()))
+            .clone();
+        counters.record(outcome, elapsed);
+    }
+
+    pub fn get(&self, module_id: &ModuleId) -> Option<ProgramStats> {
+        self.programs.read().get(module_id).map(|counters| counters.snapshot())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_track_verdicts_and_latency() {
+        let registry = ProgramStatsRegistry::new();
+        let module_id = ModuleId(uuid::Uuid::new_v4());
+        assert!(registry.get(&module_id).is_none());
+
+        for nanos in [80, 90, 100, 3_000] {
+            registry.record(&module_id, ProgramOutcome::Accept, Duration::from_nanos(nanos));
+        }
+        registry.record(&module_id, ProgramOutcome::Drop, Duration::from_nanos(120));
+        registry.record(&module_id, ProgramOutcome::Error, Duration::from_nanos(50));
+
+        let stats = registry.get(&module_id).unwrap();
+        assert_eq!((stats.executions, stats.accepted, stats.dropped, stats.errors), (6, 4, 1, 1));
+        assert!((stats.accept_ratio() - 0.8).abs() < f64::EPSILON);
+        assert_eq!(stats.latency.count(), 6);
+        assert_eq!(stats.latency.max_ns, 3_000);
+        assert_eq!(stats.latency.quantile_ns(0.5), 128);
+        assert_eq!(stats.latency.quantile_ns(1.0), 3_000);
+    }
+}
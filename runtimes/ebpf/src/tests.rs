@@ -2,6 +2,7 @@
 mod integration_tests {
     use crate::{EbpfRuntime, program::*, verifier::Verifier};
     use next_rc_shared::*;
+    use std::sync::Arc;
     use std::time::{Duration, Instant};
     
     #[test]
@@ -85,7 +86,7 @@ mod integration_tests {
             0x95, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
         ];
         
-        let module_id = runtime.compile(&bytecode, Language::C).await.unwrap();
+        let module_id = runtime.compile(&bytecode, Language::Ebpf).await.unwrap();
         
         // Create multiple instances
         let mut handles = vec![];
@@ -101,6 +102,7 @@ mod integration_tests {
                     timeout: Duration::from_millis(1),
                     memory_limit: 1024,
                     permissions: Permissions::new(TrustLevel::Low),
+                    ..Default::default()
                 };
                 
                 let start = Instant::now();
@@ -115,12 +117,9 @@ mod integration_tests {
             handles.push(handle);
         }
         
-        // Wait for all executions
-        let results: Vec<_> = futures::future::join_all(handles).await;
-        
         // All should succeed with low latency
-        for result in results {
-            let (idx, exec_result, elapsed) = result.unwrap();
+        for handle in handles {
+            let (idx, exec_result, elapsed) = handle.await.unwrap();
             assert!(exec_result.success);
             assert!(elapsed.as_micros() < 100, "Execution {} took {:?}", idx, elapsed);
         }
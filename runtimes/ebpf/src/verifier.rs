@@ -22,6 +22,14 @@ impl Verifier {
         }
     }
     
+    pub fn max_instructions(&self) -> usize {
+        self.max_instructions
+    }
+    
+    pub fn allows_unsafe(&self) -> bool {
+        self.allow_unsafe
+    }
+    
     pub fn verify(&self, bytecode: &[u8]) -> Result<()> {
         debug!("Verifying eBPF program ({} bytes)", bytecode.len());
         
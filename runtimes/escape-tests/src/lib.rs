@@ -0,0 +1,22 @@
+//! Known sandbox escape attempts, run against the real sandboxes by the tests
+//! in `tests/`. Each attempt must be rejected before it runs or contained
+//! while it runs at the trust levels it targets, so a failing test here is a
+//! security regression rather than a flaky check.
+
+use next_rc_shared::TrustLevel;
+
+pub const TRUST_LEVELS: [TrustLevel; 3] = [TrustLevel::Low, TrustLevel::Medium, TrustLevel::High];
+
+/// Trust levels that run untrusted code and must sandbox it.
+pub const SANDBOXED_TRUST_LEVELS: [TrustLevel; 2] = [TrustLevel::Low, TrustLevel::Medium];
+
+/// Encodes one eBPF instruction.
+pub fn ebpf_insn(opcode: u8, dst: u8, src: u8, offset: i16, imm: i32) -> [u8; 8] {
+    let [off_lo, off_hi] = offset.to_le_bytes();
+    let [imm0, imm1, imm2, imm3] = imm.to_le_bytes();
+    [opcode, (src << 4) | dst, off_lo, off_hi, imm0, imm1, imm2, imm3]
+}
+
+pub fn ebpf_program(insns: &[[u8; 8]]) -> Vec<u8> {
+    insns.concat()
+}
@@ -0,0 +1,100 @@
+#![cfg(target_os = "linux")]
+
+use next_rc_ebpf::EbpfRuntime;
+use next_rc_escape_tests::{ebpf_insn, ebpf_program, SANDBOXED_TRUST_LEVELS, TRUST_LEVELS};
+use next_rc_shared::{ExecutionConfig, Language, Permissions, Runtime, TrustLevel};
+use std::time::Duration;
+
+const R0: u8 = 0;
+const R1: u8 = 1;
+const R10: u8 = 10;
+
+fn exit() -> [u8; 8] {
+    ebpf_insn(0x95, 0, 0, 0, 0)
+}
+
+/// A runtime whose verifier permits memory access, so the per-trust-level
+/// gate and the interpreter's bounds checks are what stand in the way.
+fn runtime() -> EbpfRuntime {
+    EbpfRuntime::with_config(4096, true).unwrap()
+}
+
+#[tokio::test]
+async fn memory_loads_need_high_trust() {
+    let runtime = runtime();
+    // r0 = *(u32 *)(r1 + 0)
+    let program = ebpf_program(&[ebpf_insn(0x61, R0, R1, 0, 0), exit()]);
+
+    for trust_level in SANDBOXED_TRUST_LEVELS {
+        let report = runtime.validate(&program, Language::Ebpf, trust_level).await.unwrap();
+        assert!(!report.valid, "memory load passed validation at {trust_level:?}");
+    }
+
+    let report = runtime.validate(&program, Language::Ebpf, TrustLevel::High).await.unwrap();
+    assert!(report.valid, "{:?}", report.diagnostics);
+}
+
+#[tokio::test]
+async fn out_of_bounds_reads_are_contained() {
+    let runtime = runtime();
+    let attempts = [
+        // r0 = *(u64 *)(r10 + 8), just past the top of the stack
+        ebpf_program(&[ebpf_insn(0x79, R0, R10, 8, 0), exit()]),
+        // r0 = *(u64 *)(r1 + 4096), far past the end of the packet
+        ebpf_program(&[ebpf_insn(0x79, R0, R1, 4096, 0), exit()]),
+    ];
+
+    for program in attempts {
+        for trust_level in SANDBOXED_TRUST_LEVELS {
+            let report = runtime.validate(&program, Language::Ebpf, trust_level).await.unwrap();
+            assert!(!report.valid);
+        }
+
+        let module_id = runtime.compile(&program, Language::Ebpf).await.unwrap();
+        let instance_id = runtime.instantiate(module_id).await.unwrap();
+        let config = ExecutionConfig {
+            timeout: Duration::from_millis(10),
+            memory_limit: 1024,
+            permissions: Permissions::new(TrustLevel::High),
+            ..Default::default()
+        };
+
+        match runtime.execute(instance_id.clone(), config).await {
+            Ok(result) => assert!(!result.success, "out-of-bounds read returned {:?}", result.output),
+            Err(e) => assert!(e.to_string().contains("eBPF execution failed"), "{e}"),
+        }
+        runtime.destroy(instance_id).await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn jumps_out_of_the_program_are_rejected() {
+    let runtime = runtime();
+    let attempts = [
+        // goto +16, past the end
+        ebpf_program(&[ebpf_insn(0x05, 0, 0, 16, 0), exit()]),
+        // goto -4, before the start
+        ebpf_program(&[ebpf_insn(0x05, 0, 0, -4, 0), exit()]),
+    ];
+
+    for program in attempts {
+        for trust_level in TRUST_LEVELS {
+            let report = runtime.validate(&program, Language::Ebpf, trust_level).await.unwrap();
+            assert!(!report.valid, "out-of-range jump passed validation at {trust_level:?}");
+        }
+        assert!(runtime.compile(&program, Language::Ebpf).await.is_err());
+    }
+}
+
+#[tokio::test]
+async fn unknown_helpers_are_rejected() {
+    let runtime = runtime();
+    // call 99; no such helper is registered
+    let program = ebpf_program(&[ebpf_insn(0x85, 0, 0, 0, 99), exit()]);
+
+    for trust_level in TRUST_LEVELS {
+        let report = runtime.validate(&program, Language::Ebpf, trust_level).await.unwrap();
+        assert!(!report.valid, "unknown helper passed validation at {trust_level:?}");
+    }
+    assert!(runtime.compile(&program, Language::Ebpf).await.is_err());
+}
@@ -0,0 +1,63 @@
+#![cfg(all(target_os = "linux", feature = "python"))]
+
+use python_runtime::{PythonExecutionRequest, PythonRuntimeController, TrustLevel};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Known ways out of a restricted interpreter. High trust runs code
+/// unsandboxed by design, so only the sandboxed levels are checked.
+const ESCAPES: &[(&str, &str)] = &[
+    (
+        "mro walk to subclasses",
+        "for cls in ().__class__.__mro__[1].__subclasses__():\n    if cls.__name__ == 'Popen':\n        cls(['id'])",
+    ),
+    (
+        "builtins through function globals",
+        "def f():\n    pass\nf.__globals__['__builtins__']['open']('/etc/passwd').read()",
+    ),
+    ("ctypes libc call", "import ctypes\nctypes.CDLL(None).system(b'id')"),
+    ("ctypes from-import", "from ctypes import CDLL\nCDLL(None).system(b'id')"),
+    (
+        "fork via multiprocessing",
+        "import multiprocessing\nmultiprocessing.Process(target=print).start()",
+    ),
+    ("direct fork", "import os\nos.fork()"),
+    ("importlib loader", "import importlib\nimportlib.import_module('subprocess').run(['id'])"),
+];
+
+fn request(code: &str, trust_level: TrustLevel) -> PythonExecutionRequest {
+    PythonExecutionRequest {
+        id: Uuid::new_v4(),
+        code: code.to_string(),
+        runtime_hint: None,
+        trust_level,
+        timeout_ms: 1000,
+        memory_limit_mb: 64,
+        environment: HashMap::new(),
+        requirements: Vec::new(),
+        stdin: None,
+        virtual_time: None,
+        log_limits: Default::default(),
+        process_limits: Default::default(),
+    }
+}
+
+#[tokio::test]
+async fn escape_attempts_are_rejected() {
+    let controller = PythonRuntimeController::new(4).await.unwrap();
+
+    for trust_level in [TrustLevel::Low, TrustLevel::Medium] {
+        for (name, code) in ESCAPES {
+            let report = controller.validate(code, &trust_level);
+            assert!(!report.valid, "{name} passed validation at {trust_level:?}");
+
+            let outcome = controller.execute(request(code, trust_level.clone())).await;
+            assert!(
+                !outcome.as_ref().is_ok_and(|result| result.success),
+                "{name} ran at {trust_level:?}: {outcome:?}"
+            );
+        }
+    }
+
+    controller.shutdown().await.unwrap();
+}
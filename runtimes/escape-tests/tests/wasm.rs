@@ -0,0 +1,267 @@
+#![cfg(target_os = "linux")]
+
+use next_rc_escape_tests::TRUST_LEVELS;
+use next_rc_shared::{
+    ExecutionConfig, InMemoryVectorStore, Language, Permissions, Runtime, RuntimeError, TrustLevel, VectorPoint,
+    VectorStore,
+};
+use std::sync::Arc;
+use std::time::Duration;
+use wasm_runtime::WasmRuntime;
+
+/// Imports a guest might try to get resolved against host functions that
+/// were never exposed to it.
+const SMUGGLED_IMPORTS: &[(&str, &str)] = &[
+    ("env", "system"),
+    ("env", "spawn"),
+    ("wasi_snapshot_preview1", "path_open"),
+    ("wasi_snapshot_preview1", "sock_accept"),
+];
+
+fn config(trust_level: TrustLevel) -> ExecutionConfig {
+    ExecutionConfig {
+        timeout: Duration::from_secs(1),
+        memory_limit: 16 * 1024 * 1024,
+        permissions: Permissions::new(trust_level),
+        ..Default::default()
+    }
+}
+
+async fn run(runtime: &WasmRuntime, wat: &str, trust_level: TrustLevel) -> anyhow::Result<next_rc_shared::ExecutionResult> {
+    let module_id = runtime.compile(wat.as_bytes(), Language::Wasm).await?;
+    let instance_id = runtime.instantiate(module_id).await?;
+    let result = runtime.execute(instance_id.clone(), config(trust_level)).await;
+    runtime.destroy(instance_id).await?;
+    result
+}
+
+#[tokio::test]
+async fn smuggled_host_imports_are_rejected() {
+    let runtime = WasmRuntime::new_default().unwrap();
+
+    for (module, name) in SMUGGLED_IMPORTS {
+        let wat = format!(
+            r#"(module
+                (import "{module}" "{name}" (func $smuggled (result i32)))
+                (func (export "_start") (result i32) call $smuggled))"#
+        );
+
+        for trust_level in TRUST_LEVELS {
+            let report = runtime.validate(wat.as_bytes(), Language::Wasm, trust_level).await.unwrap();
+            assert!(!report.valid, "{module}::{name} passed validation at {trust_level:?}");
+        }
+
+        let outcome = run(&runtime, &wat, TrustLevel::High).await;
+        assert!(outcome.is_err(), "{module}::{name} was linked");
+    }
+}
+
+#[tokio::test]
+async fn bundle_modules_cannot_shadow_host_namespaces() {
+    let runtime = WasmRuntime::new_default().unwrap();
+    let main = wat::parse_str(
+        r#"(module
+            (import "env" "system" (func $system (result i32)))
+            (func (export "_start") (result i32) call $system))"#,
+    )
+    .unwrap();
+    let shadow = wat::parse_str(r#"(module (func (export "system") (result i32) i32.const 0))"#).unwrap();
+
+    for namespace in ["env", "wasi_snapshot_preview1"] {
+        let modules = vec![("main".to_string(), main.clone()), (namespace.to_string(), shadow.clone())];
+        let err = runtime.compile_bundle(modules, Language::Wasm).await.unwrap_err();
+        assert!(err.to_string().contains("reserved"), "{namespace}: {err}");
+    }
+}
+
+#[tokio::test]
+async fn manifests_cannot_claim_capabilities_beyond_trust() {
+    let runtime = WasmRuntime::new_default().unwrap();
+    let wat = r#"(module
+        (@custom "next-rc.manifest" "{\"capabilities\": [\"NetworkAccess\", \"ProcessSpawn\"]}")
+        (func (export "_start") (result i32) i32.const 0))"#;
+
+    for trust_level in TRUST_LEVELS {
+        let report = runtime.validate(wat.as_bytes(), Language::Wasm, trust_level).await.unwrap();
+        assert!(!report.valid, "capability claim passed validation at {trust_level:?}");
+
+        // Process spawning is never granted, so execution is refused outright.
+        let err = run(&runtime, wat, trust_level).await.unwrap_err();
+        assert!(
+            matches!(err.downcast_ref::<RuntimeError>(), Some(RuntimeError::SecurityError(_))),
+            "{trust_level:?}: {err}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn out_of_bounds_memory_access_traps() {
+    let runtime = WasmRuntime::new_default().unwrap();
+    let wat = r#"(module
+        (memory 1)
+        (func (export "_start") (result i32)
+            i32.const 0x7fff_fff0
+            i32.load))"#;
+
+    for trust_level in TRUST_LEVELS {
+        let result = run(&runtime, wat, trust_level).await.unwrap();
+        assert!(!result.success, "out-of-bounds load returned {:?}", result.output);
+    }
+}
+
+#[tokio::test]
+async fn memory_growth_is_capped() {
+    let runtime = WasmRuntime::new_default().unwrap();
+    // Asks for the full 4GiB address space; the grow must fail and return -1.
+    let wat = r#"(module
+        (memory 1)
+        (func (export "_start") (result i32)
+            i32.const 65535
+            memory.grow))"#;
+
+    for trust_level in TRUST_LEVELS {
+        let result = run(&runtime, wat, trust_level).await.unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.output.as_deref(), Some(&b"-1"[..]));
+    }
+}
+
+#[tokio::test]
+async fn table_growth_is_capped_by_trust_level() {
+    let runtime = WasmRuntime::new_default().unwrap();
+    let wat = r#"(module
+        (table 1 funcref)
+        (func (export "_start") (result i32)
+            ref.null func
+            i32.const 50000
+            table.grow))"#;
+
+    let err = run(&runtime, wat, TrustLevel::Low).await.unwrap_err();
+    assert!(
+        matches!(err.downcast_ref::<RuntimeError>(), Some(RuntimeError::ResourceLimitExceeded(_))),
+        "{err}"
+    );
+    for trust_level in [TrustLevel::Medium, TrustLevel::High] {
+        let result = run(&runtime, wat, trust_level).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(&b"1"[..]), "{trust_level:?}");
+    }
+
+    // Tables past the hard cap are refused before the module is instantiated.
+    let wat = r#"(module (table 1000001 funcref) (func (export "_start") (result i32) i32.const 0))"#;
+    let module_id = runtime.compile(wat.as_bytes(), Language::Wasm).await.unwrap();
+    let err = runtime.instantiate(module_id).await.unwrap_err();
+    assert!(
+        matches!(err.downcast_ref::<RuntimeError>(), Some(RuntimeError::ResourceLimitExceeded(_))),
+        "{err}"
+    );
+
+    // Tables under the hard cap instantiate, but only run at trust levels
+    // whose store limits cover them.
+    let wat = r#"(module (table 600000 funcref) (func (export "_start") (result i32) i32.const 0))"#;
+    for trust_level in [TrustLevel::Low, TrustLevel::Medium] {
+        let err = run(&runtime, wat, trust_level).await.unwrap_err();
+        assert!(
+            matches!(err.downcast_ref::<RuntimeError>(), Some(RuntimeError::ResourceLimitExceeded(_))),
+            "{trust_level:?}: {err}"
+        );
+    }
+    let result = run(&runtime, wat, TrustLevel::High).await.unwrap();
+    assert!(result.success);
+}
+
+#[tokio::test]
+async fn runaway_guests_are_stopped() {
+    let runtime = WasmRuntime::new_default().unwrap();
+    let wat = r#"(module
+        (func (export "_start") (result i32)
+            (loop $spin (br $spin))
+            i32.const 0))"#;
+
+    for trust_level in TRUST_LEVELS {
+        let result = run(&runtime, wat, trust_level).await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("timeout"));
+    }
+}
+
+#[tokio::test]
+async fn vector_search_requires_capability() {
+    let store = Arc::new(InMemoryVectorStore::new());
+    let points = [("a", vec![1.0, 0.0]), ("b", vec![0.0, 1.0])]
+        .into_iter()
+        .map(|(id, vector)| VectorPoint { id: id.to_string(), vector, payload: Default::default() })
+        .collect();
+    store.upsert("docs", points).await.unwrap();
+
+    let runtime = WasmRuntime::new_default().unwrap();
+    runtime.set_vector_store(Some(store));
+
+    // Returns the first character of the best match's id, or the error code.
+    let query = r#"{"collection":"docs","vector":[0.9,0.1],"limit":1}"#;
+    let wat = format!(
+        r#"(module
+            (import "env" "vector_search" (func $search (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "{}")
+            (func (export "_start") (result i32) (local $len i32)
+                (local.set $len (call $search (i32.const 0) (i32.const {}) (i32.const 1024) (i32.const 1024)))
+                (if (result i32) (i32.gt_s (local.get $len) (i32.const 0))
+                    (then (i32.load8_u (i32.const 1032)))
+                    (else (local.get $len)))))"#,
+        query.replace('"', "\\\""),
+        query.len()
+    );
+
+    let result = run(&runtime, &wat, TrustLevel::Low).await.unwrap();
+    assert_eq!(result.output.as_deref(), Some(&b"-1"[..]));
+    for trust_level in [TrustLevel::Medium, TrustLevel::High] {
+        let result = run(&runtime, &wat, trust_level).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(b'a'.to_string().as_bytes()), "{trust_level:?}");
+    }
+}
+
+#[tokio::test]
+async fn io_buffers_stay_in_guest_memory() {
+    let runtime = WasmRuntime::new_default().unwrap();
+
+    // Copies the input to offset `dest` and writes it back out, uppercased
+    // by subtracting 32 from the first byte.
+    let echo = |dest: u32| {
+        format!(
+            r#"(module
+                (import "env" "input_len" (func $input_len (result i32)))
+                (import "env" "read_input" (func $read_input (param i32 i32) (result i32)))
+                (import "env" "write_output" (func $write_output (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "_start") (result i32) (local $len i32)
+                    (local.set $len (call $read_input (i32.const {dest}) (call $input_len)))
+                    (i32.store8 (i32.const {dest}) (i32.sub (i32.load8_u (i32.const {dest})) (i32.const 32)))
+                    (call $write_output (i32.const {dest}) (local.get $len))
+                    (local.get $len)))"#
+        )
+    };
+
+    let execute = |wat: String| {
+        let runtime = &runtime;
+        async move {
+            let module_id = runtime.compile(wat.as_bytes(), Language::Wasm).await.unwrap();
+            let instance_id = runtime.instantiate(module_id).await.unwrap();
+            let config = ExecutionConfig {
+                input: Some(b"packet".to_vec()),
+                ..config(TrustLevel::Low)
+            };
+            let result = runtime.execute(instance_id.clone(), config).await.unwrap();
+            runtime.destroy(instance_id).await.unwrap();
+            result
+        }
+    };
+
+    let result = execute(echo(16)).await;
+    assert_eq!(result.output.as_deref(), Some(&b"Packet"[..]));
+
+    // Both calls trap rather than touch memory past the guest's last page
+    for dest in [65_534, u32::MAX - 2] {
+        let result = execute(echo(dest)).await;
+        assert!(!result.success, "copied to {dest}");
+    }
+}
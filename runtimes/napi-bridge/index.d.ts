@@ -12,7 +12,9 @@ export const enum Language {
   Go = 4,
   C = 5,
   Cpp = 6,
-  Wasm = 7
+  Wasm = 7,
+  /** Raw eBPF bytecode */
+  Ebpf = 8
 }
 /** Trust level for security */
 export const enum TrustLevel {
@@ -35,15 +37,114 @@ export interface ExecutionConfig {
   trustLevel: TrustLevel
   networkAccess: boolean
   filesystemAccess: boolean
+  tenantId?: string
+  tags?: Record<string, string>
+  virtualTime?: VirtualTime
+  /** Most descriptors the guest may hold open */
+  maxOpenFiles?: number
+  /** Most processes and threads the guest may run */
+  maxProcesses?: number
+  /** Form of the result's output; raw bytes when unset */
+  outputEncoding?: OutputEncoding
+  /**
+   * How much guest logging and runtime tracing the result carries;
+   * basic when unset
+   */
+  traceVerbosity?: TraceVerbosity
+  /**
+   * Bytes handed to the guest: packet data for eBPF programs, stdin for
+   * Python and `env.read_input` for WASM modules
+   */
+  input?: Buffer
+}
+/** Form an execution's output is returned in */
+export const enum OutputEncoding {
+  /** Text in `output`; output that isn't valid UTF-8 fails the execution */
+  Utf8 = 0,
+  /** Base64 text in `output` */
+  Base64 = 1,
+  /** A JSON document in `output` */
+  Json = 2,
+  /** An Arrow IPC file or stream in `outputBytes` */
+  Arrow = 3,
+  /** The raw bytes in `outputBytes` */
+  Bytes = 4
+}
+/** How much guest logging and runtime tracing an execution returns */
+export const enum TraceVerbosity {
+  /** Neither guest logs nor trace events */
+  Off = 0,
+  /** Guest logs within the default limits and the main trace events */
+  Basic = 1,
+  /** Guest logs down to trace level and every trace event */
+  Verbose = 2
+}
+/** Named execution defaults */
+export interface ExecutionProfile {
+  name: string
+  config: ExecutionConfig
+  /** Runtimes to use, most preferred first */
+  runtimes: Array<string>
+}
+/** Virtual clock for deterministic executions */
+export interface VirtualTime {
+  startUnixMs: number
+  tickNs?: number
 }
 /** Execution result */
 export interface ExecutionResult {
   success: boolean
+  /** Output in a text encoding; empty for binary ones */
   output: string
+  /** Output in a binary encoding */
+  outputBytes?: Buffer
   error?: string
   executionTimeMs: number
   memoryUsedBytes: number
   exitCode?: number
+  /** Records the guest emitted through the host logging API */
+  logs: Array<LogRecord>
+  /** Records discarded by the execution's log limits */
+  logsDropped: number
+  /** Runtime events recorded at the execution's trace verbosity */
+  trace: Array<LogRecord>
+  /** Time spent in each phase, when the runtime reports it */
+  timing?: ExecutionTiming
+}
+/** Per-phase timings of one request, in fractional milliseconds */
+export interface ExecutionTiming {
+  queueWaitMs: number
+  schedulingMs: number
+  sandboxSetupMs: number
+  compileMs: number
+  instantiateMs: number
+  executeMs: number
+  teardownMs: number
+}
+/** Guest log record */
+export interface LogRecord {
+  level: string
+  message: string
+  elapsedMs: number
+}
+/** Compiler or validation finding */
+export interface Diagnostic {
+  severity: string
+  message: string
+  file?: string
+  line?: number
+  column?: number
+  snippet?: string
+}
+/** Validate-only result */
+export interface ValidationReport {
+  valid: boolean
+  diagnostics: Array<Diagnostic>
+}
+/** Compilation result with structured diagnostics */
+export interface CompileOutput {
+  moduleId?: ModuleId
+  diagnostics: Array<Diagnostic>
 }
 /** Runtime status */
 export interface RuntimeStatus {
@@ -55,6 +156,25 @@ export interface RuntimeStatus {
   failedExecutions: number
   avgExecutionTimeMs: number
 }
+/** Readiness of pre-provisioned instances */
+export interface ProvisionReport {
+  requested: number
+  ready: number
+  pooled: number
+  failures: Array<string>
+  elapsedMs: number
+}
+/** Executions running and queued on a runtime, for shedding load early */
+export interface RuntimeLoad {
+  inFlight: number
+  queued: number
+  /** Slots free right now; absent when executions aren't limited */
+  availableSlots?: number
+  capacity?: number
+  estimatedWaitMs: number
+  /** Whether a new execution would have to queue */
+  saturated: boolean
+}
 /** Workload hint for intelligent scheduling */
 export interface WorkloadHint {
   expectedDurationMs?: number
@@ -69,6 +189,14 @@ export interface SchedulingDecision {
   reasoning: string
   confidence: number
 }
+/** Inferred language of submitted code */
+export interface LanguageDetection {
+  language: Language
+  /** From 0 to 1 */
+  confidence: number
+  /** "override", "magic_bytes", "shebang", "extension" or "content" */
+  method: string
+}
 /** Runtime performance metrics */
 export interface RuntimeMetrics {
   runtimeType: string
@@ -77,14 +205,48 @@ export interface RuntimeMetrics {
   executionOverheadPercent: number
   activeInstances: number
 }
-/** Initialize the runtime controller */
+/** PII scrubbing applied to results before they are returned */
+export interface RedactionConfig {
+  /**
+   * Redact email addresses, US social security numbers, payment card
+   * numbers and IPv4 addresses
+   */
+  builtinDetectors?: boolean
+  /** Further kinds of PII, each found by a regular expression */
+  patterns?: Array<RedactionPattern>
+}
+export interface RedactionPattern {
+  /** Shown in the `[REDACTED:<name>]` markers replacing matches */
+  name: string
+  pattern: string
+}
+/**
+ * Initialize the runtime controller for this Node environment. Safe to
+ * call from every worker thread and again after the addon is reloaded;
+ * resources are released when the environment is torn down.
+ */
 export declare function initializeRuntimeController(): void
 /** Get runtime controller version */
 export declare function getVersion(): string
 /** Get available runtimes */
 export declare function getAvailableRuntimes(): Array<string>
+/** Get a built-in execution profile ("untrusted", "internal" or "batch") */
+export declare function getExecutionProfile(name: string): ExecutionProfile
+/** Get all built-in execution profiles */
+export declare function listExecutionProfiles(): Array<ExecutionProfile>
+/**
+ * Infer the language of submitted code from magic bytes, a shebang, the
+ * file name's extension or its content; `language` overrides detection
+ */
+export declare function detectLanguage(code: Buffer, fileName?: string | undefined | null, language?: Language | undefined | null): LanguageDetection | null
 /** Get metrics for all runtimes */
 export declare function getRuntimeMetrics(): Promise<Array<RuntimeMetrics>>
+/**
+ * Redact the results of `tenant`'s executions with `config`, or, without
+ * a tenant, those of tenants that have no config of their own. A null
+ * config stops redacting them
+ */
+export declare function configureRedaction(tenant?: string | undefined | null, config?: RedactionConfig | undefined | null): void
 /** WASM Runtime Bridge */
 export declare class WasmRuntimeBridge {
   /** Create a new WASM runtime */
@@ -93,8 +255,14 @@ export declare class WasmRuntimeBridge {
   initialize(): Promise<void>
   /** Compile code to a WASM module */
   compile(code: string, language: Language): Promise<ModuleId>
-  /** Instantiate a compiled module */
+  /** Compile code, returning structured diagnostics instead of failing */
+  compileWithDiagnostics(code: string, language: Language): Promise<CompileOutput>
+  /** Validate code without compiling it into the module cache */
+  validate(code: string, language: Language, trustLevel: TrustLevel): Promise<ValidationReport>
+  /** Instantiate a compiled module, reusing a provisioned instance if available */
   instantiate(moduleId: ModuleId): Promise<InstanceId>
+  /** Pre-instantiate `count` instances of a module ahead of expected traffic */
+  provision(moduleId: ModuleId, count: number): Promise<ProvisionReport>
   /** Execute code in an instance */
   execute(instanceId: InstanceId, config: ExecutionConfig): Promise<ExecutionResult>
   /** Destroy an instance */
@@ -105,6 +273,13 @@ export declare class WasmRuntimeBridge {
   getPerformanceMetrics(): Promise<RuntimeMetrics>
   /** Pre-warm the runtime for faster startup */
   preWarm(count: number): Promise<void>
+  /**
+   * Languages this runtime can compile on this host, and the toolchains
+   * it found or is missing
+   */
+  getCapabilities(): any
+  /** Executions running and queued on this runtime */
+  currentLoad(): RuntimeLoad
   /** Get memory pool statistics */
   getMemoryStats(): Promise<any>
 }
@@ -116,10 +291,17 @@ export declare class EbpfRuntimeBridge {
   initialize(): Promise<void>
   /** Compile eBPF code to bytecode */
   compile(code: string, language: Language): Promise<ModuleId>
+  /** Compile eBPF code, returning structured diagnostics instead of failing */
+  compileWithDiagnostics(code: string, language: Language): Promise<CompileOutput>
+  /** Verify eBPF code without caching or loading it */
+  validate(code: string, language: Language, trustLevel: TrustLevel): Promise<ValidationReport>
   /** Load and verify eBPF program */
   loadProgram(moduleId: ModuleId): Promise<InstanceId>
-  /** Execute eBPF program with input data */
-  executeFilter(instanceId: InstanceId, inputData: Buffer): Promise<ExecutionResult>
+  /**
+   * Execute eBPF program with input data, returning the verdict in
+   * `output_encoding` (raw bytes when unset)
+   */
+  executeFilter(instanceId: InstanceId, inputData: Buffer, outputEncoding?: OutputEncoding | undefined | null): Promise<ExecutionResult>
   /** Execute eBPF program (general interface) */
   execute(instanceId: InstanceId, config: ExecutionConfig): Promise<ExecutionResult>
   /** Unload eBPF program */
@@ -130,8 +312,23 @@ export declare class EbpfRuntimeBridge {
   getPerformanceMetrics(): Promise<RuntimeMetrics>
   /** Verify eBPF bytecode without loading */
   verifyProgram(bytecode: Buffer): Promise<boolean>
+  /**
+   * Languages this runtime can compile on this host, and the toolchains
+   * it found or is missing
+   */
+  getCapabilities(): any
+  /** Executions running and queued on this runtime */
+  currentLoad(): RuntimeLoad
   /** Get eBPF JIT compilation statistics */
   getJitStats(): Promise<any>
+  /** Dump the current contents of an eBPF map */
+  dumpMap(mapName: string): Promise<any>
+  /** Get execution counts, accept/drop ratios and latency of one eBPF program */
+  programStats(moduleId: ModuleId): Promise<any>
+  /** Serialize a compiled program for loading on another node */
+  exportProgram(moduleId: ModuleId): Buffer
+  /** Verify and load a program from `exportProgram`, keeping its module ID */
+  importProgram(bytes: Buffer): ModuleId
   /** Enable eBPF program tracing for debugging */
   enableTracing(instanceId: InstanceId): Promise<void>
 }
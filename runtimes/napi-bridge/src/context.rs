@@ -0,0 +1,104 @@
+use napi::{Env, Error, Result, Status};
+use parking_lot::{const_mutex, Mutex};
+use std::future::Future;
+use std::sync::{Arc, Weak};
+use tokio::runtime::{Builder, Runtime};
+
+/// The tokio runtime while any Node environment holds it. Worker threads
+/// and addon instances re-created by dev-mode reloads share it; it shuts
+/// down once the last of them is torn down.
+static SHARED: Mutex<Weak<SharedRuntime>> = const_mutex(Weak::new());
+
+/// Tokio runtime shared by every environment that loaded the addon
+pub struct SharedRuntime {
+    runtime: Option<Runtime>,
+}
+
+impl SharedRuntime {
+    /// Runs `future` to completion on the shared runtime, blocking the
+    /// calling thread
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.as_ref().expect("runtime is only taken on drop").block_on(future)
+    }
+}
+
+impl Drop for SharedRuntime {
+    fn drop(&mut self) {
+        // The last reference may go from inside an async context, where
+        // a blocking shutdown would panic
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+/// One environment's hold on the shared runtime, released by the
+/// environment's teardown
+struct EnvContext {
+    runtime: Arc<SharedRuntime>,
+}
+
+/// The shared runtime, attached to `env` on first use so it lives until
+/// the environment is torn down. Creates the runtime when no environment
+/// holds it.
+pub fn attach(env: &mut Env) -> Result<Arc<SharedRuntime>> {
+    if let Some(context) = env.get_instance_data::<EnvContext>()? {
+        return Ok(context.runtime.clone());
+    }
+
+    let runtime = acquire()?;
+
+    // Already set by an earlier environment or addon instance
+    let _ = tracing_subscriber::fmt().try_init();
+
+    env.set_instance_data(EnvContext { runtime: runtime.clone() }, (), |_| {})?;
+    Ok(runtime)
+}
+
+/// The shared runtime if anything still holds it, or a new one
+fn acquire() -> Result<Arc<SharedRuntime>> {
+    let mut shared = SHARED.lock();
+    if let Some(runtime) = shared.upgrade() {
+        return Ok(runtime);
+    }
+
+    let runtime = Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("next-rc")
+        .build()
+        .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to create tokio runtime: {}", e)))?;
+    let runtime = Arc::new(SharedRuntime { runtime: Some(runtime) });
+    *shared = Arc::downgrade(&runtime);
+    Ok(runtime)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_lives_while_any_environment_holds_it() {
+        let first = acquire().unwrap();
+        let second = acquire().unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // A task that only ends when its runtime goes away
+        let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+        first.block_on(async {
+            tokio::spawn(async move {
+                let _tx = tx;
+                std::future::pending::<()>().await
+            })
+        });
+
+        drop(first);
+        assert!(SHARED.lock().upgrade().is_some());
+
+        drop(second);
+        assert!(rx.blocking_recv().is_err());
+        assert!(SHARED.lock().upgrade().is_none());
+
+        let third = acquire().unwrap();
+        assert_eq!(third.block_on(async { tokio::spawn(async { 42 }).await.unwrap() }), 42);
+    }
+}
@@ -6,6 +6,7 @@ use std::sync::Arc;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 
+use crate::context::SharedRuntime;
 use crate::types::*;
 use next_rc_ebpf::EbpfRuntime;
 use next_rc_shared::{Runtime as RuntimeTrait};
@@ -14,6 +15,8 @@ use next_rc_shared::{Runtime as RuntimeTrait};
 #[napi]
 pub struct EbpfRuntimeBridge {
     runtime: Arc<EbpfRuntime>,
+    /// Keeps the tokio runtime shared with other environments alive
+    _context: Arc<SharedRuntime>,
     programs: Arc<RwLock<HashMap<String, Arc<dyn Send + Sync>>>>,
 }
 
@@ -21,12 +24,14 @@ pub struct EbpfRuntimeBridge {
 impl EbpfRuntimeBridge {
     /// Create a new eBPF runtime
     #[napi(constructor)]
-    pub fn new() -> Result<Self> {
+    pub fn new(mut env: Env) -> Result<Self> {
+        let context = crate::context::attach(&mut env)?;
         let runtime = EbpfRuntime::new()
             .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to create eBPF runtime: {}", e)))?;
         
         Ok(Self {
             runtime: Arc::new(runtime),
+            _context: context,
             programs: Arc::new(RwLock::new(HashMap::new())),
         })
     }
@@ -49,38 +54,52 @@ impl EbpfRuntimeBridge {
             .await
             .map_err(|e| Error::new(Status::GenericFailure, format!("eBPF compilation failed: {}", e)))?;
         
-        Ok(ModuleId {
-            id: module_id.0.to_string(),
-        })
+        Ok(module_id.into())
+    }
+
+    /// Compile eBPF code, returning structured diagnostics instead of failing
+    #[napi]
+    pub async fn compile_with_diagnostics(&self, code: String, language: Language) -> Result<CompileOutput> {
+        Ok(self.runtime.compile(code.as_bytes(), language.into()).await.into())
+    }
+
+    /// Verify eBPF code without caching or loading it
+    #[napi]
+    pub async fn validate(&self, code: String, language: Language, trust_level: TrustLevel) -> Result<ValidationReport> {
+        let report = self.runtime
+            .validate(code.as_bytes(), language.into(), trust_level.into())
+            .await
+            .map_err(|e| Error::new(Status::GenericFailure, format!("eBPF validation failed: {}", e)))?;
+        
+        Ok(report.into())
     }
 
     /// Load and verify eBPF program
     #[napi]
     pub async fn load_program(&self, module_id: ModuleId) -> Result<InstanceId> {
         let runtime = &self.runtime;
-        let shared_module_id = next_rc_shared::ModuleId(
-            uuid::Uuid::parse_str(&module_id.id)
-                .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid module ID: {}", e)))?
-        );
+        let shared_module_id = module_id.parse()?;
         
         let instance_id = runtime
             .instantiate(shared_module_id)
             .await
             .map_err(|e| Error::new(Status::GenericFailure, format!("eBPF program load failed: {}", e)))?;
         
-        Ok(InstanceId {
-            id: instance_id.0.to_string(),
-        })
+        Ok(instance_id.into())
     }
 
-    /// Execute eBPF program with input data
+    /// Execute eBPF program with input data, returning the verdict in
+    /// `output_encoding` (raw bytes when unset)
     #[napi]
-    pub async fn execute_filter(&self, instance_id: InstanceId, input_data: Buffer) -> Result<ExecutionResult> {
+    pub async fn execute_filter(
+        &self,
+        instance_id: InstanceId,
+        input_data: Buffer,
+        output_encoding: Option<OutputEncoding>,
+    ) -> Result<ExecutionResult> {
         let runtime = &self.runtime;
-        let shared_instance_id = next_rc_shared::InstanceId(
-            uuid::Uuid::parse_str(&instance_id.id)
-                .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid instance ID: {}", e)))?
-        );
+        let shared_instance_id = instance_id.parse()?;
+        let output_encoding = output_encoding.as_ref().map(Into::into).unwrap_or_default();
         
         // Convert Buffer to Vec<u8>
         let data: Vec<u8> = input_data.to_vec();
@@ -94,6 +113,9 @@ impl EbpfRuntimeBridge {
                 capabilities: std::collections::HashSet::new(),
                 trust_level: next_rc_shared::TrustLevel::Low,
             },
+            input: Some(data),
+            output_encoding,
+            ..Default::default()
         };
         
         let start = std::time::Instant::now();
@@ -102,26 +124,23 @@ impl EbpfRuntimeBridge {
             .await
             .map_err(|e| Error::new(Status::GenericFailure, format!("eBPF execution failed: {}", e)))?;
         
-        let execution_time = start.elapsed();
+        let exec_result = next_rc_shared::ExecutionResult {
+            execution_time: start.elapsed(),
+            ..exec_result
+        };
 
-        Ok(ExecutionResult {
-            success: exec_result.success,
-            output: exec_result.output.map(|o| String::from_utf8_lossy(&o).to_string()).unwrap_or_default(),
-            error: exec_result.error,
-            execution_time_ms: execution_time.as_nanos() as i64 / 1_000_000, // Convert to ms
-            memory_used_bytes: exec_result.memory_used as i64,
-            exit_code: Some(0),
-        })
+        ExecutionResult::from_shared(exec_result, Some(0), None, output_encoding)
     }
 
     /// Execute eBPF program (general interface)
     #[napi]
     pub async fn execute(&self, instance_id: InstanceId, config: ExecutionConfig) -> Result<ExecutionResult> {
-        let shared_instance_id = next_rc_shared::InstanceId(
-            uuid::Uuid::parse_str(&instance_id.id)
-                .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid instance ID: {}", e)))?
-        );
+        let shared_instance_id = instance_id.parse()?;
         
+        let process_limits = config.process_limits();
+        let output_encoding = config.output_encoding();
+        let trace_verbosity = config.trace_verbosity();
+        let tenant_id = config.tenant_id.clone();
         let shared_config = next_rc_shared::ExecutionConfig {
             timeout: std::time::Duration::from_millis(config.timeout_ms as u64),
             memory_limit: config.memory_limit_bytes as usize,
@@ -129,6 +148,15 @@ impl EbpfRuntimeBridge {
                 capabilities: std::collections::HashSet::new(),
                 trust_level: config.trust_level.into(),
             },
+            tenant_id: config.tenant_id,
+            tags: config.tags.unwrap_or_default(),
+            virtual_time: config.virtual_time.map(Into::into),
+            log_limits: Default::default(),
+            process_limits,
+            input: config.input.map(|input| input.to_vec()),
+            affinity_keys: Vec::new(),
+            output_encoding,
+            trace_verbosity,
         };
 
         let start = std::time::Instant::now();
@@ -140,34 +168,28 @@ impl EbpfRuntimeBridge {
                 .map_err(|e| Error::new(Status::GenericFailure, format!("eBPF execution failed: {}", e)))?
         };
         
-        let execution_time = start.elapsed();
+        let result = next_rc_shared::ExecutionResult {
+            execution_time: start.elapsed(),
+            ..result
+        };
 
-        Ok(ExecutionResult {
-            success: result.success,
-            output: result.output.map(|o| String::from_utf8_lossy(&o).to_string()).unwrap_or_default(),
-            error: result.error,
-            execution_time_ms: execution_time.as_nanos() as i64 / 1_000_000,
-            memory_used_bytes: result.memory_used as i64,
-            exit_code: Some(0),
-        })
+        ExecutionResult::from_shared(result, Some(0), tenant_id.as_deref(), output_encoding)
     }
 
     /// Unload eBPF program
     #[napi]
     pub async fn destroy(&self, instance_id: InstanceId) -> Result<()> {
         let runtime = &self.runtime;
-        let shared_instance_id = next_rc_shared::InstanceId(
-            uuid::Uuid::parse_str(&instance_id.id)
-                .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid instance ID: {}", e)))?
-        );
+        let shared_instance_id = instance_id.parse()?;
         
+        let id = shared_instance_id.to_string();
         runtime
             .destroy(shared_instance_id)
             .await
             .map_err(|e| Error::new(Status::GenericFailure, format!("eBPF destroy failed: {}", e)))?;
 
         // Remove from tracking
-        self.programs.write().remove(&instance_id.id);
+        self.programs.write().remove(&id);
         
         Ok(())
     }
@@ -211,6 +233,20 @@ impl EbpfRuntimeBridge {
         Ok(true)
     }
 
+    /// Languages this runtime can compile on this host, and the toolchains
+    /// it found or is missing
+    #[napi]
+    pub fn get_capabilities(&self) -> Result<serde_json::Value> {
+        serde_json::to_value(self.runtime.capabilities())
+            .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
+    }
+
+    /// Executions running and queued on this runtime
+    #[napi]
+    pub fn current_load(&self) -> RuntimeLoad {
+        self.runtime.current_load().into()
+    }
+
     /// Get eBPF JIT compilation statistics
     #[napi]
     pub async fn get_jit_stats(&self) -> Result<serde_json::Value> {
@@ -223,14 +259,78 @@ impl EbpfRuntimeBridge {
         }))
     }
 
+    /// Dump the current contents of an eBPF map
+    #[napi]
+    pub async fn dump_map(&self, map_name: String) -> Result<serde_json::Value> {
+        let snapshot = self.runtime
+            .dump_map(&map_name)
+            .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+        
+        let entries: Vec<serde_json::Value> = snapshot.entries.iter().map(|entry| {
+            serde_json::json!({
+                "key": entry.key_hex(),
+                "value": entry.value.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+                "counter": entry.counter_value(),
+            })
+        }).collect();
+        
+        Ok(serde_json::json!({
+            "name": snapshot.name,
+            "entries": entries,
+        }))
+    }
+
+    /// Get execution counts, accept/drop ratios and latency of one eBPF program
+    #[napi]
+    pub async fn program_stats(&self, module_id: ModuleId) -> Result<serde_json::Value> {
+        let shared_module_id = module_id.parse()?;
+        
+        let stats = self.runtime
+            .program_stats(&shared_module_id)
+            .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+        
+        Ok(serde_json::json!({
+            "executions": stats.executions,
+            "accepted": stats.accepted,
+            "dropped": stats.dropped,
+            "errors": stats.errors,
+            "accept_ratio": stats.accept_ratio(),
+            "drop_ratio": stats.drop_ratio(),
+            "latency_ns": {
+                "mean": stats.latency.mean_ns(),
+                "p50": stats.latency.quantile_ns(0.5),
+                "p99": stats.latency.quantile_ns(0.99),
+                "max": stats.latency.max_ns,
+                "buckets": stats.latency.buckets.iter().filter(|(_, count)| *count > 0).collect::<Vec<_>>(),
+            },
+        }))
+    }
+
+    /// Serialize a compiled program for loading on another node
+    #[napi]
+    pub fn export_program(&self, module_id: ModuleId) -> Result<Buffer> {
+        let bytes = self.runtime
+            .export_program(&module_id.parse()?)
+            .map_err(|e| Error::new(Status::InvalidArg, e.to_string()))?;
+        
+        Ok(bytes.into())
+    }
+
+    /// Verify and load a program from `exportProgram`, keeping its module ID
+    #[napi]
+    pub fn import_program(&self, bytes: Buffer) -> Result<ModuleId> {
+        let module_id = self.runtime
+            .import_program(&bytes)
+            .map_err(|e| Error::new(Status::InvalidArg, format!("eBPF import failed: {}", e)))?;
+        
+        Ok(module_id.into())
+    }
+
     /// Enable eBPF program tracing for debugging
     #[napi]
     pub async fn enable_tracing(&self, instance_id: InstanceId) -> Result<()> {
         let runtime = &self.runtime;
-        let shared_instance_id = next_rc_shared::InstanceId(
-            uuid::Uuid::parse_str(&instance_id.id)
-                .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid instance ID: {}", e)))?
-        );
+        let shared_instance_id = instance_id.parse()?;
         
         // Tracing not implemented yet
         Ok(())
@@ -1,3 +1,5 @@
+mod context;
+mod redaction;
 #[cfg(feature = "wasm")]
 mod wasm_bridge;
 #[cfg(feature = "ebpf")]
@@ -10,6 +12,7 @@ use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
 pub use types::*;
+pub use redaction::*;
 #[cfg(feature = "wasm")]
 pub use wasm_bridge::*;
 #[cfg(feature = "ebpf")]
@@ -17,23 +20,12 @@ pub use ebpf_bridge::*;
 #[cfg(feature = "python")]
 pub use python_bridge::*;
 
-use tokio::runtime::Runtime;
-use std::sync::Once;
-
-static INIT: Once = Once::new();
-
-/// Initialize the runtime controller
+/// Initialize the runtime controller for this Node environment. Safe to
+/// call from every worker thread and again after the addon is reloaded;
+/// resources are released when the environment is torn down.
 #[napi]
-pub fn initialize_runtime_controller() -> Result<()> {
-    INIT.call_once(|| {
-        // Initialize tracing
-        tracing_subscriber::fmt::init();
-        
-        // Initialize tokio runtime
-        let rt = Runtime::new().expect("Failed to create tokio runtime");
-        std::mem::forget(rt); // Keep runtime alive for the entire process
-    });
-    
+pub fn initialize_runtime_controller(mut env: Env) -> Result<()> {
+    context::attach(&mut env)?;
     Ok(())
 }
 
@@ -61,6 +53,27 @@ pub fn get_available_runtimes() -> Vec<String> {
     runtimes
 }
 
+/// Get a built-in execution profile ("untrusted", "internal" or "batch")
+#[napi]
+pub fn get_execution_profile(name: String) -> Result<ExecutionProfile> {
+    next_rc_shared::Profile::by_name(&name)
+        .map(Into::into)
+        .ok_or_else(|| Error::new(Status::InvalidArg, format!("Unknown execution profile: {}", name)))
+}
+
+/// Get all built-in execution profiles
+#[napi]
+pub fn list_execution_profiles() -> Vec<ExecutionProfile> {
+    next_rc_shared::Profile::builtin().into_iter().map(Into::into).collect()
+}
+
+/// Infer the language of submitted code from magic bytes, a shebang, the
+/// file name's extension or its content; `language` overrides detection
+#[napi]
+pub fn detect_language(code: Buffer, file_name: Option<String>, language: Option<Language>) -> Option<LanguageDetection> {
+    next_rc_orchestrator::detect_language(&code, file_name.as_deref(), language.map(Into::into)).map(Into::into)
+}
+
 /// Runtime performance metrics
 #[napi(object)]
 pub struct RuntimeMetrics {
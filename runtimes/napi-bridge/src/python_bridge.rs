@@ -1,11 +1,13 @@
 #![cfg(feature = "python")]
 
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
 use napi_derive::napi;
 use std::sync::Arc;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 
+use crate::context::SharedRuntime;
 use crate::types::*;
 use python_runtime::{PythonRuntimeController, PythonExecutionRequest};
 
@@ -23,6 +25,8 @@ impl From<crate::types::TrustLevel> for python_runtime::TrustLevel {
 #[napi]
 pub struct PythonRuntimeBridge {
     runtime: Arc<PythonRuntimeController>,
+    /// Keeps the tokio runtime the controller was created on alive
+    _context: Arc<SharedRuntime>,
     executions: Arc<RwLock<HashMap<String, String>>>, // Store code as String for now
 }
 
@@ -30,9 +34,10 @@ pub struct PythonRuntimeBridge {
 impl PythonRuntimeBridge {
     /// Create a new Python runtime
     #[napi(constructor)]
-    pub fn new() -> Result<Self> {
+    pub fn new(mut env: Env) -> Result<Self> {
+        let context = crate::context::attach(&mut env)?;
         // Initialize with default concurrency
-        let runtime = tokio::runtime::Handle::current()
+        let runtime = context
             .block_on(async {
                 PythonRuntimeController::new(10).await
             })
@@ -42,6 +47,7 @@ impl PythonRuntimeBridge {
 
         Ok(Self {
             runtime: runtime_arc,
+            _context: context,
             executions: Arc::new(RwLock::new(HashMap::new())),
         })
     }
@@ -53,11 +59,34 @@ impl PythonRuntimeBridge {
         Ok(())
     }
 
-    /// Execute Python code directly
+    /// Start `count` Python interpreters ahead of expected traffic
     #[napi]
-    pub async fn execute_python(&self, code: String, config: ExecutionConfig) -> Result<ExecutionResult> {
+    pub async fn provision(&self, count: u32) -> Result<ProvisionReport> {
+        let report = self.runtime
+            .provision(count as usize)
+            .await
+            .map_err(|e| Error::new(Status::GenericFailure, format!("Provisioning failed: {}", e)))?;
+        
+        Ok(report.into())
+    }
+
+    /// Execute Python code directly, with `stdin`, or else the config's
+    /// input, served to `input()`
+    #[napi]
+    pub async fn execute_python(&self, code: String, config: ExecutionConfig, stdin: Option<String>) -> Result<ExecutionResult> {
         let runtime = &self.runtime;
         
+        let stdin = match (stdin, &config.input) {
+            (Some(stdin), _) => Some(stdin),
+            (None, Some(input)) => Some(String::from_utf8(input.to_vec()).map_err(|e| {
+                Error::new(Status::InvalidArg, format!("Python input must be UTF-8 text: {}", e))
+            })?),
+            (None, None) => None,
+        };
+        let process_limits = config.process_limits();
+        let output_encoding = config.output_encoding();
+        let trace_verbosity = config.trace_verbosity();
+        let tenant_id = config.tenant_id.clone();
         let request = PythonExecutionRequest {
             id: uuid::Uuid::new_v4(),
             code,
@@ -67,33 +96,62 @@ impl PythonRuntimeBridge {
             memory_limit_mb: (config.memory_limit_bytes / (1024 * 1024)) as u64,
             environment: HashMap::new(),
             requirements: vec![],
+            stdin,
+            virtual_time: config.virtual_time.map(Into::into),
+            log_limits: trace_verbosity.log_limits(Default::default()),
+            process_limits,
         };
 
         let result = runtime.execute(request)
             .await
             .map_err(|e| Error::new(Status::GenericFailure, format!("Python execution failed: {}", e)))?;
 
-        Ok(ExecutionResult {
+        let output = output_encoding
+            .encode(result.output.into_bytes())
+            .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+        let exit_code = result.exit_code;
+        let shared_result = next_rc_shared::ExecutionResult {
             success: result.success,
-            output: result.output,
+            output: Some(output),
             error: result.error,
-            execution_time_ms: result.execution_time_ms as i64,
-            memory_used_bytes: (result.memory_used_mb * 1024 * 1024) as i64,
-            exit_code: result.exit_code,
-        })
+            execution_time: std::time::Duration::from_millis(result.execution_time_ms),
+            memory_used: (result.memory_used_mb * 1024 * 1024) as usize,
+            logs: result.logs,
+            attestation: None,
+            environment_fingerprint: result.environment_fingerprint,
+            timing: result.timing,
+        };
+
+        ExecutionResult::from_shared(shared_result, exit_code, tenant_id.as_deref(), output_encoding)
     }
 
 
+    /// Start an interactive session whose variables persist between inputs
+    #[napi]
+    pub fn create_repl(&self, trust_level: TrustLevel, limits: Option<ReplLimits>) -> Result<PythonRepl> {
+        let repl = self.runtime
+            .create_repl(trust_level.into(), limits.map(Into::into))
+            .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to start REPL: {}", e)))?;
+
+        Ok(PythonRepl { repl: Arc::new(repl) })
+    }
+
     /// Compile Python code (placeholder for future optimization)
     #[napi]
     pub async fn compile(&self, code: String, language: Language) -> Result<ModuleId> {
         // For Python, compilation is mostly a validation step
-        let module_id = uuid::Uuid::new_v4().to_string();
+        let module_id = next_rc_shared::ModuleId(uuid::Uuid::new_v4());
         
         // Validate Python syntax
         self.validate_python_syntax(&code)?;
         
-        Ok(ModuleId { id: module_id })
+        Ok(module_id.into())
+    }
+
+    /// Run security and syntax checks without executing
+    #[napi]
+    pub async fn validate(&self, code: String, trust_level: TrustLevel) -> Result<ValidationReport> {
+        Ok(self.runtime.validate(&code, &trust_level.into()).into())
     }
 
     /// Execute code using the common Runtime interface
@@ -101,14 +159,21 @@ impl PythonRuntimeBridge {
     pub async fn execute(&self, instance_id: InstanceId, config: ExecutionConfig) -> Result<ExecutionResult> {
         // For Python runtime, we don't use separate instantiation
         // Execute directly with the instance_id as code reference
+        let instance_id = instance_id.parse()?;
         let executions = self.executions.read();
         
-        if let Some(_execution_context) = executions.get(&instance_id.id) {
+        if let Some(_execution_context) = executions.get(&instance_id.to_string()) {
             // Execute stored code context
             return Err(Error::new(Status::GenericFailure, "Stored execution context not yet implemented".to_string()));
         }
         
-        Err(Error::new(Status::InvalidArg, format!("Instance not found: {}", instance_id.id)))
+        Err(Error::new(Status::InvalidArg, format!("Instance not found: {}", instance_id)))
+    }
+
+    /// Executions running and queued for one of the controller's slots
+    #[napi]
+    pub fn current_load(&self) -> RuntimeLoad {
+        self.runtime.current_load().into()
     }
 
     /// Get Python runtime status
@@ -164,3 +229,106 @@ impl PythonRuntimeBridge {
     }
 }
 
+
+#[napi(object)]
+pub struct ReplLimits {
+    pub timeout_ms: u32,
+    pub memory_limit_mb: u32,
+    pub max_output_bytes: u32,
+    pub max_inputs: u32,
+}
+
+impl From<ReplLimits> for python_runtime::ReplLimits {
+    fn from(limits: ReplLimits) -> Self {
+        Self {
+            timeout_ms: limits.timeout_ms as u64,
+            memory_limit_mb: limits.memory_limit_mb as u64,
+            max_output_bytes: limits.max_output_bytes as usize,
+            max_inputs: limits.max_inputs as u64,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct ReplOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+    pub error: Option<String>,
+    pub truncated: bool,
+    pub execution_time_ms: i64,
+}
+
+impl From<python_runtime::ReplOutput> for ReplOutput {
+    fn from(output: python_runtime::ReplOutput) -> Self {
+        Self {
+            success: output.success,
+            stdout: output.stdout,
+            stderr: output.stderr,
+            error: output.error,
+            truncated: output.truncated,
+            execution_time_ms: output.execution_time_ms as i64,
+        }
+    }
+}
+
+/// Output written while an input is still running
+#[napi(object)]
+pub struct ReplChunk {
+    /// `stdout` or `stderr`
+    pub stream: String,
+    pub text: String,
+}
+
+/// Interactive Python session
+#[napi]
+pub struct PythonRepl {
+    repl: Arc<python_runtime::PythonRepl>,
+}
+
+#[napi]
+impl PythonRepl {
+    #[napi(getter)]
+    pub fn id(&self) -> String {
+        self.repl.id().to_string()
+    }
+
+    /// Run one input, passing its output to `on_output` as it is written
+    #[napi]
+    pub async fn eval(&self, code: String, on_output: Option<ThreadsafeFunction<ReplChunk, ErrorStrategy::Fatal>>) -> Result<ReplOutput> {
+        let result = match on_output {
+            Some(callback) => {
+                self.repl
+                    .eval_streaming(&code, move |stream, text| {
+                        let stream = match stream {
+                            python_runtime::ReplStream::Stdout => "stdout",
+                            python_runtime::ReplStream::Stderr => "stderr",
+                        };
+                        let chunk = ReplChunk { stream: stream.to_string(), text: text.to_string() };
+                        callback.call(chunk, ThreadsafeFunctionCallMode::NonBlocking);
+                    })
+                    .await
+            }
+            None => self.repl.eval(&code).await,
+        };
+
+        result
+            .map(Into::into)
+            .map_err(|e| Error::new(Status::GenericFailure, format!("REPL evaluation failed: {}", e)))
+    }
+
+    /// Candidate completions for the name or attribute being typed
+    #[napi]
+    pub async fn complete(&self, text: String) -> Result<Vec<String>> {
+        self.repl
+            .complete(&text)
+            .await
+            .map_err(|e| Error::new(Status::GenericFailure, format!("REPL completion failed: {}", e)))
+    }
+
+    /// End the session and release its interpreter state
+    #[napi]
+    pub fn close(&self) {
+        self.repl.close();
+    }
+}
@@ -0,0 +1,118 @@
+use napi::{Error, Result, Status};
+use napi_derive::napi;
+use next_rc_shared::{OutputEncoding, RedactionPolicy, Redactor};
+use parking_lot::RwLock;
+use std::sync::{Arc, LazyLock};
+
+/// Redactors every bridge applies to the results it returns, shared by
+/// all environments that loaded the addon
+static POLICY: LazyLock<RwLock<RedactionPolicy>> = LazyLock::new(Default::default);
+
+/// PII scrubbing applied to results before they are returned
+#[napi(object)]
+pub struct RedactionConfig {
+    /// Redact email addresses, US social security numbers, payment card
+    /// numbers and IPv4 addresses
+    pub builtin_detectors: Option<bool>,
+    /// Further kinds of PII, each found by a regular expression
+    pub patterns: Option<Vec<RedactionPattern>>,
+}
+
+#[napi(object)]
+pub struct RedactionPattern {
+    /// Shown in the `[REDACTED:<name>]` markers replacing matches
+    pub name: String,
+    pub pattern: String,
+}
+
+impl TryFrom<RedactionConfig> for Redactor {
+    type Error = Error;
+
+    fn try_from(config: RedactionConfig) -> Result<Self> {
+        let redactor = match config.builtin_detectors.unwrap_or(true) {
+            true => Redactor::with_builtin_detectors(),
+            false => Redactor::new(),
+        };
+        config.patterns.unwrap_or_default().into_iter().try_fold(redactor, |redactor, pattern| {
+            redactor
+                .with_pattern(pattern.name.as_str(), &pattern.pattern)
+                .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid redaction pattern {:?}: {}", pattern.name, e)))
+        })
+    }
+}
+
+/// Redact the results of `tenant`'s executions with `config`, or, without
+/// a tenant, those of tenants that have no config of their own. A null
+/// config stops redacting them
+#[napi]
+pub fn configure_redaction(tenant: Option<String>, config: Option<RedactionConfig>) -> Result<()> {
+    let redactor = config.map(Redactor::try_from).transpose()?;
+    set_redactor(tenant, redactor);
+    Ok(())
+}
+
+fn set_redactor(tenant: Option<String>, redactor: Option<Redactor>) {
+    let redactor = redactor.map(Arc::new);
+    let mut policy = POLICY.write();
+    match (tenant, redactor) {
+        (Some(tenant), Some(redactor)) => {
+            policy.redactors.insert(tenant, redactor);
+        }
+        (Some(tenant), None) => {
+            policy.redactors.remove(&tenant);
+        }
+        (None, redactor) => policy.default_redactor = redactor,
+    }
+}
+
+/// Redacts `result` for `tenant` under the configured policy
+pub fn redact(result: &mut next_rc_shared::ExecutionResult, tenant: Option<&str>, encoding: OutputEncoding) -> usize {
+    POLICY.read().redact_result(result, tenant, encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use next_rc_shared::{ExecutionLogs, ExecutionResult, LogLevel, LogRecord};
+
+    fn result() -> ExecutionResult {
+        ExecutionResult {
+            success: false,
+            output: Some(b"EMP-123456 bob@example.com".to_vec()),
+            error: Some("failed for EMP-123456".to_string()),
+            execution_time: Default::default(),
+            memory_used: 0,
+            logs: ExecutionLogs {
+                records: vec![LogRecord {
+                    level: LogLevel::Info,
+                    message: "looked up EMP-123456".to_string(),
+                    elapsed: Default::default(),
+                }],
+                ..Default::default()
+            },
+            attestation: None,
+            environment_fingerprint: None,
+            timing: None,
+        }
+    }
+
+    #[test]
+    fn test_bridge_results_are_redacted_per_tenant() {
+        let redactor = Redactor::new().with_pattern("employee_id", r"EMP-\d{6}").unwrap();
+        set_redactor(Some("bridge-test".to_string()), Some(redactor));
+
+        let mut redacted = result();
+        assert_eq!(redact(&mut redacted, Some("bridge-test"), OutputEncoding::Utf8), 3);
+        assert_eq!(redacted.output.as_deref(), Some(&b"[REDACTED:employee_id] bob@example.com"[..]));
+        assert_eq!(redacted.error.as_deref(), Some("failed for [REDACTED:employee_id]"));
+        assert_eq!(redacted.logs.records[0].message, "looked up [REDACTED:employee_id]");
+
+        let mut other = result();
+        assert_eq!(redact(&mut other, Some("other-tenant"), OutputEncoding::Utf8), 0);
+        assert_eq!(other.output, result().output);
+
+        set_redactor(Some("bridge-test".to_string()), None);
+        let mut cleared = result();
+        assert_eq!(redact(&mut cleared, Some("bridge-test"), OutputEncoding::Utf8), 0);
+    }
+}
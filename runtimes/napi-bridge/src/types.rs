@@ -1,5 +1,6 @@
 use napi_derive::napi;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 /// Language enum for runtime selection
 #[napi]
@@ -12,6 +13,8 @@ pub enum Language {
     C,
     Cpp,
     Wasm,
+    /// Raw eBPF bytecode
+    Ebpf,
 }
 
 impl From<Language> for next_rc_shared::Language {
@@ -25,6 +28,51 @@ impl From<Language> for next_rc_shared::Language {
             Language::C => next_rc_shared::Language::C,
             Language::Cpp => next_rc_shared::Language::Cpp,
             Language::Wasm => next_rc_shared::Language::Wasm,
+            Language::Ebpf => next_rc_shared::Language::Ebpf,
+        }
+    }
+}
+
+impl From<next_rc_shared::Language> for Language {
+    fn from(lang: next_rc_shared::Language) -> Self {
+        match lang {
+            next_rc_shared::Language::Rust => Language::Rust,
+            next_rc_shared::Language::JavaScript => Language::JavaScript,
+            next_rc_shared::Language::TypeScript => Language::TypeScript,
+            next_rc_shared::Language::Python => Language::Python,
+            next_rc_shared::Language::Go => Language::Go,
+            next_rc_shared::Language::C => Language::C,
+            next_rc_shared::Language::Cpp => Language::Cpp,
+            next_rc_shared::Language::Wasm => Language::Wasm,
+            next_rc_shared::Language::Ebpf => Language::Ebpf,
+        }
+    }
+}
+
+/// Inferred language of submitted code
+#[napi(object)]
+pub struct LanguageDetection {
+    pub language: Language,
+    /// From 0 to 1
+    pub confidence: f64,
+    /// "override", "magic_bytes", "shebang", "extension" or "content"
+    pub method: String,
+}
+
+impl From<next_rc_orchestrator::LanguageDetection> for LanguageDetection {
+    fn from(detection: next_rc_orchestrator::LanguageDetection) -> Self {
+        use next_rc_orchestrator::DetectionMethod;
+        Self {
+            language: detection.language.into(),
+            confidence: detection.confidence as f64,
+            method: match detection.method {
+                DetectionMethod::Override => "override",
+                DetectionMethod::MagicBytes => "magic_bytes",
+                DetectionMethod::Shebang => "shebang",
+                DetectionMethod::Extension => "extension",
+                DetectionMethod::Content => "content",
+            }
+            .to_string(),
         }
     }
 }
@@ -61,6 +109,38 @@ pub struct InstanceId {
     pub id: String,
 }
 
+/// Turns an unparseable ID into an `InvalidArg` error led by its stable code,
+/// e.g. `INVALID_MODULE_ID: ...`
+fn invalid_id(e: next_rc_shared::IdError) -> napi::Error {
+    napi::Error::new(napi::Status::InvalidArg, format!("{}: {}", e.code(), e))
+}
+
+impl ModuleId {
+    /// Parses the ID in any form `next_rc_shared::ModuleId` accepts
+    pub fn parse(&self) -> napi::Result<next_rc_shared::ModuleId> {
+        self.id.parse().map_err(invalid_id)
+    }
+}
+
+impl InstanceId {
+    /// Parses the ID in any form `next_rc_shared::InstanceId` accepts
+    pub fn parse(&self) -> napi::Result<next_rc_shared::InstanceId> {
+        self.id.parse().map_err(invalid_id)
+    }
+}
+
+impl From<next_rc_shared::ModuleId> for ModuleId {
+    fn from(module_id: next_rc_shared::ModuleId) -> Self {
+        Self { id: module_id.to_string() }
+    }
+}
+
+impl From<next_rc_shared::InstanceId> for InstanceId {
+    fn from(instance_id: next_rc_shared::InstanceId) -> Self {
+        Self { id: instance_id.to_string() }
+    }
+}
+
 /// Execution configuration
 #[napi(object)]
 pub struct ExecutionConfig {
@@ -69,17 +149,331 @@ pub struct ExecutionConfig {
     pub trust_level: TrustLevel,
     pub network_access: bool,
     pub filesystem_access: bool,
+    pub tenant_id: Option<String>,
+    pub tags: Option<HashMap<String, String>>,
+    pub virtual_time: Option<VirtualTime>,
+    /// Most descriptors the guest may hold open
+    pub max_open_files: Option<i64>,
+    /// Most processes and threads the guest may run
+    pub max_processes: Option<i64>,
+    /// Form of the result's output; raw bytes when unset
+    pub output_encoding: Option<OutputEncoding>,
+    /// How much guest logging and runtime tracing the result carries;
+    /// basic when unset
+    pub trace_verbosity: Option<TraceVerbosity>,
+    /// Bytes handed to the guest: packet data for eBPF programs, stdin for
+    /// Python and `env.read_input` for WASM modules
+    pub input: Option<napi::bindgen_prelude::Buffer>,
+}
+
+impl ExecutionConfig {
+    pub fn process_limits(&self) -> next_rc_shared::ProcessLimits {
+        next_rc_shared::ProcessLimits {
+            max_open_files: self.max_open_files.map(|max| max.max(0) as u64),
+            max_processes: self.max_processes.map(|max| max.max(0) as u64),
+        }
+    }
+
+    pub fn output_encoding(&self) -> next_rc_shared::OutputEncoding {
+        self.output_encoding.as_ref().map(Into::into).unwrap_or_default()
+    }
+
+    pub fn trace_verbosity(&self) -> next_rc_shared::TraceVerbosity {
+        match &self.trace_verbosity {
+            Some(TraceVerbosity::Off) => next_rc_shared::TraceVerbosity::Off,
+            Some(TraceVerbosity::Basic) | None => next_rc_shared::TraceVerbosity::Basic,
+            Some(TraceVerbosity::Verbose) => next_rc_shared::TraceVerbosity::Verbose,
+        }
+    }
+}
+
+/// Form an execution's output is returned in
+#[napi]
+pub enum OutputEncoding {
+    /// Text in `output`; output that isn't valid UTF-8 fails the execution
+    Utf8,
+    /// Base64 text in `output`
+    Base64,
+    /// A JSON document in `output`
+    Json,
+    /// An Arrow IPC file or stream in `outputBytes`
+    Arrow,
+    /// The raw bytes in `outputBytes`
+    Bytes,
+}
+
+impl From<&OutputEncoding> for next_rc_shared::OutputEncoding {
+    fn from(encoding: &OutputEncoding) -> Self {
+        match encoding {
+            OutputEncoding::Utf8 => next_rc_shared::OutputEncoding::Utf8,
+            OutputEncoding::Base64 => next_rc_shared::OutputEncoding::Base64,
+            OutputEncoding::Json => next_rc_shared::OutputEncoding::Json,
+            OutputEncoding::Arrow => next_rc_shared::OutputEncoding::Arrow,
+            OutputEncoding::Bytes => next_rc_shared::OutputEncoding::Bytes,
+        }
+    }
+}
+
+/// How much guest logging and runtime tracing an execution returns
+#[napi]
+pub enum TraceVerbosity {
+    /// Neither guest logs nor trace events
+    Off,
+    /// Guest logs within the default limits and the main trace events
+    Basic,
+    /// Guest logs down to trace level and every trace event
+    Verbose,
+}
+
+/// Splits encoded output between `output`, for text encodings, and
+/// `outputBytes`, for binary ones
+pub fn encoded_output(
+    output: Option<Vec<u8>>,
+    encoding: next_rc_shared::OutputEncoding,
+) -> napi::Result<(String, Option<napi::bindgen_prelude::Buffer>)> {
+    let output = output.unwrap_or_default();
+    if !encoding.is_text() {
+        return Ok((String::new(), Some(output.into())));
+    }
+    String::from_utf8(output)
+        .map(|text| (text, None))
+        .map_err(|e| napi::Error::new(napi::Status::GenericFailure, format!("Output is not valid text: {}", e)))
+}
+
+impl From<next_rc_shared::TrustLevel> for TrustLevel {
+    fn from(trust: next_rc_shared::TrustLevel) -> Self {
+        match trust {
+            next_rc_shared::TrustLevel::Low => TrustLevel::Low,
+            next_rc_shared::TrustLevel::Medium => TrustLevel::Medium,
+            next_rc_shared::TrustLevel::High => TrustLevel::High,
+        }
+    }
+}
+
+/// Named execution defaults
+#[napi(object)]
+pub struct ExecutionProfile {
+    pub name: String,
+    pub config: ExecutionConfig,
+    /// Runtimes to use, most preferred first
+    pub runtimes: Vec<String>,
+}
+
+impl From<next_rc_shared::Profile> for ExecutionProfile {
+    fn from(profile: next_rc_shared::Profile) -> Self {
+        use next_rc_shared::{Capability, RuntimeType};
+
+        let permissions = &profile.permissions;
+        Self {
+            config: ExecutionConfig {
+                timeout_ms: profile.timeout.as_millis() as i64,
+                memory_limit_bytes: profile.memory_limit as i64,
+                trust_level: permissions.trust_level.into(),
+                network_access: permissions.has_capability(Capability::NetworkAccess),
+                filesystem_access: permissions.has_capability(Capability::FileSystemRead),
+                tenant_id: None,
+                tags: None,
+                virtual_time: None,
+                max_open_files: None,
+                max_processes: None,
+                output_encoding: None,
+                trace_verbosity: None,
+                input: None,
+            },
+            runtimes: profile
+                .runtime_preference
+                .iter()
+                .map(|runtime| match runtime {
+                    RuntimeType::Wasm => "wasm",
+                    RuntimeType::Ebpf => "ebpf",
+                    RuntimeType::V8Isolate => "v8",
+                    RuntimeType::Firecracker => "firecracker",
+                    RuntimeType::Python => "python",
+                }.to_string())
+                .collect(),
+            name: profile.name,
+        }
+    }
+}
+
+/// Virtual clock for deterministic executions
+#[napi(object)]
+pub struct VirtualTime {
+    pub start_unix_ms: i64,
+    pub tick_ns: Option<i64>,
+}
+
+impl From<VirtualTime> for next_rc_shared::VirtualTime {
+    fn from(time: VirtualTime) -> Self {
+        Self {
+            start_unix_ms: time.start_unix_ms.max(0) as u64,
+            tick_ns: time.tick_ns.unwrap_or(0).max(0) as u64,
+        }
+    }
 }
 
 /// Execution result
 #[napi(object)]
 pub struct ExecutionResult {
     pub success: bool,
+    /// Output in a text encoding; empty for binary ones
     pub output: String,
+    /// Output in a binary encoding
+    pub output_bytes: Option<napi::bindgen_prelude::Buffer>,
     pub error: Option<String>,
     pub execution_time_ms: i64,
     pub memory_used_bytes: i64,
     pub exit_code: Option<i32>,
+    /// Records the guest emitted through the host logging API
+    pub logs: Vec<LogRecord>,
+    /// Records discarded by the execution's log limits
+    pub logs_dropped: i64,
+    /// Runtime events recorded at the execution's trace verbosity
+    pub trace: Vec<LogRecord>,
+    /// Time spent in each phase, when the runtime reports it
+    pub timing: Option<ExecutionTiming>,
+}
+
+impl ExecutionResult {
+    /// What a bridge returns for `result`: redacted under the tenant's
+    /// redaction config, with its output split by `encoding`
+    pub fn from_shared(
+        mut result: next_rc_shared::ExecutionResult,
+        exit_code: Option<i32>,
+        tenant: Option<&str>,
+        encoding: next_rc_shared::OutputEncoding,
+    ) -> napi::Result<Self> {
+        crate::redaction::redact(&mut result, tenant, encoding);
+        let (output, output_bytes) = encoded_output(result.output, encoding)?;
+        Ok(Self {
+            success: result.success,
+            output,
+            output_bytes,
+            error: result.error,
+            execution_time_ms: result.execution_time.as_millis() as i64,
+            memory_used_bytes: result.memory_used as i64,
+            exit_code,
+            logs: result.logs.records.into_iter().map(Into::into).collect(),
+            logs_dropped: result.logs.dropped as i64,
+            trace: result.logs.trace.into_iter().map(Into::into).collect(),
+            timing: result.timing.map(Into::into),
+        })
+    }
+}
+
+/// Per-phase timings of one request, in fractional milliseconds
+#[napi(object)]
+pub struct ExecutionTiming {
+    pub queue_wait_ms: f64,
+    pub scheduling_ms: f64,
+    pub sandbox_setup_ms: f64,
+    pub compile_ms: f64,
+    pub instantiate_ms: f64,
+    pub execute_ms: f64,
+    pub teardown_ms: f64,
+}
+
+impl From<next_rc_shared::TimingBreakdown> for ExecutionTiming {
+    fn from(timing: next_rc_shared::TimingBreakdown) -> Self {
+        let ms = |duration: std::time::Duration| duration.as_secs_f64() * 1000.0;
+        Self {
+            queue_wait_ms: ms(timing.queue_wait),
+            scheduling_ms: ms(timing.scheduling),
+            sandbox_setup_ms: ms(timing.sandbox_setup),
+            compile_ms: ms(timing.compile),
+            instantiate_ms: ms(timing.instantiate),
+            execute_ms: ms(timing.execute),
+            teardown_ms: ms(timing.teardown),
+        }
+    }
+}
+
+/// Guest log record
+#[napi(object)]
+pub struct LogRecord {
+    pub level: String, // "trace", "debug", "info", "warn", "error"
+    pub message: String,
+    pub elapsed_ms: f64,
+}
+
+impl From<next_rc_shared::LogRecord> for LogRecord {
+    fn from(record: next_rc_shared::LogRecord) -> Self {
+        Self {
+            level: record.level.to_string(),
+            message: record.message,
+            elapsed_ms: record.elapsed.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+/// Compiler or validation finding
+#[napi(object)]
+pub struct Diagnostic {
+    pub severity: String, // "error", "warning", "info"
+    pub message: String,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub snippet: Option<String>,
+}
+
+impl From<next_rc_shared::Diagnostic> for Diagnostic {
+    fn from(diagnostic: next_rc_shared::Diagnostic) -> Self {
+        Self {
+            severity: match diagnostic.severity {
+                next_rc_shared::Severity::Error => "error",
+                next_rc_shared::Severity::Warning => "warning",
+                next_rc_shared::Severity::Info => "info",
+            }
+            .to_string(),
+            message: diagnostic.message,
+            file: diagnostic.file,
+            line: diagnostic.line,
+            column: diagnostic.column,
+            snippet: diagnostic.snippet,
+        }
+    }
+}
+
+/// Validate-only result
+#[napi(object)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl From<next_rc_shared::ValidationReport> for ValidationReport {
+    fn from(report: next_rc_shared::ValidationReport) -> Self {
+        Self {
+            valid: report.valid,
+            diagnostics: report.diagnostics.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// Compilation result with structured diagnostics
+#[napi(object)]
+pub struct CompileOutput {
+    pub module_id: Option<ModuleId>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl From<anyhow::Result<next_rc_shared::ModuleId>> for CompileOutput {
+    fn from(result: anyhow::Result<next_rc_shared::ModuleId>) -> Self {
+        match result {
+            Ok(module_id) => Self {
+                module_id: Some(module_id.into()),
+                diagnostics: Vec::new(),
+            },
+            Err(e) => Self {
+                module_id: None,
+                diagnostics: next_rc_shared::CompileError::diagnostics_of(&e)
+                    .into_iter()
+                    .map(Into::into)
+                    .collect(),
+            },
+        }
+    }
 }
 
 /// Runtime status
@@ -94,6 +488,54 @@ pub struct RuntimeStatus {
     pub avg_execution_time_ms: f64,
 }
 
+/// Readiness of pre-provisioned instances
+#[napi(object)]
+pub struct ProvisionReport {
+    pub requested: u32,
+    pub ready: u32,
+    pub pooled: u32,
+    pub failures: Vec<String>,
+    pub elapsed_ms: i64,
+}
+
+impl From<next_rc_shared::ProvisionReport> for ProvisionReport {
+    fn from(report: next_rc_shared::ProvisionReport) -> Self {
+        Self {
+            requested: report.requested as u32,
+            ready: report.ready as u32,
+            pooled: report.pooled as u32,
+            failures: report.failures,
+            elapsed_ms: report.elapsed.as_millis() as i64,
+        }
+    }
+}
+
+/// Executions running and queued on a runtime, for shedding load early
+#[napi(object)]
+pub struct RuntimeLoad {
+    pub in_flight: u32,
+    pub queued: u32,
+    /// Slots free right now; absent when executions aren't limited
+    pub available_slots: Option<u32>,
+    pub capacity: Option<u32>,
+    pub estimated_wait_ms: f64,
+    /// Whether a new execution would have to queue
+    pub saturated: bool,
+}
+
+impl From<next_rc_shared::LoadReport> for RuntimeLoad {
+    fn from(load: next_rc_shared::LoadReport) -> Self {
+        Self {
+            in_flight: load.in_flight as u32,
+            queued: load.queued as u32,
+            available_slots: load.available_slots.map(|slots| slots as u32),
+            capacity: load.capacity.map(|capacity| capacity as u32),
+            estimated_wait_ms: load.estimated_wait.as_secs_f64() * 1000.0,
+            saturated: load.saturated(),
+        }
+    }
+}
+
 /// Workload hint for intelligent scheduling
 #[napi(object)]
 pub struct WorkloadHint {
@@ -6,6 +6,7 @@ use std::sync::Arc;
 use parking_lot::RwLock;
 use std::collections::HashMap;
 
+use crate::context::SharedRuntime;
 use crate::types::*;
 use wasm_runtime::{WasmRuntime, WasmConfig};
 use next_rc_shared::{Runtime as RuntimeTrait};
@@ -14,21 +15,28 @@ use next_rc_shared::{Runtime as RuntimeTrait};
 #[napi]
 pub struct WasmRuntimeBridge {
     runtime: Arc<WasmRuntime>,
+    /// Keeps the tokio runtime shared with other environments alive
+    _context: Arc<SharedRuntime>,
     instances: Arc<RwLock<HashMap<String, Arc<dyn Send + Sync>>>>,
+    /// Provisioned instance IDs per module, handed out by `instantiate`
+    pool: Arc<RwLock<HashMap<String, Vec<String>>>>,
 }
 
 #[napi]
 impl WasmRuntimeBridge {
     /// Create a new WASM runtime
     #[napi(constructor)]
-    pub fn new() -> Result<Self> {
+    pub fn new(mut env: Env) -> Result<Self> {
+        let context = crate::context::attach(&mut env)?;
         let config = WasmConfig::default();
         let runtime = WasmRuntime::new(config)
             .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to create WASM runtime: {}", e)))?;
         
         Ok(Self {
             runtime: Arc::new(runtime),
+            _context: context,
             instances: Arc::new(RwLock::new(HashMap::new())),
+            pool: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
@@ -49,39 +57,82 @@ impl WasmRuntimeBridge {
             .await
             .map_err(|e| Error::new(Status::GenericFailure, format!("Compilation failed: {}", e)))?;
         
-        Ok(ModuleId {
-            id: module_id.0.to_string(),
-        })
+        Ok(module_id.into())
+    }
+
+    /// Compile code, returning structured diagnostics instead of failing
+    #[napi]
+    pub async fn compile_with_diagnostics(&self, code: String, language: Language) -> Result<CompileOutput> {
+        Ok(self.runtime.compile(code.as_bytes(), language.into()).await.into())
     }
 
-    /// Instantiate a compiled module
+    /// Validate code without compiling it into the module cache
+    #[napi]
+    pub async fn validate(&self, code: String, language: Language, trust_level: TrustLevel) -> Result<ValidationReport> {
+        let report = self.runtime
+            .validate(code.as_bytes(), language.into(), trust_level.into())
+            .await
+            .map_err(|e| Error::new(Status::GenericFailure, format!("Validation failed: {}", e)))?;
+        
+        Ok(report.into())
+    }
+
+    /// Instantiate a compiled module, reusing a provisioned instance if available
     #[napi]
     pub async fn instantiate(&self, module_id: ModuleId) -> Result<InstanceId> {
-        let runtime = &self.runtime;
-        let shared_module_id = next_rc_shared::ModuleId(
-            uuid::Uuid::parse_str(&module_id.id)
-                .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid module ID: {}", e)))?
-        );
+        let shared_module_id = module_id.parse()?;
+        if let Some(id) = self.pool.write().get_mut(&shared_module_id.to_string()).and_then(Vec::pop) {
+            return Ok(InstanceId { id });
+        }
         
+        let runtime = &self.runtime;
         let instance_id = runtime
             .instantiate(shared_module_id)
             .await
             .map_err(|e| Error::new(Status::GenericFailure, format!("Instantiation failed: {}", e)))?;
         
-        Ok(InstanceId {
-            id: instance_id.0.to_string(),
-        })
+        Ok(instance_id.into())
+    }
+
+    /// Pre-instantiate `count` instances of a module ahead of expected traffic
+    #[napi]
+    pub async fn provision(&self, module_id: ModuleId, count: u32) -> Result<ProvisionReport> {
+        let shared_module_id = module_id.parse()?;
+        
+        let start = std::time::Instant::now();
+        let mut ready = Vec::new();
+        let mut failures = Vec::new();
+        for _ in 0..count {
+            match self.runtime.instantiate(shared_module_id.clone()).await {
+                Ok(instance_id) => ready.push(instance_id.to_string()),
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+        
+        let mut pool = self.pool.write();
+        let pooled = pool.entry(shared_module_id.to_string()).or_default();
+        let created = ready.len();
+        pooled.extend(ready);
+        
+        Ok(next_rc_shared::ProvisionReport {
+            requested: count as usize,
+            ready: created,
+            pooled: pooled.len(),
+            failures,
+            elapsed: start.elapsed(),
+        }.into())
     }
 
     /// Execute code in an instance
     #[napi]
     pub async fn execute(&self, instance_id: InstanceId, config: ExecutionConfig) -> Result<ExecutionResult> {
         let runtime = &self.runtime;
-        let shared_instance_id = next_rc_shared::InstanceId(
-            uuid::Uuid::parse_str(&instance_id.id)
-                .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid instance ID: {}", e)))?
-        );
+        let shared_instance_id = instance_id.parse()?;
         
+        let process_limits = config.process_limits();
+        let output_encoding = config.output_encoding();
+        let trace_verbosity = config.trace_verbosity();
+        let tenant_id = config.tenant_id.clone();
         let shared_config = next_rc_shared::ExecutionConfig {
             timeout: std::time::Duration::from_millis(config.timeout_ms as u64),
             memory_limit: config.memory_limit_bytes as usize,
@@ -89,6 +140,15 @@ impl WasmRuntimeBridge {
                 capabilities: std::collections::HashSet::new(), // TODO: Map capabilities
                 trust_level: config.trust_level.into(),
             },
+            tenant_id: config.tenant_id,
+            tags: config.tags.unwrap_or_default(),
+            virtual_time: config.virtual_time.map(Into::into),
+            log_limits: Default::default(),
+            process_limits,
+            input: config.input.map(|input| input.to_vec()),
+            affinity_keys: Vec::new(),
+            output_encoding,
+            trace_verbosity,
         };
 
         let result = runtime
@@ -96,32 +156,26 @@ impl WasmRuntimeBridge {
             .await
             .map_err(|e| Error::new(Status::GenericFailure, format!("Execution failed: {}", e)))?;
 
-        Ok(ExecutionResult {
-            success: result.success,
-            output: result.output.map(|o| String::from_utf8_lossy(&o).to_string()).unwrap_or_default(),
-            error: result.error,
-            execution_time_ms: result.execution_time.as_millis() as i64,
-            memory_used_bytes: result.memory_used as i64,
-            exit_code: Some(0),
-        })
+        ExecutionResult::from_shared(result, Some(0), tenant_id.as_deref(), output_encoding)
     }
 
     /// Destroy an instance
     #[napi]
     pub async fn destroy(&self, instance_id: InstanceId) -> Result<()> {
         let runtime = &self.runtime;
-        let shared_instance_id = next_rc_shared::InstanceId(
-            uuid::Uuid::parse_str(&instance_id.id)
-                .map_err(|e| Error::new(Status::InvalidArg, format!("Invalid instance ID: {}", e)))?
-        );
+        let shared_instance_id = instance_id.parse()?;
         
+        let id = shared_instance_id.to_string();
         runtime
             .destroy(shared_instance_id)
             .await
             .map_err(|e| Error::new(Status::GenericFailure, format!("Destroy failed: {}", e)))?;
 
         // Remove from tracking
-        self.instances.write().remove(&instance_id.id);
+        self.instances.write().remove(&id);
+        for pooled in self.pool.write().values_mut() {
+            pooled.retain(|pooled_id| *pooled_id != id);
+        }
         
         Ok(())
     }
@@ -166,6 +220,20 @@ impl WasmRuntimeBridge {
         Ok(())
     }
 
+    /// Languages this runtime can compile on this host, and the toolchains
+    /// it found or is missing
+    #[napi]
+    pub fn get_capabilities(&self) -> Result<serde_json::Value> {
+        serde_json::to_value(self.runtime.capabilities())
+            .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
+    }
+
+    /// Executions running and queued on this runtime
+    #[napi]
+    pub fn current_load(&self) -> RuntimeLoad {
+        self.runtime.current_load().into()
+    }
+
     /// Get memory pool statistics
     #[napi]
     pub async fn get_memory_stats(&self) -> Result<serde_json::Value> {
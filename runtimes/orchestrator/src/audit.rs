@@ -0,0 +1,73 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use next_rc_shared::InstanceId;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::SystemTime;
+use uuid::Uuid;
+
+use crate::policy::{PolicyDecision, PolicyInput};
+
+const DEFAULT_MEMORY_CAPACITY: usize = 10_000;
+
+/// A policy decision together with what it was made on.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AuditRecord {
+    pub id: Uuid,
+    pub decided_at: SystemTime,
+    pub instance_id: InstanceId,
+    pub input: PolicyInput,
+    pub decision: PolicyDecision,
+}
+
+#[async_trait]
+pub trait AuditLog: Send + Sync {
+    async fn record(&self, record: AuditRecord) -> Result<()>;
+    /// Returns decisions made for `tenant_id`, or for everyone when unset,
+    /// most recent first.
+    async fn list(&self, tenant_id: Option<&str>) -> Result<Vec<AuditRecord>>;
+}
+
+/// Bounded in-process log; the oldest records are evicted once full.
+pub struct InMemoryAuditLog {
+    records: RwLock<VecDeque<AuditRecord>>,
+    capacity: usize,
+}
+
+impl InMemoryAuditLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: RwLock::new(VecDeque::with_capacity(capacity.min(DEFAULT_MEMORY_CAPACITY))),
+            capacity,
+        }
+    }
+}
+
+impl Default for InMemoryAuditLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMORY_CAPACITY)
+    }
+}
+
+#[async_trait]
+impl AuditLog for InMemoryAuditLog {
+    async fn record(&self, record: AuditRecord) -> Result<()> {
+        let mut records = self.records.write();
+        while records.len() >= self.capacity.max(1) {
+            records.pop_front();
+        }
+        records.push_back(record);
+        Ok(())
+    }
+
+    async fn list(&self, tenant_id: Option<&str>) -> Result<Vec<AuditRecord>> {
+        let records = self.records.read();
+        Ok(records
+            .iter()
+            .rev()
+            .filter(|record| tenant_id.is_none() || record.input.tenant_id.as_deref() == tenant_id)
+            .cloned()
+            .collect())
+    }
+}
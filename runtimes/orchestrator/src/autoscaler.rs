@@ -0,0 +1,469 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use next_rc_shared::{ModuleId, RuntimeType, ScalablePool};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+use crate::orchestrator::Orchestrator;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoscalerConfig {
+    /// How often traffic is sampled and pools are resized.
+    pub interval: Duration,
+    /// Weight of the newest arrival rate in the smoothed level, from 0 to 1.
+    pub level_smoothing: f64,
+    /// Weight of the newest change in level in the smoothed trend, from 0 to 1.
+    pub trend_smoothing: f64,
+    /// Spare capacity kept above the predicted concurrency, e.g. 0.25 for 25%.
+    pub headroom: f64,
+    /// Consecutive intervals a pool must be oversized before it shrinks.
+    pub scale_down_after: u32,
+}
+
+impl Default for AutoscalerConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(10),
+            level_smoothing: 0.5,
+            trend_smoothing: 0.3,
+            headroom: 0.25,
+            scale_down_after: 3,
+        }
+    }
+}
+
+/// Limits a pool is kept within, and the latency its runtime should meet.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct PoolBounds {
+    pub min: usize,
+    pub max: usize,
+    /// 95th percentile latency beyond which the pool grows regardless of
+    /// the prediction. Unchecked when unset.
+    pub latency_slo: Option<Duration>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScalingReason {
+    /// Sized to the arrival rate predicted for the next interval.
+    PredictedDemand,
+    /// Grown because the runtime missed its latency SLO.
+    SloBreached,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScalingDecision {
+    pub pool: String,
+    pub runtime: RuntimeType,
+    pub from: usize,
+    pub target: usize,
+    /// Size the pool reached, which falls short of `target` when units are
+    /// in use or the resize failed.
+    pub reached: usize,
+    pub reason: ScalingReason,
+    /// Executions per second expected over the next interval.
+    pub predicted_rate: f64,
+    pub p95_latency: Option<Duration>,
+    pub error: Option<String>,
+    pub at: SystemTime,
+}
+
+/// Arrivals and latencies observed for one runtime since the last sample.
+#[derive(Debug, Clone, Default)]
+pub struct TrafficSample {
+    pub arrivals: u64,
+    pub elapsed: Duration,
+    pub latencies: Vec<Duration>,
+}
+
+impl TrafficSample {
+    pub fn rate(&self) -> f64 {
+        match self.elapsed.as_secs_f64() {
+            secs if secs > 0.0 => self.arrivals as f64 / secs,
+            _ => 0.0,
+        }
+    }
+
+    pub fn mean_latency(&self) -> Option<Duration> {
+        let total: Duration = self.latencies.iter().sum();
+        (!self.latencies.is_empty()).then(|| total / self.latencies.len() as u32)
+    }
+
+    pub fn p95_latency(&self) -> Option<Duration> {
+        let mut latencies = self.latencies.clone();
+        latencies.sort_unstable();
+        let index = (latencies.len() * 95).div_ceil(100).checked_sub(1)?;
+        latencies.get(index).copied()
+    }
+}
+
+struct TrafficWindow {
+    arrivals: u64,
+    latencies: Vec<Duration>,
+    since: Instant,
+}
+
+/// Counts executions per runtime between samples. The orchestrator feeds
+/// it every execution it runs.
+pub struct TrafficMonitor {
+    windows: Mutex<HashMap<RuntimeType, TrafficWindow>>,
+}
+
+/// Latencies kept per runtime between samples; arrivals are still counted
+/// past it.
+const MAX_LATENCY_SAMPLES: usize = 4096;
+
+impl TrafficMonitor {
+    pub fn new() -> Self {
+        Self {
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records an execution on `runtime` that took `latency` from arrival,
+    /// queue wait included.
+    pub fn record(&self, runtime: RuntimeType, latency: Duration) {
+        let mut windows = self.windows.lock();
+        let window = windows.entry(runtime).or_insert_with(|| TrafficWindow {
+            arrivals: 0,
+            latencies: Vec::new(),
+            since: Instant::now(),
+        });
+        window.arrivals += 1;
+        if window.latencies.len() < MAX_LATENCY_SAMPLES {
+            window.latencies.push(latency);
+        }
+    }
+
+    /// Traffic on `runtime` since the last call, starting a new window.
+    pub fn sample(&self, runtime: RuntimeType) -> TrafficSample {
+        let mut windows = self.windows.lock();
+        let window = windows.entry(runtime).or_insert_with(|| TrafficWindow {
+            arrivals: 0,
+            latencies: Vec::new(),
+            since: Instant::now(),
+        });
+        let sample = TrafficSample {
+            arrivals: window.arrivals,
+            elapsed: window.since.elapsed(),
+            latencies: std::mem::take(&mut window.latencies),
+        };
+        window.arrivals = 0;
+        window.since = Instant::now();
+        sample
+    }
+}
+
+impl Default for TrafficMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Holt's double exponential smoothing of one runtime's arrival rate.
+#[derive(Debug, Default)]
+struct Forecast {
+    level: Option<f64>,
+    trend: f64,
+    /// Mean latency of the last sample that had one.
+    mean_latency: Option<Duration>,
+}
+
+impl Forecast {
+    /// Folds in the rate observed over the last interval and predicts the
+    /// next one.
+    fn observe(&mut self, rate: f64, config: &AutoscalerConfig) -> f64 {
+        match self.level {
+            None => self.level = Some(rate),
+            Some(level) => {
+                let smoothing = config.level_smoothing;
+                let next = (smoothing * rate + (1.0 - smoothing) * (level + self.trend)).max(0.0);
+                self.trend = config.trend_smoothing * (next - level) + (1.0 - config.trend_smoothing) * self.trend;
+                self.level = Some(next);
+            }
+        }
+        (self.level.unwrap_or_default() + self.trend).max(0.0)
+    }
+}
+
+struct ManagedPool {
+    runtime: RuntimeType,
+    pool: Arc<dyn ScalablePool>,
+    bounds: PoolBounds,
+    /// Consecutive intervals the pool has been larger than needed.
+    oversized: u32,
+}
+
+/// Resizes warm pools to the traffic predicted for each runtime, within
+/// each pool's bounds, and publishes every change as a `ScalingDecision`.
+pub struct Autoscaler {
+    config: AutoscalerConfig,
+    traffic: Arc<TrafficMonitor>,
+    pools: Mutex<Vec<ManagedPool>>,
+    forecasts: Mutex<HashMap<RuntimeType, Forecast>>,
+    decisions: broadcast::Sender<ScalingDecision>,
+}
+
+impl Autoscaler {
+    pub fn new(config: AutoscalerConfig, traffic: Arc<TrafficMonitor>) -> Self {
+        let (decisions, _) = broadcast::channel(64);
+        Self {
+            config,
+            traffic,
+            pools: Mutex::new(Vec::new()),
+            forecasts: Mutex::new(HashMap::new()),
+            decisions,
+        }
+    }
+
+    /// Scales `pool` with the traffic of `runtime`.
+    pub fn add_pool(&self, runtime: RuntimeType, pool: Arc<dyn ScalablePool>, bounds: PoolBounds) {
+        info!("Autoscaling pool {} ({}..={})", pool.name(), bounds.min, bounds.max);
+        self.pools.lock().push(ManagedPool {
+            runtime,
+            pool,
+            bounds,
+            oversized: 0,
+        });
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ScalingDecision> {
+        self.decisions.subscribe()
+    }
+
+    /// Samples traffic and resizes every pool once, returning the changes
+    /// made.
+    pub async fn evaluate(&self) -> Vec<ScalingDecision> {
+        let runtimes: Vec<RuntimeType> = {
+            let mut runtimes: Vec<_> = self.pools.lock().iter().map(|pool| pool.runtime).collect();
+            runtimes.dedup();
+            runtimes
+        };
+
+        let mut predictions = HashMap::new();
+        for runtime in runtimes {
+            if predictions.contains_key(&runtime) {
+                continue;
+            }
+            let sample = self.traffic.sample(runtime);
+            let mut forecasts = self.forecasts.lock();
+            let forecast = forecasts.entry(runtime).or_default();
+            let rate = forecast.observe(sample.rate(), &self.config);
+            forecast.mean_latency = sample.mean_latency().or(forecast.mean_latency);
+            predictions.insert(runtime, (rate, forecast.mean_latency, sample.p95_latency()));
+        }
+
+        let plans: Vec<_> = {
+            let mut pools = self.pools.lock();
+            pools
+                .iter_mut()
+                .filter_map(|managed| {
+                    let (rate, mean_latency, p95_latency) = predictions[&managed.runtime];
+                    let (target, reason) = self.plan(managed, rate, mean_latency, p95_latency)?;
+                    Some((managed.runtime, managed.pool.clone(), target, reason, rate, p95_latency))
+                })
+                .collect()
+        };
+
+        let mut decisions = Vec::with_capacity(plans.len());
+        for (runtime, pool, target, reason, predicted_rate, p95_latency) in plans {
+            let from = pool.size();
+            let (reached, error) = match pool.resize(target).await {
+                Ok(reached) => (reached, None),
+                Err(e) => {
+                    warn!("Failed to resize pool {}: {}", pool.name(), e);
+                    (pool.size(), Some(e.to_string()))
+                }
+            };
+            info!("Scaled pool {} from {} to {} (target {}, {:?})", pool.name(), from, reached, target, reason);
+
+            let decision = ScalingDecision {
+                pool: pool.name(),
+                runtime,
+                from,
+                target,
+                reached,
+                reason,
+                predicted_rate,
+                p95_latency,
+                error,
+                at: SystemTime::now(),
+            };
+            let _ = self.decisions.send(decision.clone());
+            decisions.push(decision);
+        }
+        decisions
+    }
+
+    /// Size `managed` should become, or `None` to leave it alone. The pool
+    /// holds the predicted concurrency (arrival rate times mean latency)
+    /// plus headroom, grows by at least one while the SLO is missed, and
+    /// only shrinks once it has been oversized for `scale_down_after`
+    /// intervals in a row.
+    fn plan(
+        &self,
+        managed: &mut ManagedPool,
+        rate: f64,
+        mean_latency: Option<Duration>,
+        p95_latency: Option<Duration>,
+    ) -> Option<(usize, ScalingReason)> {
+        let size = managed.pool.size();
+        let concurrency = rate * mean_latency.unwrap_or_default().as_secs_f64();
+        let mut target = (concurrency * (1.0 + self.config.headroom)).ceil() as usize;
+        let mut reason = ScalingReason::PredictedDemand;
+
+        let breached = matches!((p95_latency, managed.bounds.latency_slo), (Some(p95), Some(slo)) if p95 > slo);
+        if breached && target <= size {
+            target = size + 1;
+            reason = ScalingReason::SloBreached;
+        }
+        let target = target.clamp(managed.bounds.min, managed.bounds.max.max(managed.bounds.min));
+
+        if target < size {
+            managed.oversized += 1;
+            if managed.oversized < self.config.scale_down_after {
+                return None;
+            }
+        }
+        managed.oversized = 0;
+        (target != size).then_some((target, reason))
+    }
+
+    /// Runs `evaluate` every interval until the handle is aborted.
+    pub fn spawn(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.config.interval);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                self.evaluate().await;
+            }
+        })
+    }
+}
+
+/// The orchestrator's provisioned instances of one module.
+pub struct WarmInstancePool {
+    orchestrator: Arc<Orchestrator>,
+    module_id: ModuleId,
+}
+
+impl WarmInstancePool {
+    pub fn new(orchestrator: Arc<Orchestrator>, module_id: ModuleId) -> Self {
+        Self { orchestrator, module_id }
+    }
+}
+
+#[async_trait]
+impl ScalablePool for WarmInstancePool {
+    fn name(&self) -> String {
+        format!("module:{}", self.module_id)
+    }
+
+    fn size(&self) -> usize {
+        self.orchestrator.pooled_instances(&self.module_id)
+    }
+
+    async fn resize(&self, target: usize) -> Result<usize> {
+        let size = self.size();
+        if target > size {
+            Ok(self.orchestrator.provision(self.module_id.clone(), target - size).await?.pooled)
+        } else {
+            self.orchestrator.drain_pool(&self.module_id, size - target).await?;
+            Ok(self.size())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingPool(AtomicUsize);
+
+    #[async_trait]
+    impl ScalablePool for CountingPool {
+        fn name(&self) -> String {
+            "counting".to_string()
+        }
+
+        fn size(&self) -> usize {
+            self.0.load(Ordering::SeqCst)
+        }
+
+        async fn resize(&self, target: usize) -> Result<usize> {
+            self.0.store(target, Ordering::SeqCst);
+            Ok(target)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scales_with_predicted_traffic() {
+        let traffic = Arc::new(TrafficMonitor::new());
+        let autoscaler = Autoscaler::new(
+            AutoscalerConfig {
+                scale_down_after: 2,
+                ..Default::default()
+            },
+            traffic.clone(),
+        );
+        let pool = Arc::new(CountingPool(AtomicUsize::new(0)));
+        let bounds = PoolBounds {
+            min: 1,
+            max: 8,
+            latency_slo: Some(Duration::from_millis(500)),
+        };
+        autoscaler.add_pool(RuntimeType::Wasm, pool.clone(), bounds);
+        let mut decisions = autoscaler.subscribe();
+
+        // Idle traffic keeps the minimum
+        let decision = &autoscaler.evaluate().await[0];
+        assert_eq!((decision.from, decision.reached), (0, 1));
+        assert_eq!(decisions.recv().await.unwrap().reached, 1);
+
+        // A burst of slow executions grows the pool, capped at the maximum
+        for _ in 0..100 {
+            traffic.record(RuntimeType::Wasm, Duration::from_millis(200));
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let decision = &autoscaler.evaluate().await[0];
+        assert_eq!(decision.reached, 8);
+
+        // Shrinking waits out `scale_down_after` quiet intervals
+        let mut quiet = 0;
+        while pool.size() > 1 && quiet < 20 {
+            assert!(autoscaler.evaluate().await.iter().all(|d| d.reached == 1));
+            quiet += 1;
+        }
+        assert_eq!(pool.size(), 1);
+        assert!(quiet >= 2);
+    }
+
+    #[test]
+    fn test_grows_while_slo_is_missed() {
+        let autoscaler = Autoscaler::new(AutoscalerConfig::default(), Arc::new(TrafficMonitor::new()));
+        let mut managed = ManagedPool {
+            runtime: RuntimeType::Python,
+            pool: Arc::new(CountingPool(AtomicUsize::new(2))),
+            bounds: PoolBounds {
+                min: 0,
+                max: 4,
+                latency_slo: Some(Duration::from_millis(100)),
+            },
+            oversized: 0,
+        };
+
+        let slow = Some(Duration::from_millis(300));
+        assert_eq!(autoscaler.plan(&mut managed, 0.0, None, slow), Some((3, ScalingReason::SloBreached)));
+        assert_eq!(autoscaler.plan(&mut managed, 0.0, None, Some(Duration::from_millis(50))), None);
+
+        managed.pool = Arc::new(CountingPool(AtomicUsize::new(4)));
+        assert_eq!(autoscaler.plan(&mut managed, 0.0, None, slow), None);
+    }
+}
@@ -0,0 +1,200 @@
+use next_rc_shared::RuntimeType;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant, SystemTime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CircuitBreakerConfig {
+    /// Consecutive backend failures that trip the breaker.
+    pub failure_threshold: u32,
+    /// How long the breaker stays open before allowing probes.
+    pub open_duration: Duration,
+    /// Concurrent probe calls allowed while half-open.
+    pub half_open_max_probes: u32,
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+            half_open_max_probes: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakerEvent {
+    pub runtime: RuntimeType,
+    pub from: BreakerState,
+    pub to: BreakerState,
+    pub at: SystemTime,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BreakerMetrics {
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub total_successes: u64,
+    pub trips: u64,
+    pub rejected: u64,
+}
+
+struct BreakerInner {
+    state: BreakerState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    probes_in_flight: u32,
+    total_failures: u64,
+    total_successes: u64,
+    trips: u64,
+    rejected: u64,
+}
+
+/// Tracks backend health for a single runtime. Transitions are returned to
+/// the caller so the orchestrator can publish them.
+pub struct CircuitBreaker {
+    config: CircuitBreakerConfig,
+    inner: Mutex<BreakerInner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: CircuitBreakerConfig) -> Self {
+        Self {
+            config,
+            inner: Mutex::new(BreakerInner {
+                state: BreakerState::Closed,
+                consecutive_failures: 0,
+                opened_at: None,
+                probes_in_flight: 0,
+                total_failures: 0,
+                total_successes: 0,
+                trips: 0,
+                rejected: 0,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        self.inner.lock().state
+    }
+
+    /// Asks permission for a call. Returns the permission and any state
+    /// change caused by the open period elapsing.
+    pub fn try_acquire(&self) -> (bool, Option<(BreakerState, BreakerState)>) {
+        let mut inner = self.inner.lock();
+        let mut transition = None;
+
+        if inner.state == BreakerState::Open {
+            let elapsed = inner.opened_at.map(|at| at.elapsed()).unwrap_or_default();
+            if elapsed >= self.config.open_duration {
+                inner.state = BreakerState::HalfOpen;
+                inner.probes_in_flight = 0;
+                transition = Some((BreakerState::Open, BreakerState::HalfOpen));
+            }
+        }
+
+        let allowed = match inner.state {
+            BreakerState::Closed => true,
+            BreakerState::Open => false,
+            BreakerState::HalfOpen => {
+                if inner.probes_in_flight < self.config.half_open_max_probes {
+                    inner.probes_in_flight += 1;
+                    true
+                } else {
+                    false
+                }
+            }
+        };
+
+        if !allowed {
+            inner.rejected += 1;
+        }
+        (allowed, transition)
+    }
+
+    pub fn record_success(&self) -> Option<(BreakerState, BreakerState)> {
+        let mut inner = self.inner.lock();
+        inner.total_successes += 1;
+        inner.consecutive_failures = 0;
+
+        if inner.state == BreakerState::HalfOpen {
+            inner.state = BreakerState::Closed;
+            inner.opened_at = None;
+            inner.probes_in_flight = 0;
+            return Some((BreakerState::HalfOpen, BreakerState::Closed));
+        }
+        None
+    }
+
+    pub fn record_failure(&self) -> Option<(BreakerState, BreakerState)> {
+        let mut inner = self.inner.lock();
+        inner.total_failures += 1;
+        inner.consecutive_failures += 1;
+
+        let from = inner.state;
+        let trip = match from {
+            BreakerState::Closed => inner.consecutive_failures >= self.config.failure_threshold,
+            BreakerState::HalfOpen => true,
+            BreakerState::Open => false,
+        };
+
+        if trip {
+            inner.state = BreakerState::Open;
+            inner.opened_at = Some(Instant::now());
+            inner.probes_in_flight = 0;
+            inner.trips += 1;
+            return Some((from, BreakerState::Open));
+        }
+        None
+    }
+
+    pub fn metrics(&self) -> BreakerMetrics {
+        let inner = self.inner.lock();
+        BreakerMetrics {
+            state: inner.state,
+            consecutive_failures: inner.consecutive_failures,
+            total_failures: inner.total_failures,
+            total_successes: inner.total_successes,
+            trips: inner.trips,
+            rejected: inner.rejected,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trip_probe_and_recover() {
+        let breaker = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            open_duration: Duration::from_millis(20),
+            half_open_max_probes: 1,
+        });
+
+        assert_eq!(breaker.record_failure(), None);
+        assert_eq!(breaker.record_failure(), Some((BreakerState::Closed, BreakerState::Open)));
+        assert!(!breaker.try_acquire().0);
+
+        std::thread::sleep(Duration::from_millis(25));
+        assert_eq!(breaker.try_acquire(), (true, Some((BreakerState::Open, BreakerState::HalfOpen))));
+        assert!(!breaker.try_acquire().0);
+
+        assert_eq!(breaker.record_success(), Some((BreakerState::HalfOpen, BreakerState::Closed)));
+        assert!(breaker.try_acquire().0);
+
+        let metrics = breaker.metrics();
+        assert_eq!(metrics.trips, 1);
+        assert_eq!(metrics.rejected, 2);
+    }
+}
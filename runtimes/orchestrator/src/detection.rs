@@ -0,0 +1,196 @@
+use next_rc_shared::Language;
+use serde::{Deserialize, Serialize};
+
+/// Detections below this confidence are not acted on without an override.
+pub const MIN_CONFIDENCE: f32 = 0.5;
+
+const WASM_MAGIC: &[u8] = b"\0asm";
+const ELF_MAGIC: &[u8] = b"\x7fELF";
+/// `e_machine` of eBPF objects, stored little-endian at offset 18.
+const EM_BPF: u16 = 247;
+
+/// How a language was determined, strongest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DetectionMethod {
+    /// The caller named the language.
+    Override,
+    MagicBytes,
+    Shebang,
+    Extension,
+    Content,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LanguageDetection {
+    pub language: Language,
+    /// From 0.0 to 1.0.
+    pub confidence: f32,
+    pub method: DetectionMethod,
+}
+
+impl LanguageDetection {
+    fn new(language: Language, confidence: f32, method: DetectionMethod) -> Self {
+        Self {
+            language,
+            confidence,
+            method,
+        }
+    }
+}
+
+/// Infers the language of `code` from, in order, magic bytes, a shebang,
+/// `file_name`'s extension and the content itself. `language`, when set,
+/// overrides detection.
+pub fn detect_language(code: &[u8], file_name: Option<&str>, language: Option<Language>) -> Option<LanguageDetection> {
+    if let Some(language) = language {
+        return Some(LanguageDetection::new(language, 1.0, DetectionMethod::Override));
+    }
+    from_magic_bytes(code)
+        .or_else(|| from_shebang(code))
+        .or_else(|| file_name.and_then(from_extension))
+        .or_else(|| from_content(code))
+}
+
+fn from_magic_bytes(code: &[u8]) -> Option<LanguageDetection> {
+    if code.starts_with(WASM_MAGIC) {
+        return Some(LanguageDetection::new(Language::Wasm, 1.0, DetectionMethod::MagicBytes));
+    }
+    // Only eBPF objects are runnable; other ELF binaries are native code
+    let machine = code.get(18..20).map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]));
+    if code.starts_with(ELF_MAGIC) && machine == Some(EM_BPF) {
+        return Some(LanguageDetection::new(Language::C, 0.95, DetectionMethod::MagicBytes));
+    }
+    None
+}
+
+fn from_shebang(code: &[u8]) -> Option<LanguageDetection> {
+    let line = code.strip_prefix(b"#!")?;
+    let line = String::from_utf8_lossy(&line[..line.iter().position(|&b| b == b'\n').unwrap_or(line.len())]);
+    let interpreter = line
+        .split_whitespace()
+        .find(|word| !word.ends_with("/env") && !word.starts_with('-'))?;
+    let interpreter = interpreter.rsplit('/').next().unwrap_or(interpreter);
+
+    let language = if interpreter.starts_with("python") {
+        Language::Python
+    } else if matches!(interpreter, "ts-node" | "tsx") {
+        Language::TypeScript
+    } else if matches!(interpreter, "node" | "nodejs" | "deno" | "bun") {
+        Language::JavaScript
+    } else {
+        return None;
+    };
+    Some(LanguageDetection::new(language, 0.95, DetectionMethod::Shebang))
+}
+
+fn from_extension(file_name: &str) -> Option<LanguageDetection> {
+    let (_, extension) = file_name.rsplit_once('.')?;
+    let language = match extension.to_ascii_lowercase().as_str() {
+        "py" | "pyw" => Language::Python,
+        "js" | "mjs" | "cjs" => Language::JavaScript,
+        "ts" | "mts" | "cts" => Language::TypeScript,
+        "rs" => Language::Rust,
+        "go" => Language::Go,
+        "c" | "h" => Language::C,
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" => Language::Cpp,
+        "wasm" | "wat" => Language::Wasm,
+        _ => return None,
+    };
+    Some(LanguageDetection::new(language, 0.9, DetectionMethod::Extension))
+}
+
+/// Tokens that suggest a language, each worth one point.
+const CONTENT_MARKERS: &[(Language, &[&str])] = &[
+    (Language::Python, &["def ", "import ", "from ", "print(", "elif ", "self.", "__name__", "):\n"]),
+    (Language::JavaScript, &["function ", "const ", "let ", "=> ", "console.log", "require(", "module.exports", "===", "undefined"]),
+    (Language::TypeScript, &["interface ", ": string", ": number", ": boolean", "export type ", "as const", "<T>", "readonly "]),
+    (Language::Rust, &["fn ", "let mut ", "impl ", "pub fn ", "use std::", "-> ", "println!", "match ", "&mut "]),
+    (Language::Go, &["package ", "func ", ":= ", "fmt.", "import (", "go func", "chan "]),
+    (Language::C, &["#include <std", "int main(", "printf(", "malloc(", "sizeof(", "struct ", "->", "NULL"]),
+    (Language::Cpp, &["#include <iostream>", "std::", "namespace ", "template<", "template <", "cout <<", "class ", "nullptr"]),
+];
+
+/// Scores `CONTENT_MARKERS`; confidence is the winner's share of all
+/// points, capped below the structural methods.
+fn from_content(code: &[u8]) -> Option<LanguageDetection> {
+    let text = std::str::from_utf8(code).ok()?;
+    let trimmed = text.trim_start();
+    if trimmed.starts_with("(module") || trimmed.starts_with("(component") {
+        return Some(LanguageDetection::new(Language::Wasm, 0.9, DetectionMethod::Content));
+    }
+
+    let mut scores: Vec<(Language, usize)> = CONTENT_MARKERS
+        .iter()
+        .map(|(language, markers)| (*language, markers.iter().filter(|marker| text.contains(*marker)).count()))
+        .collect();
+    // TypeScript is a superset of JavaScript, and C++ of C
+    let score_of = |scores: &[(Language, usize)], language| scores.iter().find(|(l, _)| *l == language).map_or(0, |(_, s)| *s);
+    for (superset, subset) in [(Language::TypeScript, Language::JavaScript), (Language::Cpp, Language::C)] {
+        let subset_score = score_of(&scores, subset);
+        if let Some((_, score)) = scores.iter_mut().find(|(l, score)| *l == superset && *score > 0) {
+            *score += subset_score;
+        }
+    }
+
+    let total: usize = scores.iter().map(|(_, score)| score).sum();
+    let (language, best) = scores.iter().copied().max_by_key(|(_, score)| *score)?;
+    if best == 0 {
+        return None;
+    }
+    let confidence = (best as f32 / total as f32) * 0.8;
+    Some(LanguageDetection::new(language, confidence, DetectionMethod::Content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(code: &str, file_name: Option<&str>) -> Option<(Language, DetectionMethod)> {
+        detect_language(code.as_bytes(), file_name, None).map(|detection| (detection.language, detection.method))
+    }
+
+    #[test]
+    fn test_structural_detection() {
+        assert_eq!(
+            detect_language(b"\0asm\x01\0\0\0", Some("filter.py"), None).map(|d| d.method),
+            Some(DetectionMethod::MagicBytes)
+        );
+        let mut elf = b"\x7fELF".to_vec();
+        elf.resize(18, 0);
+        elf.extend_from_slice(&EM_BPF.to_le_bytes());
+        assert_eq!(detect_language(&elf, None, None).map(|d| d.language), Some(Language::C));
+        elf[18] = 62; // x86-64
+        assert_eq!(detect_language(&elf, None, None), None);
+
+        assert_eq!(detect("#!/usr/bin/env python3\nprint(1)", None), Some((Language::Python, DetectionMethod::Shebang)));
+        assert_eq!(detect("#!/usr/bin/node\n", None), Some((Language::JavaScript, DetectionMethod::Shebang)));
+        assert_eq!(detect("x = 1", Some("job.PY")), Some((Language::Python, DetectionMethod::Extension)));
+        assert_eq!(detect("", Some("main.rs")), Some((Language::Rust, DetectionMethod::Extension)));
+    }
+
+    #[test]
+    fn test_content_detection() {
+        let cases = [
+            ("import json\n\ndef main():\n    print(json.dumps({}))\n", Language::Python),
+            ("const x = require('fs');\nfunction f() { console.log(x); }\n", Language::JavaScript),
+            ("interface User { name: string; age: number }\nconst u: User = load();\n", Language::TypeScript),
+            ("use std::io;\n\npub fn main() {\n    let mut x = 1;\n    println!(\"{}\", x);\n}\n", Language::Rust),
+            ("package main\n\nimport (\n\t\"fmt\"\n)\n\nfunc main() {\n\tx := 1\n\tfmt.Println(x)\n}\n", Language::Go),
+            ("#include <stdio.h>\nint main() {\n    printf(\"hi\");\n    return 0;\n}\n", Language::C),
+            ("#include <iostream>\nint main() {\n    std::cout << \"hi\";\n}\n", Language::Cpp),
+            ("  (module (func (export \"_start\") (result i32) i32.const 1))", Language::Wasm),
+        ];
+        for (code, language) in cases {
+            let detection = detect_language(code.as_bytes(), None, None).unwrap();
+            assert_eq!(detection.language, language, "{code}");
+            assert_eq!(detection.method, DetectionMethod::Content);
+        }
+        assert_eq!(detect("hello world", None), None);
+    }
+
+    #[test]
+    fn test_override_wins() {
+        let detection = detect_language(b"\0asm", Some("a.py"), Some(Language::Rust)).unwrap();
+        assert_eq!((detection.language, detection.method, detection.confidence), (Language::Rust, DetectionMethod::Override, 1.0));
+    }
+}
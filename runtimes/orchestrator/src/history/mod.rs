@@ -0,0 +1,257 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use next_rc_shared::{InstanceId, Language, ModuleId, RuntimeType};
+use parking_lot::RwLock;
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime};
+#[cfg(any(feature = "sqlite", feature = "postgres", test))]
+use std::time::UNIX_EPOCH;
+use uuid::Uuid;
+
+#[cfg(feature = "postgres")]
+mod postgres;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "postgres")]
+pub use self::postgres::PostgresHistoryStore;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteHistoryStore;
+
+const DEFAULT_MEMORY_CAPACITY: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum ExecutionStatus {
+    Succeeded,
+    Failed,
+    TimedOut,
+    Errored,
+}
+
+/// Metadata persisted for a single execution.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ExecutionRecord {
+    pub id: Uuid,
+    pub tenant_id: Option<String>,
+    pub runtime: RuntimeType,
+    pub language: Language,
+    pub module_id: ModuleId,
+    pub instance_id: InstanceId,
+    pub status: ExecutionStatus,
+    pub duration: Duration,
+    pub started_at: SystemTime,
+    pub error: Option<String>,
+    pub tags: HashMap<String, String>,
+}
+
+/// Query for `HistoryStore::list_executions`. Every populated field must
+/// match; `tags` matches records carrying all of the given key/value pairs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionFilter {
+    pub tenant_id: Option<String>,
+    pub runtime: Option<RuntimeType>,
+    pub language: Option<Language>,
+    pub status: Option<ExecutionStatus>,
+    pub module_id: Option<ModuleId>,
+    pub tags: HashMap<String, String>,
+    pub started_after: Option<SystemTime>,
+    pub started_before: Option<SystemTime>,
+    pub limit: Option<usize>,
+}
+
+impl ExecutionFilter {
+    pub fn matches(&self, record: &ExecutionRecord) -> bool {
+        if self.tenant_id.is_some() && self.tenant_id != record.tenant_id {
+            return false;
+        }
+        if self.runtime.is_some_and(|runtime| runtime != record.runtime) {
+            return false;
+        }
+        if self.language.is_some_and(|language| language != record.language) {
+            return false;
+        }
+        if self.status.is_some_and(|status| status != record.status) {
+            return false;
+        }
+        if self.module_id.as_ref().is_some_and(|id| *id != record.module_id) {
+            return false;
+        }
+        if self.started_after.is_some_and(|after| record.started_at < after) {
+            return false;
+        }
+        if self.started_before.is_some_and(|before| record.started_at >= before) {
+            return false;
+        }
+
+        self.tags
+            .iter()
+            .all(|(key, value)| record.tags.get(key) == Some(value))
+    }
+}
+
+#[async_trait]
+pub trait HistoryStore: Send + Sync {
+    async fn record(&self, record: ExecutionRecord) -> Result<()>;
+    async fn get(&self, id: Uuid) -> Result<Option<ExecutionRecord>>;
+    /// Returns matching executions, most recent first.
+    async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>>;
+}
+
+/// Bounded in-process store; the oldest records are evicted once full.
+pub struct InMemoryHistoryStore {
+    records: RwLock<VecDeque<ExecutionRecord>>,
+    capacity: usize,
+}
+
+impl InMemoryHistoryStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            records: RwLock::new(VecDeque::with_capacity(capacity.min(DEFAULT_MEMORY_CAPACITY))),
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.read().is_empty()
+    }
+}
+
+impl Default for InMemoryHistoryStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_MEMORY_CAPACITY)
+    }
+}
+
+#[async_trait]
+impl HistoryStore for InMemoryHistoryStore {
+    async fn record(&self, record: ExecutionRecord) -> Result<()> {
+        let mut records = self.records.write();
+        while records.len() >= self.capacity.max(1) {
+            records.pop_front();
+        }
+        records.push_back(record);
+        Ok(())
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<ExecutionRecord>> {
+        let records = self.records.read();
+        Ok(records.iter().find(|record| record.id == id).cloned())
+    }
+
+    async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>> {
+        let records = self.records.read();
+        let mut matching: Vec<_> = records
+            .iter()
+            .filter(|record| filter.matches(record))
+            .cloned()
+            .collect();
+
+        matching.sort_by_key(|record| std::cmp::Reverse(record.started_at));
+        if let Some(limit) = filter.limit {
+            matching.truncate(limit);
+        }
+
+        Ok(matching)
+    }
+}
+
+// Enums are stored as their serde names so the tables stay readable from
+// dashboards that query the database directly.
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+pub(crate) fn to_label<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(label)) => label,
+        Ok(other) => other.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+#[cfg(any(feature = "sqlite", feature = "postgres"))]
+pub(crate) fn from_label<T: DeserializeOwned>(label: &str) -> Result<T> {
+    Ok(serde_json::from_value(serde_json::Value::String(label.to_string()))?)
+}
+
+#[cfg(any(feature = "sqlite", feature = "postgres", test))]
+pub(crate) fn to_unix_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(any(feature = "sqlite", feature = "postgres", test))]
+pub(crate) fn from_unix_millis(millis: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(millis.max(0) as u64)
+}
+
+#[cfg(test)]
+pub(crate) fn sample_record(tenant: &str, status: ExecutionStatus, tags: &[(&str, &str)]) -> ExecutionRecord {
+    ExecutionRecord {
+        id: Uuid::new_v4(),
+        tenant_id: Some(tenant.to_string()),
+        runtime: RuntimeType::Wasm,
+        language: Language::Wasm,
+        module_id: ModuleId(Uuid::new_v4()),
+        instance_id: InstanceId(Uuid::new_v4()),
+        status,
+        duration: Duration::from_micros(350),
+        started_at: from_unix_millis(to_unix_millis(SystemTime::now())),
+        error: None,
+        tags: tags
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_in_memory_filtering() {
+        let store = InMemoryHistoryStore::default();
+        store.record(sample_record("acme", ExecutionStatus::Succeeded, &[("env", "prod")])).await.unwrap();
+        store.record(sample_record("acme", ExecutionStatus::Failed, &[("env", "dev")])).await.unwrap();
+        store.record(sample_record("globex", ExecutionStatus::Succeeded, &[("env", "prod")])).await.unwrap();
+
+        let filter = ExecutionFilter {
+            tenant_id: Some("acme".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(store.list_executions(&filter).await.unwrap().len(), 2);
+
+        let filter = ExecutionFilter {
+            tags: HashMap::from([("env".to_string(), "prod".to_string())]),
+            status: Some(ExecutionStatus::Succeeded),
+            ..Default::default()
+        };
+        assert_eq!(store.list_executions(&filter).await.unwrap().len(), 2);
+
+        let filter = ExecutionFilter {
+            limit: Some(1),
+            ..Default::default()
+        };
+        assert_eq!(store.list_executions(&filter).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_eviction() {
+        let store = InMemoryHistoryStore::new(2);
+        let first = sample_record("acme", ExecutionStatus::Succeeded, &[]);
+        let first_id = first.id;
+
+        store.record(first).await.unwrap();
+        store.record(sample_record("acme", ExecutionStatus::Succeeded, &[])).await.unwrap();
+        store.record(sample_record("acme", ExecutionStatus::Succeeded, &[])).await.unwrap();
+
+        assert_eq!(store.len(), 2);
+        assert!(store.get(first_id).await.unwrap().is_none());
+    }
+}
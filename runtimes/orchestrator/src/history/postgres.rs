@@ -0,0 +1,209 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio_postgres::{types::ToSql, Client, NoTls, Row};
+use tracing::error;
+use uuid::Uuid;
+
+use super::{
+    from_label, from_unix_millis, to_label, to_unix_millis, ExecutionFilter, ExecutionRecord,
+    HistoryStore,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS executions (
+        id TEXT PRIMARY KEY,
+        tenant_id TEXT,
+        runtime TEXT NOT NULL,
+        language TEXT NOT NULL,
+        module_id TEXT NOT NULL,
+        instance_id TEXT NOT NULL,
+        status TEXT NOT NULL,
+        duration_us BIGINT NOT NULL,
+        started_at_ms BIGINT NOT NULL,
+        error TEXT
+    );
+    CREATE INDEX IF NOT EXISTS executions_tenant_started
+        ON executions (tenant_id, started_at_ms DESC);
+    CREATE TABLE IF NOT EXISTS execution_tags (
+        execution_id TEXT NOT NULL REFERENCES executions (id) ON DELETE CASCADE,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (execution_id, key)
+    );
+    CREATE INDEX IF NOT EXISTS execution_tags_lookup ON execution_tags (key, value);
+";
+
+const SELECT_COLUMNS: &str = "id, tenant_id, runtime, language, module_id, instance_id, \
+                              status, duration_us, started_at_ms, error";
+
+type SqlParam = Box<dyn ToSql + Sync + Send>;
+
+/// Postgres-backed history for deployments sharing one store across nodes.
+pub struct PostgresHistoryStore {
+    client: Mutex<Client>,
+}
+
+impl PostgresHistoryStore {
+    /// Connects using a libpq-style connection string and ensures the schema exists.
+    pub async fn connect(config: &str) -> Result<Self> {
+        let (client, connection) = tokio_postgres::connect(config, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                error!("Execution history connection closed: {}", e);
+            }
+        });
+
+        client.batch_execute(SCHEMA).await?;
+
+        Ok(Self {
+            client: Mutex::new(client),
+        })
+    }
+
+    async fn load_record(client: &Client, row: &Row) -> Result<ExecutionRecord> {
+        let id: String = row.get(0);
+        let tags = client
+            .query("SELECT key, value FROM execution_tags WHERE execution_id = $1", &[&id])
+            .await?
+            .into_iter()
+            .map(|tag| (tag.get::<_, String>(0), tag.get::<_, String>(1)))
+            .collect::<HashMap<_, _>>();
+
+        Ok(ExecutionRecord {
+            id: Uuid::parse_str(&id)?,
+            tenant_id: row.get(1),
+            runtime: from_label(row.get(2))?,
+            language: from_label(row.get(3))?,
+            module_id: row.get::<_, &str>(4).parse()?,
+            instance_id: row.get::<_, &str>(5).parse()?,
+            status: from_label(row.get(6))?,
+            duration: Duration::from_micros(row.get::<_, i64>(7).max(0) as u64),
+            started_at: from_unix_millis(row.get(8)),
+            error: row.get(9),
+            tags,
+        })
+    }
+}
+
+#[async_trait]
+impl HistoryStore for PostgresHistoryStore {
+    async fn record(&self, record: ExecutionRecord) -> Result<()> {
+        let mut client = self.client.lock().await;
+        let tx = client.transaction().await?;
+        let id = record.id.to_string();
+
+        tx.execute(
+            "INSERT INTO executions (id, tenant_id, runtime, language, module_id, instance_id, \
+             status, duration_us, started_at_ms, error) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+            &[
+                &id,
+                &record.tenant_id,
+                &to_label(&record.runtime),
+                &to_label(&record.language),
+                &record.module_id.to_string(),
+                &record.instance_id.to_string(),
+                &to_label(&record.status),
+                &(record.duration.as_micros() as i64),
+                &to_unix_millis(record.started_at),
+                &record.error,
+            ],
+        )
+        .await?;
+
+        for (key, value) in &record.tags {
+            tx.execute(
+                "INSERT INTO execution_tags (execution_id, key, value) VALUES ($1, $2, $3)",
+                &[&id, key, value],
+            )
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<ExecutionRecord>> {
+        let client = self.client.lock().await;
+        let row = client
+            .query_opt(
+                &format!("SELECT {} FROM executions WHERE id = $1", SELECT_COLUMNS),
+                &[&id.to_string()],
+            )
+            .await?;
+
+        match row {
+            Some(row) => Ok(Some(Self::load_record(&client, &row).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>> {
+        let mut clauses = Vec::new();
+        let mut values: Vec<SqlParam> = Vec::new();
+        let mut push = |clause: String, value: SqlParam, values: &mut Vec<SqlParam>| {
+            values.push(value);
+            clauses.push(clause.replace('?', &format!("${}", values.len())));
+        };
+
+        if let Some(tenant_id) = &filter.tenant_id {
+            push("tenant_id = ?".into(), Box::new(tenant_id.clone()), &mut values);
+        }
+        if let Some(runtime) = &filter.runtime {
+            push("runtime = ?".into(), Box::new(to_label(runtime)), &mut values);
+        }
+        if let Some(language) = &filter.language {
+            push("language = ?".into(), Box::new(to_label(language)), &mut values);
+        }
+        if let Some(status) = &filter.status {
+            push("status = ?".into(), Box::new(to_label(status)), &mut values);
+        }
+        if let Some(module_id) = &filter.module_id {
+            push("module_id = ?".into(), Box::new(module_id.to_string()), &mut values);
+        }
+        if let Some(after) = filter.started_after {
+            push("started_at_ms >= ?".into(), Box::new(to_unix_millis(after)), &mut values);
+        }
+        if let Some(before) = filter.started_before {
+            push("started_at_ms < ?".into(), Box::new(to_unix_millis(before)), &mut values);
+        }
+        for (key, value) in &filter.tags {
+            values.push(Box::new(key.clone()));
+            let key_param = values.len();
+            values.push(Box::new(value.clone()));
+            let value_param = values.len();
+            clauses.push(format!(
+                "EXISTS (SELECT 1 FROM execution_tags t WHERE t.execution_id = executions.id \
+                 AND t.key = ${} AND t.value = ${})",
+                key_param, value_param
+            ));
+        }
+
+        let mut sql = format!("SELECT {} FROM executions", SELECT_COLUMNS);
+        if !clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&clauses.join(" AND "));
+        }
+        sql.push_str(" ORDER BY started_at_ms DESC");
+        if let Some(limit) = filter.limit {
+            values.push(Box::new(limit as i64));
+            sql.push_str(&format!(" LIMIT ${}", values.len()));
+        }
+
+        let client = self.client.lock().await;
+        let params: Vec<&(dyn ToSql + Sync)> = values
+            .iter()
+            .map(|value| value.as_ref() as &(dyn ToSql + Sync))
+            .collect();
+        let rows = client.query(&sql, &params).await?;
+
+        let mut records = Vec::with_capacity(rows.len());
+        for row in &rows {
+            records.push(Self::load_record(&client, row).await?);
+        }
+        Ok(records)
+    }
+}
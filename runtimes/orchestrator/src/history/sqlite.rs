@@ -0,0 +1,269 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use rusqlite::{params, types::Value, Connection, OptionalExtension, Row};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::{
+    from_label, from_unix_millis, to_label, to_unix_millis, ExecutionFilter, ExecutionRecord,
+    HistoryStore,
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS executions (
+        id TEXT PRIMARY KEY,
+        tenant_id TEXT,
+        runtime TEXT NOT NULL,
+        language TEXT NOT NULL,
+        module_id TEXT NOT NULL,
+        instance_id TEXT NOT NULL,
+        status TEXT NOT NULL,
+        duration_us INTEGER NOT NULL,
+        started_at_ms INTEGER NOT NULL,
+        error TEXT
+    );
+    CREATE INDEX IF NOT EXISTS executions_tenant_started
+        ON executions (tenant_id, started_at_ms DESC);
+    CREATE TABLE IF NOT EXISTS execution_tags (
+        execution_id TEXT NOT NULL REFERENCES executions (id) ON DELETE CASCADE,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        PRIMARY KEY (execution_id, key)
+    );
+    CREATE INDEX IF NOT EXISTS execution_tags_lookup ON execution_tags (key, value);
+";
+
+const SELECT_COLUMNS: &str = "id, tenant_id, runtime, language, module_id, instance_id, \
+                              status, duration_us, started_at_ms, error";
+
+/// SQLite-backed history, suitable for single-node deployments.
+pub struct SqliteHistoryStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteHistoryStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || f(&mut conn.lock()))
+            .await
+            .map_err(|e| anyhow!("History query task failed: {}", e))?
+    }
+}
+
+fn read_record(row: &Row<'_>) -> rusqlite::Result<RawRecord> {
+    Ok(RawRecord {
+        id: row.get(0)?,
+        tenant_id: row.get(1)?,
+        runtime: row.get(2)?,
+        language: row.get(3)?,
+        module_id: row.get(4)?,
+        instance_id: row.get(5)?,
+        status: row.get(6)?,
+        duration_us: row.get(7)?,
+        started_at_ms: row.get(8)?,
+        error: row.get(9)?,
+    })
+}
+
+struct RawRecord {
+    id: String,
+    tenant_id: Option<String>,
+    runtime: String,
+    language: String,
+    module_id: String,
+    instance_id: String,
+    status: String,
+    duration_us: i64,
+    started_at_ms: i64,
+    error: Option<String>,
+}
+
+impl RawRecord {
+    fn into_record(self, conn: &Connection) -> Result<ExecutionRecord> {
+        let mut stmt =
+            conn.prepare_cached("SELECT key, value FROM execution_tags WHERE execution_id = ?1")?;
+        let tags = stmt
+            .query_map(params![self.id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<HashMap<String, String>>>()?;
+
+        Ok(ExecutionRecord {
+            id: Uuid::parse_str(&self.id)?,
+            tenant_id: self.tenant_id,
+            runtime: from_label(&self.runtime)?,
+            language: from_label(&self.language)?,
+            module_id: self.module_id.parse()?,
+            instance_id: self.instance_id.parse()?,
+            status: from_label(&self.status)?,
+            duration: Duration::from_micros(self.duration_us.max(0) as u64),
+            started_at: from_unix_millis(self.started_at_ms),
+            error: self.error,
+            tags,
+        })
+    }
+}
+
+#[async_trait]
+impl HistoryStore for SqliteHistoryStore {
+    async fn record(&self, record: ExecutionRecord) -> Result<()> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute(
+                "INSERT INTO executions (id, tenant_id, runtime, language, module_id, instance_id, \
+                 status, duration_us, started_at_ms, error) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                params![
+                    record.id.to_string(),
+                    record.tenant_id,
+                    to_label(&record.runtime),
+                    to_label(&record.language),
+                    record.module_id.to_string(),
+                    record.instance_id.to_string(),
+                    to_label(&record.status),
+                    record.duration.as_micros() as i64,
+                    to_unix_millis(record.started_at),
+                    record.error,
+                ],
+            )?;
+            for (key, value) in &record.tags {
+                tx.execute(
+                    "INSERT INTO execution_tags (execution_id, key, value) VALUES (?1, ?2, ?3)",
+                    params![record.id.to_string(), key, value],
+                )?;
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get(&self, id: Uuid) -> Result<Option<ExecutionRecord>> {
+        self.with_conn(move |conn| {
+            let raw = conn
+                .query_row(
+                    &format!("SELECT {} FROM executions WHERE id = ?1", SELECT_COLUMNS),
+                    params![id.to_string()],
+                    read_record,
+                )
+                .optional()?;
+            raw.map(|raw| raw.into_record(conn)).transpose()
+        })
+        .await
+    }
+
+    async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>> {
+        let filter = filter.clone();
+        self.with_conn(move |conn| {
+            let mut clauses = Vec::new();
+            let mut values: Vec<Value> = Vec::new();
+
+            if let Some(tenant_id) = &filter.tenant_id {
+                clauses.push("tenant_id = ?");
+                values.push(Value::Text(tenant_id.clone()));
+            }
+            if let Some(runtime) = &filter.runtime {
+                clauses.push("runtime = ?");
+                values.push(Value::Text(to_label(runtime)));
+            }
+            if let Some(language) = &filter.language {
+                clauses.push("language = ?");
+                values.push(Value::Text(to_label(language)));
+            }
+            if let Some(status) = &filter.status {
+                clauses.push("status = ?");
+                values.push(Value::Text(to_label(status)));
+            }
+            if let Some(module_id) = &filter.module_id {
+                clauses.push("module_id = ?");
+                values.push(Value::Text(module_id.to_string()));
+            }
+            if let Some(after) = filter.started_after {
+                clauses.push("started_at_ms >= ?");
+                values.push(Value::Integer(to_unix_millis(after)));
+            }
+            if let Some(before) = filter.started_before {
+                clauses.push("started_at_ms < ?");
+                values.push(Value::Integer(to_unix_millis(before)));
+            }
+            for (key, value) in &filter.tags {
+                clauses.push(
+                    "EXISTS (SELECT 1 FROM execution_tags t \
+                     WHERE t.execution_id = executions.id AND t.key = ? AND t.value = ?)",
+                );
+                values.push(Value::Text(key.clone()));
+                values.push(Value::Text(value.clone()));
+            }
+
+            let mut sql = format!("SELECT {} FROM executions", SELECT_COLUMNS);
+            if !clauses.is_empty() {
+                sql.push_str(" WHERE ");
+                sql.push_str(&clauses.join(" AND "));
+            }
+            sql.push_str(" ORDER BY started_at_ms DESC");
+            if let Some(limit) = filter.limit {
+                sql.push_str(" LIMIT ?");
+                values.push(Value::Integer(limit as i64));
+            }
+
+            let raws = {
+                let mut stmt = conn.prepare(&sql)?;
+                let rows = stmt.query_map(rusqlite::params_from_iter(values), read_record)?;
+                rows.collect::<rusqlite::Result<Vec<_>>>()?
+            };
+
+            raws.into_iter().map(|raw| raw.into_record(conn)).collect()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::{sample_record, ExecutionStatus};
+
+    #[tokio::test]
+    async fn test_sqlite_round_trip() {
+        let store = SqliteHistoryStore::open_in_memory().unwrap();
+        let record = sample_record("acme", ExecutionStatus::Succeeded, &[("env", "prod"), ("team", "edge")]);
+
+        store.record(record.clone()).await.unwrap();
+        store.record(sample_record("acme", ExecutionStatus::Failed, &[("env", "dev")])).await.unwrap();
+
+        let loaded = store.get(record.id).await.unwrap().unwrap();
+        assert_eq!(loaded, record);
+
+        let filter = ExecutionFilter {
+            tenant_id: Some("acme".to_string()),
+            tags: HashMap::from([("env".to_string(), "prod".to_string())]),
+            ..Default::default()
+        };
+        let found = store.list_executions(&filter).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, record.id);
+    }
+}
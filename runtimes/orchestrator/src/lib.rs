@@ -0,0 +1,48 @@
+pub mod audit;
+pub mod autoscaler;
+pub mod circuit_breaker;
+pub mod detection;
+pub mod history;
+pub mod orchestrator;
+pub mod pipeline;
+pub mod plugins;
+pub mod policy;
+pub mod registry;
+pub mod schedule;
+pub mod staging;
+
+pub use audit::{AuditLog, AuditRecord, InMemoryAuditLog};
+pub use autoscaler::{
+    Autoscaler, AutoscalerConfig, PoolBounds, ScalingDecision, ScalingReason, TrafficMonitor, TrafficSample,
+    WarmInstancePool,
+};
+pub use circuit_breaker::{
+    BreakerEvent, BreakerMetrics, BreakerState, CircuitBreaker, CircuitBreakerConfig,
+};
+pub use detection::{detect_language, DetectionMethod, LanguageDetection};
+pub use history::{
+    ExecutionFilter, ExecutionRecord, ExecutionStatus, HistoryStore, InMemoryHistoryStore,
+};
+#[cfg(feature = "postgres")]
+pub use history::PostgresHistoryStore;
+#[cfg(feature = "sqlite")]
+pub use history::SqliteHistoryStore;
+pub use orchestrator::{Orchestrator, OrchestratorConfig};
+pub use pipeline::{HaltCondition, Pipeline, PipelineResult, PipelineStage, StageResult};
+pub use plugins::{PluginRequest, PluginVerdict, RequestPlugin};
+pub use policy::{
+    PolicyConditions, PolicyDecision, PolicyEvaluator, PolicyInput, PolicyRule, RulePolicy, Weekday,
+};
+pub use registry::{
+    InMemoryModuleRegistry, ModuleAlias, ModuleRef, ModuleRegistry, RegisteredModule,
+    RetentionPolicy,
+};
+#[cfg(feature = "sqlite")]
+pub use registry::SqliteModuleRegistry;
+pub use schedule::{
+    CronSchedule, InMemoryJobStore, JobRun, JobRunStatus, JobStore, OverlapPolicy, Schedule,
+    ScheduledJob, Scheduler,
+};
+#[cfg(feature = "sqlite")]
+pub use schedule::SqliteJobStore;
+pub use staging::{StagedArtifact, StagingArea, UploadManifest, UploadStatus, DEFAULT_MAX_ARTIFACT_SIZE};
@@ -0,0 +1,2817 @@
+use anyhow::{anyhow, Result};
+use next_rc_shared::webhooks::{WebhookDispatcher, WebhookEvent, WebhookEventKind};
+use next_rc_shared::{
+    sha256_hex, Attestation, CodeTransform, CompileError, DigestReader, EnvironmentFingerprint,
+    ExecutionConfig, ExecutionResult, InstanceId, JobPhase, Language, LoadReport, LoadTracker, ModuleDigest, ModuleId,
+    NodeKey,
+    PhaseTimeouts, Profile, ProvisionReport, RedactionPolicy, Redactor, ReuseMetrics, ReusePolicy, Runtime, RuntimeCapabilities, RuntimeError,
+    RuntimeType, TimingBreakdown, TraceCapture, TransformContext, TransformError, TransformMetrics, TransformPipeline, TrustLevel,
+    ValidationReport,
+};
+#[cfg(feature = "fault-injection")]
+use next_rc_shared::FaultInjector;
+use parking_lot::RwLock;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::{broadcast, Semaphore};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::audit::{AuditLog, AuditRecord};
+use crate::autoscaler::TrafficMonitor;
+use crate::circuit_breaker::{
+    BreakerEvent, BreakerMetrics, BreakerState, CircuitBreaker, CircuitBreakerConfig,
+};
+use crate::detection::{detect_language, LanguageDetection, MIN_CONFIDENCE};
+use crate::history::{
+    ExecutionFilter, ExecutionRecord, ExecutionStatus, HistoryStore, InMemoryHistoryStore,
+};
+use crate::pipeline::{Pipeline, PipelineResult, StageResult, PIPELINE_STAGE_TAG, PIPELINE_TAG};
+use crate::plugins::{PluginRequest, RequestPlugin};
+use crate::policy::{PolicyDecision, PolicyEvaluator, PolicyInput};
+use crate::registry::{
+    InMemoryModuleRegistry, ModuleRef, ModuleRegistry, RegisteredModule, RetentionPolicy,
+};
+use crate::staging::StagingArea;
+
+#[derive(Clone, Default)]
+pub struct OrchestratorConfig {
+    /// Where execution metadata is persisted. History is disabled when unset.
+    pub history: Option<Arc<dyn HistoryStore>>,
+    /// Receives lifecycle notifications for every execution when set.
+    pub webhooks: Option<Arc<WebhookDispatcher>>,
+    /// Applied to every registered runtime.
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Runtime that takes new compilations while a runtime's breaker is open.
+    pub fallbacks: HashMap<RuntimeType, RuntimeType>,
+    /// Where module names and aliases are kept. Defaults to an in-memory registry.
+    pub module_registry: Option<Arc<dyn ModuleRegistry>>,
+    /// Overrides `PhaseTimeouts::for_trust_level` for the given trust levels.
+    pub phase_timeouts: HashMap<TrustLevel, PhaseTimeouts>,
+    /// Executions allowed to run at once; the rest queue for a slot.
+    /// Unlimited when unset.
+    pub max_concurrent_executions: Option<usize>,
+    /// Execution profiles by name, added to or replacing the built-in ones.
+    pub profiles: HashMap<String, Profile>,
+    /// Overrides `ReusePolicy::for_trust_level` for the given trust levels.
+    pub reuse_policies: HashMap<TrustLevel, ReusePolicy>,
+    /// Signs every execution result with this node's key when set.
+    pub signing_key: Option<Arc<NodeKey>>,
+    /// Where large artifacts are uploaded in chunks before being compiled
+    /// with `compile_staged`. Staged compilation is disabled when unset.
+    pub staging: Option<Arc<StagingArea>>,
+    /// Injects failures and latency into compile, instantiate and execute
+    /// calls, for resilience testing.
+    #[cfg(feature = "fault-injection")]
+    pub faults: Option<Arc<FaultInjector>>,
+    /// Decides before every execution whether it may run. Everything runs
+    /// when unset.
+    pub policy: Option<Arc<dyn PolicyEvaluator>>,
+    /// Where policy decisions are kept, besides the `next_rc::audit` log
+    /// target.
+    pub audit: Option<Arc<dyn AuditLog>>,
+    /// Scrubs PII from the output, error and logs of each tenant's
+    /// executions before they are returned or recorded.
+    pub redactors: HashMap<String, Arc<Redactor>>,
+    /// Redacts executions of tenants without their own redactor, and those
+    /// without a tenant. Nothing is redacted for them when unset.
+    pub default_redactor: Option<Arc<Redactor>>,
+}
+
+/// Slack given to a runtime past the execute budget to report its own timeout.
+const EXECUTE_GRACE: Duration = Duration::from_millis(250);
+
+impl OrchestratorConfig {
+    pub fn with_in_memory_history() -> Self {
+        Self {
+            history: Some(Arc::new(InMemoryHistoryStore::default())),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ModuleEntry {
+    runtime: RuntimeType,
+    language: Language,
+    /// Hash of the code the runtime compiled, unknown for modules that were
+    /// only found in a persistent registry.
+    code_sha256: Option<String>,
+    /// Hashes of a bundle's members, in bundle order; empty for lone modules.
+    components: Vec<ModuleDigest>,
+    /// Trust level the module was compiled for, which sets its instantiate
+    /// budget. The default for modules only found in a persistent registry.
+    trust_level: TrustLevel,
+}
+
+#[derive(Debug, Clone)]
+struct InstanceEntry {
+    runtime: RuntimeType,
+    module_id: ModuleId,
+    language: Language,
+    /// Tenant of the last execution, or `None` while the instance is unused.
+    last_tenant: Option<Option<String>>,
+}
+
+/// Whether an execution runs on the instance it was given.
+enum Reuse {
+    FirstUse,
+    Reused,
+    Fresh,
+}
+
+/// Routes work to the registered runtimes and keeps track of which runtime
+/// owns each module and instance.
+pub struct Orchestrator {
+    runtimes: RwLock<HashMap<RuntimeType, Arc<dyn Runtime>>>,
+    modules: RwLock<HashMap<ModuleId, ModuleEntry>>,
+    instances: RwLock<HashMap<InstanceId, InstanceEntry>>,
+    /// Provisioned instances not yet handed out by `instantiate`.
+    pool: RwLock<HashMap<ModuleId, Vec<InstanceId>>>,
+    history: Option<Arc<dyn HistoryStore>>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+    breakers: RwLock<HashMap<RuntimeType, Arc<CircuitBreaker>>>,
+    breaker_config: CircuitBreakerConfig,
+    breaker_events: broadcast::Sender<BreakerEvent>,
+    fallbacks: HashMap<RuntimeType, RuntimeType>,
+    registry: Arc<dyn ModuleRegistry>,
+    phase_timeouts: HashMap<TrustLevel, PhaseTimeouts>,
+    execution_slots: Option<Arc<Semaphore>>,
+    max_concurrent_executions: Option<usize>,
+    load: LoadTracker,
+    transforms: TransformPipeline,
+    profiles: HashMap<String, Profile>,
+    reuse_policies: HashMap<TrustLevel, ReusePolicy>,
+    reuse_metrics: RwLock<HashMap<TrustLevel, ReuseMetrics>>,
+    signing_key: Option<Arc<NodeKey>>,
+    staging: Option<Arc<StagingArea>>,
+    #[cfg(feature = "fault-injection")]
+    faults: Option<Arc<FaultInjector>>,
+    policy: Option<Arc<dyn PolicyEvaluator>>,
+    audit: Option<Arc<dyn AuditLog>>,
+    plugins: RwLock<Vec<RequestPlugin>>,
+    /// Instance last used under each affinity key of a module.
+    affinity: RwLock<HashMap<(ModuleId, String), InstanceId>>,
+    traffic: Arc<TrafficMonitor>,
+    redaction: RedactionPolicy,
+}
+
+impl Orchestrator {
+    pub fn new(config: OrchestratorConfig) -> Self {
+        let (breaker_events, _) = broadcast::channel(64);
+
+        Self {
+            runtimes: RwLock::new(HashMap::new()),
+            modules: RwLock::new(HashMap::new()),
+            instances: RwLock::new(HashMap::new()),
+            pool: RwLock::new(HashMap::new()),
+            history: config.history,
+            webhooks: config.webhooks,
+            breakers: RwLock::new(HashMap::new()),
+            breaker_config: config.circuit_breaker,
+            breaker_events,
+            fallbacks: config.fallbacks,
+            registry: config
+                .module_registry
+                .unwrap_or_else(|| Arc::new(InMemoryModuleRegistry::new())),
+            phase_timeouts: config.phase_timeouts,
+            execution_slots: config
+                .max_concurrent_executions
+                .map(|slots| Arc::new(Semaphore::new(slots))),
+            max_concurrent_executions: config.max_concurrent_executions,
+            load: LoadTracker::new(),
+            transforms: TransformPipeline::new(),
+            profiles: config.profiles,
+            reuse_policies: config.reuse_policies,
+            reuse_metrics: RwLock::new(HashMap::new()),
+            signing_key: config.signing_key,
+            staging: config.staging,
+            #[cfg(feature = "fault-injection")]
+            faults: config.faults,
+            policy: config.policy,
+            audit: config.audit,
+            plugins: RwLock::new(Vec::new()),
+            affinity: RwLock::new(HashMap::new()),
+            traffic: Arc::new(TrafficMonitor::new()),
+            redaction: RedactionPolicy {
+                redactors: config.redactors,
+                default_redactor: config.default_redactor,
+            },
+        }
+    }
+
+    pub fn register_runtime(&self, runtime_type: RuntimeType, runtime: Arc<dyn Runtime>) {
+        info!("Registering {:?} runtime with orchestrator", runtime_type);
+        self.runtimes.write().insert(runtime_type, runtime);
+        self.breakers.write().insert(
+            runtime_type,
+            Arc::new(CircuitBreaker::new(self.breaker_config.clone())),
+        );
+    }
+
+    /// Adds a pass that rewrites code before every compilation it applies to.
+    /// Passes run in registration order.
+    pub fn register_transform(&self, pass: Arc<dyn CodeTransform>) {
+        info!("Registering code transform `{}`", pass.name());
+        self.transforms.register(pass);
+    }
+
+    /// Adds a plugin run on every execution request, after those already
+    /// registered, replacing any plugin with the same name. Its module must
+    /// have been compiled on the WASM runtime. Plugins run before the
+    /// policy, which sees the tags and profile they applied.
+    pub fn register_plugin(&self, plugin: RequestPlugin) -> Result<()> {
+        match self.modules.read().get(&plugin.module_id) {
+            Some(module) if module.runtime == RuntimeType::Wasm => {}
+            Some(_) => return Err(anyhow!("Plugin `{}` must be a WASM module", plugin.name)),
+            None => return Err(anyhow!("Module not found: {}", plugin.module_id.0)),
+        }
+
+        info!("Registering request plugin `{}`", plugin.name);
+        let mut plugins = self.plugins.write();
+        match plugins.iter_mut().find(|existing| existing.name == plugin.name) {
+            Some(existing) => *existing = plugin,
+            None => plugins.push(plugin),
+        }
+        Ok(())
+    }
+
+    /// Returns whether a plugin called `name` was registered.
+    pub fn unregister_plugin(&self, name: &str) -> bool {
+        let mut plugins = self.plugins.write();
+        let before = plugins.len();
+        plugins.retain(|plugin| plugin.name != name);
+        plugins.len() != before
+    }
+
+    pub fn transform_metrics(&self) -> HashMap<String, TransformMetrics> {
+        self.transforms.metrics()
+    }
+
+    pub fn available_runtimes(&self) -> Vec<RuntimeType> {
+        self.runtimes.read().keys().copied().collect()
+    }
+
+    /// Executions running and queued for a slot, so embedders can turn
+    /// requests away before they pile up behind a full orchestrator.
+    pub fn current_load(&self) -> LoadReport {
+        let available_slots = self.execution_slots.as_ref().map(|slots| slots.available_permits());
+        self.load.report(available_slots, self.max_concurrent_executions)
+    }
+
+    /// `current_load` of each registered runtime.
+    pub fn runtime_loads(&self) -> HashMap<RuntimeType, LoadReport> {
+        self.runtimes
+            .read()
+            .iter()
+            .map(|(runtime_type, runtime)| (*runtime_type, runtime.current_load()))
+            .collect()
+    }
+
+    /// Languages each registered runtime compiles on this host, and the
+    /// toolchains it found or is missing.
+    pub fn capabilities(&self) -> HashMap<RuntimeType, RuntimeCapabilities> {
+        self.runtimes
+            .read()
+            .iter()
+            .map(|(runtime_type, runtime)| (*runtime_type, runtime.capabilities()))
+            .collect()
+    }
+
+    fn runtime(&self, runtime_type: RuntimeType) -> Result<Arc<dyn Runtime>> {
+        self.runtimes
+            .read()
+            .get(&runtime_type)
+            .cloned()
+            .ok_or_else(|| anyhow!("Runtime not registered: {:?}", runtime_type))
+    }
+
+    /// Phase budgets for jobs at `trust_level`. Compiles are budgeted by the
+    /// caller's trust level, and instantiations by the level the module was
+    /// compiled at.
+    pub fn phase_timeouts(&self, trust_level: TrustLevel) -> PhaseTimeouts {
+        self.phase_timeouts
+            .get(&trust_level)
+            .copied()
+            .unwrap_or_else(|| PhaseTimeouts::for_trust_level(trust_level))
+    }
+
+    /// Contamination policy for executions at `trust_level`.
+    pub fn reuse_policy(&self, trust_level: TrustLevel) -> ReusePolicy {
+        self.reuse_policies
+            .get(&trust_level)
+            .copied()
+            .unwrap_or_else(|| ReusePolicy::for_trust_level(trust_level))
+    }
+
+    /// Instance reuse counts by trust level, for weighing warm-instance
+    /// latency against isolation.
+    pub fn reuse_metrics(&self) -> HashMap<TrustLevel, ReuseMetrics> {
+        self.reuse_metrics.read().clone()
+    }
+
+    /// Decides whether an execution for `config` may run on `instance_id`
+    /// and records the instance's new last tenant when it does.
+    fn claim_instance(&self, instance_id: &InstanceId, config: &ExecutionConfig) -> Reuse {
+        let trust_level = config.permissions.trust_level;
+        let tenant = config.tenant_id.as_deref();
+
+        let reuse = match self.instances.write().get_mut(instance_id) {
+            Some(entry) => match &entry.last_tenant {
+                Some(last) if !self.reuse_policy(trust_level).allows(last.as_deref(), tenant) => Reuse::Fresh,
+                last => {
+                    let reuse = if last.is_some() { Reuse::Reused } else { Reuse::FirstUse };
+                    entry.last_tenant = Some(config.tenant_id.clone());
+                    reuse
+                }
+            },
+            None => Reuse::FirstUse,
+        };
+
+        let mut metrics = self.reuse_metrics.write();
+        let metrics = metrics.entry(trust_level).or_default();
+        match reuse {
+            Reuse::FirstUse => metrics.first_use += 1,
+            Reuse::Reused => metrics.reused += 1,
+            Reuse::Fresh => metrics.fresh += 1,
+        }
+        reuse
+    }
+
+    /// Configured profile called `name`, falling back to the built-in ones.
+    pub fn profile(&self, name: &str) -> Result<Profile> {
+        self.profiles
+            .get(name)
+            .cloned()
+            .or_else(|| Profile::by_name(name))
+            .ok_or_else(|| anyhow!("Unknown execution profile: {}", name))
+    }
+
+    /// Delays or fails the start of `phase` as the fault injector decides.
+    /// Does nothing unless fault injection is configured.
+    async fn inject_fault(&self, phase: JobPhase) -> Result<()> {
+        #[cfg(feature = "fault-injection")]
+        if let Some(faults) = &self.faults {
+            faults.inject(phase).await?;
+        }
+        #[cfg(not(feature = "fault-injection"))]
+        let _ = phase;
+        Ok(())
+    }
+
+    /// Runs one phase of a job, failing with `RuntimeError::PhaseTimeout`
+    /// once `budget` is spent.
+    async fn within_budget<T>(
+        phase: JobPhase,
+        budget: Duration,
+        future: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        match tokio::time::timeout(budget, future).await {
+            Ok(result) => result,
+            Err(_) => Err(RuntimeError::PhaseTimeout { phase, budget }.into()),
+        }
+    }
+
+    /// Returns the runtime if its breaker admits another call.
+    fn acquire(&self, runtime_type: RuntimeType) -> Result<(Arc<dyn Runtime>, Arc<CircuitBreaker>)> {
+        let runtime = self.runtime(runtime_type)?;
+        let breaker = self
+            .breakers
+            .read()
+            .get(&runtime_type)
+            .cloned()
+            .ok_or_else(|| anyhow!("Runtime not registered: {:?}", runtime_type))?;
+
+        let (allowed, transition) = breaker.try_acquire();
+        self.publish_transition(runtime_type, transition);
+        if !allowed {
+            return Err(anyhow!("Circuit breaker open for {:?} runtime", runtime_type));
+        }
+
+        Ok((runtime, breaker))
+    }
+
+    /// Feeds a backend outcome into the breaker. Compile errors in user code
+    /// and security rejections say nothing about backend health and count as
+    /// successes.
+    fn record_outcome<T>(&self, runtime_type: RuntimeType, breaker: &CircuitBreaker, result: &Result<T>) {
+        let caller_error = |e: &anyhow::Error| {
+            e.downcast_ref::<CompileError>().is_some()
+                || e.downcast_ref::<TransformError>().is_some()
+                || matches!(e.downcast_ref::<RuntimeError>(), Some(RuntimeError::SecurityError(_)))
+        };
+        let transition = match result {
+            Err(e) if !caller_error(e) => breaker.record_failure(),
+            _ => breaker.record_success(),
+        };
+        self.publish_transition(runtime_type, transition);
+    }
+
+    fn publish_transition(&self, runtime: RuntimeType, transition: Option<(BreakerState, BreakerState)>) {
+        if let Some((from, to)) = transition {
+            warn!("{:?} circuit breaker {:?} -> {:?}", runtime, from, to);
+            let _ = self.breaker_events.send(BreakerEvent {
+                runtime,
+                from,
+                to,
+                at: SystemTime::now(),
+            });
+        }
+    }
+
+    pub fn subscribe_breaker_events(&self) -> broadcast::Receiver<BreakerEvent> {
+        self.breaker_events.subscribe()
+    }
+
+    pub fn breaker_metrics(&self) -> HashMap<RuntimeType, BreakerMetrics> {
+        self.breakers
+            .read()
+            .iter()
+            .map(|(runtime_type, breaker)| (*runtime_type, breaker.metrics()))
+            .collect()
+    }
+
+    /// Picks `runtime_type`, or its configured fallback while the primary's
+    /// breaker is open.
+    fn route_compilation(
+        &self,
+        runtime_type: RuntimeType,
+    ) -> Result<(RuntimeType, Arc<dyn Runtime>, Arc<CircuitBreaker>)> {
+        match self.acquire(runtime_type) {
+            Ok((runtime, breaker)) => Ok((runtime_type, runtime, breaker)),
+            Err(e) => match self.fallbacks.get(&runtime_type) {
+                Some(&fallback) => {
+                    let (runtime, breaker) = self.acquire(fallback).map_err(|_| e)?;
+                    info!("Routing {:?} compilation to fallback {:?}", runtime_type, fallback);
+                    Ok((fallback, runtime, breaker))
+                }
+                None => Err(e),
+            },
+        }
+    }
+
+    /// Compiles on `runtime_type`, or on its configured fallback while the
+    /// primary's breaker is open. Registered transforms run first, within the
+    /// compile budget for `trust_level`.
+    pub async fn compile(
+        &self,
+        runtime_type: RuntimeType,
+        code: &[u8],
+        language: Language,
+        trust_level: TrustLevel,
+    ) -> Result<ModuleId> {
+        let (runtime_type, runtime, breaker) = self.route_compilation(runtime_type)?;
+        let context = TransformContext {
+            runtime: runtime_type,
+            language,
+        };
+
+        let budget = self.phase_timeouts(trust_level).compile;
+        let compile = async {
+            self.inject_fault(JobPhase::Compile).await?;
+            let code = self.transforms.apply(code.to_vec(), &context).await?;
+            Ok((runtime.compile(&code, language).await?, sha256_hex(&code)))
+        };
+        let result = Self::within_budget(JobPhase::Compile, budget, compile).await;
+        self.record_outcome(runtime_type, &breaker, &result);
+        let (module_id, code_sha256) = result?;
+
+        self.track_module(module_id.clone(), runtime_type, language, trust_level, Some(code_sha256), Vec::new());
+        Ok(module_id)
+    }
+
+    /// `compile` for callers that don't name the language: it is detected
+    /// from the code and `file_name` unless `language` overrides it.
+    pub async fn compile_detected(
+        &self,
+        runtime_type: RuntimeType,
+        code: &[u8],
+        file_name: Option<&str>,
+        language: Option<Language>,
+        trust_level: TrustLevel,
+    ) -> Result<(ModuleId, LanguageDetection)> {
+        let detection = detect_language(code, file_name, language)
+            .filter(|detection| detection.confidence >= MIN_CONFIDENCE)
+            .ok_or_else(|| anyhow!("Could not detect the language of the submitted code; specify it explicitly"))?;
+        debug!("Detected {:?} ({:?}, confidence {:.2})", detection.language, detection.method, detection.confidence);
+
+        let module_id = self.compile(runtime_type, code, detection.language, trust_level).await?;
+        Ok((module_id, detection))
+    }
+
+    /// Like `compile`, but hands `reader` to the runtime so large uploads can
+    /// be validated as they arrive. Uploads that a registered transform applies
+    /// to are buffered and transformed first.
+    pub async fn compile_stream(
+        &self,
+        runtime_type: RuntimeType,
+        mut reader: Box<dyn AsyncRead + Send + Unpin>,
+        language: Language,
+        trust_level: TrustLevel,
+    ) -> Result<ModuleId> {
+        let (runtime_type, runtime, breaker) = self.route_compilation(runtime_type)?;
+        let context = TransformContext {
+            runtime: runtime_type,
+            language,
+        };
+
+        let budget = self.phase_timeouts(trust_level).compile;
+        let compile = async {
+            self.inject_fault(JobPhase::Compile).await?;
+            if !self.transforms.applies_to(&context) {
+                let (reader, digest) = DigestReader::new(reader);
+                let module_id = runtime.compile_stream(Box::new(reader), language).await?;
+                return Ok((module_id, digest.hex()));
+            }
+            let mut code = Vec::new();
+            reader.read_to_end(&mut code).await?;
+            let code = self.transforms.apply(code, &context).await?;
+            Ok((runtime.compile(&code, language).await?, sha256_hex(&code)))
+        };
+        let result = Self::within_budget(JobPhase::Compile, budget, compile).await;
+        self.record_outcome(runtime_type, &breaker, &result);
+        let (module_id, code_sha256) = result?;
+
+        self.track_module(module_id.clone(), runtime_type, language, trust_level, Some(code_sha256), Vec::new());
+        Ok(module_id)
+    }
+
+    /// Compiles an artifact uploaded to the staging area, given the
+    /// reference `StagingArea::complete` returned for it.
+    pub async fn compile_staged(
+        &self,
+        runtime_type: RuntimeType,
+        reference: &str,
+        language: Language,
+        trust_level: TrustLevel,
+    ) -> Result<ModuleId> {
+        let staging = self
+            .staging
+            .as_ref()
+            .ok_or_else(|| anyhow!("No staging area is configured"))?;
+        let artifact = staging.open(reference).await?;
+        self.compile_stream(runtime_type, Box::new(tokio::io::BufReader::new(artifact)), language, trust_level)
+            .await
+    }
+
+    /// Staging area that `compile_staged` reads from, if configured.
+    pub fn staging(&self) -> Option<&Arc<StagingArea>> {
+        self.staging.as_ref()
+    }
+
+    /// Compiles a bundle whose first module links against the others.
+    pub async fn compile_bundle(
+        &self,
+        runtime_type: RuntimeType,
+        modules: Vec<(String, Vec<u8>)>,
+        language: Language,
+        trust_level: TrustLevel,
+    ) -> Result<ModuleId> {
+        let (runtime_type, runtime, breaker) = self.route_compilation(runtime_type)?;
+
+        let context = TransformContext {
+            runtime: runtime_type,
+            language,
+        };
+
+        let budget = self.phase_timeouts(trust_level).compile;
+        let compile = async {
+            self.inject_fault(JobPhase::Compile).await?;
+            let mut transformed = Vec::with_capacity(modules.len());
+            for (name, code) in modules {
+                transformed.push((name, self.transforms.apply(code, &context).await?));
+            }
+            let hashes: Vec<_> = transformed.iter().map(|(name, code)| (name.clone(), sha256_hex(code))).collect();
+            let code_sha256 = sha256_hex(&serde_json::to_vec(&hashes)?);
+            Ok((runtime.compile_bundle(transformed, language).await?, code_sha256, hashes))
+        };
+        let result = Self::within_budget(JobPhase::Compile, budget, compile).await;
+        self.record_outcome(runtime_type, &breaker, &result);
+        let (module_id, code_sha256, hashes) = result?;
+
+        let components = hashes
+            .into_iter()
+            .map(|(name, sha256)| ModuleDigest { name: Some(name), sha256 })
+            .collect();
+        self.track_module(module_id.clone(), runtime_type, language, trust_level, Some(code_sha256), components);
+        Ok(module_id)
+    }
+
+    fn track_module(
+        &self,
+        module_id: ModuleId,
+        runtime_type: RuntimeType,
+        language: Language,
+        trust_level: TrustLevel,
+        code_sha256: Option<String>,
+        components: Vec<ModuleDigest>,
+    ) {
+        self.modules.write().insert(
+            module_id.clone(),
+            ModuleEntry {
+                runtime: runtime_type,
+                language,
+                code_sha256,
+                components,
+                trust_level,
+            },
+        );
+    }
+
+    /// Dry-run of `compile`: reports diagnostics on the transformed code
+    /// without registering a module.
+    pub async fn validate(
+        &self,
+        runtime_type: RuntimeType,
+        code: &[u8],
+        language: Language,
+        trust_level: TrustLevel,
+    ) -> Result<ValidationReport> {
+        let runtime = self.runtime(runtime_type)?;
+        let context = TransformContext {
+            runtime: runtime_type,
+            language,
+        };
+
+        let budget = self.phase_timeouts(trust_level).compile;
+        let validate = async {
+            let code = self.transforms.apply(code.to_vec(), &context).await?;
+            runtime.validate(&code, language, trust_level).await
+        };
+        Self::within_budget(JobPhase::Compile, budget, validate).await
+    }
+
+    /// Hands out a provisioned instance when one is pooled, otherwise
+    /// instantiates a fresh one within the instantiate budget for the trust
+    /// level the module was compiled for.
+    pub async fn instantiate(&self, module_id: ModuleId) -> Result<InstanceId> {
+        let module = self
+            .modules
+            .read()
+            .get(&module_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Module not found: {}", module_id.0))?;
+
+        if let Some(instance_id) = self.pool.write().get_mut(&module_id).and_then(Vec::pop) {
+            debug!("Reusing provisioned instance {} of module {}", instance_id.0, module_id.0);
+            return Ok(instance_id);
+        }
+
+        let (runtime, breaker) = self.acquire(module.runtime)?;
+        let budget = self.phase_timeouts(module.trust_level).instantiate;
+        let instantiate = async {
+            self.inject_fault(JobPhase::Instantiate).await?;
+            runtime.instantiate(module_id.clone()).await
+        };
+        let result = Self::within_budget(JobPhase::Instantiate, budget, instantiate).await;
+        self.record_outcome(module.runtime, &breaker, &result);
+        let instance_id = result?;
+
+        self.instances.write().insert(
+            instance_id.clone(),
+            InstanceEntry {
+                runtime: module.runtime,
+                module_id,
+                language: module.language,
+                last_tenant: None,
+            },
+        );
+
+        Ok(instance_id)
+    }
+
+    /// Pre-instantiates `count` instances of a module into the pool that
+    /// `instantiate` draws from, so known traffic spikes skip cold starts.
+    pub async fn provision(&self, module_id: ModuleId, count: usize) -> Result<ProvisionReport> {
+        let module = self
+            .modules
+            .read()
+            .get(&module_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Module not found: {}", module_id.0))?;
+
+        let start = Instant::now();
+        let (runtime, breaker) = self.acquire(module.runtime)?;
+
+        let budget = self.phase_timeouts(module.trust_level).instantiate;
+        let mut tasks = JoinSet::new();
+        for _ in 0..count {
+            let runtime = runtime.clone();
+            let module_id = module_id.clone();
+            tasks.spawn(async move {
+                Self::within_budget(JobPhase::Instantiate, budget, runtime.instantiate(module_id)).await
+            });
+        }
+
+        let mut ready = Vec::with_capacity(count);
+        let mut failures = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            let result = joined.map_err(|e| anyhow!("Provisioning task failed: {}", e)).and_then(|r| r);
+            self.record_outcome(module.runtime, &breaker, &result);
+            match result {
+                Ok(instance_id) => ready.push(instance_id),
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+
+        {
+            let mut instances = self.instances.write();
+            for instance_id in &ready {
+                instances.insert(
+                    instance_id.clone(),
+                    InstanceEntry {
+                        runtime: module.runtime,
+                        module_id: module_id.clone(),
+                        language: module.language,
+                        last_tenant: None,
+                    },
+                );
+            }
+        }
+
+        let report = {
+            let mut pool = self.pool.write();
+            let pooled = pool.entry(module_id.clone()).or_default();
+            pooled.extend(ready.iter().cloned());
+            ProvisionReport {
+                requested: count,
+                ready: ready.len(),
+                pooled: pooled.len(),
+                failures,
+                elapsed: start.elapsed(),
+            }
+        };
+
+        info!(
+            "Provisioned {}/{} instances of module {} ({} pooled)",
+            report.ready, report.requested, module_id.0, report.pooled
+        );
+        Ok(report)
+    }
+
+    pub fn pooled_instances(&self, module_id: &ModuleId) -> usize {
+        self.pool.read().get(module_id).map_or(0, Vec::len)
+    }
+
+    /// Destroys up to `count` provisioned instances of a module, returning
+    /// how many were destroyed.
+    pub async fn drain_pool(&self, module_id: &ModuleId, count: usize) -> Result<usize> {
+        let drained: Vec<InstanceId> = {
+            let mut pool = self.pool.write();
+            let Some(pooled) = pool.get_mut(module_id) else {
+                return Ok(0);
+            };
+            let keep = pooled.len().saturating_sub(count);
+            pooled.drain(keep..).collect()
+        };
+
+        let mut destroyed = 0;
+        for instance_id in drained {
+            let runtime = self.instances.write().remove(&instance_id).map(|entry| entry.runtime);
+            if let Some(runtime) = runtime {
+                self.runtime(runtime)?.destroy(instance_id).await?;
+                destroyed += 1;
+            }
+        }
+        debug!("Drained {} provisioned instances of module {}", destroyed, module_id.0);
+        Ok(destroyed)
+    }
+
+    /// Arrivals and latencies of the executions run so far, for autoscaling.
+    pub fn traffic(&self) -> Arc<TrafficMonitor> {
+        self.traffic.clone()
+    }
+
+    /// Instantiates a registered module, e.g. `filters/ipv4@1.2.0`,
+    /// `filters/ipv4@stable` or just `filters/ipv4` for the latest version.
+    pub async fn instantiate_named(&self, reference: &str) -> Result<InstanceId> {
+        let module_id = self.resolve_module(reference).await?;
+        self.instantiate(module_id).await
+    }
+
+    /// Stores a compiled module under `name@version`. Versions are immutable.
+    pub async fn register_module(&self, reference: &str, module_id: ModuleId) -> Result<RegisteredModule> {
+        let reference = ModuleRef::parse(reference)?;
+        let version = reference
+            .version
+            .ok_or_else(|| anyhow!("Module reference `{}` has no version", reference.name))?;
+        let entry = self
+            .modules
+            .read()
+            .get(&module_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Module not found: {}", module_id.0))?;
+
+        let registered = RegisteredModule {
+            name: reference.name,
+            version,
+            module_id,
+            runtime: entry.runtime,
+            language: entry.language,
+            registered_at: SystemTime::now(),
+        };
+        self.registry.register(registered.clone()).await?;
+        info!("Registered module {}@{}", registered.name, registered.version);
+        Ok(registered)
+    }
+
+    /// Points `name@alias` (e.g. `filters/ipv4@stable`) at a registered version.
+    pub async fn set_module_alias(&self, name: &str, alias: &str, version: &str) -> Result<()> {
+        ModuleRef::parse(&format!("{}@{}", name, alias))?;
+        self.registry.set_alias(name, alias, version).await
+    }
+
+    pub async fn resolve_module(&self, reference: &str) -> Result<ModuleId> {
+        let parsed = ModuleRef::parse(reference)?;
+        let registered = self
+            .registry
+            .resolve(&parsed)
+            .await?
+            .ok_or_else(|| anyhow!("Module not registered: {}", parsed))?;
+
+        // A persistent registry may outlive the orchestrator that filled it
+        if !self.modules.read().contains_key(&registered.module_id) {
+            self.track_module(
+                registered.module_id.clone(),
+                registered.runtime,
+                registered.language,
+                TrustLevel::default(),
+                None,
+                Vec::new(),
+            );
+        }
+        Ok(registered.module_id)
+    }
+
+    /// Compiles on the registered runtime the profile prefers most, at the
+    /// profile's trust level.
+    pub async fn compile_for_profile(&self, profile: &str, code: &[u8], language: Language) -> Result<ModuleId> {
+        let profile = self.profile(profile)?;
+        let runtime_type = profile
+            .preferred_runtime(&self.available_runtimes())
+            .ok_or_else(|| anyhow!("No runtime registered for profile {}", profile.name))?;
+        self.compile(runtime_type, code, language, profile.permissions.trust_level).await
+    }
+
+    pub async fn list_modules(&self, name: Option<&str>) -> Result<Vec<RegisteredModule>> {
+        self.registry.list(name).await
+    }
+
+    /// Drops registered versions `policy` no longer retains. Compiled modules
+    /// stay loaded in their runtime; only the names are removed.
+    pub async fn gc_modules(&self, policy: &RetentionPolicy) -> Result<Vec<RegisteredModule>> {
+        let removed = self.registry.collect_garbage(policy).await?;
+        for module in &removed {
+            debug!("Garbage-collected module {}@{}", module.name, module.version);
+        }
+        Ok(removed)
+    }
+
+    /// Runs an instance within the queue-wait and execute budgets for the
+    /// config's trust level. `config.timeout` is capped at the execute budget.
+    /// Every outcome is recorded in history and sent to webhooks, including
+    /// requests a plugin or the policy turns away before they run.
+    pub async fn execute(
+        &self,
+        instance_id: InstanceId,
+        mut config: ExecutionConfig,
+    ) -> Result<ExecutionResult> {
+        let instance = self
+            .instances
+            .read()
+            .get(&instance_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Instance not found: {}", instance_id.0))?;
+
+        let started_at = SystemTime::now();
+        let start = Instant::now();
+        let result = self.run_execution(&instance_id, &instance, &mut config).await;
+        self.record_execution(instance_id, instance, config, &result, started_at, start.elapsed()).await;
+        result
+    }
+
+    /// Everything `execute` does between finding the instance and recording
+    /// the outcome. Plugins run first, so the policy judges the request as
+    /// they left it.
+    async fn run_execution(
+        &self,
+        instance_id: &InstanceId,
+        instance: &InstanceEntry,
+        config: &mut ExecutionConfig,
+    ) -> Result<ExecutionResult> {
+        self.run_plugins(instance_id, instance, config).await?;
+        self.check_policy(instance_id, instance, config).await?;
+
+        let timeouts = self.phase_timeouts(config.permissions.trust_level);
+        config.timeout = config.timeout.min(timeouts.execute);
+
+        let mut trace = TraceCapture::new(config.trace_verbosity);
+        let mut timing = TimingBreakdown::default();
+        let queued = Instant::now();
+        let waiting = self.load.queue();
+        let _slot = match &self.execution_slots {
+            Some(slots) => {
+                let acquire = async {
+                    self.inject_fault(JobPhase::QueueWait).await?;
+                    slots.clone().acquire_owned().await.map_err(|e| anyhow!(e))
+                };
+                Some(Self::within_budget(JobPhase::QueueWait, timeouts.queue_wait, acquire).await?)
+            }
+            None => None,
+        };
+        drop(waiting);
+        let _running = self.load.run();
+        timing.queue_wait = queued.elapsed();
+        trace.basic(|| format!("Waited {:?} for an execution slot", timing.queue_wait));
+
+        let scheduling = Instant::now();
+        let (runtime, breaker) = self.acquire(instance.runtime)?;
+
+        // When the reuse policy rules out this instance, run on a fresh one
+        // of the same module and leave the caller's instance untouched
+        let reuse = self.claim_instance(instance_id, config);
+        timing.scheduling = scheduling.elapsed();
+        trace.verbose(|| {
+            let reuse = match &reuse {
+                Reuse::FirstUse => "its first use",
+                Reuse::Reused => "reused",
+                Reuse::Fresh => "replaced by a fresh instance",
+            };
+            format!("Scheduled on the {:?} runtime; instance {} {}", instance.runtime, instance_id.0, reuse)
+        });
+        let fresh = match reuse {
+            Reuse::Fresh => {
+                debug!("Reuse policy requires a fresh instance in place of {}", instance_id.0);
+                let instantiating = Instant::now();
+                let instantiate = runtime.instantiate(instance.module_id.clone());
+                let result = Self::within_budget(JobPhase::Instantiate, timeouts.instantiate, instantiate).await;
+                timing.instantiate = instantiating.elapsed();
+                self.record_outcome(instance.runtime, &breaker, &result);
+                Some(result?)
+            }
+            Reuse::FirstUse | Reuse::Reused => None,
+        };
+
+        let start = Instant::now();
+
+        // The runtime enforces `config.timeout` itself; this only catches one
+        // that fails to return
+        let execute = async {
+            self.inject_fault(JobPhase::Execute).await?;
+            runtime.execute(fresh.clone().unwrap_or_else(|| instance_id.clone()), config.clone()).await
+        };
+        let mut result = match tokio::time::timeout(config.timeout + EXECUTE_GRACE, execute).await {
+            Ok(result) => result,
+            Err(_) => Err(RuntimeError::PhaseTimeout {
+                phase: JobPhase::Execute,
+                budget: config.timeout,
+            }
+            .into()),
+        };
+        let executed = start.elapsed();
+        trace.basic(|| match &result {
+            Ok(result) if result.success => format!("Executed in {:?}", executed),
+            Ok(_) => format!("Execution failed after {:?}", executed),
+            Err(_) => format!("Runtime errored after {:?}", executed),
+        });
+        self.record_outcome(instance.runtime, &breaker, &result);
+        self.traffic.record(instance.runtime, queued.elapsed());
+
+        if let Some(fresh) = fresh {
+            let tearing_down = Instant::now();
+            if let Err(e) = runtime.destroy(fresh).await {
+                warn!("Failed to destroy single-use instance: {}", e);
+            }
+            timing.teardown = tearing_down.elapsed();
+        }
+
+        // Redacted before signing, so the attestation covers what the
+        // caller actually receives
+        if let (Some(redactor), Ok(exec_result)) = (self.redaction.redactor(config.tenant_id.as_deref()), &mut result) {
+            let redacted = redactor.redact_result(exec_result, config.output_encoding);
+            trace.verbose(|| format!("Redacted {} spans of PII", redacted));
+            if redacted > 0 {
+                info!(
+                    target: "next_rc::audit",
+                    instance = %instance_id.0,
+                    tenant = config.tenant_id.as_deref().unwrap_or("-"),
+                    redacted,
+                    "Redacted PII from execution result"
+                );
+            }
+        }
+        if let Ok(exec_result) = &mut result {
+            // Runtimes that don't break their own time down get it counted
+            // as execution
+            *exec_result.timing.get_or_insert(TimingBreakdown {
+                execute: executed,
+                ..Default::default()
+            }) += timing;
+            exec_result.environment_fingerprint = Some(self.fingerprint(runtime.as_ref(), instance, config));
+            exec_result.logs.trace.extend(trace.finish());
+        }
+        if let (Some(key), Ok(exec_result)) = (&self.signing_key, &mut result) {
+            exec_result.attestation = self.attest(key, instance, config, exec_result);
+        }
+
+        result
+    }
+
+    /// Records `result` in history and dispatches the matching webhook.
+    async fn record_execution(
+        &self,
+        instance_id: InstanceId,
+        instance: InstanceEntry,
+        config: ExecutionConfig,
+        result: &Result<ExecutionResult>,
+        started_at: SystemTime,
+        elapsed: Duration,
+    ) {
+        let (status, duration, error) = match result {
+            Ok(result) if result.success => (ExecutionStatus::Succeeded, result.execution_time, None),
+            Ok(result) if result.execution_time >= config.timeout => {
+                (ExecutionStatus::TimedOut, result.execution_time, result.error.clone())
+            }
+            Ok(result) => (ExecutionStatus::Failed, result.execution_time, result.error.clone()),
+            Err(e) if matches!(e.downcast_ref::<RuntimeError>(), Some(RuntimeError::PhaseTimeout { .. })) => {
+                (ExecutionStatus::TimedOut, elapsed, Some(e.to_string()))
+            }
+            Err(e) => (ExecutionStatus::Errored, elapsed, Some(e.to_string())),
+        };
+
+        let record = ExecutionRecord {
+            id: Uuid::new_v4(),
+            tenant_id: config.tenant_id,
+            runtime: instance.runtime,
+            language: instance.language,
+            module_id: instance.module_id,
+            instance_id,
+            status,
+            duration,
+            started_at,
+            error,
+            tags: config.tags,
+        };
+
+        if let Some(webhooks) = &self.webhooks {
+            let quota_exceeded = matches!(
+                result,
+                Err(e) if matches!(
+                    e.downcast_ref::<RuntimeError>(),
+                    Some(RuntimeError::ResourceLimitExceeded(_))
+                )
+            );
+            let kind = match record.status {
+                ExecutionStatus::Succeeded => WebhookEventKind::ExecutionCompleted,
+                _ if quota_exceeded => WebhookEventKind::QuotaExceeded,
+                _ => WebhookEventKind::ExecutionFailed,
+            };
+            webhooks.dispatch(Self::webhook_event(kind, &record));
+        }
+
+        if let Some(history) = &self.history {
+            debug!("Recording execution {} ({:?})", record.id, record.status);
+            if let Err(e) = history.record(record).await {
+                warn!("Failed to record execution history: {}", e);
+            }
+        }
+    }
+
+    /// Passes the request through every registered plugin in turn, each
+    /// seeing the changes made by those before it.
+    async fn run_plugins(&self, instance_id: &InstanceId, instance: &InstanceEntry, config: &mut ExecutionConfig) -> Result<()> {
+        let plugins = self.plugins.read().clone();
+        if plugins.is_empty() {
+            return Ok(());
+        }
+        let runtime = self
+            .runtimes
+            .read()
+            .get(&RuntimeType::Wasm)
+            .cloned()
+            .ok_or_else(|| anyhow!("Request plugins need the WASM runtime, which is not registered"))?;
+
+        for plugin in plugins {
+            let request = PluginRequest {
+                instance_id: instance_id.clone(),
+                module_id: instance.module_id.clone(),
+                runtime: instance.runtime,
+                language: instance.language,
+                tenant_id: config.tenant_id.clone(),
+                trust_level: config.permissions.trust_level,
+                capabilities: config.permissions.capabilities.clone(),
+                tags: config.tags.clone(),
+                timeout: config.timeout,
+                memory_limit: config.memory_limit,
+                input_len: config.input.as_ref().map_or(0, Vec::len),
+            };
+            let verdict = match plugin.run(runtime.as_ref(), &request).await {
+                Ok(verdict) => verdict,
+                Err(e) if plugin.fail_open => {
+                    warn!("Plugin `{}` failed, admitting request: {}", plugin.name, e);
+                    continue;
+                }
+                Err(e) => {
+                    return Err(RuntimeError::SecurityError(format!("Plugin `{}` failed: {}", plugin.name, e)).into());
+                }
+            };
+
+            if let Some(reason) = verdict.reject {
+                return Err(RuntimeError::SecurityError(format!("Rejected by plugin `{}`: {}", plugin.name, reason)).into());
+            }
+            if let Some(profile) = verdict.profile {
+                let profile = self.profile(&profile)?;
+                debug!("Plugin `{}` routed execution to profile {}", plugin.name, profile.name);
+                config.timeout = profile.timeout;
+                config.memory_limit = profile.memory_limit;
+                config.permissions = profile.permissions;
+            }
+            config.tags.extend(verdict.tags);
+        }
+        Ok(())
+    }
+
+    /// Asks the policy whether `config` may run on `instance`, logging and
+    /// recording the decision. Policies that fail to decide deny.
+    async fn check_policy(&self, instance_id: &InstanceId, instance: &InstanceEntry, config: &ExecutionConfig) -> Result<()> {
+        let Some(policy) = &self.policy else {
+            return Ok(());
+        };
+
+        let engine_config = self
+            .runtimes
+            .read()
+            .get(&instance.runtime)
+            .map(|runtime| runtime.environment().engine_config)
+            .unwrap_or_default();
+        let input = PolicyInput {
+            tenant_id: config.tenant_id.clone(),
+            trust_level: config.permissions.trust_level,
+            runtime: instance.runtime,
+            capabilities: config.permissions.capabilities.clone(),
+            tags: config.tags.clone(),
+            engine_config,
+            time: SystemTime::now(),
+        };
+        let decision = policy
+            .evaluate(&input)
+            .await
+            .unwrap_or_else(|e| PolicyDecision::deny(None, format!("Policy evaluation failed: {}", e)));
+
+        info!(
+            target: "next_rc::audit",
+            instance = %instance_id.0,
+            tenant = input.tenant_id.as_deref().unwrap_or("-"),
+            trust_level = ?input.trust_level,
+            allowed = decision.allowed,
+            rule = decision.rule.as_deref().unwrap_or("-"),
+            "Policy decision"
+        );
+        let denied = (!decision.allowed).then(|| decision.reason.clone().unwrap_or_default());
+        if let Some(audit) = &self.audit {
+            let record = AuditRecord {
+                id: Uuid::new_v4(),
+                decided_at: input.time,
+                instance_id: instance_id.clone(),
+                input,
+                decision,
+            };
+            if let Err(e) = audit.record(record).await {
+                warn!("Failed to record policy decision: {}", e);
+            }
+        }
+
+        match denied {
+            Some(reason) => Err(RuntimeError::SecurityError(format!("Execution denied by policy: {}", reason)).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// What `instance` ran with: its runtime's engine, the policy in
+    /// `config` and the hashes of the module's code.
+    fn fingerprint(&self, runtime: &dyn Runtime, instance: &InstanceEntry, config: &ExecutionConfig) -> EnvironmentFingerprint {
+        let modules = match self.modules.read().get(&instance.module_id) {
+            Some(module) if !module.components.is_empty() => module.components.clone(),
+            Some(ModuleEntry { code_sha256: Some(sha256), .. }) => vec![ModuleDigest {
+                name: None,
+                sha256: sha256.clone(),
+            }],
+            _ => Vec::new(),
+        };
+        EnvironmentFingerprint::new(instance.runtime, runtime.environment(), config.security_policy_sha256(), modules)
+    }
+
+    /// Signs `result` together with the hashes of the module's code and the
+    /// config it ran with.
+    fn attest(&self, key: &NodeKey, instance: &InstanceEntry, config: &ExecutionConfig, result: &ExecutionResult) -> Option<Attestation> {
+        let code_sha256 = self
+            .modules
+            .read()
+            .get(&instance.module_id)
+            .and_then(|module| module.code_sha256.clone());
+        let Some(code_sha256) = code_sha256 else {
+            warn!("Not signing result of module {}: its code hash is unknown", instance.module_id.0);
+            return None;
+        };
+
+        let attestation = serde_json::to_vec(config)
+            .map_err(Into::into)
+            .and_then(|input| key.attest(code_sha256, sha256_hex(&input), result.digest(), instance.runtime));
+        match attestation {
+            Ok(attestation) => Some(attestation),
+            Err(e) => {
+                warn!("Failed to sign execution result: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Runs `module_id` on the instance last used under the first of
+    /// `config.affinity_keys` that still has one, so state it kept stays
+    /// warm. Without such an instance one is taken from the pool or created,
+    /// and kept for the keys; without keys it is destroyed afterwards.
+    pub async fn execute_module(&self, module_id: ModuleId, config: ExecutionConfig) -> Result<ExecutionResult> {
+        let affine = self.affine_instance(&module_id, &config.affinity_keys);
+        let instance_id = match affine {
+            Some(instance_id) => instance_id,
+            None => self.instantiate(module_id.clone()).await?,
+        };
+
+        if config.affinity_keys.is_empty() {
+            let result = self.execute(instance_id.clone(), config).await;
+            if let Err(e) = self.destroy(instance_id).await {
+                warn!("Failed to destroy single-use instance: {}", e);
+            }
+            return result;
+        }
+
+        {
+            let mut affinity = self.affinity.write();
+            for key in &config.affinity_keys {
+                affinity.insert((module_id.clone(), key.clone()), instance_id.clone());
+            }
+        }
+        self.execute(instance_id, config).await
+    }
+
+    /// Forgets the instances kept for `key`, destroying those no other key
+    /// still routes to.
+    pub async fn release_affinity(&self, key: &str) -> Result<()> {
+        let released = {
+            let mut affinity = self.affinity.write();
+            let mut released = Vec::new();
+            affinity.retain(|(_, kept_for), instance_id| {
+                if kept_for == key {
+                    released.push(instance_id.clone());
+                }
+                kept_for != key
+            });
+            released.retain(|instance_id| !affinity.values().any(|kept| kept == instance_id));
+            released
+        };
+
+        for instance_id in released {
+            if self.instances.read().contains_key(&instance_id) {
+                self.destroy(instance_id).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Live instance of `module_id` kept for the first of `keys` that has
+    /// one. Keys whose instance is gone are forgotten.
+    fn affine_instance(&self, module_id: &ModuleId, keys: &[String]) -> Option<InstanceId> {
+        let mut affinity = self.affinity.write();
+        for key in keys {
+            let entry = (module_id.clone(), key.clone());
+            let Some(instance_id) = affinity.get(&entry) else {
+                continue;
+            };
+            if self.instances.read().contains_key(instance_id) {
+                debug!("Routing execution to instance {} for affinity key {}", instance_id.0, key);
+                return Some(instance_id.clone());
+            }
+            debug!("Instance for affinity key {} is gone, falling back", key);
+            affinity.remove(&entry);
+        }
+        None
+    }
+
+    /// `execute` with the named profile's config.
+    pub async fn execute_with_profile(&self, instance_id: InstanceId, profile: &str) -> Result<ExecutionResult> {
+        let config = self.profile(profile)?.execution_config();
+        self.execute(instance_id, config).await
+    }
+
+    /// Runs `pipeline` on `input`, each stage on a fresh instance fed the
+    /// previous stage's output. Stops at the first stage that fails or meets
+    /// its `halt_when` condition. Modules and profiles are resolved up front,
+    /// so a bad reference fails the call before any stage runs.
+    pub async fn run_pipeline(&self, pipeline: &Pipeline, input: Vec<u8>) -> Result<PipelineResult> {
+        pipeline.validate()?;
+        let start = Instant::now();
+
+        let mut plan = Vec::with_capacity(pipeline.stages.len());
+        for stage in &pipeline.stages {
+            let module_id = self.resolve_module(&stage.module).await?;
+            let runtime = self
+                .modules
+                .read()
+                .get(&module_id)
+                .map(|module| module.runtime)
+                .ok_or_else(|| anyhow!("Module not found: {}", module_id.0))?;
+            let mut config = match (&stage.config, &stage.profile) {
+                (Some(config), _) => config.clone(),
+                (None, Some(profile)) => self.profile(profile)?.execution_config(),
+                (None, None) => ExecutionConfig::default(),
+            };
+            config.tags.insert(PIPELINE_TAG.to_string(), pipeline.name.clone());
+            config.tags.insert(PIPELINE_STAGE_TAG.to_string(), stage.name.clone());
+            plan.push((stage, module_id, runtime, config));
+        }
+
+        let mut stages = Vec::with_capacity(plan.len());
+        let mut output = Some(input);
+        let mut halted_at = None;
+        for (stage, module_id, runtime, mut config) in plan {
+            let input = output.take();
+            config.input = input.clone();
+            let outcome = self.run_stage(&stage.name, module_id, runtime, config).await;
+            debug!("Pipeline {} stage {} finished (success: {})", pipeline.name, stage.name, outcome.success());
+
+            let stage_output = outcome.result.as_ref().and_then(|result| result.output.clone());
+            let halted = outcome.success()
+                && stage
+                    .halt_when
+                    .as_ref()
+                    .is_some_and(|condition| condition.matches(stage_output.as_deref().unwrap_or_default()));
+            let failed = !outcome.success();
+            output = if stage.forward_input { input } else { stage_output };
+            stages.push(outcome);
+
+            if halted {
+                halted_at = Some(stage.name.clone());
+            }
+            if halted || failed {
+                break;
+            }
+        }
+
+        Ok(PipelineResult {
+            pipeline: pipeline.name.clone(),
+            success: stages.iter().all(StageResult::success),
+            output,
+            halted_at,
+            stages,
+            total_time: start.elapsed(),
+        })
+    }
+
+    async fn run_stage(&self, name: &str, module_id: ModuleId, runtime: RuntimeType, config: ExecutionConfig) -> StageResult {
+        let mut outcome = StageResult {
+            name: name.to_string(),
+            runtime,
+            result: None,
+            error: None,
+            instantiate_time: Duration::ZERO,
+            execute_time: Duration::ZERO,
+        };
+
+        let start = Instant::now();
+        let instance_id = self.instantiate(module_id).await;
+        outcome.instantiate_time = start.elapsed();
+        let instance_id = match instance_id {
+            Ok(instance_id) => instance_id,
+            Err(e) => {
+                outcome.error = Some(e.to_string());
+                return outcome;
+            }
+        };
+
+        let start = Instant::now();
+        let result = self.execute(instance_id.clone(), config).await;
+        outcome.execute_time = start.elapsed();
+        if let Err(e) = self.destroy(instance_id).await {
+            warn!("Failed to destroy instance of pipeline stage {}: {}", name, e);
+        }
+
+        match result {
+            Ok(result) => {
+                if !result.success {
+                    outcome.error = Some(result.error.clone().unwrap_or_else(|| "Execution failed".to_string()));
+                }
+                outcome.result = Some(result);
+            }
+            Err(e) => outcome.error = Some(e.to_string()),
+        }
+        outcome
+    }
+
+    pub async fn destroy(&self, instance_id: InstanceId) -> Result<()> {
+        let instance = self
+            .instances
+            .write()
+            .remove(&instance_id)
+            .ok_or_else(|| anyhow!("Instance not found: {}", instance_id.0))?;
+
+        if let Some(pooled) = self.pool.write().get_mut(&instance.module_id) {
+            pooled.retain(|id| *id != instance_id);
+        }
+        self.affinity.write().retain(|_, kept| *kept != instance_id);
+
+        self.runtime(instance.runtime)?.destroy(instance_id).await
+    }
+
+    fn webhook_event(kind: WebhookEventKind, record: &ExecutionRecord) -> WebhookEvent {
+        WebhookEvent::new(
+            kind,
+            record.tenant_id.clone(),
+            serde_json::json!({
+                "execution_id": record.id,
+                "runtime": record.runtime,
+                "language": record.language,
+                "module_id": record.module_id,
+                "instance_id": record.instance_id,
+                "status": record.status,
+                "duration_ms": record.duration.as_millis() as u64,
+                "error": record.error,
+                "tags": record.tags,
+            }),
+        )
+    }
+
+    pub async fn list_executions(&self, filter: &ExecutionFilter) -> Result<Vec<ExecutionRecord>> {
+        match &self.history {
+            Some(history) => history.list_executions(filter).await,
+            None => Err(anyhow!("Execution history is not enabled")),
+        }
+    }
+
+    pub async fn get_execution(&self, id: Uuid) -> Result<Option<ExecutionRecord>> {
+        match &self.history {
+            Some(history) => history.get(id).await,
+            None => Err(anyhow!("Execution history is not enabled")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{HaltCondition, PipelineStage};
+    use crate::plugins::RequestPlugin;
+    use async_trait::async_trait;
+    use next_rc_shared::webhooks::{
+        verify_signature, RetryPolicy, WebhookConfig, WebhookEndpoint, WebhookTransport,
+        EVENT_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+    };
+    use next_rc_shared::{Permissions, TraceVerbosity};
+    use std::time::Duration;
+    use wasm_runtime::WasmRuntime;
+
+    type CapturedRequest = (Vec<(String, String)>, Vec<u8>);
+
+    /// Answers with the queued status codes and captures every request.
+    struct MockTransport {
+        responses: parking_lot::Mutex<Vec<u16>>,
+        requests: parking_lot::Mutex<Vec<CapturedRequest>>,
+    }
+
+    impl MockTransport {
+        fn new(responses: Vec<u16>) -> Self {
+            Self {
+                responses: parking_lot::Mutex::new(responses),
+                requests: parking_lot::Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl WebhookTransport for MockTransport {
+        async fn post(
+            &self,
+            _url: &str,
+            headers: &[(String, String)],
+            body: Vec<u8>,
+            _timeout: Duration,
+        ) -> Result<u16> {
+            self.requests.lock().push((headers.to_vec(), body));
+            let mut responses = self.responses.lock();
+            Ok(if responses.is_empty() {
+                200
+            } else {
+                responses.remove(0)
+            })
+        }
+    }
+
+    fn webhook_config() -> WebhookConfig {
+        WebhookConfig {
+            endpoints: vec![WebhookEndpoint {
+                url: "https://hooks.example.com/next-rc".to_string(),
+                secret: "s3cret".to_string(),
+                events: Default::default(),
+                tenant_id: None,
+            }],
+            retry: RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(5),
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Compiles to a fresh module id unless marked unhealthy.
+    struct StubRuntime {
+        healthy: std::sync::atomic::AtomicBool,
+    }
+
+    impl StubRuntime {
+        fn new(healthy: bool) -> Self {
+            Self {
+                healthy: std::sync::atomic::AtomicBool::new(healthy),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Runtime for StubRuntime {
+        async fn compile(&self, _code: &[u8], _language: Language) -> Result<ModuleId> {
+            if self.healthy.load(std::sync::atomic::Ordering::SeqCst) {
+                Ok(ModuleId(Uuid::new_v4()))
+            } else {
+                Err(anyhow!("backend crashed"))
+            }
+        }
+        async fn instantiate(&self, _module_id: ModuleId) -> Result<InstanceId> {
+            Ok(InstanceId(Uuid::new_v4()))
+        }
+        async fn execute(&self, _instance_id: InstanceId, _config: ExecutionConfig) -> Result<ExecutionResult> {
+            Err(anyhow!("not supported"))
+        }
+        async fn destroy(&self, _instance_id: InstanceId) -> Result<()> {
+            Ok(())
+        }
+        async fn validate(&self, _code: &[u8], _language: Language, _trust_level: TrustLevel) -> Result<ValidationReport> {
+            Ok(ValidationReport::default())
+        }
+    }
+
+    /// Succeeds after sleeping through each phase.
+    struct SlowRuntime {
+        compile_delay: Duration,
+        execute_delay: Duration,
+    }
+
+    #[async_trait]
+    impl Runtime for SlowRuntime {
+        async fn compile(&self, _code: &[u8], _language: Language) -> Result<ModuleId> {
+            tokio::time::sleep(self.compile_delay).await;
+            Ok(ModuleId(Uuid::new_v4()))
+        }
+        async fn instantiate(&self, _module_id: ModuleId) -> Result<InstanceId> {
+            Ok(InstanceId(Uuid::new_v4()))
+        }
+        async fn execute(&self, _instance_id: InstanceId, _config: ExecutionConfig) -> Result<ExecutionResult> {
+            tokio::time::sleep(self.execute_delay).await;
+            Ok(ExecutionResult {
+                success: true,
+                output: None,
+                error: None,
+                execution_time: self.execute_delay,
+                memory_used: 0,
+                logs: Default::default(),
+                attestation: None,
+                environment_fingerprint: None,
+                timing: None,
+            })
+        }
+        async fn destroy(&self, _instance_id: InstanceId) -> Result<()> {
+            Ok(())
+        }
+        async fn validate(&self, _code: &[u8], _language: Language, _trust_level: TrustLevel) -> Result<ValidationReport> {
+            Ok(ValidationReport::default())
+        }
+    }
+
+    fn header<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+        headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_executions_are_recorded() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::with_in_memory_history());
+        let runtime = WasmRuntime::with_config(2, 1024 * 1024).unwrap();
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(runtime));
+
+        let wasm = wat::parse_str(r#"(module (func (export "_start") (result i32) i32.const 7))"#).unwrap();
+        let module_id = orchestrator.compile(RuntimeType::Wasm, &wasm, Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id.clone()).await.unwrap();
+
+        let config = ExecutionConfig {
+            timeout: Duration::from_secs(1),
+            tenant_id: Some("acme".to_string()),
+            tags: HashMap::from([("job".to_string(), "nightly".to_string())]),
+            ..Default::default()
+        };
+        let result = orchestrator.execute(instance_id.clone(), config).await.unwrap();
+        assert!(result.success);
+        orchestrator.destroy(instance_id).await.unwrap();
+
+        let filter = ExecutionFilter {
+            tags: HashMap::from([("job".to_string(), "nightly".to_string())]),
+            ..Default::default()
+        };
+        let records = orchestrator.list_executions(&filter).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].tenant_id.as_deref(), Some("acme"));
+        assert_eq!(records[0].module_id, module_id);
+        assert_eq!(records[0].status, ExecutionStatus::Succeeded);
+    }
+
+    #[tokio::test]
+    async fn test_executions_that_never_run_are_recorded() {
+        let low = PhaseTimeouts {
+            queue_wait: Duration::from_millis(20),
+            ..PhaseTimeouts::for_trust_level(TrustLevel::Low)
+        };
+        let orchestrator = Arc::new(Orchestrator::new(OrchestratorConfig {
+            phase_timeouts: HashMap::from([(TrustLevel::Low, low)]),
+            max_concurrent_executions: Some(1),
+            ..OrchestratorConfig::with_in_memory_history()
+        }));
+        orchestrator.register_runtime(
+            RuntimeType::Wasm,
+            Arc::new(SlowRuntime {
+                compile_delay: Duration::ZERO,
+                execute_delay: Duration::from_millis(200),
+            }),
+        );
+
+        let module_id = orchestrator.compile(RuntimeType::Wasm, b"", Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let busy = {
+            let (orchestrator, instance_id) = (orchestrator.clone(), instance_id.clone());
+            tokio::spawn(async move { orchestrator.execute(instance_id, ExecutionConfig::default()).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let queued = ExecutionConfig {
+            tenant_id: Some("acme".to_string()),
+            ..Default::default()
+        };
+        let err = orchestrator.execute(instance_id, queued).await.unwrap_err();
+        assert!(busy.await.unwrap().unwrap().success);
+
+        let filter = ExecutionFilter {
+            tenant_id: Some("acme".to_string()),
+            ..Default::default()
+        };
+        let records = orchestrator.list_executions(&filter).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].status, ExecutionStatus::TimedOut);
+        assert_eq!(records[0].error, Some(err.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_validate_reports_diagnostics() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+        orchestrator.register_runtime(RuntimeType::Ebpf, Arc::new(next_rc_ebpf::EbpfRuntime::new().unwrap()));
+
+        let wasm = wat::parse_str(r#"(module (import "wasi" "fd_write" (func)) (func (export "main")))"#).unwrap();
+        let report = orchestrator.validate(RuntimeType::Wasm, &wasm, Language::Wasm, TrustLevel::Low).await.unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.diagnostics.len(), 2);
+        assert!(report.diagnostics[0].message.contains("wasi::fd_write"));
+        assert_eq!(report.diagnostics[1].severity, next_rc_shared::Severity::Warning);
+
+        let report = orchestrator.validate(RuntimeType::Wasm, b"not wasm", Language::Wasm, TrustLevel::Low).await.unwrap();
+        assert!(!report.valid);
+
+        // r0 = *(u32 *)(r1 + 0); exit
+        let load = [0x61, 0x10, 0, 0, 0, 0, 0, 0, 0x95, 0, 0, 0, 0, 0, 0, 0];
+        let report = orchestrator.validate(RuntimeType::Ebpf, &load, Language::Ebpf, TrustLevel::High).await.unwrap();
+        assert!(!report.valid);
+        assert!(report.diagnostics[0].message.contains("Memory access"));
+
+        let ret = [0xb7, 0, 0, 0, 1, 0, 0, 0, 0x95, 0, 0, 0, 0, 0, 0, 0];
+        let report = orchestrator.validate(RuntimeType::Ebpf, &ret, Language::Ebpf, TrustLevel::Low).await.unwrap();
+        assert!(report.valid);
+        assert!(orchestrator.modules.read().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_compile_reports_source_spans() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let wat = "(module\n  (func (export \"_start\") (result i32)\n    i32.bogus 7))";
+        let err = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap_err();
+        let diagnostics = next_rc_shared::CompileError::diagnostics_of(&err);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!((diagnostics[0].line, diagnostics[0].column), (Some(3), Some(5)));
+        assert_eq!(diagnostics[0].snippet.as_deref(), Some("    i32.bogus 7))"));
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        assert!(orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_compile_stream() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let stream = |bytes: Vec<u8>| {
+            let (mut writer, reader) = tokio::io::duplex(16);
+            tokio::spawn(async move {
+                let _ = tokio::io::AsyncWriteExt::write_all(&mut writer, &bytes).await;
+            });
+            Box::new(reader)
+        };
+
+        let wasm = wat::parse_str(r#"(module (func (export "_start") (result i32) i32.const 7))"#).unwrap();
+        let module_id = orchestrator.compile_stream(RuntimeType::Wasm, stream(wasm.clone()), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let result = orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(&b"7"[..]));
+
+        // Corrupt the `i32.const` opcode in the function body
+        let mut corrupted = wasm;
+        let len = corrupted.len();
+        corrupted[len - 3] = 0xff;
+        let err = orchestrator.compile_stream(RuntimeType::Wasm, stream(corrupted), Language::Wasm, TrustLevel::Low).await.unwrap_err();
+        let diagnostics = next_rc_shared::CompileError::diagnostics_of(&err);
+        assert!(diagnostics[0].message.contains("at offset"), "{}", diagnostics[0].message);
+    }
+
+    #[tokio::test]
+    async fn test_compile_bundle() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let app = r#"(module
+            (import "math" "double" (func $double (param i32) (result i32)))
+            (func (export "_start") (result i32) (call $double (i32.const 21))))"#;
+        let math = r#"(module
+            (func (export "double") (param i32) (result i32) (i32.mul (local.get 0) (i32.const 2))))"#;
+        let bundle = vec![
+            ("app".to_string(), app.as_bytes().to_vec()),
+            ("math".to_string(), math.as_bytes().to_vec()),
+        ];
+
+        let module_id = orchestrator.compile_bundle(RuntimeType::Wasm, bundle, Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let result = orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(&b"42"[..]));
+
+        let bundle = vec![("app".to_string(), app.as_bytes().to_vec())];
+        let err = orchestrator.compile_bundle(RuntimeType::Wasm, bundle, Language::Wasm, TrustLevel::Low).await.unwrap_err();
+        let diagnostics = next_rc_shared::CompileError::diagnostics_of(&err);
+        assert_eq!(diagnostics[0].to_string(), "app: Unresolved import math::double");
+    }
+
+    /// Rewrites WAT text, failing when there is nothing to rewrite.
+    struct ReplaceText(&'static str, &'static str);
+
+    #[async_trait]
+    impl CodeTransform for ReplaceText {
+        fn name(&self) -> &str {
+            "replace-text"
+        }
+
+        async fn transform(&self, code: Vec<u8>, _context: &TransformContext) -> Result<Vec<u8>> {
+            let text = String::from_utf8(code)?;
+            if !text.contains(self.0) {
+                return Err(anyhow!("`{}` not found", self.0));
+            }
+            Ok(text.replace(self.0, self.1).into_bytes())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transforms_run_before_compilation() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+        orchestrator.register_transform(Arc::new(ReplaceText("i32.const 7", "i32.const 8")));
+        orchestrator.register_transform(Arc::new(wasm_runtime::StripDebugSections));
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let result = orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(&b"8"[..]));
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 9))"#;
+        let err = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap_err();
+        assert_eq!(err.to_string(), "Transform pass `replace-text` failed");
+        assert_eq!(orchestrator.breaker_metrics()[&RuntimeType::Wasm].total_failures, 0);
+
+        let metrics = orchestrator.transform_metrics();
+        assert_eq!((metrics["replace-text"].runs, metrics["replace-text"].failures), (2, 1));
+        assert_eq!(metrics["wasm-strip-debug"].runs, 1);
+    }
+
+    #[tokio::test]
+    async fn test_execution_profiles() {
+        let custom = Profile {
+            name: "reports".to_string(),
+            runtime_preference: vec![RuntimeType::Python, RuntimeType::Wasm],
+            ..Profile::internal()
+        };
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            profiles: HashMap::from([("reports".to_string(), custom)]),
+            ..Default::default()
+        });
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        let module_id = orchestrator.compile_for_profile("reports", wat.as_bytes(), Language::Wasm).await.unwrap();
+        assert_eq!(orchestrator.modules.read()[&module_id].runtime, RuntimeType::Wasm);
+
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let result = orchestrator.execute_with_profile(instance_id, Profile::UNTRUSTED).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(&b"7"[..]));
+
+        assert_eq!(orchestrator.profile(Profile::BATCH).unwrap().permissions.trust_level, TrustLevel::High);
+        let err = orchestrator.profile("nightly").unwrap_err();
+        assert_eq!(err.to_string(), "Unknown execution profile: nightly");
+    }
+
+    #[tokio::test]
+    async fn test_provision_fills_pool() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(4, 1024 * 1024).unwrap()));
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+
+        let report = orchestrator.provision(module_id.clone(), 3).await.unwrap();
+        assert_eq!((report.ready, report.pooled), (3, 3));
+        assert!(report.failures.is_empty());
+
+        let instance_id = orchestrator.instantiate(module_id.clone()).await.unwrap();
+        assert_eq!(orchestrator.pooled_instances(&module_id), 2);
+        let result = orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(&b"7"[..]));
+
+        // Only one memory slot is left, so the rest fail without losing the pool
+        let report = orchestrator.provision(module_id.clone(), 2).await.unwrap();
+        assert_eq!((report.ready, report.pooled, report.failures.len()), (1, 3, 1));
+    }
+
+    #[tokio::test]
+    async fn test_autoscaled_warm_pool() {
+        use crate::autoscaler::WarmInstancePool;
+        use next_rc_shared::ScalablePool;
+
+        let orchestrator = Arc::new(Orchestrator::new(OrchestratorConfig::default()));
+        let runtime = Arc::new(WasmRuntime::with_config(4, 1024 * 1024).unwrap());
+        orchestrator.register_runtime(RuntimeType::Wasm, runtime.clone());
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id.clone()).await.unwrap();
+        orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+        assert_eq!(orchestrator.traffic().sample(RuntimeType::Wasm).arrivals, 1);
+
+        let pool = WarmInstancePool::new(orchestrator.clone(), module_id.clone());
+        assert_eq!(pool.resize(3).await.unwrap(), 3);
+        assert_eq!(runtime.get_metrics().available_slots, 0);
+
+        // Draining destroys the instances and hands their memory back
+        assert_eq!(pool.resize(1).await.unwrap(), 1);
+        assert_eq!(orchestrator.pooled_instances(&module_id), 1);
+        assert_eq!(runtime.get_metrics().available_slots, 2);
+    }
+
+    #[tokio::test]
+    async fn test_reuse_policy_isolates_tenants() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(4, 1024 * 1024).unwrap()));
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+
+        let config = |trust_level, tenant: &str| ExecutionConfig {
+            permissions: Permissions::new(trust_level),
+            tenant_id: Some(tenant.to_string()),
+            ..Default::default()
+        };
+        for (trust_level, tenant) in [
+            (TrustLevel::Medium, "a"),
+            (TrustLevel::Medium, "a"),
+            (TrustLevel::Medium, "b"),
+            (TrustLevel::Medium, "a"),
+            (TrustLevel::Low, "a"),
+        ] {
+            let result = orchestrator.execute(instance_id.clone(), config(trust_level, tenant)).await.unwrap();
+            assert_eq!(result.output.as_deref(), Some(&b"7"[..]));
+        }
+
+        let metrics = orchestrator.reuse_metrics();
+        let medium = metrics[&TrustLevel::Medium];
+        assert_eq!((medium.first_use, medium.reused, medium.fresh), (1, 2, 1));
+        assert!((medium.reuse_rate() - 2.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(metrics[&TrustLevel::Low].fresh, 1);
+    }
+
+    #[tokio::test]
+    async fn test_results_break_down_timing() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(4, 1024 * 1024).unwrap()));
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let config = |trust_level| ExecutionConfig {
+            permissions: Permissions::new(trust_level),
+            ..Default::default()
+        };
+
+        let result = orchestrator.execute(instance_id.clone(), config(TrustLevel::Medium)).await.unwrap();
+        let timing = result.timing.unwrap();
+        assert!(timing.sandbox_setup > Duration::ZERO && timing.execute > Duration::ZERO);
+        assert_eq!((timing.instantiate, timing.teardown), (Duration::ZERO, Duration::ZERO));
+
+        // Low trust won't reuse the instance, so it pays for a fresh one
+        let result = orchestrator.execute(instance_id, config(TrustLevel::Low)).await.unwrap();
+        let timing = result.timing.unwrap();
+        assert!(timing.instantiate > Duration::ZERO && timing.teardown > Duration::ZERO);
+        assert!(timing.total() >= timing.instantiate + timing.execute);
+    }
+
+    #[tokio::test]
+    async fn test_policy_gates_executions() {
+        use crate::audit::InMemoryAuditLog;
+        use crate::policy::{PolicyConditions, PolicyRule, RulePolicy};
+
+        let audit = Arc::new(InMemoryAuditLog::default());
+        let policy = RulePolicy::new(vec![PolicyRule::deny(
+            "high-trust-tenants",
+            PolicyConditions {
+                trust_levels: vec![TrustLevel::High],
+                ..Default::default()
+            },
+        )
+        .unless(PolicyConditions {
+            tenants: vec!["acme".to_string()],
+            ..Default::default()
+        })]);
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            policy: Some(Arc::new(policy)),
+            audit: Some(audit.clone()),
+            ..OrchestratorConfig::with_in_memory_history()
+        });
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let config = |tenant: &str| ExecutionConfig {
+            permissions: Permissions::new(TrustLevel::High),
+            tenant_id: Some(tenant.to_string()),
+            ..Default::default()
+        };
+
+        let err = orchestrator.execute(instance_id.clone(), config("other")).await.unwrap_err();
+        assert!(matches!(err.downcast_ref::<RuntimeError>(), Some(RuntimeError::SecurityError(_))));
+        assert!(orchestrator.execute(instance_id, config("acme")).await.unwrap().success);
+
+        let records = audit.list(None).await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records[0].decision.allowed);
+        assert_eq!(records[1].decision.rule.as_deref(), Some("high-trust-tenants"));
+        assert_eq!(audit.list(Some("other")).await.unwrap().len(), 1);
+
+        let filter = ExecutionFilter {
+            tenant_id: Some("other".to_string()),
+            ..Default::default()
+        };
+        let denied = orchestrator.list_executions(&filter).await.unwrap();
+        assert_eq!(denied.len(), 1);
+        assert_eq!(denied[0].status, ExecutionStatus::Errored);
+        assert_eq!(denied[0].error, Some(err.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_policy_sees_plugin_changes() {
+        use crate::policy::{PolicyConditions, PolicyRule, RulePolicy};
+
+        let policy = RulePolicy::new(vec![PolicyRule::deny(
+            "no-batch-route",
+            PolicyConditions {
+                tags: HashMap::from([("route".to_string(), "batch".to_string())]),
+                ..Default::default()
+            },
+        )]);
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            policy: Some(Arc::new(policy)),
+            ..OrchestratorConfig::with_in_memory_history()
+        });
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(4, 1024 * 1024).unwrap()));
+        let compile = |wat: &str| {
+            let orchestrator = &orchestrator;
+            let wat = wat.to_string();
+            async move { orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap() }
+        };
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        let instance_id = orchestrator.instantiate(compile(wat).await).await.unwrap();
+        assert!(orchestrator.execute(instance_id.clone(), ExecutionConfig::default()).await.unwrap().success);
+
+        // Tags the request `route=batch`, which only the plugin sets
+        let verdict = r#"{"tags":{"route":"batch"}}"#;
+        let router = compile(&format!(
+            r#"(module
+                (import "env" "write_output" (func $write (param i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "{}")
+                (func (export "_start") (result i32)
+                    (call $write (i32.const 0) (i32.const {}))
+                    (i32.const 0)))"#,
+            verdict.replace('"', "\\22"),
+            verdict.len()
+        ))
+        .await;
+        orchestrator.register_plugin(RequestPlugin::new("router", router)).unwrap();
+
+        let err = orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap_err();
+        assert!(err.to_string().contains("no-batch-route"), "{}", err);
+        let filter = ExecutionFilter {
+            status: Some(ExecutionStatus::Errored),
+            ..Default::default()
+        };
+        let denied = orchestrator.list_executions(&filter).await.unwrap();
+        assert_eq!(denied.len(), 1);
+        assert_eq!(denied[0].tags.get("route").map(String::as_str), Some("batch"));
+    }
+
+    #[tokio::test]
+    async fn test_request_plugins() {
+        let history = Arc::new(InMemoryHistoryStore::default());
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            history: Some(history.clone()),
+            ..Default::default()
+        });
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(4, 1024 * 1024).unwrap()));
+        let plugin = |verdict: &str| {
+            format!(
+                r#"(module
+                    (import "env" "write_output" (func $write (param i32 i32)))
+                    (memory (export "memory") 1)
+                    (data (i32.const 0) "{}")
+                    (func (export "_start") (result i32)
+                        (call $write (i32.const 0) (i32.const {}))
+                        (i32.const 0)))"#,
+                verdict.replace('"', "\\22"),
+                verdict.len()
+            )
+        };
+        let compile = |wat: String| {
+            let orchestrator = &orchestrator;
+            async move { orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap() }
+        };
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        let instance_id = orchestrator.instantiate(compile(wat.to_string()).await).await.unwrap();
+
+        let router = compile(plugin(r#"{"tags":{"route":"batch"},"profile":"batch"}"#)).await;
+        orchestrator.register_plugin(RequestPlugin::new("router", router)).unwrap();
+        assert!(orchestrator.execute(instance_id.clone(), ExecutionConfig::default()).await.unwrap().success);
+        let records = history.list_executions(&ExecutionFilter::default()).await.unwrap();
+        assert_eq!(records[0].tags.get("route").map(String::as_str), Some("batch"));
+
+        let quota = compile(plugin(r#"{"reject":"quota exhausted"}"#)).await;
+        orchestrator.register_plugin(RequestPlugin::new("quota", quota)).unwrap();
+        let err = orchestrator.execute(instance_id.clone(), ExecutionConfig::default()).await.unwrap_err();
+        assert_eq!(err.to_string(), "Security violation: Rejected by plugin `quota`: quota exhausted");
+        assert!(orchestrator.unregister_plugin("quota"));
+        let records = history.list_executions(&ExecutionFilter::default()).await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().any(|record| {
+            record.status == ExecutionStatus::Errored && record.error == Some(err.to_string())
+        }));
+
+        let broken = compile(r#"(module (func (export "_start") (result i32) unreachable))"#.to_string()).await;
+        orchestrator.register_plugin(RequestPlugin::new("broken", broken.clone())).unwrap();
+        let err = orchestrator.execute(instance_id.clone(), ExecutionConfig::default()).await.unwrap_err();
+        let records = history.list_executions(&ExecutionFilter::default()).await.unwrap();
+        assert!(records.iter().any(|record| record.error == Some(err.to_string())));
+        orchestrator.register_plugin(RequestPlugin::new("broken", broken).fail_open()).unwrap();
+        assert!(orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_affinity_keys_route_to_warm_instances() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(4, 1024 * 1024).unwrap()));
+
+        // Counts the executions its instance has run
+        let wat = r#"(module
+            (global $runs (mut i32) (i32.const 0))
+            (func (export "_start") (result i32)
+                (global.set $runs (i32.add (global.get $runs) (i32.const 1)))
+                (global.get $runs)))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let run = |keys: &[&str]| {
+            let config = ExecutionConfig {
+                permissions: Permissions::new(TrustLevel::Medium),
+                affinity_keys: keys.iter().map(|key| key.to_string()).collect(),
+                ..Default::default()
+            };
+            let (orchestrator, module_id) = (&orchestrator, module_id.clone());
+            async move {
+                let output = orchestrator.execute_module(module_id, config).await.unwrap().output.unwrap();
+                String::from_utf8(output).unwrap()
+            }
+        };
+
+        assert_eq!(run(&["session-1", "dataset-a"]).await, "1");
+        assert_eq!(run(&["session-1"]).await, "2");
+        // Another session on the same dataset shares its instance
+        assert_eq!(run(&["session-2", "dataset-a"]).await, "3");
+        assert_eq!(run(&[]).await, "1");
+
+        // Low trust never reuses instances, affinity or not
+        let low = ExecutionConfig {
+            affinity_keys: vec!["session-2".to_string()],
+            ..Default::default()
+        };
+        let result = orchestrator.execute_module(module_id.clone(), low).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(&b"1"[..]));
+
+        orchestrator.release_affinity("session-1").await.unwrap();
+        assert_eq!(run(&["session-2"]).await, "4");
+        orchestrator.release_affinity("session-2").await.unwrap();
+        orchestrator.release_affinity("dataset-a").await.unwrap();
+        assert_eq!(run(&["session-2"]).await, "1");
+    }
+
+    #[tokio::test]
+    async fn test_results_are_signed() {
+        let (key, _) = NodeKey::generate().unwrap();
+        let key = Arc::new(key);
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            signing_key: Some(key.clone()),
+            ..Default::default()
+        });
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let mut result = orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+
+        result.verify_attestation(key.public_key()).unwrap();
+        let attestation = result.attestation.clone().unwrap();
+        assert_eq!(attestation.claims.code_sha256, sha256_hex(wat.as_bytes()));
+        assert_eq!(attestation.claims.runtime, RuntimeType::Wasm);
+
+        let (other, _) = NodeKey::generate().unwrap();
+        assert!(result.verify_attestation(other.public_key()).is_err());
+        result.output = Some(b"8".to_vec());
+        assert!(result.verify_attestation(key.public_key()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_results_carry_environment_fingerprint() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(4, 1024 * 1024).unwrap()));
+
+        let main = r#"(module (import "lib" "seven" (func $seven (result i32))) (func (export "_start") (result i32) call $seven))"#;
+        let lib = r#"(module (func (export "seven") (result i32) i32.const 7))"#;
+        let modules = vec![("main".to_string(), main.as_bytes().to_vec()), ("lib".to_string(), lib.as_bytes().to_vec())];
+        let bundle_id = orchestrator.compile_bundle(RuntimeType::Wasm, modules, Language::Wasm, TrustLevel::Low).await.unwrap();
+        let lone_id = orchestrator.compile(RuntimeType::Wasm, lib.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+
+        let run = |module_id: ModuleId, config: ExecutionConfig| {
+            let orchestrator = &orchestrator;
+            async move {
+                let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+                let result = orchestrator.execute(instance_id, config).await.unwrap();
+                result.environment_fingerprint.unwrap()
+            }
+        };
+        let fingerprint = run(bundle_id.clone(), ExecutionConfig::default()).await;
+        assert_eq!(fingerprint.runtime, RuntimeType::Wasm);
+        assert_eq!(fingerprint.engine, "wasmtime 41");
+        assert_eq!(fingerprint.engine_config["memory_slots"], "4");
+        let names: Vec<_> = fingerprint.modules.iter().map(|module| module.name.as_deref()).collect();
+        assert_eq!(names, [Some("main"), Some("lib")]);
+        assert_eq!(fingerprint.modules[1].sha256, sha256_hex(lib.as_bytes()));
+
+        // Identical conditions give identical fingerprints; a different
+        // policy or module does not
+        assert_eq!(run(bundle_id.clone(), ExecutionConfig::default()).await.digest(), fingerprint.digest());
+        let high = ExecutionConfig {
+            permissions: Permissions::new(TrustLevel::High),
+            tenant_id: Some("acme".to_string()),
+            ..Default::default()
+        };
+        assert_ne!(run(bundle_id, high).await.security_policy_sha256, fingerprint.security_policy_sha256);
+        let lone = run(lone_id, ExecutionConfig::default()).await;
+        assert_eq!(lone.modules, [ModuleDigest { name: None, sha256: sha256_hex(lib.as_bytes()) }]);
+        assert_eq!(lone.security_policy_sha256, fingerprint.security_policy_sha256);
+    }
+
+    #[tokio::test]
+    async fn test_compile_staged_artifact() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = Arc::new(StagingArea::new(dir.path()).unwrap());
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            staging: Some(staging.clone()),
+            ..Default::default()
+        });
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let wasm = wat::parse_str(r#"(module (func (export "_start") (result i32) i32.const 42))"#).unwrap();
+        let upload = staging.begin(wasm.len() as u64, &sha256_hex(&wasm)).await.unwrap();
+        for (i, chunk) in wasm.chunks(8).enumerate() {
+            staging.put_chunk(upload.upload_id, (i * 8) as u64, chunk, None).await.unwrap();
+        }
+        let staged = staging.complete(upload.upload_id).await.unwrap();
+
+        let module_id = orchestrator.compile_staged(RuntimeType::Wasm, &staged.reference, Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let result = orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(&b"42"[..]));
+
+        let missing = format!("sha256:{}", sha256_hex(b"missing"));
+        assert!(orchestrator.compile_staged(RuntimeType::Wasm, &missing, Language::Wasm, TrustLevel::Low).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slow_staged_compile_gets_the_callers_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = Arc::new(StagingArea::new(dir.path()).unwrap());
+        let low = PhaseTimeouts {
+            compile: Duration::from_millis(20),
+            ..PhaseTimeouts::for_trust_level(TrustLevel::Low)
+        };
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            staging: Some(staging.clone()),
+            phase_timeouts: HashMap::from([(TrustLevel::Low, low)]),
+            ..Default::default()
+        });
+        orchestrator.register_runtime(
+            RuntimeType::Wasm,
+            Arc::new(SlowRuntime {
+                compile_delay: Duration::from_millis(200),
+                execute_delay: Duration::ZERO,
+            }),
+        );
+
+        let artifact = b"large artifact".to_vec();
+        let upload = staging.begin(artifact.len() as u64, &sha256_hex(&artifact)).await.unwrap();
+        staging.put_chunk(upload.upload_id, 0, &artifact, None).await.unwrap();
+        let staged = staging.complete(upload.upload_id).await.unwrap();
+
+        let err = orchestrator
+            .compile_staged(RuntimeType::Wasm, &staged.reference, Language::Wasm, TrustLevel::Low)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<RuntimeError>(),
+            Some(RuntimeError::PhaseTimeout { phase: JobPhase::Compile, .. })
+        ));
+
+        assert!(orchestrator
+            .compile_staged(RuntimeType::Wasm, &staged.reference, Language::Wasm, TrustLevel::High)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_phase_timeouts_name_the_phase() {
+        let low = PhaseTimeouts {
+            compile: Duration::from_millis(20),
+            queue_wait: Duration::from_millis(20),
+            ..PhaseTimeouts::for_trust_level(TrustLevel::Low)
+        };
+        let medium = PhaseTimeouts {
+            execute: Duration::from_millis(50),
+            ..PhaseTimeouts::for_trust_level(TrustLevel::Medium)
+        };
+        let orchestrator = Arc::new(Orchestrator::new(OrchestratorConfig {
+            phase_timeouts: HashMap::from([(TrustLevel::Low, low), (TrustLevel::Medium, medium)]),
+            max_concurrent_executions: Some(1),
+            ..Default::default()
+        }));
+        let slow = |compile_ms, execute_ms| {
+            Arc::new(SlowRuntime {
+                compile_delay: Duration::from_millis(compile_ms),
+                execute_delay: Duration::from_millis(execute_ms),
+            })
+        };
+        orchestrator.register_runtime(RuntimeType::Wasm, slow(200, 0));
+        orchestrator.register_runtime(RuntimeType::Ebpf, slow(0, 400));
+        let phase_of = |e: anyhow::Error| match e.downcast_ref::<RuntimeError>() {
+            Some(RuntimeError::PhaseTimeout { phase, .. }) => Some(*phase),
+            _ => None,
+        };
+
+        let err = orchestrator.compile(RuntimeType::Wasm, b"", Language::Wasm, TrustLevel::Low).await.unwrap_err();
+        assert_eq!(phase_of(err), Some(JobPhase::Compile));
+        // The caller's trust level picks the budget
+        assert!(orchestrator.compile(RuntimeType::Wasm, b"", Language::Wasm, TrustLevel::Medium).await.is_ok());
+
+        let module_id = orchestrator.compile(RuntimeType::Ebpf, b"", Language::C, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let busy = {
+            let (orchestrator, instance_id) = (orchestrator.clone(), instance_id.clone());
+            tokio::spawn(async move { orchestrator.execute(instance_id, ExecutionConfig::default()).await })
+        };
+        tokio::time::sleep(Duration::from_millis(10)).await;
+
+        let err = orchestrator.execute(instance_id.clone(), ExecutionConfig::default()).await.unwrap_err();
+        assert_eq!(phase_of(err), Some(JobPhase::QueueWait));
+
+        let config = ExecutionConfig {
+            permissions: Permissions::new(TrustLevel::Medium),
+            ..Default::default()
+        };
+        let err = orchestrator.execute(instance_id, config).await.unwrap_err();
+        assert_eq!(err.to_string(), "Timed out in execute phase after 50ms");
+        assert!(busy.await.unwrap().unwrap().success);
+    }
+
+    #[tokio::test]
+    async fn test_current_load() {
+        let orchestrator = Arc::new(Orchestrator::new(OrchestratorConfig {
+            max_concurrent_executions: Some(1),
+            ..Default::default()
+        }));
+        orchestrator.register_runtime(
+            RuntimeType::Ebpf,
+            Arc::new(SlowRuntime {
+                compile_delay: Duration::ZERO,
+                execute_delay: Duration::from_millis(100),
+            }),
+        );
+        let idle = orchestrator.current_load();
+        assert_eq!((idle.available_slots, idle.capacity, idle.saturated()), (Some(1), Some(1), false));
+
+        let module_id = orchestrator.compile(RuntimeType::Ebpf, b"", Language::C, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        orchestrator.execute(instance_id.clone(), ExecutionConfig::default()).await.unwrap();
+
+        let mut running = Vec::new();
+        for _ in 0..3 {
+            let (orchestrator, instance_id) = (orchestrator.clone(), instance_id.clone());
+            running.push(tokio::spawn(async move { orchestrator.execute(instance_id, ExecutionConfig::default()).await }));
+        }
+        tokio::time::sleep(Duration::from_millis(30)).await;
+
+        let load = orchestrator.current_load();
+        assert_eq!((load.in_flight, load.queued), (1, 2));
+        assert!(load.saturated());
+        // A new caller waits out the running execution and both queued ones
+        assert!(load.estimated_wait >= Duration::from_millis(300), "{:?}", load.estimated_wait);
+        assert_eq!(orchestrator.runtime_loads()[&RuntimeType::Ebpf], LoadReport::default());
+
+        for execution in running {
+            execution.await.unwrap().unwrap();
+        }
+        assert_eq!(orchestrator.current_load().in_flight, 0);
+    }
+
+    #[cfg(feature = "fault-injection")]
+    #[tokio::test]
+    async fn test_fault_injection() {
+        use next_rc_shared::{FaultConfig, FaultCounts, LatencyFault};
+
+        let faults = Arc::new(FaultInjector::new(FaultConfig {
+            compile_failure_rate: 1.0,
+            seed: Some(7),
+            ..Default::default()
+        }));
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            faults: Some(faults.clone()),
+            ..Default::default()
+        });
+        orchestrator.register_runtime(
+            RuntimeType::Wasm,
+            Arc::new(SlowRuntime {
+                compile_delay: Duration::ZERO,
+                execute_delay: Duration::ZERO,
+            }),
+        );
+        let is_fault = |e: &anyhow::Error| e.to_string().contains("Injected fault");
+
+        let err = orchestrator.compile(RuntimeType::Wasm, b"", Language::Wasm, TrustLevel::Low).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(RuntimeError::CompilationError(_))));
+
+        faults.set_config(FaultConfig {
+            pool_exhaustion_rate: 1.0,
+            ..Default::default()
+        });
+        let module_id = orchestrator.compile(RuntimeType::Wasm, b"", Language::Wasm, TrustLevel::Low).await.unwrap();
+        let err = orchestrator.instantiate(module_id.clone()).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(RuntimeError::MemoryError(_))) && is_fault(&err));
+
+        faults.set_config(FaultConfig {
+            sandbox_failure_rate: 1.0,
+            ..Default::default()
+        });
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let err = orchestrator.execute(instance_id.clone(), ExecutionConfig::default()).await.unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(RuntimeError::SecurityError(_))) && is_fault(&err));
+
+        faults.set_config(FaultConfig {
+            latency: Some(LatencyFault {
+                rate: 1.0,
+                min: Duration::from_millis(50),
+                max: Duration::from_millis(50),
+                phases: vec![JobPhase::Execute],
+            }),
+            ..Default::default()
+        });
+        let start = Instant::now();
+        assert!(orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap().success);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        assert_eq!(
+            faults.counts(),
+            FaultCounts {
+                compile_failures: 1,
+                pool_exhaustions: 1,
+                sandbox_failures: 1,
+                delays: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_toolchains_are_reported() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let capabilities = orchestrator.capabilities().remove(&RuntimeType::Wasm).unwrap();
+        assert!(capabilities.languages.contains(&Language::Wasm));
+        for (name, language) in [("rustc", Language::Rust), ("clang", Language::C), ("tinygo", Language::Go)] {
+            let toolchain = capabilities.toolchains.iter().find(|toolchain| toolchain.name == name).unwrap();
+            assert_eq!(capabilities.languages.contains(&language), toolchain.available());
+            if toolchain.available() {
+                continue;
+            }
+
+            let err = orchestrator.compile(RuntimeType::Wasm, b"", language, TrustLevel::Low).await.unwrap_err();
+            assert!(matches!(err.downcast_ref(), Some(RuntimeError::CompilationError(_))));
+            assert!(err.to_string().contains(&format!("toolchain '{}' not found, install", name)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_named_modules() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let compile = |value: i32| {
+            let wat = format!(r#"(module (func (export "_start") (result i32) i32.const {}))"#, value);
+            let orchestrator = &orchestrator;
+            async move { orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap() }
+        };
+        let v1 = compile(1).await;
+        orchestrator.register_module("filters/ipv4@1.0.0", v1.clone()).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let v2 = compile(2).await;
+        orchestrator.register_module("filters/ipv4@1.1.0", v2.clone()).await.unwrap();
+
+        assert!(orchestrator.register_module("filters/ipv4@1.1.0", v1.clone()).await.is_err());
+        assert!(orchestrator.register_module("filters/ipv4", v1.clone()).await.is_err());
+        assert_eq!(orchestrator.resolve_module("filters/ipv4").await.unwrap(), v2);
+
+        orchestrator.set_module_alias("filters/ipv4", "stable", "1.0.0").await.unwrap();
+        let instance_id = orchestrator.instantiate_named("filters/ipv4@stable").await.unwrap();
+        let result = orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(&b"1"[..]));
+
+        let policy = RetentionPolicy {
+            keep_latest: 0,
+            max_age: None,
+        };
+        let removed = orchestrator.gc_modules(&policy).await.unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].version, "1.1.0");
+        assert_eq!(orchestrator.list_modules(Some("filters/ipv4")).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_pipeline_across_runtimes() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::with_in_memory_history());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+        orchestrator.register_runtime(RuntimeType::Ebpf, Arc::new(next_rc_ebpf::EbpfRuntime::new().unwrap()));
+
+        // r0 = verdict; exit
+        for (reference, verdict) in [("filters/accept@1.0.0", 1), ("filters/drop@1.0.0", 0)] {
+            let program = [0xb7, 0, 0, 0, verdict, 0, 0, 0, 0x95, 0, 0, 0, 0, 0, 0, 0];
+            let module_id = orchestrator.compile(RuntimeType::Ebpf, &program, Language::Ebpf, TrustLevel::Low).await.unwrap();
+            orchestrator.register_module(reference, module_id).await.unwrap();
+        }
+        // Writes its input back out with the first byte uppercased
+        let upper = r#"(module
+            (import "env" "input_len" (func $input_len (result i32)))
+            (import "env" "read_input" (func $read_input (param i32 i32) (result i32)))
+            (import "env" "write_output" (func $write_output (param i32 i32)))
+            (memory (export "memory") 1)
+            (func (export "_start") (result i32) (local $len i32)
+                (local.set $len (call $read_input (i32.const 0) (call $input_len)))
+                (i32.store8 (i32.const 0) (i32.sub (i32.load8_u (i32.const 0)) (i32.const 32)))
+                (call $write_output (i32.const 0) (local.get $len))
+                (i32.const 0)))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, upper.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        orchestrator.register_module("transforms/upper@1.0.0", module_id).await.unwrap();
+        let trap = r#"(module (func (export "_start") (result i32) unreachable))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, trap.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        orchestrator.register_module("transforms/trap@1.0.0", module_id).await.unwrap();
+
+        let pipeline = |filter: &str, transform: &str| {
+            let filter = PipelineStage::new("filter", filter)
+                .with_halt_when(HaltCondition::Rejected)
+                .with_forward_input(true);
+            Pipeline::new("ingest", vec![filter, PipelineStage::new("transform", transform)])
+        };
+
+        let result = orchestrator.run_pipeline(&pipeline("filters/accept", "transforms/upper"), b"packet".to_vec()).await.unwrap();
+        assert!(result.success);
+        assert_eq!(result.output.as_deref(), Some(&b"Packet"[..]));
+        let runtimes: Vec<_> = result.stages.iter().map(|stage| stage.runtime).collect();
+        assert_eq!(runtimes, [RuntimeType::Ebpf, RuntimeType::Wasm]);
+        let filter = ExecutionFilter {
+            tags: HashMap::from([(PIPELINE_STAGE_TAG.to_string(), "transform".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(orchestrator.list_executions(&filter).await.unwrap().len(), 1);
+
+        let result = orchestrator.run_pipeline(&pipeline("filters/drop", "transforms/upper"), b"packet".to_vec()).await.unwrap();
+        assert!(result.success);
+        assert_eq!((result.halted_at.as_deref(), result.stages.len()), (Some("filter"), 1));
+
+        let result = orchestrator.run_pipeline(&pipeline("filters/accept", "transforms/trap"), b"packet".to_vec()).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.stages.len(), 2);
+        assert!(result.stages[1].error.as_deref().unwrap().contains("unreachable"));
+
+        // Unknown modules fail the call before any stage runs
+        let executions = orchestrator.list_executions(&ExecutionFilter::default()).await.unwrap().len();
+        assert!(orchestrator.run_pipeline(&pipeline("filters/accept", "transforms/missing"), Vec::new()).await.is_err());
+        assert_eq!(orchestrator.list_executions(&ExecutionFilter::default()).await.unwrap().len(), executions);
+    }
+
+    #[tokio::test]
+    async fn test_manifest_capabilities_are_enforced() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let wat = r#"(module
+            (@custom "next-rc.manifest" "{\"entry_points\": [\"run\"], \"capabilities\": [\"NetworkAccess\"]}")
+            (func (export "run") (result i32) i32.const 5))"#;
+
+        let report = orchestrator.validate(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        assert!(!report.valid);
+        assert_eq!(report.diagnostics[0].file.as_deref(), Some("next-rc.manifest"));
+        let report = orchestrator.validate(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::High).await.unwrap();
+        assert!(report.valid);
+
+        let module_id = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        assert!(orchestrator.execute(instance_id.clone(), ExecutionConfig::default()).await.is_err());
+
+        let config = ExecutionConfig {
+            permissions: Permissions::new(TrustLevel::High),
+            ..Default::default()
+        };
+        let result = orchestrator.execute(instance_id, config).await.unwrap();
+        assert_eq!(result.output.as_deref(), Some(&b"5"[..]));
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker_routes_to_fallback() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            circuit_breaker: CircuitBreakerConfig {
+                failure_threshold: 2,
+                open_duration: Duration::from_millis(20),
+                half_open_max_probes: 1,
+            },
+            fallbacks: HashMap::from([(RuntimeType::Python, RuntimeType::Wasm)]),
+            ..Default::default()
+        });
+        let python = Arc::new(StubRuntime::new(false));
+        orchestrator.register_runtime(RuntimeType::Python, python.clone());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(StubRuntime::new(true)));
+        let mut events = orchestrator.subscribe_breaker_events();
+
+        for _ in 0..2 {
+            assert!(orchestrator.compile(RuntimeType::Python, b"print(1)", Language::Python, TrustLevel::Low).await.is_err());
+        }
+        let event = events.try_recv().unwrap();
+        assert_eq!((event.runtime, event.from, event.to), (RuntimeType::Python, BreakerState::Closed, BreakerState::Open));
+
+        let module_id = orchestrator.compile(RuntimeType::Python, b"print(1)", Language::Python, TrustLevel::Low).await.unwrap();
+        assert_eq!(orchestrator.modules.read()[&module_id].runtime, RuntimeType::Wasm);
+
+        python.healthy.store(true, std::sync::atomic::Ordering::SeqCst);
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        let module_id = orchestrator.compile(RuntimeType::Python, b"print(1)", Language::Python, TrustLevel::Low).await.unwrap();
+        assert_eq!(orchestrator.modules.read()[&module_id].runtime, RuntimeType::Python);
+        assert_eq!(events.try_recv().unwrap().to, BreakerState::HalfOpen);
+        assert_eq!(events.try_recv().unwrap().to, BreakerState::Closed);
+
+        let metrics = &orchestrator.breaker_metrics()[&RuntimeType::Python];
+        assert_eq!((metrics.trips, metrics.rejected), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_retries_and_signs() {
+        let transport = Arc::new(MockTransport::new(vec![503, 429, 204]));
+        let dispatcher = WebhookDispatcher::new(webhook_config(), transport.clone());
+        let endpoint = dispatcher.config().endpoints[0].clone();
+        let event = WebhookEvent::new(WebhookEventKind::QuotaExceeded, None, serde_json::json!({}));
+
+        let report = dispatcher.deliver(&endpoint, &event).await;
+        assert!(report.delivered);
+        assert_eq!(report.attempts, 3);
+
+        let requests = transport.requests.lock();
+        let (headers, body) = &requests[2];
+        verify_signature(
+            "s3cret",
+            header(headers, TIMESTAMP_HEADER),
+            body,
+            header(headers, SIGNATURE_HEADER),
+        )
+        .unwrap();
+        assert!(verify_signature(
+            "other",
+            header(headers, TIMESTAMP_HEADER),
+            body,
+            header(headers, SIGNATURE_HEADER)
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_webhook_gives_up_on_client_error() {
+        let transport = Arc::new(MockTransport::new(vec![400, 200]));
+        let dispatcher = WebhookDispatcher::new(webhook_config(), transport.clone());
+        let endpoint = dispatcher.config().endpoints[0].clone();
+        let event = WebhookEvent::new(
+            WebhookEventKind::ExecutionFailed,
+            None,
+            serde_json::json!({}),
+        );
+
+        let report = dispatcher.deliver(&endpoint, &event).await;
+        assert!(!report.delivered);
+        assert_eq!(report.status, Some(400));
+        assert_eq!(transport.requests.lock().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_execution_fires_webhook() {
+        let transport = Arc::new(MockTransport::new(Vec::new()));
+        let dispatcher = Arc::new(WebhookDispatcher::new(webhook_config(), transport.clone()));
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            webhooks: Some(dispatcher),
+            ..Default::default()
+        });
+        let runtime = WasmRuntime::with_config(2, 1024 * 1024).unwrap();
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(runtime));
+
+        let wasm = wat::parse_str(r#"(module (func (export "_start") (result i32) i32.const 7))"#).unwrap();
+        let module_id = orchestrator.compile(RuntimeType::Wasm, &wasm, Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        orchestrator.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+
+        for _ in 0..100 {
+            if !transport.requests.lock().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let requests = transport.requests.lock();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(header(&requests[0].0, EVENT_HEADER), "execution_completed");
+        let event: WebhookEvent = serde_json::from_slice(&requests[0].1).unwrap();
+        assert_eq!(event.data["status"], "Succeeded");
+    }
+
+    #[tokio::test]
+    async fn test_denied_execution_fires_failure_webhook() {
+        use crate::policy::{PolicyConditions, PolicyRule, RulePolicy};
+
+        let transport = Arc::new(MockTransport::new(Vec::new()));
+        let dispatcher = Arc::new(WebhookDispatcher::new(webhook_config(), transport.clone()));
+        let policy = RulePolicy::new(vec![PolicyRule::deny(
+            "no-high-trust",
+            PolicyConditions {
+                trust_levels: vec![TrustLevel::High],
+                ..Default::default()
+            },
+        )]);
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            webhooks: Some(dispatcher),
+            policy: Some(Arc::new(policy)),
+            ..Default::default()
+        });
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        let wasm = wat::parse_str(r#"(module (func (export "_start") (result i32) i32.const 7))"#).unwrap();
+        let module_id = orchestrator.compile(RuntimeType::Wasm, &wasm, Language::Wasm, TrustLevel::Low).await.unwrap();
+        let instance_id = orchestrator.instantiate(module_id).await.unwrap();
+        let config = ExecutionConfig {
+            permissions: Permissions::new(TrustLevel::High),
+            ..Default::default()
+        };
+        let err = orchestrator.execute(instance_id, config).await.unwrap_err();
+
+        for _ in 0..100 {
+            if !transport.requests.lock().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let requests = transport.requests.lock();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(header(&requests[0].0, EVENT_HEADER), "execution_failed");
+        let event: WebhookEvent = serde_json::from_slice(&requests[0].1).unwrap();
+        assert_eq!(event.data["status"], "Errored");
+        assert_eq!(event.data["error"], err.to_string());
+    }
+
+    #[tokio::test]
+    async fn test_tenant_redaction() {
+        let redactor = Redactor::with_builtin_detectors().with_pattern("employee_id", r"EMP-\d{6}").unwrap();
+        let orchestrator = Orchestrator::new(OrchestratorConfig {
+            redactors: HashMap::from([("acme".to_string(), Arc::new(redactor))]),
+            ..OrchestratorConfig::with_in_memory_history()
+        });
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+
+        // Writes its input back out
+        let echo = r#"(module
+            (import "env" "input_len" (func $input_len (result i32)))
+            (import "env" "read_input" (func $read_input (param i32 i32) (result i32)))
+            (import "env" "write_output" (func $write_output (param i32 i32)))
+            (memory (export "memory") 1)
+            (func (export "_start") (result i32)
+                (call $write_output (i32.const 0) (call $read_input (i32.const 0) (call $input_len)))
+                (i32.const 0)))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, echo.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let input = b"bob@example.com paid with 4111 1111 1111 1111 as EMP-004217".to_vec();
+        let config = |tenant: &str| ExecutionConfig {
+            tenant_id: Some(tenant.to_string()),
+            input: Some(input.clone()),
+            ..Default::default()
+        };
+
+        let result = orchestrator.execute_module(module_id.clone(), config("acme")).await.unwrap();
+        assert_eq!(
+            String::from_utf8(result.output.unwrap()).unwrap(),
+            "[REDACTED:email] paid with [REDACTED:card_number] as [REDACTED:employee_id]"
+        );
+
+        let result = orchestrator.execute_module(module_id, config("globex")).await.unwrap();
+        assert_eq!(result.output, Some(input));
+    }
+
+    #[tokio::test]
+    async fn test_trace_verbosity() {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::default());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(2, 1024 * 1024).unwrap()));
+        let module = r#"(module (func (export "_start") (result i32) (i32.const 0)))"#;
+        let module_id = orchestrator.compile(RuntimeType::Wasm, module.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        let trace = |trace_verbosity| {
+            let orchestrator = &orchestrator;
+            let module_id = module_id.clone();
+            async move {
+                let config = ExecutionConfig {
+                    trace_verbosity,
+                    ..Default::default()
+                };
+                let result = orchestrator.execute_module(module_id, config).await.unwrap();
+                result.logs.trace.into_iter().map(|record| record.message).collect::<Vec<_>>()
+            }
+        };
+
+        assert!(trace(TraceVerbosity::Off).await.is_empty());
+        let basic = trace(TraceVerbosity::Basic).await;
+        assert!(basic.iter().any(|message| message.starts_with("Executed in")), "{:?}", basic);
+        assert!(!basic.iter().any(|message| message.starts_with("Scheduled on")), "{:?}", basic);
+        let verbose = trace(TraceVerbosity::Verbose).await;
+        assert!(verbose.iter().any(|message| message.starts_with("Scheduled on the Wasm runtime")), "{:?}", verbose);
+    }
+}
@@ -0,0 +1,153 @@
+use anyhow::{bail, Result};
+use next_rc_shared::{ExecutionConfig, ExecutionResult, RuntimeType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
+
+use crate::registry::ModuleRef;
+
+/// Execution tag holding the name of the pipeline that started it.
+pub const PIPELINE_TAG: &str = "next-rc.pipeline";
+
+/// Execution tag holding the name of the stage that started it.
+pub const PIPELINE_STAGE_TAG: &str = "next-rc.pipeline.stage";
+
+/// Chain of registered modules, possibly on different runtimes, where each
+/// stage's output is the next stage's input. Defined as data, so pipelines
+/// can be loaded from JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Pipeline {
+    pub name: String,
+    pub stages: Vec<PipelineStage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineStage {
+    pub name: String,
+    /// Registry reference, such as `filters/ipv4@stable`, resolved before
+    /// the first stage runs.
+    pub module: String,
+    /// Named profile the stage runs with. Ignored when `config` is set;
+    /// without either the stage runs with `ExecutionConfig::default()`.
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(default)]
+    pub config: Option<ExecutionConfig>,
+    /// Ends the pipeline after this stage, successfully, when its output
+    /// meets the condition.
+    #[serde(default)]
+    pub halt_when: Option<HaltCondition>,
+    /// Passes the stage's input, rather than its output, to the next stage.
+    /// For filters whose output is only a verdict.
+    #[serde(default)]
+    pub forward_input: bool,
+}
+
+impl PipelineStage {
+    pub fn new(name: impl Into<String>, module: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            module: module.into(),
+            profile: None,
+            config: None,
+            halt_when: None,
+            forward_input: false,
+        }
+    }
+
+    pub fn with_profile(mut self, profile: impl Into<String>) -> Self {
+        self.profile = Some(profile.into());
+        self
+    }
+
+    pub fn with_config(mut self, config: ExecutionConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    pub fn with_halt_when(mut self, condition: HaltCondition) -> Self {
+        self.halt_when = Some(condition);
+        self
+    }
+
+    pub fn with_forward_input(mut self, forward_input: bool) -> Self {
+        self.forward_input = forward_input;
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HaltCondition {
+    /// Output is empty or all zero bytes, as from an eBPF filter that
+    /// dropped its packet.
+    Rejected,
+    /// Output equals the given text, ignoring surrounding whitespace.
+    Equals(String),
+}
+
+impl HaltCondition {
+    pub fn matches(&self, output: &[u8]) -> bool {
+        match self {
+            HaltCondition::Rejected => output.iter().all(|byte| *byte == 0),
+            HaltCondition::Equals(expected) => String::from_utf8_lossy(output).trim() == expected.trim(),
+        }
+    }
+}
+
+impl Pipeline {
+    pub fn new(name: impl Into<String>, stages: Vec<PipelineStage>) -> Self {
+        Self {
+            name: name.into(),
+            stages,
+        }
+    }
+
+    /// Checks the pipeline's shape without resolving its modules or profiles.
+    pub fn validate(&self) -> Result<()> {
+        if self.stages.is_empty() {
+            bail!("Pipeline `{}` has no stages", self.name);
+        }
+        let mut names = HashSet::new();
+        for stage in &self.stages {
+            if !names.insert(stage.name.as_str()) {
+                bail!("Pipeline `{}` has more than one stage named `{}`", self.name, stage.name);
+            }
+            ModuleRef::parse(&stage.module)?;
+        }
+        Ok(())
+    }
+}
+
+/// What one stage did. Stages after a halt or a failure don't run and are
+/// left out of `PipelineResult::stages`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StageResult {
+    pub name: String,
+    pub runtime: RuntimeType,
+    /// `None` when the stage failed before it could execute.
+    pub result: Option<ExecutionResult>,
+    /// Why the stage failed, when it did.
+    pub error: Option<String>,
+    pub instantiate_time: Duration,
+    pub execute_time: Duration,
+}
+
+impl StageResult {
+    pub fn success(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineResult {
+    pub pipeline: String,
+    /// Every stage that ran succeeded.
+    pub success: bool,
+    /// Output of the last stage that ran, or its input if it forwards it.
+    pub output: Option<Vec<u8>>,
+    /// Stage whose `halt_when` condition ended the pipeline early.
+    pub halted_at: Option<String>,
+    pub stages: Vec<StageResult>,
+    pub total_time: Duration,
+}
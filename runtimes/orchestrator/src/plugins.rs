@@ -0,0 +1,111 @@
+use anyhow::{anyhow, Result};
+use next_rc_shared::{
+    Capability, ExecutionConfig, InstanceId, Language, ModuleId, Permissions, Runtime, RuntimeType, TrustLevel,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tracing::warn;
+
+const DEFAULT_PLUGIN_TIMEOUT: Duration = Duration::from_millis(50);
+const DEFAULT_PLUGIN_MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Operator-supplied WASM module run on every execution request before it
+/// is admitted. Like any guest its `_start` returns an `i32`; it reads a
+/// `PluginRequest` as JSON through `env.read_input` and answers with a
+/// `PluginVerdict` as JSON through `env.write_output`.
+#[derive(Debug, Clone)]
+pub struct RequestPlugin {
+    pub name: String,
+    /// Module compiled on the WASM runtime.
+    pub module_id: ModuleId,
+    pub timeout: Duration,
+    pub memory_limit: usize,
+    /// Admits requests when the plugin traps, times out or answers with
+    /// something other than a verdict, instead of rejecting them.
+    pub fail_open: bool,
+}
+
+impl RequestPlugin {
+    pub fn new(name: impl Into<String>, module_id: ModuleId) -> Self {
+        Self {
+            name: name.into(),
+            module_id,
+            timeout: DEFAULT_PLUGIN_TIMEOUT,
+            memory_limit: DEFAULT_PLUGIN_MEMORY_LIMIT,
+            fail_open: false,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    pub fn fail_open(mut self) -> Self {
+        self.fail_open = true;
+        self
+    }
+
+    /// Runs the plugin on a fresh instance of its module, with low trust.
+    pub(crate) async fn run(&self, runtime: &dyn Runtime, request: &PluginRequest) -> Result<PluginVerdict> {
+        let instance_id = runtime.instantiate(self.module_id.clone()).await?;
+        let config = ExecutionConfig {
+            timeout: self.timeout,
+            memory_limit: self.memory_limit,
+            permissions: Permissions::new(TrustLevel::Low),
+            input: Some(serde_json::to_vec(request)?),
+            ..Default::default()
+        };
+        let result = runtime.execute(instance_id.clone(), config).await;
+        if let Err(e) = runtime.destroy(instance_id).await {
+            warn!("Failed to destroy instance of plugin `{}`: {}", self.name, e);
+        }
+
+        let result = result?;
+        if !result.success {
+            return Err(anyhow!(result.error.unwrap_or_else(|| "plugin failed".to_string())));
+        }
+        match result.output.as_deref() {
+            None | Some([]) => Ok(PluginVerdict::default()),
+            Some(output) => Ok(serde_json::from_slice(output)?),
+        }
+    }
+}
+
+/// What a plugin is told about the request. The execution's input isn't
+/// included, only its length.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginRequest {
+    pub instance_id: InstanceId,
+    pub module_id: ModuleId,
+    pub runtime: RuntimeType,
+    pub language: Language,
+    pub tenant_id: Option<String>,
+    pub trust_level: TrustLevel,
+    pub capabilities: HashSet<Capability>,
+    pub tags: HashMap<String, String>,
+    pub timeout: Duration,
+    pub memory_limit: usize,
+    pub input_len: usize,
+}
+
+/// A plugin's answer. Fields left out change nothing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PluginVerdict {
+    /// Rejects the request, telling the caller why.
+    #[serde(default)]
+    pub reject: Option<String>,
+    /// Added to the request's tags, replacing those with the same keys.
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    /// Runs the request with this profile's timeout, memory limit and
+    /// permissions.
+    #[serde(default)]
+    pub profile: Option<String>,
+}
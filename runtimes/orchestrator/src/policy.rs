@@ -0,0 +1,286 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use next_rc_shared::{Capability, RuntimeType, TrustLevel};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// What a policy decides an execution on.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PolicyInput {
+    pub tenant_id: Option<String>,
+    pub trust_level: TrustLevel,
+    pub runtime: RuntimeType,
+    pub capabilities: HashSet<Capability>,
+    pub tags: HashMap<String, String>,
+    /// Settings of the engine that would run the execution, as recorded in
+    /// its fingerprint; eBPF reports `allow_unsafe` here.
+    pub engine_config: BTreeMap<String, String>,
+    pub time: SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PolicyDecision {
+    pub allowed: bool,
+    /// Rule that denied the execution, when one did.
+    pub rule: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl PolicyDecision {
+    pub fn allow() -> Self {
+        Self {
+            allowed: true,
+            rule: None,
+            reason: None,
+        }
+    }
+
+    pub fn deny(rule: Option<String>, reason: impl Into<String>) -> Self {
+        Self {
+            allowed: false,
+            rule,
+            reason: Some(reason.into()),
+        }
+    }
+}
+
+/// Decides whether executions may run, without leaving the node.
+/// `RulePolicy` covers simple rules; implement this to evaluate with an
+/// embedded engine such as OPA instead. Errors deny the execution.
+#[async_trait]
+pub trait PolicyEvaluator: Send + Sync {
+    async fn evaluate(&self, input: &PolicyInput) -> Result<PolicyDecision>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Weekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl Weekday {
+    /// Day of the week `time` falls on, in UTC.
+    pub fn of(time: SystemTime) -> Self {
+        let days_since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 86_400;
+        // The epoch was a Thursday
+        [
+            Weekday::Thursday,
+            Weekday::Friday,
+            Weekday::Saturday,
+            Weekday::Sunday,
+            Weekday::Monday,
+            Weekday::Tuesday,
+            Weekday::Wednesday,
+        ][(days_since_epoch % 7) as usize]
+    }
+}
+
+/// Matches executions on every populated field; empty fields match
+/// anything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PolicyConditions {
+    #[serde(default)]
+    pub trust_levels: Vec<TrustLevel>,
+    #[serde(default)]
+    pub runtimes: Vec<RuntimeType>,
+    #[serde(default)]
+    pub tenants: Vec<String>,
+    /// Matches executions requesting any of these.
+    #[serde(default)]
+    pub capabilities: Vec<Capability>,
+    /// Days of the week, in UTC.
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+    #[serde(default)]
+    pub engine_config: BTreeMap<String, String>,
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    /// Operator flags that must all be set on the policy.
+    #[serde(default)]
+    pub flags: Vec<String>,
+}
+
+impl PolicyConditions {
+    fn matches(&self, input: &PolicyInput, flags: &HashSet<String>) -> bool {
+        (self.trust_levels.is_empty() || self.trust_levels.contains(&input.trust_level))
+            && (self.runtimes.is_empty() || self.runtimes.contains(&input.runtime))
+            && (self.tenants.is_empty() || input.tenant_id.as_ref().is_some_and(|tenant| self.tenants.contains(tenant)))
+            && (self.capabilities.is_empty() || self.capabilities.iter().any(|c| input.capabilities.contains(c)))
+            && (self.days.is_empty() || self.days.contains(&Weekday::of(input.time)))
+            && self.engine_config.iter().all(|(name, value)| input.engine_config.get(name) == Some(value))
+            && self.tags.iter().all(|(key, value)| input.tags.get(key) == Some(value))
+            && self.flags.iter().all(|flag| flags.contains(flag))
+    }
+}
+
+/// Denies executions matching `when`, except those also matching `unless`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PolicyRule {
+    pub name: String,
+    pub when: PolicyConditions,
+    #[serde(default)]
+    pub unless: Option<PolicyConditions>,
+    /// Shown to callers the rule denies.
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl PolicyRule {
+    pub fn deny(name: impl Into<String>, when: PolicyConditions) -> Self {
+        Self {
+            name: name.into(),
+            when,
+            unless: None,
+            reason: None,
+        }
+    }
+
+    pub fn unless(mut self, conditions: PolicyConditions) -> Self {
+        self.unless = Some(conditions);
+        self
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+}
+
+/// Deny rules checked in order, plus operator flags the rules can require,
+/// such as a licensed feature being enabled. Executions no rule denies are
+/// allowed.
+pub struct RulePolicy {
+    rules: Vec<PolicyRule>,
+    flags: RwLock<HashSet<String>>,
+}
+
+impl RulePolicy {
+    pub fn new(rules: Vec<PolicyRule>) -> Self {
+        Self {
+            rules,
+            flags: RwLock::new(HashSet::new()),
+        }
+    }
+
+    pub fn set_flag(&self, flag: impl Into<String>, enabled: bool) {
+        let flag = flag.into();
+        if enabled {
+            self.flags.write().insert(flag);
+        } else {
+            self.flags.write().remove(&flag);
+        }
+    }
+
+    pub fn rules(&self) -> &[PolicyRule] {
+        &self.rules
+    }
+}
+
+#[async_trait]
+impl PolicyEvaluator for RulePolicy {
+    async fn evaluate(&self, input: &PolicyInput) -> Result<PolicyDecision> {
+        let flags = self.flags.read();
+        let denied_by = self.rules.iter().find(|rule| {
+            rule.when.matches(input, &flags) && !rule.unless.as_ref().is_some_and(|unless| unless.matches(input, &flags))
+        });
+        Ok(match denied_by {
+            Some(rule) => PolicyDecision::deny(
+                Some(rule.name.clone()),
+                rule.reason.clone().unwrap_or_else(|| format!("Denied by policy rule `{}`", rule.name)),
+            ),
+            None => PolicyDecision::allow(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn input(trust_level: TrustLevel, tenant: &str, days_since_epoch: u64) -> PolicyInput {
+        PolicyInput {
+            tenant_id: Some(tenant.to_string()),
+            trust_level,
+            runtime: RuntimeType::Wasm,
+            capabilities: HashSet::new(),
+            tags: HashMap::new(),
+            engine_config: BTreeMap::new(),
+            time: UNIX_EPOCH + Duration::from_secs(days_since_epoch * 86_400),
+        }
+    }
+
+    #[test]
+    fn test_weekdays() {
+        assert_eq!(Weekday::of(UNIX_EPOCH), Weekday::Thursday);
+        // 2024-06-01
+        assert_eq!(Weekday::of(UNIX_EPOCH + Duration::from_secs(19_875 * 86_400)), Weekday::Saturday);
+    }
+
+    #[tokio::test]
+    async fn test_rules() {
+        let policy = RulePolicy::new(vec![
+            PolicyRule::deny(
+                "high-trust-tenants",
+                PolicyConditions {
+                    trust_levels: vec![TrustLevel::High],
+                    ..Default::default()
+                },
+            )
+            .unless(PolicyConditions {
+                tenants: vec!["acme".to_string()],
+                ..Default::default()
+            }),
+            PolicyRule::deny(
+                "no-subprocesses-on-weekends",
+                PolicyConditions {
+                    capabilities: vec![Capability::ProcessSpawn],
+                    days: vec![Weekday::Saturday, Weekday::Sunday],
+                    ..Default::default()
+                },
+            )
+            .with_reason("Subprocesses are not allowed on weekends"),
+            PolicyRule::deny(
+                "ebpf-unsafe",
+                PolicyConditions {
+                    runtimes: vec![RuntimeType::Ebpf],
+                    engine_config: BTreeMap::from([("allow_unsafe".to_string(), "true".to_string())]),
+                    ..Default::default()
+                },
+            )
+            .unless(PolicyConditions {
+                flags: vec!["ebpf-unsafe".to_string()],
+                ..Default::default()
+            }),
+        ]);
+        let denied_by = |input: PolicyInput| {
+            let policy = &policy;
+            async move { policy.evaluate(&input).await.unwrap().rule }
+        };
+
+        assert_eq!(denied_by(input(TrustLevel::High, "acme", 0)).await, None);
+        assert_eq!(denied_by(input(TrustLevel::High, "other", 0)).await.as_deref(), Some("high-trust-tenants"));
+
+        let mut weekend = input(TrustLevel::Medium, "other", 19_875);
+        weekend.capabilities.insert(Capability::ProcessSpawn);
+        let decision = policy.evaluate(&weekend).await.unwrap();
+        assert_eq!(decision.reason.as_deref(), Some("Subprocesses are not allowed on weekends"));
+        weekend.time += Duration::from_secs(2 * 86_400);
+        assert!(policy.evaluate(&weekend).await.unwrap().allowed);
+
+        let mut unsafe_ebpf = input(TrustLevel::Medium, "other", 0);
+        unsafe_ebpf.runtime = RuntimeType::Ebpf;
+        unsafe_ebpf.engine_config.insert("allow_unsafe".to_string(), "true".to_string());
+        assert_eq!(denied_by(unsafe_ebpf.clone()).await.as_deref(), Some("ebpf-unsafe"));
+        policy.set_flag("ebpf-unsafe", true);
+        assert_eq!(denied_by(unsafe_ebpf).await, None);
+    }
+}
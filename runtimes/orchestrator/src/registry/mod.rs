@@ -0,0 +1,296 @@
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use next_rc_shared::{Language, ModuleId, RuntimeType};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::{Duration, SystemTime};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteModuleRegistry;
+
+/// `name@version` reference such as `filters/ipv4@1.2.0`. The version may
+/// also be an alias; without one the most recently registered version wins.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ModuleRef {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl ModuleRef {
+    pub fn parse(reference: &str) -> Result<Self> {
+        let (name, version) = match reference.rsplit_once('@') {
+            Some((name, version)) => (name, Some(version)),
+            None => (reference, None),
+        };
+
+        let valid = |part: &str| {
+            !part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '/'))
+        };
+        if !valid(name) || name.starts_with('/') || name.ends_with('/') {
+            bail!("Invalid module name in `{}`", reference);
+        }
+        if version.is_some_and(|version| !valid(version) || version.contains('/')) {
+            bail!("Invalid module version in `{}`", reference);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            version: version.map(str::to_string),
+        })
+    }
+}
+
+impl fmt::Display for ModuleRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{}@{}", self.name, version),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+/// A compiled module stored under a name and version.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegisteredModule {
+    pub name: String,
+    pub version: String,
+    pub module_id: ModuleId,
+    pub runtime: RuntimeType,
+    pub language: Language,
+    pub registered_at: SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleAlias {
+    pub name: String,
+    pub alias: String,
+    pub version: String,
+}
+
+/// Which versions `ModuleRegistry::collect_garbage` keeps. Aliased versions
+/// are always kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Most recent versions kept per name.
+    pub keep_latest: usize,
+    /// Older versions beyond `keep_latest` are removed once they reach this
+    /// age; when unset they are removed immediately.
+    pub max_age: Option<Duration>,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            keep_latest: 5,
+            max_age: None,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Versions in `modules` the policy lets go of.
+    pub fn expired(
+        &self,
+        modules: &[RegisteredModule],
+        aliases: &[ModuleAlias],
+        now: SystemTime,
+    ) -> Vec<RegisteredModule> {
+        let pinned: HashSet<(&str, &str)> = aliases
+            .iter()
+            .map(|alias| (alias.name.as_str(), alias.version.as_str()))
+            .collect();
+
+        let mut by_name: HashMap<&str, Vec<&RegisteredModule>> = HashMap::new();
+        for module in modules {
+            by_name.entry(module.name.as_str()).or_default().push(module);
+        }
+
+        let mut expired = Vec::new();
+        for versions in by_name.values_mut() {
+            versions.sort_by_key(|module| std::cmp::Reverse(module.registered_at));
+            for module in versions.iter().skip(self.keep_latest) {
+                if pinned.contains(&(module.name.as_str(), module.version.as_str())) {
+                    continue;
+                }
+                let age = now.duration_since(module.registered_at).unwrap_or_default();
+                if self.max_age.is_none_or(|max_age| age >= max_age) {
+                    expired.push((*module).clone());
+                }
+            }
+        }
+        expired
+    }
+}
+
+/// Maps human-readable names to compiled modules.
+#[async_trait]
+pub trait ModuleRegistry: Send + Sync {
+    /// Versions are immutable: registering an existing version fails.
+    async fn register(&self, module: RegisteredModule) -> Result<()>;
+    async fn get(&self, name: &str, version: &str) -> Result<Option<RegisteredModule>>;
+    /// Lists registered versions, newest first, optionally for a single name.
+    async fn list(&self, name: Option<&str>) -> Result<Vec<RegisteredModule>>;
+    async fn remove(&self, name: &str, version: &str) -> Result<bool>;
+    /// Points `name@alias` at an existing version, replacing any previous target.
+    async fn set_alias(&self, name: &str, alias: &str, version: &str) -> Result<()>;
+    async fn aliases(&self, name: Option<&str>) -> Result<Vec<ModuleAlias>>;
+
+    async fn resolve(&self, reference: &ModuleRef) -> Result<Option<RegisteredModule>> {
+        let Some(version) = &reference.version else {
+            return Ok(self.list(Some(&reference.name)).await?.into_iter().next());
+        };
+
+        let aliased = self
+            .aliases(Some(&reference.name))
+            .await?
+            .into_iter()
+            .find(|alias| alias.alias == *version);
+        match aliased {
+            Some(alias) => self.get(&alias.name, &alias.version).await,
+            None => self.get(&reference.name, version).await,
+        }
+    }
+
+    /// Removes the versions `policy` no longer retains and returns them.
+    async fn collect_garbage(&self, policy: &RetentionPolicy) -> Result<Vec<RegisteredModule>> {
+        let modules = self.list(None).await?;
+        let aliases = self.aliases(None).await?;
+        let expired = policy.expired(&modules, &aliases, SystemTime::now());
+        for module in &expired {
+            self.remove(&module.name, &module.version).await?;
+        }
+        Ok(expired)
+    }
+}
+
+/// Process-local registry; names are lost on restart.
+#[derive(Default)]
+pub struct InMemoryModuleRegistry {
+    modules: RwLock<HashMap<(String, String), RegisteredModule>>,
+    aliases: RwLock<HashMap<(String, String), String>>,
+}
+
+impl InMemoryModuleRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl ModuleRegistry for InMemoryModuleRegistry {
+    async fn register(&self, module: RegisteredModule) -> Result<()> {
+        let mut modules = self.modules.write();
+        let key = (module.name.clone(), module.version.clone());
+        if modules.contains_key(&key) {
+            bail!("Module {}@{} is already registered", module.name, module.version);
+        }
+        modules.insert(key, module);
+        Ok(())
+    }
+
+    async fn get(&self, name: &str, version: &str) -> Result<Option<RegisteredModule>> {
+        Ok(self.modules.read().get(&(name.to_string(), version.to_string())).cloned())
+    }
+
+    async fn list(&self, name: Option<&str>) -> Result<Vec<RegisteredModule>> {
+        let mut modules: Vec<_> = self
+            .modules
+            .read()
+            .values()
+            .filter(|module| name.is_none_or(|name| module.name == name))
+            .cloned()
+            .collect();
+        modules.sort_by_key(|module| std::cmp::Reverse(module.registered_at));
+        Ok(modules)
+    }
+
+    async fn remove(&self, name: &str, version: &str) -> Result<bool> {
+        self.aliases
+            .write()
+            .retain(|(alias_name, _), target| alias_name != name || target != version);
+        Ok(self.modules.write().remove(&(name.to_string(), version.to_string())).is_some())
+    }
+
+    async fn set_alias(&self, name: &str, alias: &str, version: &str) -> Result<()> {
+        if !self.modules.read().contains_key(&(name.to_string(), version.to_string())) {
+            bail!("Module {}@{} is not registered", name, version);
+        }
+        self.aliases
+            .write()
+            .insert((name.to_string(), alias.to_string()), version.to_string());
+        Ok(())
+    }
+
+    async fn aliases(&self, name: Option<&str>) -> Result<Vec<ModuleAlias>> {
+        Ok(self
+            .aliases
+            .read()
+            .iter()
+            .filter(|((alias_name, _), _)| name.is_none_or(|name| alias_name == name))
+            .map(|((name, alias), version)| ModuleAlias {
+                name: name.clone(),
+                alias: alias.clone(),
+                version: version.clone(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn sample_module(name: &str, version: &str, age: Duration) -> RegisteredModule {
+    RegisteredModule {
+        name: name.to_string(),
+        version: version.to_string(),
+        module_id: ModuleId(uuid::Uuid::new_v4()),
+        runtime: RuntimeType::Wasm,
+        language: Language::Wasm,
+        registered_at: crate::history::from_unix_millis(crate::history::to_unix_millis(SystemTime::now() - age)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_module_ref() {
+        let reference = ModuleRef::parse("filters/ipv4@1.2.0").unwrap();
+        assert_eq!(reference.name, "filters/ipv4");
+        assert_eq!(reference.version.as_deref(), Some("1.2.0"));
+        assert_eq!(ModuleRef::parse("filters/ipv4").unwrap().version, None);
+        assert!(ModuleRef::parse("filters/ipv4@").is_err());
+        assert!(ModuleRef::parse("/ipv4@1").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_aliases_and_retention() {
+        let registry = InMemoryModuleRegistry::new();
+        for (version, age) in [("1.0.0", 300), ("1.1.0", 200), ("1.2.0", 100)] {
+            registry.register(sample_module("filters/ipv4", version, Duration::from_secs(age))).await.unwrap();
+        }
+        assert!(registry.register(sample_module("filters/ipv4", "1.2.0", Duration::ZERO)).await.is_err());
+        registry.set_alias("filters/ipv4", "stable", "1.0.0").await.unwrap();
+
+        let latest = registry.resolve(&ModuleRef::parse("filters/ipv4").unwrap()).await.unwrap().unwrap();
+        assert_eq!(latest.version, "1.2.0");
+        let stable = registry.resolve(&ModuleRef::parse("filters/ipv4@stable").unwrap()).await.unwrap().unwrap();
+        assert_eq!(stable.version, "1.0.0");
+
+        let policy = RetentionPolicy {
+            keep_latest: 1,
+            max_age: None,
+        };
+        let removed = registry.collect_garbage(&policy).await.unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].version, "1.1.0");
+        assert_eq!(registry.list(Some("filters/ipv4")).await.unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,235 @@
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::path::Path;
+use std::sync::Arc;
+
+use super::{ModuleAlias, ModuleRegistry, RegisteredModule};
+use crate::history::{from_label, from_unix_millis, to_label, to_unix_millis};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS module_versions (
+        name TEXT NOT NULL,
+        version TEXT NOT NULL,
+        module_id TEXT NOT NULL,
+        runtime TEXT NOT NULL,
+        language TEXT NOT NULL,
+        registered_at_ms INTEGER NOT NULL,
+        PRIMARY KEY (name, version)
+    );
+    CREATE TABLE IF NOT EXISTS module_aliases (
+        name TEXT NOT NULL,
+        alias TEXT NOT NULL,
+        version TEXT NOT NULL,
+        PRIMARY KEY (name, alias),
+        FOREIGN KEY (name, version) REFERENCES module_versions (name, version) ON DELETE CASCADE
+    );
+";
+
+const SELECT_COLUMNS: &str = "name, version, module_id, runtime, language, registered_at_ms";
+
+/// SQLite-backed registry, so names survive restarts of a single node. Only
+/// the names are persisted: the modules themselves must be recompiled and
+/// re-registered after a restart.
+pub struct SqliteModuleRegistry {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteModuleRegistry {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || f(&mut conn.lock()))
+            .await
+            .map_err(|e| anyhow!("Registry query task failed: {}", e))?
+    }
+}
+
+fn read_module(row: &Row<'_>) -> rusqlite::Result<RawModule> {
+    Ok(RawModule {
+        name: row.get(0)?,
+        version: row.get(1)?,
+        module_id: row.get(2)?,
+        runtime: row.get(3)?,
+        language: row.get(4)?,
+        registered_at_ms: row.get(5)?,
+    })
+}
+
+struct RawModule {
+    name: String,
+    version: String,
+    module_id: String,
+    runtime: String,
+    language: String,
+    registered_at_ms: i64,
+}
+
+impl RawModule {
+    fn into_module(self) -> Result<RegisteredModule> {
+        Ok(RegisteredModule {
+            name: self.name,
+            version: self.version,
+            module_id: self.module_id.parse()?,
+            runtime: from_label(&self.runtime)?,
+            language: from_label(&self.language)?,
+            registered_at: from_unix_millis(self.registered_at_ms),
+        })
+    }
+}
+
+#[async_trait]
+impl ModuleRegistry for SqliteModuleRegistry {
+    async fn register(&self, module: RegisteredModule) -> Result<()> {
+        self.with_conn(move |conn| {
+            let inserted = conn.execute(
+                "INSERT OR IGNORE INTO module_versions (name, version, module_id, runtime, language, \
+                 registered_at_ms) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    module.name,
+                    module.version,
+                    module.module_id.to_string(),
+                    to_label(&module.runtime),
+                    to_label(&module.language),
+                    to_unix_millis(module.registered_at),
+                ],
+            )?;
+            if inserted == 0 {
+                bail!("Module {}@{} is already registered", module.name, module.version);
+            }
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get(&self, name: &str, version: &str) -> Result<Option<RegisteredModule>> {
+        let (name, version) = (name.to_string(), version.to_string());
+        self.with_conn(move |conn| {
+            let raw = conn
+                .query_row(
+                    &format!("SELECT {} FROM module_versions WHERE name = ?1 AND version = ?2", SELECT_COLUMNS),
+                    params![name, version],
+                    read_module,
+                )
+                .optional()?;
+            raw.map(RawModule::into_module).transpose()
+        })
+        .await
+    }
+
+    async fn list(&self, name: Option<&str>) -> Result<Vec<RegisteredModule>> {
+        let name = name.map(str::to_string);
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(&format!(
+                "SELECT {} FROM module_versions WHERE ?1 IS NULL OR name = ?1 \
+                 ORDER BY registered_at_ms DESC",
+                SELECT_COLUMNS
+            ))?;
+            let raws = stmt
+                .query_map(params![name], read_module)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            raws.into_iter().map(RawModule::into_module).collect()
+        })
+        .await
+    }
+
+    async fn remove(&self, name: &str, version: &str) -> Result<bool> {
+        let (name, version) = (name.to_string(), version.to_string());
+        self.with_conn(move |conn| {
+            let removed = conn.execute(
+                "DELETE FROM module_versions WHERE name = ?1 AND version = ?2",
+                params![name, version],
+            )?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    async fn set_alias(&self, name: &str, alias: &str, version: &str) -> Result<()> {
+        let (name, alias, version) = (name.to_string(), alias.to_string(), version.to_string());
+        self.with_conn(move |conn| {
+            let exists = conn
+                .query_row(
+                    "SELECT 1 FROM module_versions WHERE name = ?1 AND version = ?2",
+                    params![name, version],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some();
+            if !exists {
+                bail!("Module {}@{} is not registered", name, version);
+            }
+            conn.execute(
+                "INSERT INTO module_aliases (name, alias, version) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT (name, alias) DO UPDATE SET version = excluded.version",
+                params![name, alias, version],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn aliases(&self, name: Option<&str>) -> Result<Vec<ModuleAlias>> {
+        let name = name.map(str::to_string);
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT name, alias, version FROM module_aliases WHERE ?1 IS NULL OR name = ?1",
+            )?;
+            let aliases = stmt
+                .query_map(params![name], |row| {
+                    Ok(ModuleAlias {
+                        name: row.get(0)?,
+                        alias: row.get(1)?,
+                        version: row.get(2)?,
+                    })
+                })?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(aliases)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{sample_module, ModuleRef};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_sqlite_round_trip() {
+        let registry = SqliteModuleRegistry::open_in_memory().unwrap();
+        let module = sample_module("filters/ipv4", "1.2.0", Duration::ZERO);
+
+        registry.register(module.clone()).await.unwrap();
+        assert!(registry.register(module.clone()).await.is_err());
+        registry.set_alias("filters/ipv4", "stable", "1.2.0").await.unwrap();
+
+        let resolved = registry.resolve(&ModuleRef::parse("filters/ipv4@stable").unwrap()).await.unwrap();
+        assert_eq!(resolved, Some(module));
+
+        assert!(registry.remove("filters/ipv4", "1.2.0").await.unwrap());
+        assert!(registry.aliases(None).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::{anyhow, bail, Result};
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Days searched for a matching date before giving up; enough for any
+/// satisfiable expression, including 29 February ones across century years.
+const MAX_SEARCH_DAYS: u64 = 366 * 9;
+
+/// Five-field cron expression (`minute hour day-of-month month day-of-week`),
+/// evaluated in UTC. Fields take `*`, numbers, ranges (`1-5`), steps (`*/15`,
+/// `10-50/20`) and comma lists; day-of-week counts Sunday as 0 or 7. As in
+/// classic cron, when both day fields are restricted a date matching either
+/// one fires. `@hourly`, `@daily`, `@weekly`, `@monthly` and `@yearly` are
+/// accepted as shorthands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    expression: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl CronSchedule {
+    /// First matching minute strictly after `after`, or `None` if the
+    /// expression can never match (such as 30 February).
+    pub fn next_after(&self, after: SystemTime) -> Option<SystemTime> {
+        let start = after.duration_since(UNIX_EPOCH).ok()?.as_secs() / 60 + 1;
+        let first_day = start / 1440;
+        let mut first_minute = start % 1440;
+
+        for day in first_day..first_day + MAX_SEARCH_DAYS {
+            if self.matches_day(day) {
+                for minute_of_day in first_minute..1440 {
+                    if bit(self.hours, minute_of_day / 60) && bit(self.minutes, minute_of_day % 60) {
+                        return Some(UNIX_EPOCH + Duration::from_secs((day * 1440 + minute_of_day) * 60));
+                    }
+                }
+            }
+            first_minute = 0;
+        }
+        None
+    }
+
+    fn matches_day(&self, days_since_epoch: u64) -> bool {
+        let (_, month, day) = civil_from_days(days_since_epoch);
+        // 1 January 1970 was a Thursday
+        let weekday = (days_since_epoch + 4) % 7;
+        if !bit(self.months, month) {
+            return false;
+        }
+
+        let day_matches = bit(self.days, day);
+        let weekday_matches = bit(self.weekdays, weekday);
+        match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day_matches || weekday_matches,
+            _ => day_matches && weekday_matches,
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let expanded = match expression.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            bail!("Cron expression {:?} must have five fields", expression);
+        };
+
+        let mut weekday_bits = parse_field(weekdays, 0, 7)?;
+        // Sunday may be written as 7
+        if bit(weekday_bits, 7) {
+            weekday_bits = (weekday_bits & !(1 << 7)) | 1;
+        }
+
+        Ok(Self {
+            expression: expression.trim().to_string(),
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekday_bits,
+            days_restricted: !days.starts_with('*'),
+            weekdays_restricted: !weekdays.starts_with('*'),
+        })
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.expression)
+    }
+}
+
+fn bit(set: u64, value: u64) -> bool {
+    set & (1 << value) != 0
+}
+
+/// Bitmask of the values in `field`, each within `min..=max`.
+fn parse_field(field: &str, min: u64, max: u64) -> Result<u64> {
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u64>().map_err(|_| anyhow!("Invalid step in {:?}", part))?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            bail!("Step must be positive in {:?}", part);
+        }
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (parse_value(start, part)?, parse_value(end, part)?),
+                // `a/n` runs from `a` to the end of the field
+                None if part.contains('/') => (parse_value(range, part)?, max),
+                None => {
+                    let value = parse_value(range, part)?;
+                    (value, value)
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            bail!("{:?} is outside {}-{}", part, min, max);
+        }
+
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+fn parse_value(value: &str, part: &str) -> Result<u64> {
+    value.parse().map_err(|_| anyhow!("Invalid value in {:?}", part))
+}
+
+/// (year, month, day) of the given day since the Unix epoch.
+fn civil_from_days(days_since_epoch: u64) -> (u64, u64, u64) {
+    let z = days_since_epoch + 719_468;
+    let era = z / 146_097;
+    let day_of_era = z % 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
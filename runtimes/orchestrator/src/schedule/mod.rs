@@ -0,0 +1,634 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use next_rc_shared::{ExecutionConfig, ExecutionResult};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use crate::history::{ExecutionFilter, ExecutionRecord};
+use crate::orchestrator::Orchestrator;
+use crate::registry::ModuleRef;
+
+mod cron;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use self::cron::CronSchedule;
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteJobStore;
+
+/// Execution tag holding the id of the scheduled job that started it.
+pub const SCHEDULE_JOB_TAG: &str = "next-rc.schedule.job";
+
+/// Execution tag holding the id of the job run that started it.
+pub const SCHEDULE_RUN_TAG: &str = "next-rc.schedule.run";
+
+/// Shortest period `Schedule::Every` accepts.
+const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Runs kept per job by `InMemoryJobStore`.
+const DEFAULT_RUN_CAPACITY: usize = 1_000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Schedule {
+    /// Cron expression; see `CronSchedule` for the syntax.
+    Cron(String),
+    /// Fixed period, counted from the job's creation.
+    Every(Duration),
+}
+
+impl Schedule {
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Schedule::Cron(expression) => expression.parse::<CronSchedule>().map(|_| ()),
+            Schedule::Every(period) if *period < MIN_INTERVAL => {
+                Err(anyhow!("Schedule period must be at least {:?}", MIN_INTERVAL))
+            }
+            Schedule::Every(_) => Ok(()),
+        }
+    }
+
+    /// First firing strictly after `after` of a job created at `created_at`.
+    pub fn next_after(&self, created_at: SystemTime, after: SystemTime) -> Result<Option<SystemTime>> {
+        match self {
+            Schedule::Cron(expression) => Ok(expression.parse::<CronSchedule>()?.next_after(after)),
+            Schedule::Every(period) => {
+                let period = period.as_millis().max(1);
+                let elapsed = after.duration_since(created_at).map(|d| d.as_millis()).unwrap_or(0);
+                let next = (elapsed / period + 1) * period;
+                Ok(Some(created_at + Duration::from_millis(next as u64)))
+            }
+        }
+    }
+}
+
+/// What happens when a job fires while its previous run is still going.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OverlapPolicy {
+    /// Drop the new firing.
+    #[default]
+    Skip,
+    /// Start the new firing once the previous run finishes. At most one
+    /// firing waits; later ones are skipped.
+    Queue,
+    /// Abandon the previous run and start the new one.
+    CancelPrevious,
+}
+
+/// A module executed on a schedule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    pub id: Uuid,
+    pub name: String,
+    /// Registry reference, such as `reports/daily@stable`, resolved at every
+    /// run so the job picks up new versions behind an alias.
+    pub module: String,
+    pub config: ExecutionConfig,
+    pub schedule: Schedule,
+    /// Each firing is delayed by a random amount up to this, spreading out
+    /// jobs that share a schedule.
+    #[serde(default)]
+    pub jitter: Duration,
+    #[serde(default)]
+    pub overlap: OverlapPolicy,
+    pub enabled: bool,
+    pub created_at: SystemTime,
+    /// Next firing as the schedule gives it.
+    pub next_fire_at: Option<SystemTime>,
+    /// When the next firing runs, after jitter.
+    pub next_run_at: Option<SystemTime>,
+}
+
+impl ScheduledJob {
+    pub fn new(name: impl Into<String>, module: impl Into<String>, schedule: Schedule, config: ExecutionConfig) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            module: module.into(),
+            config,
+            schedule,
+            jitter: Duration::ZERO,
+            overlap: OverlapPolicy::default(),
+            enabled: true,
+            created_at: SystemTime::now(),
+            next_fire_at: None,
+            next_run_at: None,
+        }
+    }
+
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn with_overlap(mut self, overlap: OverlapPolicy) -> Self {
+        self.overlap = overlap;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum JobRunStatus {
+    /// Waiting for the previous run under `OverlapPolicy::Queue`.
+    Queued,
+    Running,
+    Succeeded,
+    /// The execution failed, or the module couldn't be resolved or started.
+    Failed,
+    /// Dropped because the previous run was still going.
+    Skipped,
+    /// Abandoned for a newer run or because the job was removed.
+    Cancelled,
+}
+
+/// One firing of a scheduled job. Executions it starts carry
+/// `SCHEDULE_RUN_TAG` with the run's id in the orchestrator's history.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobRun {
+    pub id: Uuid,
+    pub job_id: Uuid,
+    pub scheduled_for: SystemTime,
+    pub started_at: Option<SystemTime>,
+    pub finished_at: Option<SystemTime>,
+    pub status: JobRunStatus,
+    pub error: Option<String>,
+}
+
+impl JobRun {
+    fn new(job_id: Uuid, scheduled_for: SystemTime, status: JobRunStatus) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            job_id,
+            scheduled_for,
+            started_at: None,
+            finished_at: None,
+            status,
+            error: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(self.status, JobRunStatus::Queued | JobRunStatus::Running)
+    }
+}
+
+/// Persists scheduled jobs and their runs, so schedules survive restarts.
+#[async_trait]
+pub trait JobStore: Send + Sync {
+    /// Inserts the job, or replaces the one with the same id.
+    async fn save_job(&self, job: ScheduledJob) -> Result<()>;
+    async fn get_job(&self, id: Uuid) -> Result<Option<ScheduledJob>>;
+    async fn list_jobs(&self) -> Result<Vec<ScheduledJob>>;
+    /// Removes the job and its runs.
+    async fn remove_job(&self, id: Uuid) -> Result<bool>;
+    /// Inserts the run, or replaces the one with the same id.
+    async fn record_run(&self, run: JobRun) -> Result<()>;
+    /// The job's runs, most recently scheduled first.
+    async fn list_runs(&self, job_id: Uuid, limit: Option<usize>) -> Result<Vec<JobRun>>;
+}
+
+/// Process-local store that keeps the latest runs of each job.
+pub struct InMemoryJobStore {
+    jobs: RwLock<HashMap<Uuid, ScheduledJob>>,
+    runs: RwLock<HashMap<Uuid, Vec<JobRun>>>,
+    run_capacity: usize,
+}
+
+impl InMemoryJobStore {
+    pub fn new(run_capacity: usize) -> Self {
+        Self {
+            jobs: RwLock::new(HashMap::new()),
+            runs: RwLock::new(HashMap::new()),
+            run_capacity,
+        }
+    }
+}
+
+impl Default for InMemoryJobStore {
+    fn default() -> Self {
+        Self::new(DEFAULT_RUN_CAPACITY)
+    }
+}
+
+#[async_trait]
+impl JobStore for InMemoryJobStore {
+    async fn save_job(&self, job: ScheduledJob) -> Result<()> {
+        self.jobs.write().insert(job.id, job);
+        Ok(())
+    }
+
+    async fn get_job(&self, id: Uuid) -> Result<Option<ScheduledJob>> {
+        Ok(self.jobs.read().get(&id).cloned())
+    }
+
+    async fn list_jobs(&self) -> Result<Vec<ScheduledJob>> {
+        let mut jobs: Vec<ScheduledJob> = self.jobs.read().values().cloned().collect();
+        jobs.sort_by_key(|job| job.created_at);
+        Ok(jobs)
+    }
+
+    async fn remove_job(&self, id: Uuid) -> Result<bool> {
+        self.runs.write().remove(&id);
+        Ok(self.jobs.write().remove(&id).is_some())
+    }
+
+    async fn record_run(&self, run: JobRun) -> Result<()> {
+        let mut runs = self.runs.write();
+        let runs = runs.entry(run.job_id).or_default();
+        match runs.iter_mut().find(|existing| existing.id == run.id) {
+            Some(existing) => *existing = run,
+            None => {
+                runs.push(run);
+                runs.sort_by_key(|run| std::cmp::Reverse(run.scheduled_for));
+                runs.truncate(self.run_capacity);
+            }
+        }
+        Ok(())
+    }
+
+    async fn list_runs(&self, job_id: Uuid, limit: Option<usize>) -> Result<Vec<JobRun>> {
+        let runs = self.runs.read();
+        let runs = runs.get(&job_id).map(Vec::as_slice).unwrap_or_default();
+        Ok(runs.iter().take(limit.unwrap_or(usize::MAX)).cloned().collect())
+    }
+}
+
+/// The run a job is currently executing.
+struct ActiveRun {
+    run_id: Uuid,
+    cancel: Arc<Notify>,
+    /// Firing held back by `OverlapPolicy::Queue` until this run finishes.
+    queued: Option<JobRun>,
+}
+
+enum Admission {
+    Start,
+    Queue,
+    Skip,
+}
+
+/// Fires scheduled jobs against an orchestrator. Call `start` to check for
+/// due jobs periodically, or drive it with `run_due`.
+#[derive(Clone)]
+pub struct Scheduler {
+    orchestrator: Arc<Orchestrator>,
+    store: Arc<dyn JobStore>,
+    active: Arc<Mutex<HashMap<Uuid, ActiveRun>>>,
+}
+
+impl Scheduler {
+    pub fn new(orchestrator: Arc<Orchestrator>, store: Arc<dyn JobStore>) -> Self {
+        Self {
+            orchestrator,
+            store,
+            active: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Validates and stores `job`, planning its first firing from now.
+    pub async fn add_job(&self, mut job: ScheduledJob) -> Result<ScheduledJob> {
+        ModuleRef::parse(&job.module)?;
+        job.schedule.validate()?;
+        Self::plan_next(&mut job, SystemTime::now())?;
+        self.store.save_job(job.clone()).await?;
+        Ok(job)
+    }
+
+    /// Removes the job, cancelling its current and queued runs.
+    pub async fn remove_job(&self, id: Uuid) -> Result<bool> {
+        let queued = match self.active.lock().get_mut(&id) {
+            Some(active) => {
+                active.cancel.notify_one();
+                active.queued.take()
+            }
+            None => None,
+        };
+        if let Some(mut run) = queued {
+            run.status = JobRunStatus::Cancelled;
+            self.record(run).await;
+        }
+        self.store.remove_job(id).await
+    }
+
+    /// Pauses or resumes a job. A paused job's current run is left to
+    /// finish; a resumed job fires next at its first slot after now.
+    pub async fn set_enabled(&self, id: Uuid, enabled: bool) -> Result<ScheduledJob> {
+        let mut job = self
+            .store
+            .get_job(id)
+            .await?
+            .ok_or_else(|| anyhow!("Scheduled job not found: {}", id))?;
+        job.enabled = enabled;
+        if enabled {
+            Self::plan_next(&mut job, SystemTime::now())?;
+        }
+        self.store.save_job(job.clone()).await?;
+        Ok(job)
+    }
+
+    pub async fn jobs(&self) -> Result<Vec<ScheduledJob>> {
+        self.store.list_jobs().await
+    }
+
+    pub async fn runs(&self, job_id: Uuid, limit: Option<usize>) -> Result<Vec<JobRun>> {
+        self.store.list_runs(job_id, limit).await
+    }
+
+    /// Executions the run started, from the orchestrator's history.
+    pub async fn run_executions(&self, run_id: Uuid) -> Result<Vec<ExecutionRecord>> {
+        let filter = ExecutionFilter {
+            tags: HashMap::from([(SCHEDULE_RUN_TAG.to_string(), run_id.to_string())]),
+            ..Default::default()
+        };
+        self.orchestrator.list_executions(&filter).await
+    }
+
+    /// Fires every enabled job due at `now`, returning the runs it created.
+    /// A job that missed several firings, such as while the node was down,
+    /// fires once and resumes from `now`.
+    pub async fn run_due(&self, now: SystemTime) -> Result<Vec<JobRun>> {
+        let mut fired = Vec::new();
+        for mut job in self.store.list_jobs().await? {
+            let (Some(fire_at), Some(run_at)) = (job.next_fire_at, job.next_run_at) else {
+                continue;
+            };
+            if !job.enabled || run_at > now {
+                continue;
+            }
+
+            Self::plan_next(&mut job, now)?;
+            self.store.save_job(job.clone()).await?;
+            fired.push(self.fire(job, fire_at).await);
+        }
+        Ok(fired)
+    }
+
+    /// Calls `run_due` every `tick` until the returned task is aborted.
+    pub fn start(&self, tick: Duration) -> JoinHandle<()> {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tick);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                if let Err(e) = scheduler.run_due(SystemTime::now()).await {
+                    warn!("Failed to run scheduled jobs: {}", e);
+                }
+            }
+        })
+    }
+
+    fn plan_next(job: &mut ScheduledJob, after: SystemTime) -> Result<()> {
+        job.next_fire_at = job.schedule.next_after(job.created_at, after)?;
+        job.next_run_at = job.next_fire_at.map(|fire_at| {
+            let jitter = fastrand::u64(0..=job.jitter.as_millis() as u64);
+            fire_at + Duration::from_millis(jitter)
+        });
+        Ok(())
+    }
+
+    async fn fire(&self, job: ScheduledJob, scheduled_for: SystemTime) -> JobRun {
+        let mut run = JobRun::new(job.id, scheduled_for, JobRunStatus::Running);
+        let admission = match self.active.lock().get_mut(&job.id) {
+            None => Admission::Start,
+            Some(active) => match job.overlap {
+                OverlapPolicy::Skip => Admission::Skip,
+                OverlapPolicy::Queue if active.queued.is_none() => {
+                    run.status = JobRunStatus::Queued;
+                    active.queued = Some(run.clone());
+                    Admission::Queue
+                }
+                OverlapPolicy::Queue => Admission::Skip,
+                OverlapPolicy::CancelPrevious => {
+                    active.cancel.notify_one();
+                    Admission::Start
+                }
+            },
+        };
+
+        match admission {
+            Admission::Start => {
+                run.started_at = Some(SystemTime::now());
+                let cancel = Arc::new(Notify::new());
+                self.active.lock().insert(
+                    job.id,
+                    ActiveRun {
+                        run_id: run.id,
+                        cancel: cancel.clone(),
+                        queued: None,
+                    },
+                );
+                self.record(run.clone()).await;
+                tokio::spawn(self.clone().drive(job, run.clone(), cancel));
+            }
+            Admission::Queue => self.record(run.clone()).await,
+            Admission::Skip => {
+                debug!("Skipping run of job {}: previous run still going", job.id);
+                run.status = JobRunStatus::Skipped;
+                run.finished_at = Some(SystemTime::now());
+                self.record(run.clone()).await;
+            }
+        }
+        run
+    }
+
+    /// Executes `run`, then any firing queued behind it, until none is left.
+    async fn drive(self, job: ScheduledJob, mut run: JobRun, mut cancel: Arc<Notify>) {
+        loop {
+            let outcome = self.execute(&job, run.id, &cancel).await;
+            run.finished_at = Some(SystemTime::now());
+            (run.status, run.error) = match outcome {
+                Ok(Some(result)) if result.success => (JobRunStatus::Succeeded, None),
+                Ok(Some(result)) => (JobRunStatus::Failed, result.error),
+                Ok(None) => (JobRunStatus::Cancelled, None),
+                Err(e) => (JobRunStatus::Failed, Some(e.to_string())),
+            };
+            self.record(run.clone()).await;
+
+            let next = {
+                let mut active = self.active.lock();
+                match active.get_mut(&job.id) {
+                    Some(current) if current.run_id == run.id => match current.queued.take() {
+                        Some(queued) => {
+                            cancel = Arc::new(Notify::new());
+                            current.run_id = queued.id;
+                            current.cancel = cancel.clone();
+                            Some(queued)
+                        }
+                        None => {
+                            active.remove(&job.id);
+                            None
+                        }
+                    },
+                    // Superseded by a newer run under `OverlapPolicy::CancelPrevious`
+                    _ => None,
+                }
+            };
+            let Some(queued) = next else {
+                return;
+            };
+
+            run = queued;
+            run.status = JobRunStatus::Running;
+            run.started_at = Some(SystemTime::now());
+            self.record(run.clone()).await;
+        }
+    }
+
+    /// Runs the job's module on a fresh instance. `None` means the run was
+    /// cancelled before it finished.
+    async fn execute(&self, job: &ScheduledJob, run_id: Uuid, cancel: &Notify) -> Result<Option<ExecutionResult>> {
+        let instance_id = self.orchestrator.instantiate_named(&job.module).await?;
+
+        let mut config = job.config.clone();
+        config.tags.insert(SCHEDULE_JOB_TAG.to_string(), job.id.to_string());
+        config.tags.insert(SCHEDULE_RUN_TAG.to_string(), run_id.to_string());
+
+        let result = tokio::select! {
+            result = self.orchestrator.execute(instance_id.clone(), config) => Some(result),
+            _ = cancel.notified() => None,
+        };
+        if let Err(e) = self.orchestrator.destroy(instance_id).await {
+            warn!("Failed to destroy instance of scheduled job {}: {}", job.id, e);
+        }
+        result.transpose()
+    }
+
+    async fn record(&self, run: JobRun) {
+        if let Err(e) = self.store.record_run(run).await {
+            warn!("Failed to record scheduled job run: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrchestratorConfig;
+    use next_rc_shared::{Language, RuntimeType, TrustLevel};
+    use std::time::UNIX_EPOCH;
+    use wasm_runtime::WasmRuntime;
+
+    fn at(minutes: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        // 2024-01-01 00:00 UTC, a Monday
+        let monday = 28_401_120;
+
+        let business = "*/15 9-17 * * 1-5".parse::<CronSchedule>().unwrap();
+        assert_eq!(business.next_after(at(monday)), Some(at(monday + 9 * 60)));
+        assert_eq!(business.next_after(at(monday + 9 * 60)), Some(at(monday + 9 * 60 + 15)));
+        // Friday 17:45 rolls over to Monday 09:00
+        let friday_close = monday + 4 * 1440 + 17 * 60 + 45;
+        assert_eq!(business.next_after(at(friday_close)), Some(at(monday + 7 * 1440 + 9 * 60)));
+
+        // 29 February 2024
+        let leap_day = "0 12 29 2 *".parse::<CronSchedule>().unwrap();
+        assert_eq!(leap_day.next_after(at(monday)), Some(at(monday + 59 * 1440 + 12 * 60)));
+
+        // Either day field matches when both are restricted: the 15th or any Sunday
+        let either = "0 0 15 * 7".parse::<CronSchedule>().unwrap();
+        assert_eq!(either.next_after(at(monday)), Some(at(monday + 6 * 1440)));
+
+        assert_eq!("@daily".parse::<CronSchedule>().unwrap().next_after(at(monday)), Some(at(monday + 1440)));
+        assert!("0 0 30 2 *".parse::<CronSchedule>().unwrap().next_after(at(monday)).is_none());
+
+        for invalid in ["* * * *", "60 * * * *", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+            assert!(invalid.parse::<CronSchedule>().is_err(), "{invalid}");
+        }
+    }
+
+    async fn scheduler_with_module(wat: &str) -> Scheduler {
+        let orchestrator = Orchestrator::new(OrchestratorConfig::with_in_memory_history());
+        orchestrator.register_runtime(RuntimeType::Wasm, Arc::new(WasmRuntime::with_config(4, 1024 * 1024).unwrap()));
+        let module_id = orchestrator.compile(RuntimeType::Wasm, wat.as_bytes(), Language::Wasm, TrustLevel::Low).await.unwrap();
+        orchestrator.register_module("jobs/report@1.0.0", module_id).await.unwrap();
+        Scheduler::new(Arc::new(orchestrator), Arc::new(InMemoryJobStore::default()))
+    }
+
+    async fn wait_for_finish(scheduler: &Scheduler, job_id: Uuid) -> Vec<JobRun> {
+        for _ in 0..200 {
+            let runs = scheduler.runs(job_id, None).await.unwrap();
+            if runs.iter().all(JobRun::is_finished) {
+                return runs;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("scheduled runs did not finish");
+    }
+
+    #[tokio::test]
+    async fn test_scheduled_runs_link_to_history() {
+        let scheduler = scheduler_with_module(r#"(module (func (export "_start") (result i32) i32.const 7))"#).await;
+        let job = ScheduledJob::new("report", "jobs/report", Schedule::Every(Duration::from_secs(60)), ExecutionConfig::default());
+        let job = scheduler.add_job(job).await.unwrap();
+        let first = job.next_fire_at.unwrap();
+        assert_eq!(first, job.created_at + Duration::from_secs(60));
+
+        assert!(scheduler.run_due(first - Duration::from_secs(1)).await.unwrap().is_empty());
+        let fired = scheduler.run_due(first).await.unwrap();
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].scheduled_for, first);
+
+        let runs = wait_for_finish(&scheduler, job.id).await;
+        assert_eq!(runs[0].status, JobRunStatus::Succeeded);
+        let executions = scheduler.run_executions(runs[0].id).await.unwrap();
+        assert_eq!(executions.len(), 1);
+        assert_eq!(executions[0].tags[SCHEDULE_JOB_TAG], job.id.to_string());
+
+        let job = scheduler.set_enabled(job.id, false).await.unwrap();
+        assert!(scheduler.run_due(job.next_run_at.unwrap()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_overlap_policies() {
+        let scheduler = scheduler_with_module(
+            r#"(module (func (export "_start") (result i32) (loop $spin (br $spin)) i32.const 0))"#,
+        )
+        .await;
+        let config = ExecutionConfig {
+            timeout: Duration::from_millis(200),
+            ..Default::default()
+        };
+
+        for (overlap, expected) in [
+            (OverlapPolicy::Skip, [JobRunStatus::Running, JobRunStatus::Skipped, JobRunStatus::Skipped]),
+            (OverlapPolicy::Queue, [JobRunStatus::Running, JobRunStatus::Queued, JobRunStatus::Skipped]),
+            (OverlapPolicy::CancelPrevious, [JobRunStatus::Running, JobRunStatus::Running, JobRunStatus::Running]),
+        ] {
+            let job = ScheduledJob::new("spin", "jobs/report", Schedule::Every(Duration::from_secs(1)), config.clone())
+                .with_overlap(overlap);
+            let job = scheduler.add_job(job).await.unwrap();
+
+            let mut now = job.next_run_at.unwrap();
+            let mut statuses = Vec::new();
+            for _ in 0..3 {
+                statuses.extend(scheduler.run_due(now).await.unwrap().into_iter().map(|run| run.status));
+                now += Duration::from_secs(1);
+            }
+            assert_eq!(statuses, expected, "{overlap:?}");
+
+            let runs = wait_for_finish(&scheduler, job.id).await;
+            let count = |status| runs.iter().filter(|run| run.status == status).count();
+            match overlap {
+                OverlapPolicy::Skip => assert_eq!(count(JobRunStatus::Failed), 1),
+                OverlapPolicy::Queue => assert_eq!(count(JobRunStatus::Failed), 2),
+                OverlapPolicy::CancelPrevious => {
+                    assert_eq!(count(JobRunStatus::Cancelled), 2);
+                    assert_eq!(count(JobRunStatus::Failed), 1);
+                }
+            }
+            scheduler.remove_job(job.id).await.unwrap();
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::{JobRun, JobStore, ScheduledJob};
+use crate::history::to_unix_millis;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS scheduled_jobs (
+        id TEXT PRIMARY KEY,
+        created_at_ms INTEGER NOT NULL,
+        job TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS job_runs (
+        id TEXT PRIMARY KEY,
+        job_id TEXT NOT NULL,
+        scheduled_for_ms INTEGER NOT NULL,
+        run TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS job_runs_job_id ON job_runs (job_id, scheduled_for_ms);
+";
+
+/// SQLite-backed job store, so schedules and their run history survive
+/// restarts of a single node. Jobs and runs are stored as JSON.
+pub struct SqliteJobStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteJobStore {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    async fn with_conn<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || f(&mut conn.lock()))
+            .await
+            .map_err(|e| anyhow!("Job store query task failed: {}", e))?
+    }
+}
+
+#[async_trait]
+impl JobStore for SqliteJobStore {
+    async fn save_job(&self, job: ScheduledJob) -> Result<()> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO scheduled_jobs (id, created_at_ms, job) VALUES (?1, ?2, ?3) \
+                 ON CONFLICT (id) DO UPDATE SET job = excluded.job",
+                params![job.id.to_string(), to_unix_millis(job.created_at), serde_json::to_string(&job)?],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_job(&self, id: Uuid) -> Result<Option<ScheduledJob>> {
+        self.with_conn(move |conn| {
+            let job: Option<String> = conn
+                .query_row("SELECT job FROM scheduled_jobs WHERE id = ?1", params![id.to_string()], |row| {
+                    row.get(0)
+                })
+                .optional()?;
+            Ok(job.map(|job| serde_json::from_str(&job)).transpose()?)
+        })
+        .await
+    }
+
+    async fn list_jobs(&self) -> Result<Vec<ScheduledJob>> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare("SELECT job FROM scheduled_jobs ORDER BY created_at_ms")?;
+            let jobs = stmt
+                .query_map([], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            jobs.iter().map(|job| Ok(serde_json::from_str(job)?)).collect()
+        })
+        .await
+    }
+
+    async fn remove_job(&self, id: Uuid) -> Result<bool> {
+        self.with_conn(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM job_runs WHERE job_id = ?1", params![id.to_string()])?;
+            let removed = tx.execute("DELETE FROM scheduled_jobs WHERE id = ?1", params![id.to_string()])?;
+            tx.commit()?;
+            Ok(removed > 0)
+        })
+        .await
+    }
+
+    async fn record_run(&self, run: JobRun) -> Result<()> {
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT INTO job_runs (id, job_id, scheduled_for_ms, run) VALUES (?1, ?2, ?3, ?4) \
+                 ON CONFLICT (id) DO UPDATE SET run = excluded.run",
+                params![
+                    run.id.to_string(),
+                    run.job_id.to_string(),
+                    to_unix_millis(run.scheduled_for),
+                    serde_json::to_string(&run)?,
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list_runs(&self, job_id: Uuid, limit: Option<usize>) -> Result<Vec<JobRun>> {
+        self.with_conn(move |conn| {
+            let mut stmt = conn.prepare(
+                "SELECT run FROM job_runs WHERE job_id = ?1 ORDER BY scheduled_for_ms DESC LIMIT ?2",
+            )?;
+            let limit = limit.map(|limit| limit as i64).unwrap_or(-1);
+            let runs = stmt
+                .query_map(params![job_id.to_string(), limit], |row| row.get::<_, String>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            runs.iter().map(|run| Ok(serde_json::from_str(run)?)).collect()
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::schedule::{JobRunStatus, Schedule};
+    use next_rc_shared::ExecutionConfig;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_sqlite_round_trip() {
+        let store = SqliteJobStore::open_in_memory().unwrap();
+        let job = ScheduledJob::new("report", "jobs/report@stable", Schedule::Cron("0 6 * * *".into()), ExecutionConfig::default())
+            .with_jitter(Duration::from_secs(30));
+        store.save_job(job.clone()).await.unwrap();
+
+        let mut first = JobRun::new(job.id, job.created_at, JobRunStatus::Running);
+        let second = JobRun::new(job.id, job.created_at + Duration::from_secs(60), JobRunStatus::Skipped);
+        store.record_run(first.clone()).await.unwrap();
+        store.record_run(second.clone()).await.unwrap();
+        first.status = JobRunStatus::Succeeded;
+        store.record_run(first.clone()).await.unwrap();
+
+        let stored = store.get_job(job.id).await.unwrap().unwrap();
+        assert_eq!((stored.schedule, stored.jitter), (job.schedule, job.jitter));
+        assert_eq!(store.list_runs(job.id, None).await.unwrap(), vec![second, first.clone()]);
+        assert_eq!(store.list_runs(job.id, Some(1)).await.unwrap().len(), 1);
+
+        assert!(store.remove_job(job.id).await.unwrap());
+        assert!(store.list_jobs().await.unwrap().is_empty());
+        assert!(store.list_runs(job.id, None).await.unwrap().is_empty());
+    }
+}
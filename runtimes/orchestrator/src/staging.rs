@@ -0,0 +1,324 @@
+use anyhow::{anyhow, bail, Result};
+use next_rc_shared::{sha256_hex, DigestReader};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+/// Largest artifact `StagingArea` accepts unless configured otherwise.
+pub const DEFAULT_MAX_ARTIFACT_SIZE: u64 = 1 << 30;
+
+const REFERENCE_PREFIX: &str = "sha256:";
+
+/// What the client declared when it began an upload.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadManifest {
+    pub size: u64,
+    /// Hex SHA-256 of the whole artifact.
+    pub sha256: String,
+    pub created_at: SystemTime,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UploadStatus {
+    pub upload_id: Uuid,
+    pub size: u64,
+    /// Bytes received so far, which is where the next chunk must start.
+    pub received: u64,
+}
+
+impl UploadStatus {
+    pub fn is_complete(&self) -> bool {
+        self.received == self.size
+    }
+}
+
+/// A verified artifact, compiled by passing `reference` to
+/// `Orchestrator::compile_staged`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StagedArtifact {
+    /// `sha256:<hex>` of the artifact's content.
+    pub reference: String,
+    pub size: u64,
+}
+
+/// Disk-backed area where large artifacts are uploaded in chunks and kept,
+/// addressed by content, until they are compiled.
+///
+/// An upload survives restarts: its progress is the length of its partial
+/// file, so a client that lost track asks for `status` and resumes from
+/// `received`.
+pub struct StagingArea {
+    root: PathBuf,
+    max_artifact_size: u64,
+    /// Serializes writes to each upload.
+    locks: Mutex<HashMap<Uuid, Arc<tokio::sync::Mutex<()>>>>,
+}
+
+impl StagingArea {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        std::fs::create_dir_all(root.join("uploads"))?;
+        std::fs::create_dir_all(root.join("artifacts"))?;
+        Ok(Self {
+            root,
+            max_artifact_size: DEFAULT_MAX_ARTIFACT_SIZE,
+            locks: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn with_max_artifact_size(mut self, max_artifact_size: u64) -> Self {
+        self.max_artifact_size = max_artifact_size;
+        self
+    }
+
+    /// Starts an upload of `size` bytes whose content must hash to `sha256`.
+    pub async fn begin(&self, size: u64, sha256: &str) -> Result<UploadStatus> {
+        if size == 0 || size > self.max_artifact_size {
+            bail!("Artifact of {} bytes is outside the limit of {} bytes", size, self.max_artifact_size);
+        }
+        let manifest = UploadManifest {
+            size,
+            sha256: parse_digest(sha256)?,
+            created_at: SystemTime::now(),
+        };
+
+        let upload_id = Uuid::new_v4();
+        File::create(self.part_path(upload_id)).await?;
+        fs::write(self.manifest_path(upload_id), serde_json::to_vec(&manifest)?).await?;
+        Ok(UploadStatus {
+            upload_id,
+            size,
+            received: 0,
+        })
+    }
+
+    /// Appends `data`, which starts at `offset` in the artifact. Chunks must
+    /// arrive in order; bytes already received are skipped, so a chunk whose
+    /// acknowledgement was lost can simply be sent again. When given,
+    /// `chunk_sha256` is checked before anything is written.
+    pub async fn put_chunk(
+        &self,
+        upload_id: Uuid,
+        offset: u64,
+        data: &[u8],
+        chunk_sha256: Option<&str>,
+    ) -> Result<UploadStatus> {
+        if let Some(expected) = chunk_sha256 {
+            if parse_digest(expected)? != sha256_hex(data) {
+                bail!("Chunk at offset {} of upload {} does not match its checksum", offset, upload_id);
+            }
+        }
+
+        let lock = self.lock(upload_id);
+        let _guard = lock.lock().await;
+        let mut status = self.status(upload_id).await?;
+        if offset > status.received {
+            bail!(
+                "Chunk at offset {} of upload {} leaves a gap; resume from offset {}",
+                offset,
+                upload_id,
+                status.received
+            );
+        }
+
+        let fresh = &data[(status.received - offset).min(data.len() as u64) as usize..];
+        if status.received + fresh.len() as u64 > status.size {
+            bail!("Upload {} exceeds its declared size of {} bytes", upload_id, status.size);
+        }
+        if !fresh.is_empty() {
+            let mut part = OpenOptions::new().append(true).open(self.part_path(upload_id)).await?;
+            part.write_all(fresh).await?;
+            part.flush().await?;
+            status.received += fresh.len() as u64;
+        }
+        Ok(status)
+    }
+
+    pub async fn status(&self, upload_id: Uuid) -> Result<UploadStatus> {
+        let manifest = self.manifest(upload_id).await?;
+        let received = fs::metadata(self.part_path(upload_id)).await?.len();
+        Ok(UploadStatus {
+            upload_id,
+            size: manifest.size,
+            received,
+        })
+    }
+
+    /// Verifies a fully received upload against its declared checksum and
+    /// moves it into the artifact store. Corrupt uploads are discarded.
+    pub async fn complete(&self, upload_id: Uuid) -> Result<StagedArtifact> {
+        let lock = self.lock(upload_id);
+        let _guard = lock.lock().await;
+        let manifest = self.manifest(upload_id).await?;
+        let part_path = self.part_path(upload_id);
+        let received = fs::metadata(&part_path).await?.len();
+        if received != manifest.size {
+            bail!("Upload {} is incomplete: {} of {} bytes received", upload_id, received, manifest.size);
+        }
+
+        let (mut reader, digest) = DigestReader::new(File::open(&part_path).await?);
+        tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+        if digest.hex() != manifest.sha256 {
+            self.remove_upload(upload_id).await?;
+            bail!("Upload {} does not match its declared checksum and was discarded", upload_id);
+        }
+
+        fs::rename(&part_path, self.root.join("artifacts").join(&manifest.sha256)).await?;
+        self.remove_upload(upload_id).await?;
+        Ok(StagedArtifact {
+            reference: format!("{}{}", REFERENCE_PREFIX, manifest.sha256),
+            size: manifest.size,
+        })
+    }
+
+    pub async fn abort(&self, upload_id: Uuid) -> Result<()> {
+        self.manifest(upload_id).await?;
+        self.remove_upload(upload_id).await
+    }
+
+    /// Aborts uploads begun more than `max_age` ago, returning their ids.
+    pub async fn expire_uploads(&self, max_age: Duration) -> Result<Vec<Uuid>> {
+        let mut expired = Vec::new();
+        let mut entries = fs::read_dir(self.root.join("uploads")).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            let Some(upload_id) = path
+                .extension()
+                .filter(|extension| *extension == "json")
+                .and_then(|_| path.file_stem()?.to_str()?.parse::<Uuid>().ok())
+            else {
+                continue;
+            };
+            let manifest = self.manifest(upload_id).await?;
+            if manifest.created_at.elapsed().unwrap_or_default() > max_age {
+                self.remove_upload(upload_id).await?;
+                expired.push(upload_id);
+            }
+        }
+        Ok(expired)
+    }
+
+    /// Opens the artifact that `reference` names.
+    pub async fn open(&self, reference: &str) -> Result<File> {
+        File::open(self.artifact_path(reference)?)
+            .await
+            .map_err(|_| anyhow!("No staged artifact `{}`", reference))
+    }
+
+    pub async fn remove(&self, reference: &str) -> Result<()> {
+        fs::remove_file(self.artifact_path(reference)?)
+            .await
+            .map_err(|_| anyhow!("No staged artifact `{}`", reference))
+    }
+
+    fn artifact_path(&self, reference: &str) -> Result<PathBuf> {
+        let digest = reference
+            .strip_prefix(REFERENCE_PREFIX)
+            .ok_or_else(|| anyhow!("Invalid artifact reference `{}`", reference))?;
+        Ok(self.root.join("artifacts").join(parse_digest(digest)?))
+    }
+
+    fn part_path(&self, upload_id: Uuid) -> PathBuf {
+        self.root.join("uploads").join(format!("{}.part", upload_id))
+    }
+
+    fn manifest_path(&self, upload_id: Uuid) -> PathBuf {
+        self.root.join("uploads").join(format!("{}.json", upload_id))
+    }
+
+    async fn manifest(&self, upload_id: Uuid) -> Result<UploadManifest> {
+        let bytes = fs::read(self.manifest_path(upload_id))
+            .await
+            .map_err(|_| anyhow!("Unknown upload {}", upload_id))?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    async fn remove_upload(&self, upload_id: Uuid) -> Result<()> {
+        for path in [self.part_path(upload_id), self.manifest_path(upload_id)] {
+            remove_if_exists(&path).await?;
+        }
+        self.locks.lock().remove(&upload_id);
+        Ok(())
+    }
+
+    fn lock(&self, upload_id: Uuid) -> Arc<tokio::sync::Mutex<()>> {
+        self.locks.lock().entry(upload_id).or_default().clone()
+    }
+}
+
+/// Lowercases a hex SHA-256, which also keeps it safe to use as a file name.
+fn parse_digest(digest: &str) -> Result<String> {
+    if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("Invalid SHA-256 digest `{}`", digest);
+    }
+    Ok(digest.to_ascii_lowercase())
+}
+
+async fn remove_if_exists(path: &Path) -> Result<()> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    #[tokio::test]
+    async fn test_chunked_upload_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = StagingArea::new(dir.path()).unwrap();
+        let artifact: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+
+        let upload = staging.begin(artifact.len() as u64, &sha256_hex(&artifact)).await.unwrap();
+        let id = upload.upload_id;
+        staging.put_chunk(id, 0, &artifact[..4096], Some(&sha256_hex(&artifact[..4096]))).await.unwrap();
+        assert!(staging.put_chunk(id, 8192, &artifact[8192..], None).await.is_err());
+        assert!(staging.put_chunk(id, 4096, &artifact[4096..8192], Some(&sha256_hex(b"x"))).await.is_err());
+        assert!(staging.complete(id).await.is_err());
+
+        // A restarted node picks the upload up from disk
+        let staging = StagingArea::new(dir.path()).unwrap();
+        let status = staging.status(id).await.unwrap();
+        assert_eq!(status.received, 4096);
+        // Retried chunks overlap what was already received
+        staging.put_chunk(id, 2048, &artifact[2048..8192], None).await.unwrap();
+        let status = staging.put_chunk(id, 8192, &artifact[8192..], None).await.unwrap();
+        assert!(status.is_complete());
+
+        let staged = staging.complete(id).await.unwrap();
+        assert_eq!(staged.reference, format!("sha256:{}", sha256_hex(&artifact)));
+        let mut stored = Vec::new();
+        staging.open(&staged.reference).await.unwrap().read_to_end(&mut stored).await.unwrap();
+        assert_eq!(stored, artifact);
+        assert!(staging.status(id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_uploads_are_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let staging = StagingArea::new(dir.path()).unwrap().with_max_artifact_size(16);
+        assert!(staging.begin(17, &sha256_hex(b"")).await.is_err());
+        assert!(staging.begin(4, "../../etc/passwd").await.is_err());
+
+        let upload = staging.begin(4, &sha256_hex(b"wasm")).await.unwrap();
+        staging.put_chunk(upload.upload_id, 0, b"wasn", None).await.unwrap();
+        assert!(staging.put_chunk(upload.upload_id, 4, b"!", None).await.is_err());
+        assert!(staging.complete(upload.upload_id).await.is_err());
+        assert!(staging.status(upload.upload_id).await.is_err());
+        assert!(staging.open(&format!("sha256:{}", sha256_hex(b"wasn"))).await.is_err());
+        assert!(staging.open("sha256:../uploads").await.is_err());
+
+        let stale = staging.begin(4, &sha256_hex(b"wasm")).await.unwrap();
+        assert!(staging.expire_uploads(Duration::from_secs(60)).await.unwrap().is_empty());
+        assert_eq!(staging.expire_uploads(Duration::ZERO).await.unwrap(), vec![stale.upload_id]);
+    }
+}
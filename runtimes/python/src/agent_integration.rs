@@ -1,17 +1,22 @@
 use crate::{
     AgentWorkflowRequest, AgentWorkflowResult, AgentStep, ModelConfig,
-    PythonExecutionRequest, PythonRuntimeController, TrustLevel, Result
+    PythonExecutionRequest, PythonRuntimeController, ToolLimits, TrustLevel, Result
 };
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 use serde_json::{json, Value};
 use tokio::time::timeout;
 use metrics::{Counter, Histogram, Gauge};
+use next_rc_shared::webhooks::{WebhookDispatcher, WebhookEvent, WebhookEventKind};
+use next_rc_shared::{sha256_hex, NodeKey, RuntimeType};
 
 pub struct SmolAgentsRunner {
     python_runtime: Arc<PythonRuntimeController>,
     metrics: Arc<AgentMetrics>,
+    webhooks: Option<Arc<WebhookDispatcher>>,
+    signing_key: Option<Arc<NodeKey>>,
 }
 
 struct AgentMetrics {
@@ -39,15 +44,30 @@ impl SmolAgentsRunner {
         Self {
             python_runtime,
             metrics,
+            webhooks: None,
+            signing_key: None,
         }
     }
 
+    /// Emits an approval-needed webhook whenever a workflow sets `approval_request`.
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookDispatcher>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// Signs every workflow result with this node's key.
+    pub fn with_signing_key(mut self, signing_key: Arc<NodeKey>) -> Self {
+        self.signing_key = Some(signing_key);
+        self
+    }
+
     pub async fn run_workflow(&self, request: AgentWorkflowRequest) -> Result<AgentWorkflowResult> {
         let start_time = Instant::now();
         self.metrics.workflow_executions.increment(1);
 
         // Generate Python code for the smolagents workflow
         let python_code = self.generate_agent_code(&request)?;
+        let code_sha256 = sha256_hex(python_code.as_bytes());
         
         // Create execution request
         let execution_request = PythonExecutionRequest {
@@ -65,6 +85,10 @@ impl SmolAgentsRunner {
                 "requests".to_string(),
                 "numpy".to_string(),
             ],
+            stdin: None,
+            virtual_time: None,
+            log_limits: Default::default(),
+            process_limits: Default::default(),
         };
 
         // Execute the workflow
@@ -76,7 +100,7 @@ impl SmolAgentsRunner {
         let execution_time = start_time.elapsed().as_millis() as u64;
         self.metrics.workflow_duration.record(execution_time as f64);
 
-        if execution_result.success {
+        let mut result = if execution_result.success {
             self.metrics.successful_workflows.increment(1);
             
             // Parse the result
@@ -85,8 +109,19 @@ impl SmolAgentsRunner {
             // Update metrics
             self.metrics.total_steps.increment(workflow_result.intermediate_steps.len() as u64);
             self.metrics.tokens_used.increment(workflow_result.tokens_used as u64);
+
+            if let (Some(webhooks), Some(approval_request)) = (&self.webhooks, &workflow_result.approval_request) {
+                webhooks.dispatch(WebhookEvent::new(
+                    WebhookEventKind::ApprovalNeeded,
+                    None,
+                    json!({
+                        "workflow_id": request.id,
+                        "approval_request": approval_request,
+                    }),
+                ));
+            }
             
-            Ok(AgentWorkflowResult {
+            AgentWorkflowResult {
                 id: request.id,
                 success: true,
                 final_output: workflow_result.final_output,
@@ -94,11 +129,13 @@ impl SmolAgentsRunner {
                 execution_time_ms: execution_time,
                 tokens_used: workflow_result.tokens_used,
                 error: None,
-            })
+                approval_request: workflow_result.approval_request,
+                attestation: None,
+            }
         } else {
             self.metrics.failed_workflows.increment(1);
             
-            Ok(AgentWorkflowResult {
+            AgentWorkflowResult {
                 id: request.id,
                 success: false,
                 final_output: Value::Null,
@@ -106,17 +143,52 @@ impl SmolAgentsRunner {
                 execution_time_ms: execution_time,
                 tokens_used: 0,
                 error: execution_result.error,
-            })
+                approval_request: None,
+                attestation: None,
+            }
+        };
+
+        if let Some(signing_key) = &self.signing_key {
+            result.attestation = Self::workflow_inputs(&request)
+                .and_then(|inputs| {
+                    signing_key
+                        .attest(code_sha256, sha256_hex(&inputs), result.digest(), RuntimeType::Python)
+                        .map_err(Into::into)
+                })
+                .map_err(|e| tracing::warn!("Failed to sign workflow result: {}", e))
+                .ok();
         }
+
+        Ok(result)
+    }
+
+    /// What a workflow ran with, as signed. API keys are left out so
+    /// consumers can recompute the hash without them.
+    fn workflow_inputs(request: &AgentWorkflowRequest) -> Result<Vec<u8>> {
+        Ok(serde_json::to_vec(&json!({
+            "input_data": request.input_data,
+            "model_name": request.model_config.model_name,
+            "base_url": request.model_config.base_url,
+            "max_tokens": request.model_config.max_tokens,
+            "temperature": request.model_config.temperature,
+            "tools": request.tools,
+            "max_iterations": request.max_iterations,
+            "timeout_ms": request.timeout_ms,
+            "tool_limits": request.tool_limits,
+        }))?)
     }
 
     fn generate_agent_code(&self, request: &AgentWorkflowRequest) -> Result<String> {
         let input_data_json = serde_json::to_string(&request.input_data)?;
         let tools_json = serde_json::to_string(&request.tools)?;
+        // Encoded twice: a JSON string literal is also a valid Python one.
+        let tool_limits_json = serde_json::to_string(&serde_json::to_string(&request.tool_limits)?)?;
         
         let code = format!(r#"
 import json
 import sys
+import threading
+import time
 import traceback
 from typing import Dict, Any, List
 from smolagents import CodeAgent, HfApiModel, DuckDuckGoSearchTool, PythonInterpreterTool
@@ -144,15 +216,94 @@ else:
     # Use a default model if no API key provided
     model = HfApiModel(model_id="microsoft/DialoGPT-medium")
 
+# Per-tool limits, enforced on every call whatever the agent decides
+tool_limits = json.loads({})
+tool_calls = {{}}
+tool_steps = []
+
+class ToolLimitExceeded(Exception):
+    pass
+
+def _jsonable(value):
+    try:
+        json.dumps(value)
+        return value
+    except (TypeError, ValueError):
+        return repr(value)
+
+def _limit_tool(tool, name):
+    limits = tool_limits.get(name) or {{}}
+    forward = tool.forward
+
+    def limited_forward(*args, **kwargs):
+        step = {{
+            "tool_used": name,
+            "input": _jsonable({{"args": list(args), "kwargs": kwargs}}),
+            "output": None,
+            "timestamp": int(time.time()),
+            "limit_hit": None,
+        }}
+        tool_steps.append(step)
+        tool_calls[name] = tool_calls.get(name, 0) + 1
+
+        max_calls = limits.get("max_calls")
+        if max_calls is not None and tool_calls[name] > max_calls:
+            step["limit_hit"] = {{"kind": "max_calls", "limit": max_calls}}
+            raise ToolLimitExceeded(f"{{name}} may be called at most {{max_calls}} times")
+
+        timeout_ms = limits.get("timeout_ms")
+        if timeout_ms is None:
+            output = forward(*args, **kwargs)
+        else:
+            # The call can't be killed, so it is abandoned on a daemon thread.
+            outcome = {{}}
+            def call():
+                try:
+                    outcome["value"] = forward(*args, **kwargs)
+                except BaseException as error:
+                    outcome["error"] = error
+            worker = threading.Thread(target=call, daemon=True)
+            worker.start()
+            worker.join(timeout_ms / 1000)
+            if worker.is_alive():
+                step["limit_hit"] = {{"kind": "timeout", "limit": timeout_ms}}
+                raise ToolLimitExceeded(f"{{name}} did not finish within {{timeout_ms}} ms")
+            if "error" in outcome:
+                raise outcome["error"]
+            output = outcome["value"]
+
+        step["output"] = _jsonable(output)
+        return output
+
+    tool.forward = limited_forward
+    return tool
+
+class VectorSearchTool(Tool):
+    name = "vector_search"
+    description = "Finds the stored documents whose embeddings are closest to a query embedding."
+    inputs = {{
+        "collection": {{"type": "string", "description": "Collection to search"}},
+        "vector": {{"type": "array", "description": "Query embedding"}},
+        "limit": {{"type": "integer", "description": "Most matches to return", "nullable": True}},
+    }}
+    output_type = "array"
+
+    def forward(self, collection, vector, limit=5):
+        # Served by the host's vector store, so no network access is needed
+        import next_rc_vectors
+        return next_rc_vectors.search(collection, vector, limit or 5)
+
 # Initialize tools
 available_tools = []
 requested_tools = {}
 
 for tool_name in requested_tools:
     if tool_name == "search":
-        available_tools.append(DuckDuckGoSearchTool())
+        available_tools.append(_limit_tool(DuckDuckGoSearchTool(), tool_name))
     elif tool_name == "python":
-        available_tools.append(PythonInterpreterTool())
+        available_tools.append(_limit_tool(PythonInterpreterTool(), tool_name))
+    elif tool_name == "vector_search":
+        available_tools.append(_limit_tool(VectorSearchTool(), tool_name))
     elif tool_name == "calculator":
         # Add calculator tool if available
         pass
@@ -180,9 +331,10 @@ try:
     workflow_result = {{
         "success": True,
         "final_output": result,
-        "intermediate_steps": [],
+        "intermediate_steps": tool_steps,
         "tokens_used": 0,
-        "error": None
+        "error": None,
+        "approval_request": locals().get('approval_request')
     }}
     
     print("WORKFLOW_RESULT_START")
@@ -193,7 +345,7 @@ except Exception as e:
     error_result = {{
         "success": False,
         "final_output": None,
-        "intermediate_steps": [],
+        "intermediate_steps": tool_steps,
         "tokens_used": 0,
         "error": str(e)
     }}
@@ -209,6 +361,7 @@ except Exception as e:
             request.model_config.base_url.as_deref().unwrap_or(""),
             request.model_config.max_tokens.unwrap_or(1024),
             request.model_config.temperature.unwrap_or(0.7),
+            tool_limits_json,
             tools_json,
             request.max_iterations,
             input_data_json,
@@ -255,6 +408,7 @@ except Exception as e:
                     final_output: parsed["final_output"].clone(),
                     intermediate_steps: self.parse_intermediate_steps(&parsed["intermediate_steps"])?,
                     tokens_used: parsed["tokens_used"].as_u64().unwrap_or(0) as u32,
+                    approval_request: parsed.get("approval_request").filter(|v| !v.is_null()).cloned(),
                 });
             }
         }
@@ -264,6 +418,7 @@ except Exception as e:
             final_output: Value::String(output.to_string()),
             intermediate_steps: vec![],
             tokens_used: 0,
+            approval_request: None,
         })
     }
 
@@ -281,10 +436,15 @@ except Exception as e:
                             .to_string(),
                         input: step_obj.get("input").cloned().unwrap_or(Value::Null),
                         output: step_obj.get("output").cloned().unwrap_or(Value::Null),
-                        timestamp: SystemTime::now()
-                            .duration_since(UNIX_EPOCH)
-                            .unwrap()
-                            .as_secs(),
+                        timestamp: step_obj.get("timestamp")
+                            .and_then(|v| v.as_u64())
+                            .unwrap_or_else(|| SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .unwrap()
+                                .as_secs()),
+                        limit_hit: step_obj.get("limit_hit")
+                            .filter(|v| !v.is_null())
+                            .and_then(|v| serde_json::from_value(v.clone()).ok()),
                     };
                     steps.push(step);
                 }
@@ -321,6 +481,7 @@ struct WorkflowResult {
     final_output: Value,
     intermediate_steps: Vec<AgentStep>,
     tokens_used: u32,
+    approval_request: Option<Value>,
 }
 
 // Example usage and test functions
@@ -354,6 +515,7 @@ result = {
             tools: vec!["python".to_string()],
             max_iterations: 5,
             timeout_ms: 30000,
+            tool_limits: HashMap::new(),
         };
 
         self.run_workflow(request).await
@@ -380,6 +542,10 @@ result = agent.run(f"Search for information about: {query}")
             tools: vec!["search".to_string(), "python".to_string()],
             max_iterations: 10,
             timeout_ms: 60000,
+            tool_limits: HashMap::from([
+                ("search".to_string(), ToolLimits { max_calls: Some(5), timeout_ms: Some(30_000) }),
+                ("python".to_string(), ToolLimits { max_calls: None, timeout_ms: Some(30_000) }),
+            ]),
         };
 
         self.run_workflow(request).await
@@ -0,0 +1,203 @@
+//! `next-rc repl --lang python --trust <low|medium|high>`
+//!
+//! Interactive Python session on the runtime controller. Output streams as
+//! it is written; `:complete <text>` lists completions and `:quit` (or EOF)
+//! ends the session.
+
+use python_runtime::{PythonRuntimeController, ReplStream, TrustLevel};
+use std::io::{BufRead, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: next-rc repl --lang python [--trust low|medium|high]";
+
+struct ReplArgs {
+    trust_level: TrustLevel,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<ReplArgs, String> {
+    match args.next().as_deref() {
+        Some("repl") => {}
+        Some(command) => return Err(format!("unknown command {:?}", command)),
+        None => return Err("missing command".to_string()),
+    }
+
+    let mut language = None;
+    let mut trust_level = TrustLevel::Low;
+    while let Some(flag) = args.next() {
+        let value = args.next().ok_or_else(|| format!("{} needs a value", flag))?;
+        match flag.as_str() {
+            "--lang" => language = Some(value),
+            "--trust" => {
+                trust_level = match value.as_str() {
+                    "low" => TrustLevel::Low,
+                    "medium" => TrustLevel::Medium,
+                    "high" => TrustLevel::High,
+                    _ => return Err(format!("unknown trust level {:?}", value)),
+                }
+            }
+            _ => return Err(format!("unknown flag {:?}", flag)),
+        }
+    }
+
+    match language.as_deref() {
+        Some("python") => Ok(ReplArgs { trust_level }),
+        Some(language) => Err(format!("REPLs are only available for python, not {:?}", language)),
+        None => Err("missing --lang".to_string()),
+    }
+}
+
+/// Reads one input, continuing past the first line while it opens a block
+/// until a blank line ends it. `None` at end of input.
+fn read_input(lines: &mut impl Iterator<Item = std::io::Result<String>>) -> Option<String> {
+    prompt(">>> ");
+    let mut input = lines.next()?.ok()?;
+    if input.trim_end().ends_with(':') {
+        loop {
+            prompt("... ");
+            match lines.next() {
+                Some(Ok(line)) if !line.trim().is_empty() => {
+                    input.push('\n');
+                    input.push_str(&line);
+                }
+                _ => break,
+            }
+        }
+    }
+    Some(input)
+}
+
+/// What the session does with one input.
+#[derive(Debug, PartialEq, Eq)]
+enum Command<'a> {
+    Quit,
+    Complete(&'a str),
+    Eval(&'a str),
+    Skip,
+}
+
+fn parse_command(input: &str) -> Command<'_> {
+    let input = input.trim_end();
+    if input == ":quit" {
+        Command::Quit
+    } else if let Some(text) = input.strip_prefix(":complete ") {
+        Command::Complete(text)
+    } else if input.trim().is_empty() {
+        Command::Skip
+    } else {
+        Command::Eval(input)
+    }
+}
+
+fn prompt(text: &str) {
+    print!("{}", text);
+    let _ = std::io::stdout().flush();
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("next-rc: {}\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let repl = match PythonRuntimeController::new(1).await.and_then(|controller| controller.create_repl(args.trust_level, None)) {
+        Ok(repl) => repl,
+        Err(e) => {
+            eprintln!("next-rc: failed to start REPL: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    while let Some(input) = read_input(&mut lines) {
+        let input = match parse_command(&input) {
+            Command::Quit => break,
+            Command::Complete(text) => {
+                match repl.complete(text).await {
+                    Ok(matches) => println!("{}", matches.join("  ")),
+                    Err(e) => eprintln!("{}", e),
+                }
+                continue;
+            }
+            Command::Skip => continue,
+            Command::Eval(input) => input,
+        };
+
+        let result = repl
+            .eval_streaming(input, |stream, text| match stream {
+                ReplStream::Stdout => {
+                    print!("{}", text);
+                    let _ = std::io::stdout().flush();
+                }
+                ReplStream::Stderr => eprint!("{}", text),
+            })
+            .await;
+        match result {
+            Ok(output) => {
+                if let Some(error) = output.error {
+                    eprint!("{}", error);
+                }
+                if output.truncated {
+                    eprintln!("[output truncated at {} bytes]", repl.limits().max_output_bytes);
+                }
+            }
+            Err(e) => eprintln!("{}", e),
+        }
+        if repl.is_closed() {
+            eprintln!("next-rc: REPL closed");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Result<ReplArgs, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn lines(lines: &[&str]) -> impl Iterator<Item = std::io::Result<String>> {
+        lines.iter().map(|line| Ok(line.to_string())).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert_eq!(args(&["repl", "--lang", "python"]).unwrap().trust_level, TrustLevel::Low);
+        assert_eq!(args(&["repl", "--trust", "high", "--lang", "python"]).unwrap().trust_level, TrustLevel::High);
+
+        assert_eq!(args(&[]).err().unwrap(), "missing command");
+        assert_eq!(args(&["run"]).err().unwrap(), "unknown command \"run\"");
+        assert_eq!(args(&["repl"]).err().unwrap(), "missing --lang");
+        assert_eq!(args(&["repl", "--lang"]).err().unwrap(), "--lang needs a value");
+        assert_eq!(args(&["repl", "--lang", "wasm"]).err().unwrap(), "REPLs are only available for python, not \"wasm\"");
+        assert_eq!(args(&["repl", "--lang", "python", "--trust", "root"]).err().unwrap(), "unknown trust level \"root\"");
+        assert_eq!(args(&["repl", "--verbose", "yes"]).err().unwrap(), "unknown flag \"--verbose\"");
+    }
+
+    #[test]
+    fn test_read_input_continues_open_blocks() {
+        let mut input = lines(&["x = 1", "for i in range(2):", "    print(i)", "    print(x)", "", "if x:", "    x"]);
+        assert_eq!(read_input(&mut input).as_deref(), Some("x = 1"));
+        assert_eq!(read_input(&mut input).as_deref(), Some("for i in range(2):\n    print(i)\n    print(x)"));
+        // End of input also ends a block
+        assert_eq!(read_input(&mut input).as_deref(), Some("if x:\n    x"));
+        assert_eq!(read_input(&mut input), None);
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command(":quit\n"), Command::Quit);
+        assert_eq!(parse_command(":complete str.up"), Command::Complete("str.up"));
+        assert_eq!(parse_command("   "), Command::Skip);
+        assert_eq!(parse_command("print(1)  "), Command::Eval("print(1)"));
+        assert_eq!(parse_command(":completely"), Command::Eval(":completely"));
+    }
+}
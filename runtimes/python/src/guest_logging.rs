@@ -0,0 +1,52 @@
+use next_rc_shared::LogLevel;
+
+/// Python module routing the standard `logging` package into a host sink.
+/// `install(sink)` replaces the root logger's handlers with one that calls
+/// `sink(levelno, message)` and never touches files, sockets or stderr;
+/// `restore()` puts the previous handlers back.
+pub const LOG_CAPTURE_SHIM: &str = r#"
+import logging as _logging
+
+_previous = None
+
+
+class _HostHandler(_logging.Handler):
+    def __init__(self, sink):
+        super().__init__(_logging.NOTSET)
+        self._sink = sink
+
+    def emit(self, record):
+        try:
+            self._sink(record.levelno, self.format(record))
+        except Exception:
+            pass
+
+
+def install(sink):
+    global _previous
+    root = _logging.getLogger()
+    if _previous is None:
+        _previous = (root.handlers[:], root.level)
+    root.handlers = [_HostHandler(sink)]
+    root.setLevel(_logging.NOTSET)
+
+
+def restore():
+    global _previous
+    if _previous is not None:
+        root = _logging.getLogger()
+        root.handlers, level = _previous
+        root.setLevel(level)
+        _previous = None
+"#;
+
+/// Maps a `logging` level number onto the nearest `LogLevel` at or below it.
+pub fn python_log_level(levelno: i32) -> LogLevel {
+    match levelno {
+        n if n >= 40 => LogLevel::Error,
+        n if n >= 30 => LogLevel::Warn,
+        n if n >= 20 => LogLevel::Info,
+        n if n >= 10 => LogLevel::Debug,
+        _ => LogLevel::Trace,
+    }
+}
@@ -5,15 +5,32 @@ pub mod pyo3_runtime;
 pub mod wasm_runtime;
 pub mod scheduler;
 pub mod security;
+pub mod requirements;
 pub mod agent_integration;
+pub mod virtual_time;
+pub mod guest_logging;
+pub mod vector_search;
+#[cfg(feature = "wasm")]
+pub mod package_image;
+pub mod shadow;
+pub mod repl;
+pub mod module_runtime;
 
-pub use runtime::PythonRuntimeController;
+pub use runtime::{BackendLimits, PythonRuntimeController};
+pub use module_runtime::PythonModuleRuntime;
 #[cfg(feature = "pyo3")]
 pub use pyo3_runtime::PyO3Runtime;
 #[cfg(feature = "wasm")]
 pub use wasm_runtime::WasmPythonRuntime;
+#[cfg(feature = "wasm")]
+pub use package_image::PackageImage;
 pub use scheduler::PythonScheduler;
 pub use agent_integration::SmolAgentsRunner;
+pub use shadow::ShadowConfig;
+pub use requirements::RequirementsPolicyViolation;
+pub use repl::{ReplLimits, ReplOutput, ReplStream};
+#[cfg(feature = "pyo3")]
+pub use repl::PythonRepl;
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -29,9 +46,32 @@ pub struct PythonExecutionRequest {
     pub memory_limit_mb: u64,
     pub environment: HashMap<String, String>,
     pub requirements: Vec<String>,
+    /// Content served to the code on stdin, read by `input()`. Without it
+    /// stdin is empty and `input()` raises `EOFError`. Only PyO3 serves
+    /// stdin; the WASM backend rejects requests with it.
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Runs the code against a virtual clock instead of the host's.
+    #[serde(default)]
+    pub virtual_time: Option<next_rc_shared::VirtualTime>,
+    /// Bounds on the records captured from the `logging` package.
+    #[serde(default)]
+    pub log_limits: next_rc_shared::LogLimits,
+    /// Caps on open descriptors and processes, reported as
+    /// `ResourceLimitExceeded` when the code runs into them.
+    #[serde(default)]
+    pub process_limits: next_rc_shared::ProcessLimits,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl PythonExecutionRequest {
+    /// SHA-256 of the trust level and limits the code is confined by.
+    pub fn security_policy_sha256(&self) -> String {
+        let policy = (&self.trust_level, self.timeout_ms, self.memory_limit_mb, self.process_limits);
+        next_rc_shared::sha256_hex(&serde_json::to_vec(&policy).unwrap_or_default())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum PythonRuntimeType {
     PyO3,        // High-performance native execution
     Wasm,        // Sandboxed WASM execution
@@ -51,10 +91,35 @@ pub struct PythonExecutionResult {
     pub success: bool,
     pub output: String,
     pub error: Option<String>,
+    /// Type of the exception a failed execution raised, e.g.
+    /// `ModuleNotFoundError`, when the backend reports it.
+    #[serde(default)]
+    pub exception: Option<String>,
     pub runtime_used: PythonRuntimeType,
     pub execution_time_ms: u64,
     pub memory_used_mb: u64,
     pub exit_code: Option<i32>,
+    /// Records emitted through the `logging` package.
+    #[serde(default)]
+    pub logs: next_rc_shared::ExecutionLogs,
+    /// Set when the first backend failed for environmental reasons and the
+    /// execution was retried on `runtime_used`.
+    #[serde(default)]
+    pub fallback: Option<RuntimeFallback>,
+    /// Backend, interpreter, policy, packages and code the execution ran with.
+    #[serde(default)]
+    pub environment_fingerprint: Option<next_rc_shared::EnvironmentFingerprint>,
+    /// Time spent in each phase of the request.
+    #[serde(default)]
+    pub timing: Option<next_rc_shared::TimingBreakdown>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuntimeFallback {
+    /// Backend the first attempt ran on.
+    pub from: PythonRuntimeType,
+    /// Error that attempt failed with.
+    pub reason: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -66,6 +131,33 @@ pub struct AgentWorkflowRequest {
     pub tools: Vec<String>,
     pub max_iterations: u32,
     pub timeout_ms: u64,
+    /// Per-tool caps, keyed by tool name, enforced on every call the
+    /// workflow makes regardless of `max_iterations`.
+    #[serde(default)]
+    pub tool_limits: HashMap<String, ToolLimits>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolLimits {
+    /// Calls allowed over the whole workflow; later calls fail.
+    pub max_calls: Option<u32>,
+    /// Longest a single call may run before it fails.
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolLimitKind {
+    MaxCalls,
+    Timeout,
+}
+
+/// A tool call refused or cut short by its `ToolLimits`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolLimitHit {
+    pub kind: ToolLimitKind,
+    /// The limit that was reached: a call count or milliseconds.
+    pub limit: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -86,6 +178,21 @@ pub struct AgentWorkflowResult {
     pub execution_time_ms: u64,
     pub tokens_used: u32,
     pub error: Option<String>,
+    /// Set when the agent paused and is waiting for a human decision.
+    #[serde(default)]
+    pub approval_request: Option<serde_json::Value>,
+    /// Signature by the executing node, when result signing is enabled.
+    #[serde(default)]
+    pub attestation: Option<next_rc_shared::Attestation>,
+}
+
+impl AgentWorkflowResult {
+    /// SHA-256 over the outcome, final output, steps and error, ignoring
+    /// timings.
+    pub fn digest(&self) -> String {
+        let contents = (self.success, &self.final_output, &self.intermediate_steps, &self.error);
+        next_rc_shared::sha256_hex(&serde_json::to_vec(&contents).unwrap_or_default())
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,6 +202,9 @@ pub struct AgentStep {
     pub input: serde_json::Value,
     pub output: serde_json::Value,
     pub timestamp: u64,
+    /// Set when the call hit one of the tool's limits.
+    #[serde(default)]
+    pub limit_hit: Option<ToolLimitHit>,
 }
 
 pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
\ No newline at end of file
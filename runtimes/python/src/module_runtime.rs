@@ -0,0 +1,140 @@
+use crate::{PythonExecutionRequest, PythonRuntimeController, TrustLevel};
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use dashmap::DashMap;
+use next_rc_shared::{
+    ExecutionConfig, ExecutionResult, InstanceId, Language, LoadReport, ModuleId, Runtime, RuntimeCapabilities,
+    RuntimeError, ValidationReport,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+const MIB: usize = 1024 * 1024;
+
+/// Serves a `PythonRuntimeController` through the shared `Runtime` trait, so
+/// the orchestrator can run Python source next to its other runtimes. A
+/// module is the source itself; each execution runs it from the top, with
+/// the execution's input on stdin and its stdout as the output.
+pub struct PythonModuleRuntime {
+    controller: Arc<PythonRuntimeController>,
+    modules: DashMap<ModuleId, Arc<str>>,
+    instances: DashMap<InstanceId, ModuleId>,
+}
+
+impl PythonModuleRuntime {
+    pub fn new(controller: Arc<PythonRuntimeController>) -> Self {
+        Self {
+            controller,
+            modules: DashMap::new(),
+            instances: DashMap::new(),
+        }
+    }
+}
+
+fn trust_level(trust_level: next_rc_shared::TrustLevel) -> TrustLevel {
+    match trust_level {
+        next_rc_shared::TrustLevel::Low => TrustLevel::Low,
+        next_rc_shared::TrustLevel::Medium => TrustLevel::Medium,
+        next_rc_shared::TrustLevel::High => TrustLevel::High,
+    }
+}
+
+/// Keeps `RuntimeError`s downcastable once they cross into `anyhow`.
+fn into_anyhow(error: Box<dyn std::error::Error + Send + Sync>) -> anyhow::Error {
+    match error.downcast::<RuntimeError>() {
+        Ok(error) => (*error).into(),
+        Err(error) => anyhow!(error),
+    }
+}
+
+fn source(code: &[u8], language: Language) -> anyhow::Result<&str> {
+    if language != Language::Python {
+        bail!("Unsupported language for the Python runtime: {:?}", language);
+    }
+    Ok(std::str::from_utf8(code)?)
+}
+
+#[async_trait]
+impl Runtime for PythonModuleRuntime {
+    async fn compile(&self, code: &[u8], language: Language) -> anyhow::Result<ModuleId> {
+        let code = source(code, language)?;
+        let module_id = ModuleId(Uuid::new_v4());
+        self.modules.insert(module_id.clone(), code.into());
+        Ok(module_id)
+    }
+
+    async fn instantiate(&self, module_id: ModuleId) -> anyhow::Result<InstanceId> {
+        if !self.modules.contains_key(&module_id) {
+            bail!("Module not found: {}", module_id.0);
+        }
+        let instance_id = InstanceId(Uuid::new_v4());
+        self.instances.insert(instance_id.clone(), module_id);
+        Ok(instance_id)
+    }
+
+    async fn execute(&self, instance_id: InstanceId, config: ExecutionConfig) -> anyhow::Result<ExecutionResult> {
+        let code = self
+            .instances
+            .get(&instance_id)
+            .and_then(|module_id| self.modules.get(&*module_id).map(|code| code.clone()))
+            .ok_or_else(|| anyhow!("Instance not found: {}", instance_id.0))?;
+
+        let request = PythonExecutionRequest {
+            id: Uuid::new_v4(),
+            code: code.to_string(),
+            runtime_hint: None,
+            trust_level: trust_level(config.permissions.trust_level),
+            timeout_ms: config.timeout.as_millis() as u64,
+            memory_limit_mb: (config.memory_limit / MIB).max(1) as u64,
+            environment: HashMap::new(),
+            requirements: Vec::new(),
+            stdin: config.input.map(|input| String::from_utf8_lossy(&input).into_owned()),
+            virtual_time: config.virtual_time,
+            log_limits: config.trace_verbosity.log_limits(config.log_limits),
+            process_limits: config.process_limits,
+        };
+        let encoding = config.output_encoding;
+        let result = self.controller.execute(request).await.map_err(into_anyhow)?;
+
+        Ok(ExecutionResult {
+            success: result.success,
+            output: Some(encoding.encode(result.output.into_bytes())?),
+            error: result.error,
+            execution_time: Duration::from_millis(result.execution_time_ms),
+            memory_used: result.memory_used_mb as usize * MIB,
+            logs: result.logs,
+            attestation: None,
+            environment_fingerprint: result.environment_fingerprint,
+            timing: result.timing,
+        })
+    }
+
+    async fn destroy(&self, instance_id: InstanceId) -> anyhow::Result<()> {
+        self.instances
+            .remove(&instance_id)
+            .map(|_| ())
+            .ok_or_else(|| anyhow!("Instance not found: {}", instance_id.0))
+    }
+
+    async fn validate(
+        &self,
+        code: &[u8],
+        language: Language,
+        trust_level: next_rc_shared::TrustLevel,
+    ) -> anyhow::Result<ValidationReport> {
+        Ok(self.controller.validate(source(code, language)?, &self::trust_level(trust_level)))
+    }
+
+    fn capabilities(&self) -> RuntimeCapabilities {
+        RuntimeCapabilities {
+            languages: vec![Language::Python],
+            toolchains: self.controller.toolchains(),
+        }
+    }
+
+    fn current_load(&self) -> LoadReport {
+        self.controller.current_load()
+    }
+}
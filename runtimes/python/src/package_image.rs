@@ -0,0 +1,150 @@
+use crate::Result;
+use next_rc_shared::ToolchainStatus;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Where the image is mounted, read-only, inside WASM guests.
+pub const GUEST_SITE_PACKAGES: &str = "/site-packages";
+
+/// Lists the baked requirements, at the root of the image.
+const MANIFEST_FILE: &str = "next-rc-packages.json";
+
+/// Files that can only be loaded by a native interpreter.
+const NATIVE_EXTENSIONS: &[&str] = &["so", "pyd", "dylib", "dll"];
+
+/// Pure-Python packages baked into a directory that the WASM backend mounts
+/// into every guest, so their imports work without pip or network access at
+/// run time.
+#[derive(Debug, Clone)]
+pub struct PackageImage {
+    root: PathBuf,
+    manifest: PackageManifest,
+    /// Installed distributions, including dependencies, by name.
+    packages: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PackageManifest {
+    requirements: Vec<String>,
+}
+
+impl PackageImage {
+    /// Build step: installs `requirements` and their dependencies into
+    /// `root` from platform-independent wheels only, so a package with
+    /// native code fails here instead of at import time in the guest.
+    pub async fn bake(root: impl Into<PathBuf>, requirements: &[String]) -> Result<Self> {
+        let root = root.into();
+        let pip = ToolchainStatus::probe_command(
+            "pip",
+            "python3",
+            &["-m", "pip", "--version"],
+            "install python3 with pip to bake package images",
+        );
+        let python = pip.require()?;
+        tokio::fs::create_dir_all(&root).await?;
+
+        let output = Command::new(python)
+            .args(["-m", "pip", "install", "--quiet", "--no-compile"])
+            .args(["--only-binary=:all:", "--platform", "any", "--implementation", "py"])
+            .arg("--target")
+            .arg(&root)
+            .arg("--")
+            .args(requirements)
+            .output()
+            .await?;
+        if !output.status.success() {
+            return Err(format!(
+                "Failed to bake packages into {}: {}",
+                root.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ).into());
+        }
+
+        Self::check_pure(&root)?;
+
+        let manifest = PackageManifest {
+            requirements: requirements.to_vec(),
+        };
+        tokio::fs::write(root.join(MANIFEST_FILE), serde_json::to_vec_pretty(&manifest)?).await?;
+
+        let packages = installed_packages(&root)?;
+        Ok(Self { root, manifest, packages })
+    }
+
+    /// Opens an image baked earlier.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        let manifest = std::fs::read(root.join(MANIFEST_FILE))
+            .map_err(|e| format!("{} is not a package image: {}", root.display(), e))?;
+        let manifest = serde_json::from_slice(&manifest)?;
+        let packages = installed_packages(&root)?;
+        Ok(Self { root, manifest, packages })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn requirements(&self) -> &[String] {
+        &self.manifest.requirements
+    }
+
+    /// Versions pip resolved for every installed distribution, by name.
+    pub fn packages(&self) -> &BTreeMap<String, String> {
+        &self.packages
+    }
+
+    /// Whether `requirement` was baked in, matched by distribution name
+    /// without version specifiers or extras.
+    pub fn provides(&self, requirement: &str) -> bool {
+        let name = distribution_name(requirement);
+        self.manifest
+            .requirements
+            .iter()
+            .any(|baked| distribution_name(baked) == name)
+    }
+
+    fn check_pure(dir: &Path) -> Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                Self::check_pure(&path)?;
+            } else if path
+                .extension()
+                .and_then(|extension| extension.to_str())
+                .is_some_and(|extension| NATIVE_EXTENSIONS.contains(&extension))
+            {
+                return Err(format!("Package image contains native code: {}", path.display()).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `requests` from `Requests[socks]>=2.31`, normalized as pip does.
+/// Reads name and version off each `<name>-<version>.dist-info` directory
+/// pip leaves in `root`.
+fn installed_packages(root: &Path) -> Result<BTreeMap<String, String>> {
+    let mut packages = BTreeMap::new();
+    for entry in std::fs::read_dir(root)? {
+        let file_name = entry?.file_name();
+        let Some(stem) = file_name.to_str().and_then(|name| name.strip_suffix(".dist-info")) else {
+            continue;
+        };
+        if let Some((name, version)) = stem.rsplit_once('-') {
+            packages.insert(distribution_name(name), version.to_string());
+        }
+    }
+    Ok(packages)
+}
+
+fn distribution_name(requirement: &str) -> String {
+    requirement
+        .split(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+        .replace(['_', '.'], "-")
+}
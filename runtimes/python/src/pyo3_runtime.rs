@@ -1,8 +1,10 @@
 use crate::{PythonExecutionRequest, PythonExecutionResult, PythonRuntimeType, TrustLevel, Result};
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyModule, PyString};
+use pyo3::exceptions::{PyRuntimeError, PySyntaxError};
+use pyo3::types::{PyCFunction, PyDict, PyModule, PyString, PyTuple};
 use pyo3_asyncio::tokio::future_into_py;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use parking_lot::RwLock;
@@ -10,11 +12,25 @@ use dashmap::DashMap;
 use uuid::Uuid;
 use tokio::time::timeout;
 use metrics::{Counter, Histogram, Gauge};
+use next_rc_shared::{
+    guest_vector_search, Diagnostic, ExecutionLogs, LogCapture, ProvisionReport, RuntimeEnvironment, RuntimeError,
+    TimingBreakdown, ToolchainStatus, VectorStore,
+};
+use crate::security::SandboxContext;
+use crate::guest_logging::{python_log_level, LOG_CAPTURE_SHIM};
+use crate::requirements::Requirement;
+use crate::vector_search::VECTOR_SEARCH_SHIM;
+use crate::virtual_time::VIRTUAL_TIME_SHIM;
 
 pub struct PyO3Runtime {
     interpreters: Arc<DashMap<Uuid, Arc<RwLock<PythonInterpreter>>>>,
+    /// Interpreters created ahead of time by `provision`.
+    warm: Arc<parking_lot::Mutex<Vec<PythonInterpreter>>>,
     security_manager: Arc<crate::security::SecurityManager>,
     metrics: Arc<PyO3Metrics>,
+    vector_store: RwLock<Option<Arc<dyn VectorStore>>>,
+    /// Installs requests' requirements; probed at start-up.
+    pip: ToolchainStatus,
 }
 
 struct PythonInterpreter {
@@ -46,11 +62,24 @@ impl PyO3Runtime {
 
         Ok(Self {
             interpreters: Arc::new(DashMap::new()),
+            warm: Arc::new(parking_lot::Mutex::new(Vec::new())),
             security_manager,
             metrics,
+            vector_store: RwLock::new(None),
+            pip: ToolchainStatus::probe("pip", "install pip or submit requests without requirements"),
         })
     }
 
+    pub fn pip(&self) -> &ToolchainStatus {
+        &self.pip
+    }
+
+    /// Serves `next_rc_vectors` at trust levels allowing vector search;
+    /// `None` makes every search raise `PermissionError`.
+    pub fn set_vector_store(&self, store: Option<Arc<dyn VectorStore>>) {
+        *self.vector_store.write() = store;
+    }
+
     pub async fn execute(&self, request: PythonExecutionRequest) -> Result<PythonExecutionResult> {
         let start_time = Instant::now();
         self.metrics.execution_count.increment(1);
@@ -59,7 +88,11 @@ impl PyO3Runtime {
         let restrictions = self.security_manager.get_restrictions(&request.trust_level);
         
         // Get or create interpreter for this request
-        let interpreter = self.get_or_create_interpreter(&request).await?;
+        let interpreter = self
+            .get_or_create_interpreter(&request)
+            .await
+            .map_err(|e| RuntimeError::InstantiationError(format!("PyO3 interpreter failed to start: {}", e)))?;
+        let setup_done = Instant::now();
         
         // Execute with timeout
         let execution_future = self.execute_with_interpreter(interpreter, &request);
@@ -67,6 +100,11 @@ impl PyO3Runtime {
             Duration::from_millis(request.timeout_ms),
             execution_future
         ).await??;
+        let timing = TimingBreakdown {
+            sandbox_setup: setup_done - start_time,
+            execute: setup_done.elapsed(),
+            ..Default::default()
+        };
 
         let execution_time = start_time.elapsed().as_millis() as u64;
         metrics::histogram!("python_pyo3_execution_duration_ms").record(execution_time as f64);
@@ -76,20 +114,129 @@ impl PyO3Runtime {
             success: execution_result.success,
             output: execution_result.output,
             error: execution_result.error,
+            exception: execution_result.exception,
             runtime_used: PythonRuntimeType::PyO3,
             execution_time_ms: execution_time,
             memory_used_mb: execution_result.memory_used_mb,
             exit_code: execution_result.exit_code,
+            logs: execution_result.logs,
+            fallback: None,
+            environment_fingerprint: None,
+            timing: Some(timing),
+        })
+    }
+
+    /// Interpreter version and the installed versions of `requirements`,
+    /// as `importlib.metadata` reports them.
+    pub fn environment(&self, requirements: &[String]) -> RuntimeEnvironment {
+        Python::with_gil(|py| {
+            let version = py.version_info();
+            let mut packages = BTreeMap::new();
+            if let Ok(metadata) = py.import("importlib.metadata") {
+                for requirement in requirements.iter().filter_map(|spec| Requirement::parse(spec).ok()) {
+                    let installed = metadata
+                        .call_method1("version", (requirement.name.as_str(),))
+                        .and_then(|version| version.extract::<String>());
+                    if let Ok(installed) = installed {
+                        packages.insert(requirement.name, installed);
+                    }
+                }
+            }
+
+            RuntimeEnvironment {
+                engine: format!("CPython {}.{}.{}", version.major, version.minor, version.patch),
+                engine_config: BTreeMap::new(),
+                packages,
+            }
+        })
+    }
+
+    /// Compiles `code` with the builtin `compile()` without running it,
+    /// reporting syntax errors with their source position.
+    pub fn check_syntax(&self, code: &str) -> std::result::Result<(), Diagnostic> {
+        const FILENAME: &str = "<submission>";
+
+        Python::with_gil(|py| {
+            let compiled = py
+                .import("builtins")
+                .and_then(|builtins| builtins.getattr("compile"))
+                .and_then(|compile| compile.call1((code, FILENAME, "exec")));
+
+            match compiled {
+                Ok(_) => Ok(()),
+                Err(e) if e.is_instance_of::<PySyntaxError>(py) => {
+                    let value = e.value(py);
+                    let field = |name: &str| value.getattr(name).and_then(|v| v.extract::<Option<u32>>()).ok().flatten();
+
+                    let message = value
+                        .getattr("msg")
+                        .and_then(|msg| msg.extract::<String>())
+                        .unwrap_or_else(|_| e.to_string());
+                    let mut diagnostic = Diagnostic::error(message).in_file(FILENAME);
+                    if let Some(line) = field("lineno") {
+                        diagnostic = diagnostic.at(line, field("offset").unwrap_or(1));
+                    }
+                    if let Ok(Some(text)) = value.getattr("text").and_then(|t| t.extract::<Option<String>>()) {
+                        diagnostic = diagnostic.with_snippet(text.trim_end());
+                    }
+                    Err(diagnostic)
+                }
+                Err(e) => Err(Diagnostic::error(e.to_string())),
+            }
         })
     }
 
+    /// Creates `count` interpreters up front for requests that don't need a
+    /// custom environment or extra requirements.
+    pub async fn provision(&self, count: usize) -> ProvisionReport {
+        let start_time = Instant::now();
+        let mut ready = 0;
+        let mut failures = Vec::new();
+        
+        for _ in 0..count {
+            match self.create_interpreter(&HashMap::new(), &[]).await {
+                Ok(interpreter) => {
+                    self.warm.lock().push(interpreter);
+                    ready += 1;
+                }
+                Err(e) => failures.push(e.to_string()),
+            }
+        }
+        
+        ProvisionReport {
+            requested: count,
+            ready,
+            pooled: self.warm.lock().len(),
+            failures,
+            elapsed: start_time.elapsed(),
+        }
+    }
+
+    pub fn warm_interpreters(&self) -> usize {
+        self.warm.lock().len()
+    }
+
+    /// Drops warm interpreters until at most `target` remain.
+    pub fn retire_warm(&self, target: usize) -> usize {
+        let mut warm = self.warm.lock();
+        warm.truncate(target);
+        warm.len()
+    }
+
     async fn get_or_create_interpreter(&self, request: &PythonExecutionRequest) -> Result<Arc<RwLock<PythonInterpreter>>> {
-        // Create a new interpreter for each request (isolation)
+        // Create a new interpreter for each request (isolation), taking a
+        // provisioned one when the request needs no special setup
         let interpreter_id = Uuid::new_v4();
         
-        let interpreter = Arc::new(RwLock::new(
-            self.create_interpreter(request).await?
-        ));
+        let warm = if request.environment.is_empty() && request.requirements.is_empty() {
+            self.warm.lock().pop()
+        } else {
+            None
+        };
+        let interpreter = Arc::new(RwLock::new(match warm {
+            Some(interpreter) => interpreter,
+            None => self.create_interpreter(&request.environment, &request.requirements).await?,
+        }));
         
         self.interpreters.insert(interpreter_id, interpreter.clone());
         self.metrics.active_interpreters.set(self.interpreters.len() as f64);
@@ -97,20 +244,24 @@ impl PyO3Runtime {
         Ok(interpreter)
     }
 
-    async fn create_interpreter(&self, request: &PythonExecutionRequest) -> Result<PythonInterpreter> {
+    async fn create_interpreter(&self, environment: &HashMap<String, String>, requirements: &[String]) -> Result<PythonInterpreter> {
+        let pip = match requirements.is_empty() {
+            true => None,
+            false => Some(self.pip.require()?),
+        };
         Python::with_gil(|py| {
             let sys = py.import("sys")?;
             let os = py.import("os")?;
             
             // Set up environment variables
             let env = os.getattr("environ")?;
-            for (key, value) in &request.environment {
+            for (key, value) in environment {
                 env.set_item(key, value)?;
             }
             
             // Install requirements if specified
-            if !request.requirements.is_empty() {
-                self.install_requirements(py, &request.requirements)?;
+            if let Some(pip) = pip {
+                self.install_requirements(py, pip, requirements)?;
             }
             
             // Create isolated globals
@@ -153,13 +304,14 @@ impl PyO3Runtime {
         Ok(())
     }
 
-    fn install_requirements(&self, py: Python, requirements: &[String]) -> PyResult<()> {
+    fn install_requirements(&self, py: Python, pip: &Path, requirements: &[String]) -> PyResult<()> {
         let subprocess = py.import("subprocess")?;
+        let pip = pip.to_string_lossy();
         
         for requirement in requirements {
             // Use pip to install requirement
             let args = vec![
-                "pip", "install", "--user", "--quiet", requirement
+                pip.as_ref(), "install", "--user", "--quiet", requirement
             ];
             
             let result = subprocess.call_method1(
@@ -186,12 +338,26 @@ impl PyO3Runtime {
     ) -> Result<ExecutionResult> {
         let code = request.code.clone();
         let memory_limit = request.memory_limit_mb;
+        let virtual_time = request.virtual_time;
+        let log_limits = request.log_limits;
+        let stdin = request.stdin.clone().unwrap_or_default();
+        let vector_store = match self.security_manager.get_restrictions(&request.trust_level).vector_search {
+            true => self.vector_store.read().clone(),
+            false => None,
+        };
+        let handle = tokio::runtime::Handle::current();
+        // Process limits are rlimits, so they're applied in a forked child
+        // rather than to the host process
+        let sandbox = match request.process_limits.is_unlimited() {
+            true => None,
+            false => Some(self.security_manager.create_sandbox(&request.trust_level, request.process_limits)?),
+        };
         
         // Execute in thread pool to avoid blocking
         let result = tokio::task::spawn_blocking(move || {
             let interpreter = interpreter.read();
             
-            Python::with_gil(|py| {
+            let run = |py: Python| {
                 // Set memory limit
                 Self::set_memory_limit(py, memory_limit)?;
                 
@@ -200,24 +366,72 @@ impl PyO3Runtime {
                 globals.set_item("__name__", "__main__")?;
                 globals.set_item("__builtins__", py.import("builtins")?)?;
                 
-                // Capture stdout/stderr
+                // Capture stdout/stderr, and serve stdin from the request so
+                // `input()` never reads the host's stdin
                 let io = py.import("io")?;
                 let stdout = io.call_method0("StringIO")?;
                 let stderr = io.call_method0("StringIO")?;
+                let stdin = io.call_method1("StringIO", (stdin,))?;
                 
                 let sys = py.import("sys")?;
                 let old_stdout = sys.getattr("stdout")?;
                 let old_stderr = sys.getattr("stderr")?;
+                let old_stdin = sys.getattr("stdin")?;
                 
                 sys.setattr("stdout", stdout)?;
                 sys.setattr("stderr", stderr)?;
+                sys.setattr("stdin", stdin)?;
+                
+                // Swap in the virtual clock for the duration of the run
+                let clock_shim = match virtual_time {
+                    Some(time) => {
+                        let shim = PyModule::from_code(py, VIRTUAL_TIME_SHIM, "next_rc_virtual_time.py", "next_rc_virtual_time")?;
+                        shim.call_method1("install", (time.start_unix_ms, time.tick_ns))?;
+                        Some(shim)
+                    }
+                    None => None,
+                };
+                
+                // Route `logging` records into the execution's log capture
+                let logs = Arc::new(parking_lot::Mutex::new(LogCapture::new(log_limits)));
+                let sink_logs = logs.clone();
+                let sink = PyCFunction::new_closure(py, None, None, move |args: &PyTuple, _kwargs: Option<&PyDict>| -> PyResult<()> {
+                    let (levelno, message): (i32, String) = args.extract()?;
+                    sink_logs.lock().push(python_log_level(levelno), &message);
+                    Ok(())
+                })?;
+                let log_shim = PyModule::from_code(py, LOG_CAPTURE_SHIM, "next_rc_logging.py", "next_rc_logging")?;
+                log_shim.call_method1("install", (sink,))?;
+                
+                // Answer `next_rc_vectors` searches from the host's store
+                let vector_shim = PyModule::from_code(py, VECTOR_SEARCH_SHIM, "next_rc_vectors.py", "next_rc_vectors")?;
+                sys.getattr("modules")?.set_item("next_rc_vectors", vector_shim)?;
+                if let Some(store) = vector_store {
+                    let search = PyCFunction::new_closure(py, None, None, move |args: &PyTuple, _kwargs: Option<&PyDict>| -> PyResult<String> {
+                        let (query,): (String,) = args.extract()?;
+                        let matches = args
+                            .py()
+                            .allow_threads(|| handle.block_on(guest_vector_search(store.as_ref(), query.as_bytes())))
+                            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+                        Ok(String::from_utf8_lossy(&matches).into_owned())
+                    })?;
+                    vector_shim.call_method1("install", (search,))?;
+                }
                 
                 // Execute the code
                 let exec_result = py.run(&code, Some(globals), None);
                 
-                // Restore stdout/stderr
+                vector_shim.call_method0("restore")?;
+                log_shim.call_method0("restore")?;
+                if let Some(shim) = clock_shim {
+                    shim.call_method0("restore")?;
+                }
+                let logs = std::mem::replace(&mut *logs.lock(), LogCapture::new(log_limits)).finish();
+                
+                // Restore stdio
                 sys.setattr("stdout", old_stdout)?;
                 sys.setattr("stderr", old_stderr)?;
+                sys.setattr("stdin", old_stdin)?;
                 
                 // Get output
                 let output = stdout.call_method0("getvalue")?.extract::<String>()?;
@@ -231,24 +445,52 @@ impl PyO3Runtime {
                         success: true,
                         output,
                         error: if error_output.is_empty() { None } else { Some(error_output) },
+                        exception: None,
                         memory_used_mb: memory_used,
                         exit_code: Some(0),
+                        logs,
                     }),
                     Err(e) => Ok::<ExecutionResult, anyhow::Error>(ExecutionResult {
                         success: false,
                         output,
                         error: Some(format!("{}\n{}", e, error_output)),
+                        exception: e.get_type(py).name().ok().map(str::to_string),
                         memory_used_mb: memory_used,
                         exit_code: Some(1),
+                        logs,
                     }),
                 }
+            };
+            
+            Python::with_gil(|py| match &sandbox {
+                Some(sandbox) => Self::run_sandboxed(sandbox, || run(py)),
+                None => run(py),
             })
         }).await??;
         
         Ok(result)
     }
 
-    fn set_memory_limit(py: Python, limit_mb: u64) -> PyResult<()> {
+    /// Runs `run` in a child forked with `sandbox` applied, taking CPython
+    /// through the fork the way `os.fork` does. Must be called with the GIL
+    /// held.
+    fn run_sandboxed<T>(sandbox: &SandboxContext, run: impl FnOnce() -> anyhow::Result<T>) -> anyhow::Result<T>
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        // SAFETY: the caller holds the GIL, and each hook runs in the
+        // process CPython expects it in
+        unsafe { pyo3::ffi::PyOS_BeforeFork() };
+        let result = sandbox.run_in_child(|| {
+            unsafe { pyo3::ffi::PyOS_AfterFork_Child() };
+            run().map_err(Into::into)
+        });
+        unsafe { pyo3::ffi::PyOS_AfterFork_Parent() };
+        
+        result.map_err(|e| anyhow::anyhow!(e))
+    }
+
+    pub(crate) fn set_memory_limit(py: Python, limit_mb: u64) -> PyResult<()> {
         let resource = py.import("resource")?;
         let rlimit_as = resource.getattr("RLIMIT_AS")?;
         let limit_bytes = (limit_mb * 1024 * 1024) as u64;
@@ -275,13 +517,15 @@ impl PyO3Runtime {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
 struct ExecutionResult {
     success: bool,
     output: String,
     error: Option<String>,
+    exception: Option<String>,
     memory_used_mb: u64,
     exit_code: Option<i32>,
+    logs: ExecutionLogs,
 }
 
 unsafe impl Send for PythonInterpreter {}
@@ -292,4 +536,63 @@ impl Drop for PyO3Runtime {
         // Clean up all interpreters
         self.interpreters.clear();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::security::SecurityManager;
+    use next_rc_shared::ProcessLimits;
+
+    fn runtime() -> PyO3Runtime {
+        PyO3Runtime::new(Arc::new(SecurityManager::new().unwrap())).unwrap()
+    }
+
+    fn request(code: &str, trust_level: TrustLevel) -> PythonExecutionRequest {
+        PythonExecutionRequest {
+            id: Uuid::new_v4(),
+            code: code.to_string(),
+            runtime_hint: Some(PythonRuntimeType::PyO3),
+            trust_level,
+            timeout_ms: 10_000,
+            memory_limit_mb: 1024,
+            environment: HashMap::new(),
+            requirements: Vec::new(),
+            stdin: None,
+            virtual_time: None,
+            log_limits: Default::default(),
+            process_limits: Default::default(),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_descriptor_limit_applies_in_a_child() {
+        let runtime = runtime();
+        let code = "files = [open('/dev/null') for _ in range(64)]\nprint(len(files))";
+
+        let mut limited = request(code, TrustLevel::Medium);
+        limited.process_limits = ProcessLimits { max_open_files: Some(32), max_processes: None };
+        let result = runtime.execute(limited).await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("Too many open files"));
+
+        // The host process keeps its own limit
+        let result = runtime.execute(request(code, TrustLevel::Medium)).await.unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.output.trim(), "64");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_input_reads_the_request_stdin() {
+        let runtime = runtime();
+        let mut with_stdin = request("name = input()\nprint(f'hello {name}')\nprint(input())", TrustLevel::Medium);
+        with_stdin.stdin = Some("world\nagain\n".to_string());
+        let result = runtime.execute(with_stdin).await.unwrap();
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.output, "hello world\nagain\n");
+
+        // Without stdin `input()` sees end of input, not the host's stdin
+        let result = runtime.execute(request("input()", TrustLevel::Medium)).await.unwrap();
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("EOFError"));
+    }
+}
@@ -0,0 +1,425 @@
+use crate::TrustLevel;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "pyo3")]
+pub use self::interpreter::PythonRepl;
+
+/// Caps on a single REPL: on each input, and on how many it accepts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplLimits {
+    /// Longest one input may run before it is interrupted.
+    pub timeout_ms: u64,
+    pub memory_limit_mb: u64,
+    /// Output kept per input, across stdout and stderr; the rest is dropped.
+    pub max_output_bytes: usize,
+    /// Inputs accepted before the REPL has to be recreated.
+    pub max_inputs: u64,
+}
+
+impl ReplLimits {
+    pub fn for_trust_level(trust_level: &TrustLevel) -> Self {
+        match trust_level {
+            TrustLevel::Low => Self {
+                timeout_ms: 5_000,
+                memory_limit_mb: 128,
+                max_output_bytes: 64 * 1024,
+                max_inputs: 500,
+            },
+            TrustLevel::Medium => Self {
+                timeout_ms: 30_000,
+                memory_limit_mb: 512,
+                max_output_bytes: 1024 * 1024,
+                max_inputs: 5_000,
+            },
+            TrustLevel::High => Self {
+                timeout_ms: 300_000,
+                memory_limit_mb: 4096,
+                max_output_bytes: 16 * 1024 * 1024,
+                max_inputs: 100_000,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReplStream {
+    Stdout,
+    Stderr,
+}
+
+/// Outcome of one REPL input. A trailing expression's value is echoed to
+/// stdout, as in the interactive interpreter.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplOutput {
+    pub success: bool,
+    pub stdout: String,
+    pub stderr: String,
+    /// Traceback of the exception the input raised, including the
+    /// `TimeoutError` it is interrupted with.
+    pub error: Option<String>,
+    /// Set when output past `ReplLimits::max_output_bytes` was dropped.
+    pub truncated: bool,
+    pub execution_time_ms: u64,
+}
+
+#[cfg(feature = "pyo3")]
+mod interpreter {
+    use super::{ReplLimits, ReplOutput, ReplStream};
+    use crate::security::SecurityManager;
+    use crate::{PyO3Runtime, Result, TrustLevel};
+    use next_rc_shared::RuntimeError;
+    use pyo3::prelude::*;
+    use pyo3::types::{PyCFunction, PyDict, PyModule, PyTuple};
+    use std::os::raw::c_long;
+    use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+    use uuid::Uuid;
+
+    /// Output captured so far from one input.
+    #[derive(Debug, Default)]
+    struct Captured {
+        stdout: String,
+        stderr: String,
+        truncated: bool,
+    }
+
+    impl Captured {
+        /// Keeps as much of `text` as `max_bytes` leaves room for and returns
+        /// the kept part.
+        fn push<'a>(&mut self, stream: ReplStream, text: &'a str, max_bytes: usize) -> &'a str {
+            let mut end = text.len().min(max_bytes.saturating_sub(self.stdout.len() + self.stderr.len()));
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.truncated |= end < text.len();
+
+            let kept = &text[..end];
+            match stream {
+                ReplStream::Stdout => self.stdout.push_str(kept),
+                ReplStream::Stderr => self.stderr.push_str(kept),
+            }
+            kept
+        }
+    }
+
+    /// Completions returned for one request.
+    const MAX_COMPLETIONS: usize = 200;
+
+    /// How long an interrupted input gets to unwind before the REPL is
+    /// given up on.
+    const INTERRUPT_GRACE: Duration = Duration::from_millis(500);
+
+    /// Python module driving a REPL namespace from the host. It is host code,
+    /// so the trust level's code checks apply to the inputs it runs instead.
+    /// `run` returns the traceback of a failed input, or `None`.
+    const REPL_SHIM: &str = r#"
+import ast as _ast
+import io as _io
+import rlcompleter as _rlcompleter
+import sys as _sys
+import traceback as _traceback
+
+
+class _Stream:
+    def __init__(self, sink, name):
+        self._sink = sink
+        self._name = name
+
+    def write(self, text):
+        self._sink(self._name, text)
+        return len(text)
+
+    def flush(self):
+        pass
+
+
+def run(source, namespace, sink):
+    saved = _sys.stdout, _sys.stderr, _sys.stdin
+    _sys.stdout, _sys.stderr = _Stream(sink, "stdout"), _Stream(sink, "stderr")
+    _sys.stdin = _io.StringIO()
+    try:
+        tree = _ast.parse(source, "<repl>", "exec")
+        last = tree.body.pop() if tree.body and isinstance(tree.body[-1], _ast.Expr) else None
+        exec(compile(tree, "<repl>", "exec"), namespace)
+        if last is not None:
+            value = eval(compile(_ast.Expression(last.value), "<repl>", "eval"), namespace)
+            if value is not None:
+                namespace["_"] = value
+                print(repr(value))
+        return None
+    except BaseException as error:
+        # Start the traceback at the input; syntax errors never got that far.
+        frames = None if isinstance(error, SyntaxError) else error.__traceback__.tb_next
+        return "".join(_traceback.format_exception(type(error), error, frames))
+    finally:
+        _sys.stdout, _sys.stderr, _sys.stdin = saved
+
+
+def complete(text, namespace, limit):
+    completer = _rlcompleter.Completer(namespace)
+    matches = []
+    while len(matches) < limit:
+        match = completer.complete(text, len(matches))
+        if match is None:
+            break
+        matches.append(match)
+    return matches
+"#;
+
+    /// Interactive session on the PyO3 backend whose namespace persists
+    /// across inputs. Inputs run one at a time, each checked against the
+    /// trust level's code restrictions first.
+    pub struct PythonRepl {
+        id: Uuid,
+        trust_level: TrustLevel,
+        limits: ReplLimits,
+        security_manager: Arc<SecurityManager>,
+        namespace: Py<PyDict>,
+        shim: Py<PyModule>,
+        /// Inputs accepted so far; held while one runs.
+        inputs: tokio::sync::Mutex<u64>,
+        closed: AtomicBool,
+    }
+
+    impl PythonRepl {
+        /// REPLs keep an interpreter alive between inputs, which only the
+        /// PyO3 backend can do. Low trust gets one too: its inputs are held
+        /// to the low trust code restrictions and the REPL to its limits.
+        pub(crate) fn new(trust_level: TrustLevel, limits: ReplLimits, security_manager: Arc<SecurityManager>) -> Result<Self> {
+            let (namespace, shim) = Python::with_gil(|py| -> PyResult<_> {
+                let namespace = PyDict::new(py);
+                namespace.set_item("__name__", "__main__")?;
+                namespace.set_item("__builtins__", py.import("builtins")?)?;
+                let shim = PyModule::from_code(py, REPL_SHIM, "next_rc_repl.py", "next_rc_repl")?;
+                Ok((namespace.into(), shim.into()))
+            })?;
+
+            Ok(Self {
+                id: Uuid::new_v4(),
+                trust_level,
+                limits,
+                security_manager,
+                namespace,
+                shim,
+                inputs: tokio::sync::Mutex::new(0),
+                closed: AtomicBool::new(false),
+            })
+        }
+
+        pub fn id(&self) -> Uuid {
+            self.id
+        }
+
+        pub fn trust_level(&self) -> &TrustLevel {
+            &self.trust_level
+        }
+
+        pub fn limits(&self) -> ReplLimits {
+            self.limits
+        }
+
+        pub fn is_closed(&self) -> bool {
+            self.closed.load(Ordering::SeqCst)
+        }
+
+        pub async fn eval(&self, code: &str) -> Result<ReplOutput> {
+            self.eval_streaming(code, |_, _| {}).await
+        }
+
+        /// Runs `code` in the REPL's namespace, passing each chunk of output
+        /// to `on_output` as it is written.
+        pub async fn eval_streaming<F>(&self, code: &str, on_output: F) -> Result<ReplOutput>
+        where
+            F: Fn(ReplStream, &str) + Send + Sync + 'static,
+        {
+            self.check_open()?;
+            self.security_manager.validate_code(code, &self.trust_level)?;
+
+            let mut inputs = self.inputs.lock().await;
+            if *inputs >= self.limits.max_inputs {
+                return Err(RuntimeError::ResourceLimitExceeded(format!(
+                    "REPL {} reached its limit of {} inputs",
+                    self.id, self.limits.max_inputs
+                )).into());
+            }
+            *inputs += 1;
+
+            let start = Instant::now();
+            let captured = Arc::new(parking_lot::Mutex::new(Captured::default()));
+            let sink_captured = captured.clone();
+            let max_output_bytes = self.limits.max_output_bytes;
+            let code = code.to_string();
+
+            let error = self.run(move |py, shim, namespace| {
+                let sink = PyCFunction::new_closure(py, None, None, move |args: &PyTuple, _kwargs: Option<&PyDict>| -> PyResult<()> {
+                    let (stream, text): (String, String) = args.extract()?;
+                    let stream = if stream == "stderr" { ReplStream::Stderr } else { ReplStream::Stdout };
+                    let kept = sink_captured.lock().push(stream, &text, max_output_bytes).to_string();
+                    if !kept.is_empty() {
+                        on_output(stream, &kept);
+                    }
+                    Ok(())
+                })?;
+                shim.call_method1("run", (code, namespace, sink))?.extract::<Option<String>>()
+            }).await?;
+
+            let captured = std::mem::take(&mut *captured.lock());
+            Ok(ReplOutput {
+                success: error.is_none(),
+                stdout: captured.stdout,
+                stderr: captured.stderr,
+                error,
+                truncated: captured.truncated,
+                execution_time_ms: start.elapsed().as_millis() as u64,
+            })
+        }
+
+        /// Completions for `text` from the REPL's namespace, as tab completion
+        /// in the interactive interpreter would offer them. Completing an
+        /// attribute evaluates the expression before the dot, so `text` is
+        /// checked like an input.
+        pub async fn complete(&self, text: &str) -> Result<Vec<String>> {
+            self.check_open()?;
+            self.security_manager.validate_code(text, &self.trust_level)?;
+
+            let _inputs = self.inputs.lock().await;
+            let text = text.to_string();
+            let matches = self.run(move |_py, shim, namespace| {
+                shim.call_method1("complete", (text, namespace, MAX_COMPLETIONS))?.extract::<Vec<String>>()
+            }).await?;
+
+            Ok(matches.into_iter().filter(|candidate| !candidate.trim().is_empty()).collect())
+        }
+
+        /// Drops the REPL's state; later inputs are refused.
+        pub fn close(&self) {
+            if !self.closed.swap(true, Ordering::SeqCst) {
+                Python::with_gil(|py| self.namespace.as_ref(py).clear());
+            }
+        }
+
+        fn check_open(&self) -> Result<()> {
+            if self.is_closed() {
+                return Err(format!("REPL {} is closed", self.id).into());
+            }
+            Ok(())
+        }
+
+        /// Runs `task` on a blocking thread under the REPL's limits. A task
+        /// still running at the timeout has `TimeoutError` raised inside it;
+        /// if it can't unwind, the REPL is closed.
+        async fn run<T, F>(&self, task: F) -> Result<T>
+        where
+            T: Send + 'static,
+            F: FnOnce(Python<'_>, &PyModule, &PyDict) -> PyResult<T> + Send + 'static,
+        {
+            let (shim, namespace) = Python::with_gil(|py| (self.shim.clone_ref(py), self.namespace.clone_ref(py)));
+            let memory_limit_mb = self.limits.memory_limit_mb;
+            let thread = Arc::new(AtomicI64::new(0));
+            let running = thread.clone();
+
+            let mut handle = tokio::task::spawn_blocking(move || {
+                Python::with_gil(|py| {
+                    PyO3Runtime::set_memory_limit(py, memory_limit_mb)?;
+                    let ident: c_long = py.import("threading")?.call_method0("get_ident")?.extract()?;
+                    running.store(ident as i64, Ordering::SeqCst);
+
+                    let result = task(py, shim.as_ref(py), namespace.as_ref(py));
+
+                    // Both sides hold the GIL, so no interrupt lands after this
+                    running.store(0, Ordering::SeqCst);
+                    unsafe {
+                        pyo3::ffi::PyThreadState_SetAsyncExc(ident, std::ptr::null_mut());
+                    }
+                    result
+                })
+            });
+
+            let result = match tokio::time::timeout(Duration::from_millis(self.limits.timeout_ms), &mut handle).await {
+                Ok(result) => result,
+                Err(_) => {
+                    tokio::task::spawn_blocking(move || {
+                        Python::with_gil(|_py| {
+                            let ident = thread.load(Ordering::SeqCst) as c_long;
+                            if ident != 0 {
+                                unsafe {
+                                    pyo3::ffi::PyThreadState_SetAsyncExc(ident, pyo3::ffi::PyExc_TimeoutError);
+                                }
+                            }
+                        })
+                    }).await?;
+
+                    match tokio::time::timeout(INTERRUPT_GRACE, handle).await {
+                        Ok(result) => result,
+                        Err(_) => {
+                            self.closed.store(true, Ordering::SeqCst);
+                            return Err(RuntimeError::TimeoutError.into());
+                        }
+                    }
+                }
+            };
+            Ok(result??)
+        }
+    }
+
+    impl Drop for PythonRepl {
+        fn drop(&mut self) {
+            self.close();
+        }
+    }
+}
+
+#[cfg(all(test, feature = "pyo3"))]
+mod tests {
+    use super::*;
+    use crate::security::SecurityManager;
+    use std::sync::Arc;
+
+    fn repl(trust_level: TrustLevel) -> PythonRepl {
+        let limits = ReplLimits {
+            memory_limit_mb: 1024,
+            ..ReplLimits::for_trust_level(&trust_level)
+        };
+        PythonRepl::new(trust_level, limits, Arc::new(SecurityManager::new().unwrap())).unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_namespace_persists_across_inputs() {
+        let repl = repl(TrustLevel::Medium);
+        assert!(repl.eval("answer = 6").await.unwrap().success);
+        let output = repl.eval("for i in range(2):\n    print(i)\nanswer * 7").await.unwrap();
+        assert_eq!(output.stdout, "0\n1\n42\n");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_complete_offers_namespace_names() {
+        let repl = repl(TrustLevel::Medium);
+        repl.eval("class Counter:\n    total = 1\ncounter = Counter()").await.unwrap();
+        assert_eq!(repl.complete("coun").await.unwrap(), vec!["counter"]);
+        assert_eq!(repl.complete("counter.to").await.unwrap(), vec!["counter.total"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_inputs_are_held_to_the_trust_level() {
+        let low = repl(TrustLevel::Low);
+        assert!(low.eval("import os").await.is_err());
+        assert!(low.complete("__import__('os').").await.is_err());
+        assert!(low.eval("print(len('abc'))").await.unwrap().success);
+
+        let high = repl(TrustLevel::High);
+        assert!(high.eval("import os").await.unwrap().success);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_inputs_past_the_limit_are_refused() {
+        let limits = ReplLimits {
+            max_inputs: 1,
+            memory_limit_mb: 1024,
+            ..ReplLimits::for_trust_level(&TrustLevel::Medium)
+        };
+        let repl = PythonRepl::new(TrustLevel::Medium, limits, Arc::new(SecurityManager::new().unwrap())).unwrap();
+        repl.eval("1").await.unwrap();
+        assert!(repl.eval("2").await.is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::TrustLevel;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fmt;
+
+/// A requirement refused by the trust level's package policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageViolation {
+    pub requirement: String,
+    pub reason: String,
+}
+
+/// Returned before anything is installed when a request's `requirements`
+/// break its trust level's package policy.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequirementsPolicyViolation {
+    pub trust_level: TrustLevel,
+    pub violations: Vec<PackageViolation>,
+}
+
+impl fmt::Display for RequirementsPolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Requirements not allowed at trust level {:?}: ", self.trust_level)?;
+        for (i, violation) in self.violations.iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{} ({})", violation.requirement, violation.reason)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for RequirementsPolicyViolation {}
+
+/// A requirement or policy entry such as `requests>=2.31,<3`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    /// Distribution name, normalized as pip does.
+    pub name: String,
+    pub constraints: Vec<(Comparison, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Ge,
+    Le,
+    Gt,
+    Lt,
+}
+
+impl Requirement {
+    /// Parses `name[extras]` followed by comma-separated comparisons.
+    /// Markers, URLs and `~=`/`===` are rejected rather than guessed at.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let spec = spec.trim();
+        let name_end = spec
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+            .unwrap_or(spec.len());
+        let (name, mut rest) = spec.split_at(name_end);
+        if name.is_empty() {
+            return Err("missing package name".to_string());
+        }
+
+        if rest.starts_with('[') {
+            let close = rest.find(']').ok_or("unterminated extras")?;
+            rest = &rest[close + 1..];
+        }
+
+        let mut constraints = Vec::new();
+        for clause in rest.split(',').map(str::trim).filter(|clause| !clause.is_empty()) {
+            let (comparison, version) = [
+                ("==", Comparison::Eq),
+                ("!=", Comparison::Ne),
+                (">=", Comparison::Ge),
+                ("<=", Comparison::Le),
+                (">", Comparison::Gt),
+                ("<", Comparison::Lt),
+            ]
+            .iter()
+            .find_map(|(operator, comparison)| clause.strip_prefix(operator).map(|version| (*comparison, version.trim())))
+            .ok_or_else(|| format!("unsupported version clause `{}`", clause))?;
+            if version.is_empty() || version.starts_with('=') || version.contains(|c: char| c.is_whitespace() || c == ';' || c == '@') {
+                return Err(format!("unsupported version clause `{}`", clause));
+            }
+            constraints.push((comparison, version.to_string()));
+        }
+
+        Ok(Self {
+            name: name.to_ascii_lowercase().replace(['_', '.'], "-"),
+            constraints,
+        })
+    }
+
+    /// The exact version this requirement pins with `==`, if any.
+    pub fn pinned_version(&self) -> Option<&str> {
+        self.constraints
+            .iter()
+            .find(|(comparison, version)| *comparison == Comparison::Eq && !version.contains('*'))
+            .map(|(_, version)| version.as_str())
+    }
+
+    pub fn allows(&self, version: &str) -> bool {
+        self.constraints.iter().all(|(comparison, bound)| {
+            let ordering = compare_versions(version, bound);
+            match comparison {
+                Comparison::Eq => ordering == Ordering::Equal,
+                Comparison::Ne => ordering != Ordering::Equal,
+                Comparison::Ge => ordering != Ordering::Less,
+                Comparison::Le => ordering != Ordering::Greater,
+                Comparison::Gt => ordering == Ordering::Greater,
+                Comparison::Lt => ordering == Ordering::Less,
+            }
+        })
+    }
+}
+
+/// Checks `requirements` against a policy: denied names always fail, and
+/// when `allowed` is non-empty every requirement must match an entry in it.
+/// Entries with version constraints need the requirement to pin a version
+/// (`name==x.y`) inside them, since a range can't be checked before pip
+/// resolves it.
+pub fn check_requirements(requirements: &[String], allowed: &[String], blocked: &[String]) -> Vec<PackageViolation> {
+    let parse_policy = |specs: &[String]| -> Vec<Requirement> { specs.iter().filter_map(|spec| Requirement::parse(spec).ok()).collect() };
+    let allowed = parse_policy(allowed);
+    let blocked = parse_policy(blocked);
+
+    let mut violations = Vec::new();
+    for spec in requirements {
+        let violation = |reason: String| PackageViolation {
+            requirement: spec.clone(),
+            reason,
+        };
+
+        let requirement = match Requirement::parse(spec) {
+            Ok(requirement) => requirement,
+            Err(e) => {
+                violations.push(violation(e));
+                continue;
+            }
+        };
+
+        if blocked.iter().any(|entry| entry.name == requirement.name) {
+            violations.push(violation("package is blocked".to_string()));
+            continue;
+        }
+        if allowed.is_empty() {
+            continue;
+        }
+
+        let Some(entry) = allowed.iter().find(|entry| entry.name == requirement.name) else {
+            violations.push(violation("package is not on the allowlist".to_string()));
+            continue;
+        };
+        if entry.constraints.is_empty() {
+            continue;
+        }
+        match requirement.pinned_version() {
+            Some(version) if entry.allows(version) => {}
+            Some(version) => violations.push(violation(format!("version {} is outside the allowed range", version))),
+            None => violations.push(violation("must pin an exact version with `==`".to_string())),
+        }
+    }
+    violations
+}
+
+/// Compares dotted release numbers component by component, numerically
+/// where both sides are numbers. Missing components count as zero.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let mut a_parts = a.split('.');
+    let mut b_parts = b.split('.');
+    loop {
+        let ordering = match (a_parts.next(), b_parts.next()) {
+            (None, None) => return Ordering::Equal,
+            (a, b) => {
+                let (a, b) = (a.unwrap_or("0"), b.unwrap_or("0"));
+                match (a.parse::<u64>(), b.parse::<u64>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => a.cmp(b),
+                }
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use crate::{
     PythonExecutionRequest, PythonExecutionResult, PythonRuntimeType, 
-    PythonScheduler,
-    security::SecurityManager, Result
+    PythonScheduler, RuntimeFallback, ShadowConfig,
+    security::SecurityManager, shadow, Result
 };
 #[cfg(feature = "wasm")]
 use crate::WasmPythonRuntime;
@@ -9,11 +9,20 @@ use crate::WasmPythonRuntime;
 use crate::PyO3Runtime;
 use std::sync::Arc;
 use std::time::Instant;
-use tokio::sync::Semaphore;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::{Semaphore, SemaphorePermit};
 use parking_lot::RwLock;
 use dashmap::DashMap;
 use uuid::Uuid;
 use metrics::{Counter, Histogram, Gauge};
+use next_rc_shared::{
+    sha256_hex, EnvironmentFingerprint, LoadReport, LoadTracker, ModuleDigest, ProvisionReport, RuntimeEnvironment,
+    RuntimeError, RuntimeType, ScalablePool, TimingBreakdown, ToolchainStatus, ValidationReport,
+};
+
+/// Types of the exceptions raised when a backend lacks a module the code
+/// imports.
+const ENVIRONMENT_ERRORS: &[&str] = &["ModuleNotFoundError", "ImportError"];
 
 pub struct PythonRuntimeController {
     #[cfg(feature = "pyo3")]
@@ -23,8 +32,53 @@ pub struct PythonRuntimeController {
     scheduler: Arc<PythonScheduler>,
     security_manager: Arc<SecurityManager>,
     execution_semaphore: Arc<Semaphore>,
+    max_concurrent_executions: usize,
+    load: LoadTracker,
     active_executions: Arc<DashMap<Uuid, ExecutionContext>>,
     metrics: Arc<RuntimeMetrics>,
+    shadow: RwLock<Option<ShadowConfig>>,
+    pyo3_slots: Arc<BackendSlots>,
+    wasm_slots: Arc<BackendSlots>,
+}
+
+/// Concurrent executions allowed on each backend, on top of the
+/// controller-wide limit. Callers over a backend's limit queue for it in
+/// arrival order.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct BackendLimits {
+    pub pyo3: usize,
+    pub wasm: usize,
+}
+
+impl BackendLimits {
+    /// Lets either backend take every slot of the controller.
+    pub fn uniform(max_concurrent_executions: usize) -> Self {
+        Self {
+            pyo3: max_concurrent_executions,
+            wasm: max_concurrent_executions,
+        }
+    }
+}
+
+struct BackendSlots {
+    semaphore: Semaphore,
+    limit: usize,
+    in_use: AtomicUsize,
+    queued: AtomicUsize,
+    utilization: Gauge,
+    queue_depth: Gauge,
+}
+
+/// Held while an execution runs on a backend.
+struct BackendPermit<'a> {
+    slots: &'a BackendSlots,
+    _permit: SemaphorePermit<'a>,
+}
+
+/// Counts a caller as queued for a backend until it is dropped, whether
+/// the caller got a slot or gave up waiting.
+struct QueuedCaller<'a> {
+    slots: &'a BackendSlots,
 }
 
 struct ExecutionContext {
@@ -44,8 +98,58 @@ struct RuntimeMetrics {
     memory_usage: Gauge,
 }
 
+impl BackendSlots {
+    fn new(backend: &'static str, limit: usize) -> Self {
+        Self {
+            semaphore: Semaphore::new(limit),
+            limit,
+            in_use: AtomicUsize::new(0),
+            queued: AtomicUsize::new(0),
+            utilization: metrics::gauge!("python_runtime_backend_utilization", "backend" => backend),
+            queue_depth: metrics::gauge!("python_runtime_backend_queue_depth", "backend" => backend),
+        }
+    }
+
+    async fn acquire(&self) -> Result<BackendPermit<'_>> {
+        let permit = {
+            let _queued = QueuedCaller::new(self);
+            self.semaphore.acquire().await?
+        };
+        
+        self.record_in_use(self.in_use.fetch_add(1, Ordering::SeqCst) + 1);
+        Ok(BackendPermit { slots: self, _permit: permit })
+    }
+
+    fn record_in_use(&self, in_use: usize) {
+        self.utilization.set(in_use as f64 / self.limit.max(1) as f64);
+    }
+}
+
+impl<'a> QueuedCaller<'a> {
+    fn new(slots: &'a BackendSlots) -> Self {
+        slots.queue_depth.set((slots.queued.fetch_add(1, Ordering::SeqCst) + 1) as f64);
+        Self { slots }
+    }
+}
+
+impl Drop for QueuedCaller<'_> {
+    fn drop(&mut self) {
+        self.slots.queue_depth.set((self.slots.queued.fetch_sub(1, Ordering::SeqCst) - 1) as f64);
+    }
+}
+
+impl Drop for BackendPermit<'_> {
+    fn drop(&mut self) {
+        self.slots.record_in_use(self.slots.in_use.fetch_sub(1, Ordering::SeqCst) - 1);
+    }
+}
+
 impl PythonRuntimeController {
     pub async fn new(max_concurrent_executions: usize) -> Result<Self> {
+        Self::with_backend_limits(max_concurrent_executions, BackendLimits::uniform(max_concurrent_executions)).await
+    }
+
+    pub async fn with_backend_limits(max_concurrent_executions: usize, backend_limits: BackendLimits) -> Result<Self> {
         let security_manager = Arc::new(SecurityManager::new()?);
         
         #[cfg(feature = "pyo3")]
@@ -76,23 +180,70 @@ impl PythonRuntimeController {
             scheduler,
             security_manager,
             execution_semaphore,
+            max_concurrent_executions,
+            load: LoadTracker::new(),
             active_executions,
             metrics,
+            shadow: RwLock::new(None),
+            pyo3_slots: Arc::new(BackendSlots::new("pyo3", backend_limits.pyo3)),
+            wasm_slots: Arc::new(BackendSlots::new("wasm", backend_limits.wasm)),
         })
     }
 
+    /// Mounts pre-baked pure-Python packages into the WASM backend, so code
+    /// importing them can run there, including at low trust.
+    #[cfg(feature = "wasm")]
+    pub fn set_package_image(&self, image: Option<crate::PackageImage>) -> Result<()> {
+        self.wasm_runtime.set_package_image(image)
+    }
+
+    /// Store answering `next_rc_vectors` searches on the PyO3 backend, at
+    /// trust levels whose restrictions allow vector search.
+    #[cfg(feature = "pyo3")]
+    pub fn set_vector_store(&self, store: Option<Arc<dyn next_rc_shared::VectorStore>>) {
+        self.pyo3_runtime.set_vector_store(store);
+    }
+
+    /// Starts a REPL whose namespace persists across inputs, with the trust
+    /// level's default limits unless `limits` overrides them.
+    #[cfg(feature = "pyo3")]
+    pub fn create_repl(&self, trust_level: crate::TrustLevel, limits: Option<crate::ReplLimits>) -> Result<crate::PythonRepl> {
+        let limits = limits.unwrap_or_else(|| crate::ReplLimits::for_trust_level(&trust_level));
+        crate::PythonRepl::new(trust_level, limits, self.security_manager.clone())
+    }
+
+    /// Enables shadow execution on a second backend, or turns it off with
+    /// `None`. The secondary must be a backend that executes the code.
+    pub fn set_shadow(&self, config: Option<ShadowConfig>) -> Result<()> {
+        if let Some(config) = &config {
+            if !shadow::executes_code(&config.secondary) {
+                return Err(format!("Cannot shadow on {:?}: the backend does not execute code", config.secondary).into());
+            }
+        }
+        *self.shadow.write() = config;
+        Ok(())
+    }
+
     pub async fn execute(&self, request: PythonExecutionRequest) -> Result<PythonExecutionResult> {
         // Acquire execution slot
+        let queued = Instant::now();
+        let waiting = self.load.queue();
         let _permit = self.execution_semaphore.acquire().await?;
+        drop(waiting);
+        let _running = self.load.run();
+        let queue_wait = queued.elapsed();
         
         let start_time = Instant::now();
         self.metrics.total_executions.increment(1);
         
         // Validate code for security
         self.security_manager.validate_code(&request.code, &request.trust_level)?;
+        self.security_manager.check_requirements(&request.requirements, &request.trust_level)?;
         
         // Select runtime based on workload and trust level
+        let scheduling = Instant::now();
         let runtime_type = self.scheduler.select_runtime(&request);
+        let scheduling = scheduling.elapsed();
         
         // Track execution
         let execution_context = ExecutionContext {
@@ -103,8 +254,100 @@ impl PythonRuntimeController {
         self.active_executions.insert(request.id, execution_context);
         self.metrics.active_executions.set(self.active_executions.len() as f64);
         
-        // Execute based on selected runtime
-        let result: Result<PythonExecutionResult> = match runtime_type {
+        // Execute on the selected runtime, retrying once on the other backend
+        // when the first attempt fails for reasons outside the submitted code
+        let mut result = self.execute_on(&runtime_type, &request).await;
+        if let Some(reason) = Self::environment_failure(&result) {
+            if let Some(fallback) = self.scheduler.fallback_runtime(&request, &runtime_type) {
+                self.scheduler.record_execution_result(
+                    runtime_type.clone(),
+                    self.analyze_workload(&request.code),
+                    start_time.elapsed().as_millis() as u64,
+                    false
+                );
+                result = self.execute_on(&fallback, &request).await.map(|mut exec_result| {
+                    exec_result.fallback = Some(RuntimeFallback {
+                        from: runtime_type.clone(),
+                        reason,
+                    });
+                    exec_result
+                });
+            }
+        }
+        
+        // Report running into a process limit as such, not as a guest exception
+        let result = result.and_then(|exec_result| {
+            let violation = exec_result
+                .error
+                .as_deref()
+                .filter(|_| !exec_result.success)
+                .and_then(|error| request.process_limits.violation(error));
+            match violation {
+                Some(violation) => Err(violation.into()),
+                None => Ok(exec_result),
+            }
+        });
+        
+        let result = result.map(|mut exec_result| {
+            exec_result.environment_fingerprint = Some(self.fingerprint(&request, &exec_result.runtime_used));
+            *exec_result.timing.get_or_insert_with(Default::default) += TimingBreakdown {
+                queue_wait,
+                scheduling,
+                ..Default::default()
+            };
+            exec_result
+        });
+        
+        if let Ok(exec_result) = &result {
+            self.shadow(&request, exec_result);
+        }
+        
+        // Clean up execution tracking
+        self.active_executions.remove(&request.id);
+        self.metrics.active_executions.set(self.active_executions.len() as f64);
+        
+        // Record metrics
+        let execution_time = start_time.elapsed().as_millis() as u64;
+        metrics::histogram!("python_runtime_execution_duration_ms").record(execution_time as f64);
+        
+        match &result {
+            Ok(exec_result) => {
+                if exec_result.success {
+                    metrics::counter!("python_runtime_successful_executions").increment(1);
+                } else {
+                    metrics::counter!("python_runtime_failed_executions").increment(1);
+                }
+                
+                // Update scheduler with performance data
+                let workload_type = self.analyze_workload(&request.code);
+                self.scheduler.record_execution_result(
+                    exec_result.runtime_used.clone(),
+                    workload_type,
+                    exec_result.execution_time_ms,
+                    exec_result.success
+                );
+                
+                self.metrics.memory_usage.set(exec_result.memory_used_mb as f64);
+            }
+            Err(_) => {
+                metrics::counter!("python_runtime_failed_executions").increment(1);
+            }
+        }
+        
+        result
+    }
+
+    async fn execute_on(&self, runtime_type: &PythonRuntimeType, request: &PythonExecutionRequest) -> Result<PythonExecutionResult> {
+        // Wait for a slot on the backend that will actually run the code
+        let queued = Instant::now();
+        let _slot = match runtime_type {
+            PythonRuntimeType::PyO3 if cfg!(feature = "pyo3") => Some(self.pyo3_slots.acquire().await?),
+            PythonRuntimeType::PyO3 | PythonRuntimeType::Wasm => Some(self.wasm_slots.acquire().await?),
+            PythonRuntimeType::Hybrid => None,
+        };
+        let queue_wait = queued.elapsed();
+        
+        let result = match runtime_type {
             PythonRuntimeType::PyO3 => {
                 self.metrics.pyo3_executions.increment(1);
                 #[cfg(feature = "pyo3")]
@@ -121,7 +364,9 @@ impl PythonRuntimeController {
                     }
                     #[cfg(not(feature = "wasm"))]
                     {
-                        Err("No Python runtime available (both PyO3 and WASM features are disabled)".into())
+                        Err(RuntimeError::InstantiationError(
+                            "No Python runtime available (both PyO3 and WASM features are disabled)".to_string(),
+                        ).into())
                     }
                 }
             }
@@ -133,48 +378,81 @@ impl PythonRuntimeController {
                 }
                 #[cfg(not(feature = "wasm"))]
                 {
-                    Err("WASM runtime not available (wasm feature is disabled)".into())
+                    Err(RuntimeError::InstantiationError(
+                        "WASM runtime not available (wasm feature is disabled)".to_string(),
+                    ).into())
                 }
             }
             PythonRuntimeType::Hybrid => {
                 // This should not happen as scheduler should resolve to concrete runtime
-                return Err("Hybrid runtime not resolved by scheduler".into());
+                Err("Hybrid runtime not resolved by scheduler".into())
             }
         };
         
-        // Clean up execution tracking
-        self.active_executions.remove(&request.id);
-        self.metrics.active_executions.set(self.active_executions.len() as f64);
-        
-        // Record metrics
-        let execution_time = start_time.elapsed().as_millis() as u64;
-        metrics::histogram!("python_runtime_execution_duration_ms").record(execution_time as f64);
+        result.map(|mut exec_result| {
+            exec_result.timing.get_or_insert_with(Default::default).queue_wait += queue_wait;
+            exec_result
+        })
+    }
+
+    /// What `runtime_used` ran `request` with.
+    fn fingerprint(&self, request: &PythonExecutionRequest, runtime_used: &PythonRuntimeType) -> EnvironmentFingerprint {
+        let environment = match runtime_used {
+            #[cfg(feature = "pyo3")]
+            PythonRuntimeType::PyO3 => self.pyo3_runtime.environment(&request.requirements),
+            #[cfg(feature = "wasm")]
+            PythonRuntimeType::Wasm => self.wasm_runtime.environment(),
+            _ => RuntimeEnvironment::default(),
+        };
+        let code = ModuleDigest {
+            name: None,
+            sha256: sha256_hex(request.code.as_bytes()),
+        };
+        EnvironmentFingerprint::new(RuntimeType::Python, environment, request.security_policy_sha256(), vec![code])
+    }
+
+    /// Re-runs a sampled request on the shadow backend in the background and
+    /// reports any difference from `primary`. The run waits for a slot on
+    /// the backend like any other, but never delays the request.
+    fn shadow(&self, request: &PythonExecutionRequest, primary: &PythonExecutionResult) -> Option<tokio::task::JoinHandle<bool>> {
+        let config = self.shadow.read().clone()?;
+        if !config.samples(request, &primary.runtime_used) {
+            return None;
+        }
         
-        match &result {
-            Ok(exec_result) => {
-                if exec_result.success {
-                    metrics::counter!("python_runtime_successful_executions").increment(1);
-                } else {
-                    metrics::counter!("python_runtime_failed_executions").increment(1);
-                }
-                
-                // Update scheduler with performance data
-                let workload_type = self.analyze_workload(&request.code);
-                self.scheduler.record_execution_result(
-                    runtime_type,
-                    workload_type,
-                    exec_result.execution_time_ms,
-                    exec_result.success
-                );
-                
-                self.metrics.memory_usage.set(exec_result.memory_used_mb as f64);
+        match config.secondary {
+            #[cfg(feature = "pyo3")]
+            PythonRuntimeType::PyO3 => {
+                let runtime = self.pyo3_runtime.clone();
+                let slots = self.pyo3_slots.clone();
+                let shadow_request = request.clone();
+                Some(shadow::spawn(request.clone(), primary.clone(), config.secondary, async move {
+                    let _slot = slots.acquire().await?;
+                    runtime.execute(shadow_request).await
+                }))
             }
-            Err(_) => {
-                metrics::counter!("python_runtime_failed_executions").increment(1);
+            _ => None,
+        }
+    }
+
+    /// Why `result` failed for reasons outside the submitted code: the backend
+    /// failing to start, or the code raising one of `ENVIRONMENT_ERRORS`.
+    /// `None` for successes, timeouts, security and limit errors, and every
+    /// other exception, whatever its message says.
+    fn environment_failure(result: &Result<PythonExecutionResult>) -> Option<String> {
+        match result {
+            Err(e) => match e.downcast_ref::<RuntimeError>() {
+                Some(RuntimeError::InstantiationError(_)) => Some(e.to_string()),
+                _ => None,
+            },
+            Ok(exec_result) if !exec_result.success => {
+                let exception = exec_result.exception.as_deref()?;
+                ENVIRONMENT_ERRORS
+                    .contains(&exception)
+                    .then(|| exec_result.error.clone().unwrap_or_else(|| exception.to_string()))
             }
+            Ok(_) => None,
         }
-        
-        result
     }
 
     fn analyze_workload(&self, code: &str) -> crate::scheduler::WorkloadType {
@@ -192,6 +470,54 @@ impl PythonRuntimeController {
         }
     }
 
+    /// Runs the security checks (and a syntax check when PyO3 is available)
+    /// without executing the code.
+    pub fn validate(&self, code: &str, trust_level: &crate::TrustLevel) -> ValidationReport {
+        #[allow(unused_mut)]
+        let mut diagnostics = self.security_manager.check_code(code, trust_level);
+
+        #[cfg(feature = "pyo3")]
+        if let Err(diagnostic) = self.pyo3_runtime.check_syntax(code) {
+            diagnostics.push(diagnostic);
+        }
+
+        ValidationReport::from_diagnostics(diagnostics)
+    }
+
+    /// Toolchains the backends shell out to, as probed at start-up.
+    pub fn toolchains(&self) -> Vec<ToolchainStatus> {
+        #[cfg(feature = "pyo3")]
+        {
+            vec![self.pyo3_runtime.pip().clone()]
+        }
+        #[cfg(not(feature = "pyo3"))]
+        {
+            Vec::new()
+        }
+    }
+
+    /// Warms `count` PyO3 interpreters so bursts of requests skip interpreter
+    /// start-up. The WASM sandbox instantiates per request and is not pooled.
+    pub async fn provision(&self, count: usize) -> Result<ProvisionReport> {
+        #[cfg(feature = "pyo3")]
+        {
+            Ok(self.pyo3_runtime.provision(count).await)
+        }
+        #[cfg(not(feature = "pyo3"))]
+        {
+            let _ = count;
+            Err("Warm interpreters require the pyo3 feature".into())
+        }
+    }
+
+    /// Executions running and queued for one of the controller's slots.
+    pub fn current_load(&self) -> LoadReport {
+        self.load.report(
+            Some(self.execution_semaphore.available_permits()),
+            Some(self.max_concurrent_executions),
+        )
+    }
+
     pub async fn get_runtime_status(&self) -> RuntimeStatus {
         RuntimeStatus {
             active_executions: self.active_executions.len() as u32,
@@ -202,6 +528,10 @@ impl PythonRuntimeController {
             wasm_executions: 0, // Placeholder
             current_memory_usage_mb: 0, // Placeholder
             available_slots: self.execution_semaphore.available_permits() as u32,
+            pyo3_available_slots: self.pyo3_slots.semaphore.available_permits() as u32,
+            wasm_available_slots: self.wasm_slots.semaphore.available_permits() as u32,
+            pyo3_queued: self.pyo3_slots.queued.load(Ordering::SeqCst) as u32,
+            wasm_queued: self.wasm_slots.queued.load(Ordering::SeqCst) as u32,
         }
     }
 
@@ -228,6 +558,47 @@ pub struct RuntimeStatus {
     pub wasm_executions: u64,
     pub current_memory_usage_mb: u64,
     pub available_slots: u32,
+    pub pyo3_available_slots: u32,
+    pub wasm_available_slots: u32,
+    /// Executions waiting for a PyO3 slot.
+    pub pyo3_queued: u32,
+    /// Executions waiting for a WASM slot.
+    pub wasm_queued: u32,
+}
+
+/// Sizes the PyO3 warm interpreter pool that `provision` fills.
+#[async_trait::async_trait]
+impl ScalablePool for PythonRuntimeController {
+    fn name(&self) -> String {
+        "python:pyo3_interpreters".to_string()
+    }
+
+    fn size(&self) -> usize {
+        #[cfg(feature = "pyo3")]
+        {
+            self.pyo3_runtime.warm_interpreters()
+        }
+        #[cfg(not(feature = "pyo3"))]
+        {
+            0
+        }
+    }
+
+    async fn resize(&self, target: usize) -> anyhow::Result<usize> {
+        let size = self.size();
+        if target > size {
+            let report = self.provision(target - size).await.map_err(|e| anyhow::anyhow!("{}", e))?;
+            return Ok(report.pooled);
+        }
+        #[cfg(feature = "pyo3")]
+        {
+            Ok(self.pyo3_runtime.retire_warm(target))
+        }
+        #[cfg(not(feature = "pyo3"))]
+        {
+            Ok(size)
+        }
+    }
 }
 
 impl Drop for PythonRuntimeController {
@@ -235,4 +606,306 @@ impl Drop for PythonRuntimeController {
         // Ensure all executions are cleaned up
         self.active_executions.clear();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrustLevel;
+
+    fn failed(error: &str, exception: Option<&str>) -> Result<PythonExecutionResult> {
+        Ok(PythonExecutionResult {
+            id: Uuid::new_v4(),
+            success: false,
+            output: String::new(),
+            error: Some(error.to_string()),
+            exception: exception.map(str::to_string),
+            runtime_used: PythonRuntimeType::PyO3,
+            execution_time_ms: 1,
+            memory_used_mb: 1,
+            exit_code: Some(1),
+            logs: Default::default(),
+            fallback: None,
+            environment_fingerprint: None,
+            timing: None,
+        })
+    }
+
+    fn request(code: &str, trust_level: TrustLevel, runtime: PythonRuntimeType) -> PythonExecutionRequest {
+        PythonExecutionRequest {
+            id: Uuid::new_v4(),
+            code: code.to_string(),
+            runtime_hint: Some(runtime),
+            trust_level,
+            timeout_ms: 10_000,
+            memory_limit_mb: 1024,
+            environment: Default::default(),
+            requirements: Vec::new(),
+            stdin: None,
+            virtual_time: None,
+            log_limits: Default::default(),
+            process_limits: Default::default(),
+        }
+    }
+
+    fn is_resource_limit(result: &Result<PythonExecutionResult>) -> bool {
+        matches!(
+            result.as_ref().err().and_then(|e| e.downcast_ref::<RuntimeError>()),
+            Some(RuntimeError::ResourceLimitExceeded(_))
+        )
+    }
+
+    #[tokio::test]
+    async fn test_shadows_need_a_backend_that_executes_code() {
+        let controller = PythonRuntimeController::new(1).await.unwrap();
+        assert!(controller.set_shadow(Some(ShadowConfig::new(PythonRuntimeType::Wasm, 1.0))).is_err());
+        assert!(controller.set_shadow(Some(ShadowConfig::new(PythonRuntimeType::Hybrid, 1.0))).is_err());
+        assert_eq!(controller.set_shadow(Some(ShadowConfig::new(PythonRuntimeType::PyO3, 1.0))).is_ok(), cfg!(feature = "pyo3"));
+        controller.set_shadow(None).unwrap();
+    }
+
+    #[cfg(feature = "pyo3")]
+    #[tokio::test]
+    async fn test_placeholder_results_are_not_shadowed() {
+        let controller = PythonRuntimeController::new(1).await.unwrap();
+        controller.set_shadow(Some(ShadowConfig::new(PythonRuntimeType::PyO3, 1.0))).unwrap();
+
+        let request = request("print(1 + 2)", TrustLevel::High, PythonRuntimeType::Wasm);
+        let mut primary = failed("", None).unwrap();
+        primary.runtime_used = PythonRuntimeType::Wasm;
+        assert!(controller.shadow(&request, &primary).is_none());
+    }
+
+    #[cfg(feature = "pyo3")]
+    #[tokio::test]
+    async fn test_pyo3_requirements_follow_the_package_allow_list() {
+        let controller = PythonRuntimeController::new(1).await.unwrap();
+        let with_requirements = |requirements: &[&str]| {
+            let mut request = request("print(1)", TrustLevel::Medium, PythonRuntimeType::PyO3);
+            request.requirements = requirements.iter().map(|spec| spec.to_string()).collect();
+            request
+        };
+        let violations = |result: Result<PythonExecutionResult>| -> Vec<String> {
+            result
+                .err()
+                .and_then(|e| e.downcast::<crate::RequirementsPolicyViolation>().ok())
+                .map(|violation| violation.violations.into_iter().map(|v| v.requirement).collect())
+                .unwrap_or_default()
+        };
+
+        // Rejected before pip runs, with the allowed pin left out
+        let result = controller.execute(with_requirements(&["leftpad==1.0", "pip", "six==1.16.0"])).await;
+        assert_eq!(violations(result), vec!["leftpad==1.0", "pip"]);
+
+        // Entries with a range need a pin inside it
+        let result = controller.execute(with_requirements(&["attrs", "attrs==22.1", "six==1.16.0"])).await;
+        assert_eq!(violations(result), vec!["attrs", "attrs==22.1"]);
+    }
+
+    /// Value of every gauge registered while it was the local recorder.
+    #[derive(Default)]
+    struct GaugeRecorder {
+        gauges: parking_lot::Mutex<std::collections::HashMap<String, Arc<GaugeValue>>>,
+    }
+
+    #[derive(Default)]
+    struct GaugeValue(parking_lot::Mutex<f64>);
+
+    impl GaugeRecorder {
+        fn value(&self, name: &str) -> f64 {
+            self.gauges.lock().get(name).map_or(0.0, |gauge| *gauge.0.lock())
+        }
+    }
+
+    impl metrics::GaugeFn for GaugeValue {
+        fn increment(&self, value: f64) {
+            *self.0.lock() += value;
+        }
+
+        fn decrement(&self, value: f64) {
+            *self.0.lock() -= value;
+        }
+
+        fn set(&self, value: f64) {
+            *self.0.lock() = value;
+        }
+    }
+
+    impl metrics::Recorder for GaugeRecorder {
+        fn describe_counter(&self, _: metrics::KeyName, _: Option<metrics::Unit>, _: metrics::SharedString) {}
+        fn describe_gauge(&self, _: metrics::KeyName, _: Option<metrics::Unit>, _: metrics::SharedString) {}
+        fn describe_histogram(&self, _: metrics::KeyName, _: Option<metrics::Unit>, _: metrics::SharedString) {}
+
+        fn register_counter(&self, _: &metrics::Key, _: &metrics::Metadata<'_>) -> Counter {
+            Counter::noop()
+        }
+
+        fn register_gauge(&self, key: &metrics::Key, _: &metrics::Metadata<'_>) -> Gauge {
+            Gauge::from_arc(self.gauges.lock().entry(key.name().to_string()).or_default().clone())
+        }
+
+        fn register_histogram(&self, _: &metrics::Key, _: &metrics::Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    fn backend_slots(limit: usize) -> (Arc<BackendSlots>, GaugeRecorder) {
+        let recorder = GaugeRecorder::default();
+        let slots = metrics::with_local_recorder(&recorder, || BackendSlots::new("pyo3", limit));
+        (Arc::new(slots), recorder)
+    }
+
+    async fn until_queued(slots: &BackendSlots, queued: usize) {
+        while slots.queued.load(Ordering::SeqCst) != queued {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_backend_slots_queue_past_their_limit() {
+        let (slots, recorder) = backend_slots(2);
+
+        let first = slots.acquire().await.unwrap();
+        assert_eq!(recorder.value("python_runtime_backend_utilization"), 0.5);
+        let second = slots.acquire().await.unwrap();
+        assert_eq!(recorder.value("python_runtime_backend_utilization"), 1.0);
+
+        let waiter = tokio::spawn({
+            let slots = slots.clone();
+            async move {
+                let _permit = slots.acquire().await.unwrap();
+                slots.in_use.load(Ordering::SeqCst)
+            }
+        });
+        until_queued(&slots, 1).await;
+        assert_eq!(recorder.value("python_runtime_backend_queue_depth"), 1.0);
+
+        drop(first);
+        assert_eq!(waiter.await.unwrap(), 2);
+        assert_eq!(slots.queued.load(Ordering::SeqCst), 0);
+        assert_eq!(recorder.value("python_runtime_backend_queue_depth"), 0.0);
+        assert_eq!(recorder.value("python_runtime_backend_utilization"), 0.5);
+
+        drop(second);
+        assert_eq!(recorder.value("python_runtime_backend_utilization"), 0.0);
+        assert_eq!(slots.semaphore.available_permits(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_waiters_leave_the_queue() {
+        let (slots, recorder) = backend_slots(1);
+        let held = slots.acquire().await.unwrap();
+
+        let waiter = tokio::spawn({
+            let slots = slots.clone();
+            async move { slots.acquire().await.is_ok() }
+        });
+        until_queued(&slots, 1).await;
+
+        waiter.abort();
+        assert!(waiter.await.unwrap_err().is_cancelled());
+        assert_eq!(slots.queued.load(Ordering::SeqCst), 0);
+        assert_eq!(recorder.value("python_runtime_backend_queue_depth"), 0.0);
+        assert_eq!(recorder.value("python_runtime_backend_utilization"), 1.0);
+
+        drop(held);
+        let _permit = slots.acquire().await.unwrap();
+        assert_eq!(slots.queued.load(Ordering::SeqCst), 0);
+        assert_eq!(recorder.value("python_runtime_backend_utilization"), 1.0);
+    }
+
+    #[test]
+    fn test_startup_errors_are_environment_failures() {
+        let result: Result<PythonExecutionResult> =
+            Err(RuntimeError::InstantiationError("WASM instance failed to start: out of memory".to_string()).into());
+        assert_eq!(
+            PythonRuntimeController::environment_failure(&result).as_deref(),
+            Some("Instantiation failed: WASM instance failed to start: out of memory"),
+        );
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_environment_failures() {
+        let elapsed = tokio::time::timeout(std::time::Duration::ZERO, std::future::pending::<()>()).await.unwrap_err();
+        let errors: Vec<Box<dyn std::error::Error + Send + Sync>> = vec![
+            elapsed.into(),
+            RuntimeError::SecurityError("Blocked import: os".to_string()).into(),
+            RuntimeError::ResourceLimitExceeded("process limit of 0 leaves no thread to run on".to_string()).into(),
+            RuntimeError::InternalError("Hybrid runtime not resolved by scheduler".to_string()).into(),
+            // Untyped errors, even ones naming an import failure
+            "ModuleNotFoundError: No module named 'numpy'".into(),
+        ];
+        for error in errors {
+            let message = error.to_string();
+            assert_eq!(PythonRuntimeController::environment_failure(&Err(error)), None, "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_import_exceptions_are_environment_failures() {
+        let error = "ModuleNotFoundError: No module named 'numpy'\n";
+        assert_eq!(
+            PythonRuntimeController::environment_failure(&failed(error, Some("ModuleNotFoundError"))).as_deref(),
+            Some(error),
+        );
+        assert!(PythonRuntimeController::environment_failure(&failed("ImportError: cannot import name 'x'", Some("ImportError"))).is_some());
+    }
+
+    #[test]
+    fn test_exceptions_match_on_type_not_message() {
+        // The message mentions an import error, but the code raised ValueError
+        let result = failed("ValueError: expected ModuleNotFoundError or ImportError", Some("ValueError"));
+        assert_eq!(PythonRuntimeController::environment_failure(&result), None);
+
+        // Nothing to go on when the backend doesn't report the type
+        let result = failed("ModuleNotFoundError: No module named 'numpy'", None);
+        assert_eq!(PythonRuntimeController::environment_failure(&result), None);
+
+        let mut result = failed("", None);
+        if let Ok(exec_result) = &mut result {
+            exec_result.success = true;
+        }
+        assert_eq!(PythonRuntimeController::environment_failure(&result), None);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "pyo3"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_pyo3_descriptor_limit_is_a_resource_limit() {
+        let controller = PythonRuntimeController::new(1).await.unwrap();
+        let mut limited = request(
+            "files = [open('/dev/null') for _ in range(64)]",
+            TrustLevel::Medium,
+            PythonRuntimeType::PyO3,
+        );
+        limited.process_limits.max_open_files = Some(32);
+
+        let result = controller.execute(limited).await;
+        assert!(is_resource_limit(&result), "{:?}", result);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "pyo3"))]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_pyo3_process_limit_is_a_resource_limit() {
+        let controller = PythonRuntimeController::new(1).await.unwrap();
+        // Root is exempt from RLIMIT_NPROC, so the code gives it up first
+        let code = "import os, threading\nif os.geteuid() == 0:\n    os.setuid(65534)\nthreading.Thread(target=print).start()";
+        let mut limited = request(code, TrustLevel::High, PythonRuntimeType::PyO3);
+        limited.process_limits.max_processes = Some(1);
+
+        let result = controller.execute(limited).await;
+        assert!(is_resource_limit(&result), "{:?}", result);
+    }
+
+    #[cfg(feature = "wasm")]
+    #[tokio::test]
+    async fn test_wasm_limits_below_its_fixed_descriptors_are_resource_limits() {
+        let controller = PythonRuntimeController::new(1).await.unwrap();
+        for (max_open_files, max_processes) in [(Some(2), None), (None, Some(0))] {
+            let mut limited = request("print(1)", TrustLevel::Low, PythonRuntimeType::Wasm);
+            limited.process_limits.max_open_files = max_open_files;
+            limited.process_limits.max_processes = max_processes;
+
+            let result = controller.execute(limited).await;
+            assert!(is_resource_limit(&result), "{:?}", result);
+        }
+    }
+}
@@ -161,6 +161,24 @@ impl PythonScheduler {
         }
     }
 
+    /// Backend to retry on after `failed` hit an environment error, if the
+    /// code can run there. Requests pinned to a backend stay put, low-trust
+    /// code never leaves WASM, and ML workloads need PyO3's native extensions.
+    pub fn fallback_runtime(&self, request: &PythonExecutionRequest, failed: &PythonRuntimeType) -> Option<PythonRuntimeType> {
+        if matches!(request.runtime_hint, Some(PythonRuntimeType::PyO3 | PythonRuntimeType::Wasm)) {
+            return None;
+        }
+
+        match failed {
+            PythonRuntimeType::PyO3 => {
+                let workload_type = self.workload_profiler.analyze_workload(&request.code);
+                (workload_type != WorkloadType::MachineLearning).then_some(PythonRuntimeType::Wasm)
+            }
+            PythonRuntimeType::Wasm => (request.trust_level != TrustLevel::Low).then_some(PythonRuntimeType::PyO3),
+            PythonRuntimeType::Hybrid => None,
+        }
+    }
+
     pub fn record_execution_result(&self, runtime: PythonRuntimeType, workload_type: WorkloadType, 
                                   execution_time_ms: u64, success: bool) {
         let mut history = self.performance_history.write();
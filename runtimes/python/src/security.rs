@@ -1,13 +1,14 @@
 use crate::{TrustLevel, Result};
+use crate::requirements::{check_requirements, RequirementsPolicyViolation};
+use next_rc_shared::{Diagnostic, ProcessLimits};
 use std::collections::HashMap;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 
 #[cfg(target_os = "linux")]
-use nix::sys::signal::{self, Signal};
+use seccompiler::{BpfProgram, SeccompAction, SeccompFilter, SeccompRule};
 #[cfg(target_os = "linux")]
-use nix::unistd::{fork, ForkResult};
-#[cfg(target_os = "linux")]
-use seccomp::{SeccompFilter, SeccompRule, SeccompCondition, SeccompAction};
+use std::collections::BTreeMap;
 
 pub struct SecurityManager {
     restrictions: HashMap<TrustLevel, SecurityRestrictions>,
@@ -21,7 +22,16 @@ pub struct SecurityRestrictions {
     pub blocked_imports: Vec<String>,
     pub allowed_functions: Vec<String>,
     pub blocked_functions: Vec<String>,
+    /// Dunder attributes used to walk from any object to `object` and its
+    /// subclasses, escaping the import and builtin restrictions.
+    pub blocked_attributes: Vec<String>,
+    /// Packages `requirements` may name, as `name` or `name>=x,<y`. Empty
+    /// allows any package not in `blocked_packages`.
+    pub allowed_packages: Vec<String>,
+    pub blocked_packages: Vec<String>,
     pub network_access: bool,
+    /// Queries against the host's vector store through `next_rc_vectors`.
+    pub vector_search: bool,
     pub file_system_access: bool,
     pub subprocess_access: bool,
     pub use_seccomp: bool,
@@ -57,6 +67,10 @@ impl SecurityManager {
                 "__import__".to_string(),
                 "eval".to_string(),
                 "exec".to_string(),
+                "ctypes".to_string(),
+                "cffi".to_string(),
+                "multiprocessing".to_string(),
+                "importlib".to_string(),
             ],
             allowed_functions: vec![
                 "print".to_string(),
@@ -73,7 +87,6 @@ impl SecurityManager {
             ],
             blocked_functions: vec![
                 "open".to_string(),
-                "input".to_string(),
                 "eval".to_string(),
                 "exec".to_string(),
                 "compile".to_string(),
@@ -86,7 +99,29 @@ impl SecurityManager {
                 "vars".to_string(),
                 "dir".to_string(),
             ],
+            blocked_attributes: vec![
+                "__class__".to_string(),
+                "__bases__".to_string(),
+                "__base__".to_string(),
+                "__mro__".to_string(),
+                "__subclasses__".to_string(),
+                "__globals__".to_string(),
+                "__builtins__".to_string(),
+                "__code__".to_string(),
+            ],
+            allowed_packages: vec![
+                "python-dateutil>=2.8".to_string(),
+                "pytz".to_string(),
+                "six".to_string(),
+                "attrs>=23".to_string(),
+                "tabulate".to_string(),
+            ],
+            blocked_packages: vec![
+                "pip".to_string(),
+                "setuptools".to_string(),
+            ],
             network_access: false,
+            vector_search: false,
             file_system_access: false,
             subprocess_access: false,
             use_seccomp: true,
@@ -113,6 +148,7 @@ impl SecurityManager {
                 "transformers".to_string(),
                 "huggingface_hub".to_string(),
                 "smolagents".to_string(),
+                "next_rc_vectors".to_string(),
             ],
             blocked_imports: vec![
                 "os".to_string(),
@@ -120,6 +156,10 @@ impl SecurityManager {
                 "subprocess".to_string(),
                 "socket".to_string(),
                 "__import__".to_string(),
+                "ctypes".to_string(),
+                "cffi".to_string(),
+                "multiprocessing".to_string(),
+                "importlib".to_string(),
             ],
             allowed_functions: vec![
                 "print".to_string(),
@@ -144,7 +184,39 @@ impl SecurityManager {
                 "locals".to_string(),
                 "vars".to_string(),
             ],
+            blocked_attributes: vec![
+                "__class__".to_string(),
+                "__bases__".to_string(),
+                "__base__".to_string(),
+                "__mro__".to_string(),
+                "__subclasses__".to_string(),
+                "__globals__".to_string(),
+                "__builtins__".to_string(),
+                "__code__".to_string(),
+            ],
+            allowed_packages: vec![
+                "python-dateutil>=2.8".to_string(),
+                "pytz".to_string(),
+                "six".to_string(),
+                "attrs>=23".to_string(),
+                "tabulate".to_string(),
+                "requests>=2.31,<3".to_string(),
+                "urllib3>=2,<3".to_string(),
+                "certifi".to_string(),
+                "idna".to_string(),
+                "charset-normalizer".to_string(),
+                "numpy>=1.26".to_string(),
+                "pandas>=2".to_string(),
+                "transformers".to_string(),
+                "huggingface_hub".to_string(),
+                "smolagents".to_string(),
+            ],
+            blocked_packages: vec![
+                "pip".to_string(),
+                "setuptools".to_string(),
+            ],
             network_access: true,
+            vector_search: true,
             file_system_access: true,
             subprocess_access: false,
             use_seccomp: true,
@@ -159,7 +231,11 @@ impl SecurityManager {
             blocked_imports: vec![], // No imports blocked
             allowed_functions: vec![], // All functions allowed
             blocked_functions: vec![], // No functions blocked
+            blocked_attributes: vec![],
+            allowed_packages: vec![], // Any package allowed
+            blocked_packages: vec![],
             network_access: true,
+            vector_search: true,
             file_system_access: true,
             subprocess_access: true,
             use_seccomp: false,
@@ -174,163 +250,98 @@ impl SecurityManager {
             .expect("Trust level not found in restrictions")
     }
 
-    pub fn create_sandbox(&self, trust_level: &TrustLevel) -> Result<SandboxContext> {
+    /// Prepares the sandbox for `trust_level` without applying it; see
+    /// `SandboxContext::run_in_child`.
+    pub fn create_sandbox(&self, trust_level: &TrustLevel, process_limits: ProcessLimits) -> Result<SandboxContext> {
         let restrictions = self.get_restrictions(trust_level);
         
-        #[cfg(target_os = "linux")]
-        {
-            if restrictions.use_namespaces {
-                return self.create_namespace_sandbox(restrictions);
-            }
-        }
-        
         Ok(SandboxContext {
             restrictions: restrictions.clone(),
+            process_limits,
             #[cfg(target_os = "linux")]
-            seccomp_filter: None,
+            seccomp_filter: match restrictions.use_seccomp {
+                true => Some(self.create_seccomp_filter(restrictions)?),
+                false => None,
+            },
         })
     }
 
+    /// Fails the syscalls `restrictions` forbid with `EACCES` and allows
+    /// every other syscall.
     #[cfg(target_os = "linux")]
-    fn create_namespace_sandbox(&self, restrictions: &SecurityRestrictions) -> Result<SandboxContext> {
-        use nix::sched::{unshare, CloneFlags};
-        use nix::mount::{mount, MsFlags};
-        use std::ffi::CString;
-
-        // Create new namespaces
-        let mut flags = CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWNS;
+    fn create_seccomp_filter(&self, restrictions: &SecurityRestrictions) -> Result<BpfProgram> {
+        let mut blocked = Vec::new();
         
         if !restrictions.network_access {
-            flags |= CloneFlags::CLONE_NEWNET;
+            blocked.extend([libc::SYS_socket, libc::SYS_connect]);
         }
         
         if !restrictions.file_system_access {
-            flags |= CloneFlags::CLONE_NEWNS;
+            blocked.extend([libc::SYS_open, libc::SYS_openat]);
         }
         
-        unshare(flags)?;
-
-        // Set up seccomp filter if required
-        let seccomp_filter = if restrictions.use_seccomp {
-            Some(self.create_seccomp_filter(restrictions)?)
-        } else {
-            None
-        };
-
-        Ok(SandboxContext {
-            restrictions: restrictions.clone(),
-            seccomp_filter,
-        })
-    }
-
-    #[cfg(target_os = "linux")]
-    fn create_seccomp_filter(&self, restrictions: &SecurityRestrictions) -> Result<SeccompFilter> {
-        let mut filter = SeccompFilter::new();
-        
-        // Allow basic system calls
-        filter.add_rule(SeccompRule::new(
-            libc::SYS_read,
-            vec![],
-            SeccompAction::Allow,
-        )?)?;
-        
-        filter.add_rule(SeccompRule::new(
-            libc::SYS_write,
-            vec![],
-            SeccompAction::Allow,
-        )?)?;
-        
-        filter.add_rule(SeccompRule::new(
-            libc::SYS_mmap,
-            vec![],
-            SeccompAction::Allow,
-        )?)?;
-        
-        filter.add_rule(SeccompRule::new(
-            libc::SYS_munmap,
-            vec![],
-            SeccompAction::Allow,
-        )?)?;
-        
-        filter.add_rule(SeccompRule::new(
-            libc::SYS_brk,
-            vec![],
-            SeccompAction::Allow,
-        )?)?;
+        if !restrictions.subprocess_access {
+            blocked.extend([libc::SYS_fork, libc::SYS_execve]);
+        }
         
-        filter.add_rule(SeccompRule::new(
-            libc::SYS_exit,
-            vec![],
+        // A syscall with no rules matches unconditionally
+        let rules: BTreeMap<i64, Vec<SeccompRule>> = blocked.into_iter().map(|syscall| (syscall, Vec::new())).collect();
+        let filter = SeccompFilter::new(
+            rules,
             SeccompAction::Allow,
-        )?)?;
+            SeccompAction::Errno(libc::EACCES as u32),
+            std::env::consts::ARCH.try_into()?,
+        )?;
         
-        filter.add_rule(SeccompRule::new(
-            libc::SYS_exit_group,
-            vec![],
-            SeccompAction::Allow,
-        )?)?;
+        Ok(filter.try_into()?)
+    }
 
-        // Block dangerous system calls
-        if !restrictions.network_access {
-            filter.add_rule(SeccompRule::new(
-                libc::SYS_socket,
-                vec![],
-                SeccompAction::Errno(libc::EACCES),
-            )?)?;
-            
-            filter.add_rule(SeccompRule::new(
-                libc::SYS_connect,
-                vec![],
-                SeccompAction::Errno(libc::EACCES),
-            )?)?;
-        }
-        
-        if !restrictions.file_system_access {
-            filter.add_rule(SeccompRule::new(
-                libc::SYS_open,
-                vec![],
-                SeccompAction::Errno(libc::EACCES),
-            )?)?;
-            
-            filter.add_rule(SeccompRule::new(
-                libc::SYS_openat,
-                vec![],
-                SeccompAction::Errno(libc::EACCES),
-            )?)?;
-        }
-        
-        if !restrictions.subprocess_access {
-            filter.add_rule(SeccompRule::new(
-                libc::SYS_fork,
-                vec![],
-                SeccompAction::Errno(libc::EACCES),
-            )?)?;
-            
-            filter.add_rule(SeccompRule::new(
-                libc::SYS_execve,
-                vec![],
-                SeccompAction::Errno(libc::EACCES),
-            )?)?;
+    pub fn validate_code(&self, code: &str, trust_level: &TrustLevel) -> Result<()> {
+        match self.check_code(code, trust_level).into_iter().next() {
+            Some(violation) => Err(violation.message.into()),
+            None => Ok(()),
         }
+    }
 
-        Ok(filter)
+    /// Rejects `requirements` the trust level's package policy doesn't allow,
+    /// listing every offending package.
+    pub fn check_requirements(&self, requirements: &[String], trust_level: &TrustLevel) -> std::result::Result<(), RequirementsPolicyViolation> {
+        let restrictions = self.get_restrictions(trust_level);
+        let violations = check_requirements(requirements, &restrictions.allowed_packages, &restrictions.blocked_packages);
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(RequirementsPolicyViolation {
+                trust_level: trust_level.clone(),
+                violations,
+            })
+        }
     }
 
-    pub fn validate_code(&self, code: &str, trust_level: &TrustLevel) -> Result<()> {
+    /// Collects every security violation in `code` instead of stopping at the first.
+    pub fn check_code(&self, code: &str, trust_level: &TrustLevel) -> Vec<Diagnostic> {
         let restrictions = self.get_restrictions(trust_level);
+        let mut violations = Vec::new();
         
         // Check for blocked imports
         for blocked_import in &restrictions.blocked_imports {
             if code.contains(&format!("import {}", blocked_import)) ||
                code.contains(&format!("from {}", blocked_import)) {
-                return Err(format!("Blocked import detected: {}", blocked_import).into());
+                violations.push(Diagnostic::error(format!("Blocked import detected: {}", blocked_import)));
             }
         }
         
         // Check for blocked functions
         for blocked_function in &restrictions.blocked_functions {
             if code.contains(&format!("{}(", blocked_function)) {
-                return Err(format!("Blocked function detected: {}", blocked_function).into());
+                violations.push(Diagnostic::error(format!("Blocked function detected: {}", blocked_function)));
+            }
+        }
+        
+        // Check for introspection used to reach unrestricted objects
+        for blocked_attribute in &restrictions.blocked_attributes {
+            if code.contains(blocked_attribute.as_str()) {
+                violations.push(Diagnostic::error(format!("Blocked attribute detected: {}", blocked_attribute)));
             }
         }
         
@@ -349,26 +360,135 @@ impl SecurityManager {
         
         for pattern in dangerous_patterns {
             if code.contains(pattern) {
-                return Err(format!("Dangerous pattern detected: {}", pattern).into());
+                violations.push(Diagnostic::error(format!("Dangerous pattern detected: {}", pattern)));
             }
         }
         
-        Ok(())
+        violations
     }
 }
 
 pub struct SandboxContext {
     pub restrictions: SecurityRestrictions,
+    pub process_limits: ProcessLimits,
     #[cfg(target_os = "linux")]
-    pub seccomp_filter: Option<SeccompFilter>,
+    pub seccomp_filter: Option<BpfProgram>,
 }
 
 impl SandboxContext {
+    /// Moves the calling process into the sandbox. Nothing here can be
+    /// undone, so only call it in a process that exists to be sandboxed.
     pub fn activate(&self) -> Result<()> {
         #[cfg(target_os = "linux")]
         {
+            if self.restrictions.use_namespaces {
+                self.enter_namespaces()?;
+            }
+            
+            // Before seccomp, which may forbid setrlimit
+            self.apply_process_limits()?;
+            
             if let Some(filter) = &self.seccomp_filter {
-                filter.load()?;
+                seccompiler::apply_filter(filter)?;
+            }
+        }
+        
+        Ok(())
+    }
+
+    /// Runs `f` in a forked child that activates the sandbox first, so the
+    /// host process never takes its namespaces, filter or rlimits. The
+    /// result comes back over a pipe as JSON.
+    #[cfg(target_os = "linux")]
+    pub fn run_in_child<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Result<T>,
+    {
+        use nix::sys::wait::waitpid;
+        use nix::unistd::{fork, ForkResult};
+        use std::fs::File;
+        use std::io::{Read, Write};
+        use std::os::fd::FromRawFd;
+        
+        let mut fds = [0; 2];
+        // SAFETY: pipe2 writes two descriptors into the array it is given
+        if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } != 0 {
+            return Err(std::io::Error::last_os_error().into());
+        }
+        // SAFETY: both descriptors are fresh and owned by nothing else
+        let (mut reader, mut writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+        
+        // SAFETY: the child runs `f` and exits without returning to the caller
+        match unsafe { fork() }? {
+            ForkResult::Child => {
+                drop(reader);
+                let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| self.activate().and_then(|()| f())))
+                    .unwrap_or_else(|_| Err("sandboxed child panicked".into()))
+                    .map_err(|e| e.to_string());
+                let sent = serde_json::to_writer(&mut writer, &result).is_ok() && writer.flush().is_ok();
+                // SAFETY: skips the destructors and exit handlers the child
+                // inherited from the host
+                unsafe { libc::_exit(if sent { 0 } else { 1 }) }
+            }
+            ForkResult::Parent { child } => {
+                drop(writer);
+                let mut message = Vec::new();
+                let read = reader.read_to_end(&mut message);
+                let status = waitpid(child, None)?;
+                read?;
+                if message.is_empty() {
+                    return Err(format!("sandboxed child exited without a result: {:?}", status).into());
+                }
+                let result: std::result::Result<T, String> = serde_json::from_slice(&message)?;
+                Ok(result?)
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn run_in_child<T, F>(&self, _f: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned,
+        F: FnOnce() -> Result<T>,
+    {
+        Err("sandboxed children are only supported on Linux".into())
+    }
+
+    /// Unshares the PID and mount namespaces, and the network namespace
+    /// when the sandbox has no network access. Only children started
+    /// afterwards enter the new PID namespace.
+    #[cfg(target_os = "linux")]
+    fn enter_namespaces(&self) -> Result<()> {
+        use nix::sched::{unshare, CloneFlags};
+        
+        let mut flags = CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWNS;
+        
+        if !self.restrictions.network_access {
+            flags |= CloneFlags::CLONE_NEWNET;
+        }
+        
+        unshare(flags)?;
+        Ok(())
+    }
+
+    /// Caps descriptors and processes for the sandboxed process. The kernel
+    /// ignores RLIMIT_NPROC for root, so privileged sandboxes also need a
+    /// cgroup pids controller to hold the process limit.
+    #[cfg(target_os = "linux")]
+    fn apply_process_limits(&self) -> Result<()> {
+        let limits = [
+            (libc::RLIMIT_NOFILE, self.process_limits.max_open_files),
+            (libc::RLIMIT_NPROC, self.process_limits.max_processes),
+        ];
+        
+        for (resource, max) in limits {
+            if let Some(max) = max {
+                let rlimit = libc::rlimit { rlim_cur: max, rlim_max: max };
+                // SAFETY: setrlimit only reads the struct it is given
+                if unsafe { libc::setrlimit(resource, &rlimit) } != 0 {
+                    return Err(std::io::Error::last_os_error().into());
+                }
             }
         }
         
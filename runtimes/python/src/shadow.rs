@@ -0,0 +1,216 @@
+use crate::{PythonExecutionRequest, PythonExecutionResult, PythonRuntimeType, Result, TrustLevel};
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Re-runs a sample of requests on a second backend and compares the
+/// results, to check a backend before traffic is migrated onto it. Callers
+/// only ever see the primary result.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowConfig {
+    /// Backend the sampled requests are additionally run on.
+    pub secondary: PythonRuntimeType,
+    /// Fraction of requests to shadow, from 0.0 to 1.0.
+    pub sample_rate: f64,
+}
+
+impl ShadowConfig {
+    pub fn new(secondary: PythonRuntimeType, sample_rate: f64) -> Self {
+        Self {
+            secondary,
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Whether `request`, which ran on `primary`, should be shadowed. The
+    /// sample is keyed on the request id so retries land the same way, and
+    /// low-trust code is never shadowed outside the WASM sandbox. Only runs
+    /// on backends that execute the code can be compared.
+    pub fn samples(&self, request: &PythonExecutionRequest, primary: &PythonRuntimeType) -> bool {
+        if self.secondary == *primary || !executes_code(primary) || !executes_code(&self.secondary) {
+            return false;
+        }
+        if request.trust_level == TrustLevel::Low && self.secondary != PythonRuntimeType::Wasm {
+            return false;
+        }
+
+        let bucket = (request.id.as_u128() % 10_000) as f64 / 10_000.0;
+        bucket < self.sample_rate
+    }
+}
+
+/// Whether `runtime` runs the submitted code. The WASM backend's
+/// interpreter is still a placeholder that only reports the code's size, so
+/// its results say nothing about the code, and without the `pyo3` feature
+/// PyO3 requests run there too.
+pub fn executes_code(runtime: &PythonRuntimeType) -> bool {
+    cfg!(feature = "pyo3") && *runtime == PythonRuntimeType::PyO3
+}
+
+/// Runs `shadow` off the request path and reports it against `primary`.
+/// The task resolves to whether the results matched.
+pub fn spawn(
+    request: PythonExecutionRequest,
+    primary: PythonExecutionResult,
+    secondary: PythonRuntimeType,
+    shadow: impl Future<Output = Result<PythonExecutionResult>> + Send + 'static,
+) -> JoinHandle<bool> {
+    tokio::spawn(async move {
+        let result = shadow.await;
+        report(&request, &primary, &secondary, &result)
+    })
+}
+
+/// Differences between the primary result and the shadow run, empty when
+/// they agree. Failures are compared by exception type only, as tracebacks
+/// differ between backends.
+pub fn compare(primary: &PythonExecutionResult, shadow: &Result<PythonExecutionResult>) -> Vec<String> {
+    let shadow = match shadow {
+        Ok(shadow) => shadow,
+        Err(e) => return vec![format!("shadow run failed: {}", e)],
+    };
+
+    let mut differences = Vec::new();
+    if primary.success != shadow.success {
+        differences.push(format!("success: {} vs {}", primary.success, shadow.success));
+    }
+    if primary.output != shadow.output {
+        differences.push(format!("output: {:?} vs {:?}", primary.output, shadow.output));
+    }
+    let primary_exception = primary.error.as_deref().map(exception_type);
+    let shadow_exception = shadow.error.as_deref().map(exception_type);
+    if primary_exception != shadow_exception {
+        differences.push(format!("exception: {:?} vs {:?}", primary_exception, shadow_exception));
+    }
+    differences
+}
+
+/// Logs the outcome of a shadow run with everything needed to reproduce
+/// it, returning whether it matched.
+pub fn report(request: &PythonExecutionRequest, primary: &PythonExecutionResult, secondary: &PythonRuntimeType, shadow: &Result<PythonExecutionResult>) -> bool {
+    metrics::counter!("python_shadow_executions_total").increment(1);
+
+    let differences = compare(primary, shadow);
+    if differences.is_empty() {
+        debug!(request_id = %request.id, "Shadow run on {:?} matched {:?}", secondary, primary.runtime_used);
+        return true;
+    }
+
+    metrics::counter!("python_shadow_mismatches_total").increment(1);
+    warn!(
+        request_id = %request.id,
+        trust_level = ?request.trust_level,
+        primary = ?primary.runtime_used,
+        secondary = ?secondary,
+        code = %request.code,
+        primary_result = ?primary,
+        shadow_result = ?shadow,
+        "Shadow execution mismatch: {}",
+        differences.join("; ")
+    );
+    false
+}
+
+/// `ValueError` from an error ending in `ValueError: bad value`.
+fn exception_type(error: &str) -> &str {
+    let last_line = error.trim_end().lines().last().unwrap_or_default();
+    last_line.split(':').next().unwrap_or_default().trim()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::oneshot;
+    use uuid::Uuid;
+
+    fn request(trust_level: TrustLevel) -> PythonExecutionRequest {
+        PythonExecutionRequest {
+            id: Uuid::new_v4(),
+            code: "print(1 + 2)".to_string(),
+            runtime_hint: None,
+            trust_level,
+            timeout_ms: 10_000,
+            memory_limit_mb: 1024,
+            environment: Default::default(),
+            requirements: Vec::new(),
+            stdin: None,
+            virtual_time: None,
+            log_limits: Default::default(),
+            process_limits: Default::default(),
+        }
+    }
+
+    fn result(output: &str, error: Option<&str>) -> PythonExecutionResult {
+        PythonExecutionResult {
+            id: Uuid::new_v4(),
+            success: error.is_none(),
+            output: output.to_string(),
+            error: error.map(str::to_string),
+            exception: None,
+            runtime_used: PythonRuntimeType::PyO3,
+            execution_time_ms: 1,
+            memory_used_mb: 1,
+            exit_code: Some(error.map_or(0, |_| 1)),
+            logs: Default::default(),
+            fallback: None,
+            environment_fingerprint: None,
+            timing: None,
+        }
+    }
+
+    #[test]
+    fn test_placeholder_backends_are_never_sampled() {
+        let request = request(TrustLevel::High);
+        assert!(!ShadowConfig::new(PythonRuntimeType::Wasm, 1.0).samples(&request, &PythonRuntimeType::PyO3));
+        assert!(!ShadowConfig::new(PythonRuntimeType::PyO3, 1.0).samples(&request, &PythonRuntimeType::Wasm));
+        assert!(!ShadowConfig::new(PythonRuntimeType::PyO3, 1.0).samples(&request, &PythonRuntimeType::PyO3));
+        assert!(!ShadowConfig::new(PythonRuntimeType::Hybrid, 1.0).samples(&request, &PythonRuntimeType::PyO3));
+    }
+
+    #[test]
+    fn test_failures_compare_by_exception_type() {
+        let primary = result("", Some("Traceback (most recent call last):\n  File \"a\"\nValueError: bad"));
+        let same = result("", Some("Traceback:\n  File \"<string>\", line 1\nValueError: other"));
+        assert!(compare(&primary, &Ok(same)).is_empty());
+
+        let different = result("", Some("TypeError: bad"));
+        assert_eq!(compare(&primary, &Ok(different)).len(), 1);
+        assert_eq!(compare(&primary, &Err("no backend".into())), vec!["shadow run failed: no backend"]);
+    }
+
+    #[tokio::test]
+    async fn test_spawned_runs_do_not_hold_up_the_caller() {
+        let (send, receive) = oneshot::channel();
+        let handle = spawn(request(TrustLevel::High), result("3\n", None), PythonRuntimeType::PyO3, async move {
+            Ok(receive.await.unwrap())
+        });
+        tokio::task::yield_now().await;
+        assert!(!handle.is_finished());
+
+        send.send(result("3\n", None)).unwrap();
+        assert!(handle.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_spawned_runs_report_mismatches() {
+        let shadow = async { Ok(result("4\n", None)) };
+        assert!(!spawn(request(TrustLevel::High), result("3\n", None), PythonRuntimeType::PyO3, shadow).await.unwrap());
+
+        let failed = async { Err("backend failed to start".into()) };
+        assert!(!spawn(request(TrustLevel::High), result("3\n", None), PythonRuntimeType::PyO3, failed).await.unwrap());
+    }
+
+    #[cfg(feature = "pyo3")]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_spawned_pyo3_runs_execute_the_code() {
+        let runtime = crate::PyO3Runtime::new(std::sync::Arc::new(crate::security::SecurityManager::new().unwrap())).unwrap();
+        let shadow_request = request(TrustLevel::High);
+        let primary = runtime.execute(shadow_request.clone()).await.unwrap();
+        assert_eq!(primary.output.trim(), "3");
+
+        let rerun = shadow_request.clone();
+        let shadow = async move { runtime.execute(rerun).await };
+        assert!(spawn(shadow_request, primary, PythonRuntimeType::PyO3, shadow).await.unwrap());
+    }
+}
@@ -0,0 +1,28 @@
+/// Python module exposing the host's vector store as
+/// `next_rc_vectors.search(collection, vector, limit=5)`, which returns the
+/// closest points, best first, as `{"id", "score", "payload"}` dicts.
+/// `install(search)` takes a host function mapping a JSON query to JSON
+/// matches; until then, and after `restore()`, searches raise
+/// `PermissionError`.
+pub const VECTOR_SEARCH_SHIM: &str = r#"
+import json as _json
+
+_search = None
+
+
+def search(collection, vector, limit=5):
+    if _search is None:
+        raise PermissionError("vector search is not available to this execution")
+    query = {"collection": collection, "vector": [float(x) for x in vector], "limit": limit}
+    return _json.loads(_search(_json.dumps(query)))
+
+
+def install(host_search):
+    global _search
+    _search = host_search
+
+
+def restore():
+    global _search
+    _search = None
+"#;
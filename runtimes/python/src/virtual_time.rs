@@ -0,0 +1,109 @@
+#[cfg(feature = "wasm")]
+use next_rc_shared::{VirtualClock, VirtualTime};
+#[cfg(feature = "wasm")]
+use parking_lot::Mutex;
+#[cfg(feature = "wasm")]
+use std::sync::Arc;
+#[cfg(feature = "wasm")]
+use std::time::Duration;
+
+/// Python module swapping the `time` and `datetime` clocks for a virtual one.
+/// `install()` patches process-wide, so `restore()` must run before the
+/// interpreter is handed to another execution.
+pub const VIRTUAL_TIME_SHIM: &str = r#"
+import datetime as _datetime
+import time as _time
+
+_originals = {}
+
+
+class _VirtualClock:
+    def __init__(self, start_ns, tick_ns):
+        self.start_ns = start_ns
+        self.tick_ns = tick_ns
+        self.elapsed_ns = 0
+
+    def monotonic_ns(self):
+        self.elapsed_ns += self.tick_ns
+        return self.elapsed_ns
+
+    def time_ns(self):
+        return self.start_ns + self.monotonic_ns()
+
+    def sleep(self, seconds):
+        if seconds < 0:
+            raise ValueError("sleep length must be non-negative")
+        self.elapsed_ns += int(seconds * 1_000_000_000)
+
+
+def install(start_unix_ms, tick_ns):
+    clock = _VirtualClock(start_unix_ms * 1_000_000, tick_ns)
+
+    class datetime(_datetime.datetime):
+        @classmethod
+        def now(cls, tz=None):
+            return cls.fromtimestamp(clock.time_ns() / 1e9, tz)
+
+        @classmethod
+        def utcnow(cls):
+            return cls.now(_datetime.timezone.utc).replace(tzinfo=None)
+
+        @classmethod
+        def today(cls):
+            return cls.now()
+
+    patches = {
+        (_time, "time"): lambda: clock.time_ns() / 1e9,
+        (_time, "time_ns"): clock.time_ns,
+        (_time, "monotonic"): lambda: clock.monotonic_ns() / 1e9,
+        (_time, "monotonic_ns"): clock.monotonic_ns,
+        (_time, "perf_counter"): lambda: clock.monotonic_ns() / 1e9,
+        (_time, "perf_counter_ns"): clock.monotonic_ns,
+        (_time, "sleep"): clock.sleep,
+        (_datetime, "datetime"): datetime,
+    }
+    for (module, name), value in patches.items():
+        _originals.setdefault((module, name), getattr(module, name))
+        setattr(module, name, value)
+
+
+def restore():
+    for (module, name), value in _originals.items():
+        setattr(module, name, value)
+    _originals.clear()
+"#;
+
+/// WASI wall and monotonic clock backed by a `VirtualClock`, for the WASM
+/// sandbox.
+#[cfg(feature = "wasm")]
+#[derive(Clone)]
+pub struct VirtualWasiClock(Arc<Mutex<VirtualClock>>);
+
+#[cfg(feature = "wasm")]
+impl VirtualWasiClock {
+    pub fn new(time: VirtualTime) -> Self {
+        Self(Arc::new(Mutex::new(VirtualClock::new(time))))
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl wasmtime_wasi::HostWallClock for VirtualWasiClock {
+    fn resolution(&self) -> Duration {
+        Duration::from_nanos(1)
+    }
+
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.0.lock().realtime_ns())
+    }
+}
+
+#[cfg(feature = "wasm")]
+impl wasmtime_wasi::HostMonotonicClock for VirtualWasiClock {
+    fn resolution(&self) -> u64 {
+        1
+    }
+
+    fn now(&self) -> u64 {
+        self.0.lock().monotonic_ns()
+    }
+}
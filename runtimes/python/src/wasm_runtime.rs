@@ -1,28 +1,45 @@
 use crate::{PythonExecutionRequest, PythonExecutionResult, PythonRuntimeType, Result};
+use crate::package_image::{PackageImage, GUEST_SITE_PACKAGES};
+use crate::virtual_time::VirtualWasiClock;
 use wasmtime::*;
-use wasmtime_wasi::{WasiCtx, WasiCtxBuilder};
+use wasmtime_wasi::{DirPerms, FilePerms, WasiCtx, WasiCtxBuilder};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use dashmap::DashMap;
 use uuid::Uuid;
 use tokio::time::timeout;
 use metrics::{Counter, Histogram, Gauge};
+use next_rc_shared::{sha256_hex, ProcessLimits, RuntimeEnvironment, RuntimeError, TimingBreakdown};
+
+/// Descriptors inherited for stdin, stdout and stderr.
+const WASI_STDIO_FDS: u64 = 3;
 
 pub struct WasmPythonRuntime {
     engine: Engine,
     python_module: Arc<RwLock<Option<Module>>>,
-    instances: Arc<DashMap<Uuid, Arc<RwLock<WasmInstance>>>>,
+    instances: Arc<DashMap<Uuid, Arc<Mutex<WasmInstance>>>>,
     metrics: Arc<WasmMetrics>,
+    package_image: RwLock<Option<Arc<PackageImage>>>,
+    /// SHA-256 of the interpreter module, set once it is compiled.
+    interpreter_sha256: String,
 }
 
 struct WasmInstance {
-    store: Store<WasiCtx>,
+    store: Store<GuestState>,
     instance: Instance,
     memory_usage: u64,
     created_at: Instant,
 }
 
+/// Data of an instance's store.
+struct GuestState {
+    /// Unread until the interpreter imports WASI.
+    _wasi: WasiCtx,
+    /// Caps the guest's memory at the request's limit.
+    limits: StoreLimits,
+}
+
 struct WasmMetrics {
     execution_count: Counter,
     execution_duration: Histogram,
@@ -42,6 +59,7 @@ impl WasmPythonRuntime {
         config.wasm_multi_memory(true);
         config.wasm_threads(true);
         config.async_support(true);
+        config.consume_fuel(true);
         
         // Enable Cranelift optimizations
         config.cranelift_nan_canonicalization(true);
@@ -62,6 +80,8 @@ impl WasmPythonRuntime {
             python_module: Arc::new(RwLock::new(None)),
             instances: Arc::new(DashMap::new()),
             metrics,
+            package_image: RwLock::new(None),
+            interpreter_sha256: String::new(),
         };
 
         // Pre-compile Python WASM module
@@ -76,6 +96,7 @@ impl WasmPythonRuntime {
         // In a real implementation, this would load a pre-built Python WASM module
         // For now, we'll create a minimal Python interpreter in WASM
         let python_wasm_bytes = self.get_python_wasm_bytes().await?;
+        self.interpreter_sha256 = sha256_hex(&python_wasm_bytes);
         
         let module = Module::new(&self.engine, python_wasm_bytes)?;
         
@@ -93,17 +114,88 @@ impl WasmPythonRuntime {
         // 2. Or compile Python to WASM using py2wasm
         // 3. Or use a cached version
         
-        // For now, we'll create a minimal WASM module with basic Python functionality
-        let wasm_bytes = include_bytes!("../assets/python_minimal.wasm");
+        // For now, we'll create a minimal WASM module with basic Python
+        // functionality. Wasmtime compiles the text format directly.
+        let wasm_bytes = include_bytes!("../assets/python_minimal.wat");
         Ok(wasm_bytes.to_vec())
     }
 
+    /// Engine settings `new` configures, the interpreter module's hash and
+    /// the packages of the mounted image.
+    pub fn environment(&self) -> RuntimeEnvironment {
+        let engine_config = [
+            ("cranelift_opt_level", "speed"),
+            ("cranelift_nan_canonicalization", "true"),
+            ("wasm_simd", "true"),
+            ("wasm_bulk_memory", "true"),
+            ("wasm_reference_types", "true"),
+            ("wasm_multi_value", "true"),
+            ("wasm_multi_memory", "true"),
+            ("wasm_threads", "true"),
+            ("async_support", "true"),
+            ("interpreter_sha256", self.interpreter_sha256.as_str()),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+        
+        RuntimeEnvironment {
+            engine: "wasmtime 26".to_string(),
+            engine_config,
+            packages: self
+                .package_image
+                .read()
+                .as_ref()
+                .map(|image| image.packages().clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Mounts `image` into every instance created from now on, or stops
+    /// mounting one with `None`. Images are refused while the interpreter
+    /// is a placeholder that cannot import anything from them.
+    pub fn set_package_image(&self, image: Option<PackageImage>) -> Result<()> {
+        if image.is_some() {
+            return Err(RuntimeError::InstantiationError(
+                "The WASM Python interpreter cannot import packages yet; package images are not supported".to_string(),
+            ).into());
+        }
+        *self.package_image.write() = None;
+        Ok(())
+    }
+
     pub async fn execute(&self, request: PythonExecutionRequest) -> Result<PythonExecutionResult> {
         let start_time = Instant::now();
         self.metrics.execution_count.increment(1);
 
+        // The placeholder interpreter has no stdin to serve
+        if request.stdin.is_some() {
+            return Err(RuntimeError::InstantiationError(
+                "The WASM Python interpreter cannot read stdin yet; run code reading stdin on PyO3".to_string(),
+            ).into());
+        }
+
+        // Guests can't reach pip, so requirements must already be baked in
+        let package_image = self.package_image.read().clone();
+        let missing: Vec<&str> = request
+            .requirements
+            .iter()
+            .filter(|requirement| !package_image.as_ref().is_some_and(|image| image.provides(requirement)))
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            return Err(RuntimeError::InstantiationError(format!(
+                "Requirements not in the WASM package image: {}", missing.join(", ")
+            )).into());
+        }
+
         // Create WASM instance
-        let instance = self.create_instance(&request).await?;
+        Self::check_process_limits(&request.process_limits, package_image.is_some())?;
+        let instance = self
+            .create_instance(&request, package_image)
+            .await
+            .map_err(|e| RuntimeError::InstantiationError(format!("WASM instance failed to start: {}", e)))?;
+        let instantiated = Instant::now();
         
         // Execute with timeout
         let execution_future = self.execute_with_instance(instance, &request);
@@ -111,6 +203,11 @@ impl WasmPythonRuntime {
             Duration::from_millis(request.timeout_ms),
             execution_future
         ).await??;
+        let timing = TimingBreakdown {
+            instantiate: instantiated - start_time,
+            execute: instantiated.elapsed(),
+            ..Default::default()
+        };
 
         let execution_time = start_time.elapsed().as_millis() as u64;
         metrics::histogram!("python_wasm_execution_duration_ms").record(execution_time as f64);
@@ -120,36 +217,58 @@ impl WasmPythonRuntime {
             success: execution_result.success,
             output: execution_result.output,
             error: execution_result.error,
+            exception: execution_result.exception,
             runtime_used: PythonRuntimeType::Wasm,
             execution_time_ms: execution_time,
             memory_used_mb: execution_result.memory_used_mb,
             exit_code: execution_result.exit_code,
+            logs: Default::default(),
+            fallback: None,
+            environment_fingerprint: None,
+            timing: Some(timing),
         })
     }
 
-    async fn create_instance(&self, request: &PythonExecutionRequest) -> Result<Arc<RwLock<WasmInstance>>> {
+    async fn create_instance(
+        &self,
+        request: &PythonExecutionRequest,
+        package_image: Option<Arc<PackageImage>>,
+    ) -> Result<Arc<Mutex<WasmInstance>>> {
         let instance_id = Uuid::new_v4();
         
         // Create WASI context with proper sandboxing
-        let wasi_ctx = WasiCtxBuilder::new()
-            .inherit_stdio()
-            .inherit_args()
-            .build();
+        let mut wasi_builder = WasiCtxBuilder::new();
+        wasi_builder.inherit_stdio().inherit_args();
+        if let Some(image) = &package_image {
+            wasi_builder.preopened_dir(image.root(), GUEST_SITE_PACKAGES, DirPerms::READ, FilePerms::READ)?;
+            wasi_builder.env("PYTHONPATH", GUEST_SITE_PACKAGES);
+        }
+        if let Some(time) = request.virtual_time {
+            let clock = VirtualWasiClock::new(time);
+            wasi_builder.wall_clock(clock.clone()).monotonic_clock(clock);
+        }
+        let guest = GuestState {
+            _wasi: wasi_builder.build(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size((request.memory_limit_mb * 1024 * 1024) as usize)
+                .trap_on_grow_failure(true)
+                .build(),
+        };
         
-        let mut store = Store::new(&self.engine, wasi_ctx);
+        let mut store = Store::new(&self.engine, guest);
+        store.limiter(|guest| &mut guest.limits);
         
         // Set resource limits
         store.set_fuel(1_000_000)?; // Limit execution fuel
         
         // Get the pre-compiled Python module
-        let python_module = self.python_module.read();
-        let module = python_module.as_ref()
+        let module = self.python_module.read().clone()
             .ok_or("Python WASM module not compiled")?;
         
         // Create instance
-        let instance = Instance::new(&mut store, module, &[])?;
+        let instance = Instance::new_async(&mut store, &module, &[]).await?;
         
-        let wasm_instance = Arc::new(RwLock::new(WasmInstance {
+        let wasm_instance = Arc::new(Mutex::new(WasmInstance {
             store,
             instance,
             memory_usage: 0,
@@ -164,18 +283,14 @@ impl WasmPythonRuntime {
 
     async fn execute_with_instance(
         &self,
-        instance: Arc<RwLock<WasmInstance>>,
+        instance: Arc<Mutex<WasmInstance>>,
         request: &PythonExecutionRequest
     ) -> Result<ExecutionResult> {
         let code = request.code.clone();
-        let memory_limit = request.memory_limit_mb;
         
         // Execute synchronously to avoid threading issues
         let result = (|| -> Result<ExecutionResult> {
-            let mut instance = instance.write();
-            
-            // Set memory limit
-            Self::set_memory_limit(&mut instance.store, memory_limit)?;
+            let _instance = instance.lock();
             
             // Simplified WASM execution - placeholder implementation
             let code_bytes = code.as_bytes();
@@ -188,6 +303,7 @@ impl WasmPythonRuntime {
                     success: true,
                     output,
                     error: None,
+                    exception: None,
                     memory_used_mb: memory_used,
                     exit_code: Some(0),
                 })
@@ -196,6 +312,7 @@ impl WasmPythonRuntime {
                     success: false,
                     output: String::new(),
                     error: Some(output),
+                    exception: None,
                     memory_used_mb: memory_used,
                     exit_code: Some(result),
                 })
@@ -217,12 +334,20 @@ impl WasmPythonRuntime {
         Ok("WASM execution output".to_string())
     }
 
-    fn set_memory_limit(store: &mut Store<WasiCtx>, limit_mb: u64) -> Result<()> {
-        let limit_bytes = limit_mb * 1024 * 1024;
-        
-        // Memory limit setting - placeholder implementation
-        // store.set_wasm_max_size(limit_bytes as usize)?;
-        
+    /// The WASI context gets stdio, the read-only package image if one is
+    /// mounted, and nothing else: no sockets and no wasi-threads. Guests
+    /// therefore hold a fixed set of descriptors and one thread, and only
+    /// limits below that can be exceeded.
+    fn check_process_limits(limits: &ProcessLimits, mounts_image: bool) -> Result<()> {
+        let descriptors = WASI_STDIO_FDS + u64::from(mounts_image);
+        if let Some(max) = limits.max_open_files.filter(|max| *max < descriptors) {
+            return Err(RuntimeError::ResourceLimitExceeded(format!(
+                "open file limit of {} is below the {} descriptors every instance holds", max, descriptors
+            )).into());
+        }
+        if limits.max_processes == Some(0) {
+            return Err(RuntimeError::ResourceLimitExceeded("process limit of 0 leaves no thread to run on".to_string()).into());
+        }
         Ok(())
     }
 
@@ -245,6 +370,7 @@ struct ExecutionResult {
     success: bool,
     output: String,
     error: Option<String>,
+    exception: Option<String>,
     memory_used_mb: u64,
     exit_code: Option<i32>,
 }
@@ -254,4 +380,67 @@ impl Drop for WasmPythonRuntime {
         // Clean up all instances
         self.instances.clear();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TrustLevel;
+    use std::collections::HashMap;
+
+    fn request(code: &str) -> PythonExecutionRequest {
+        PythonExecutionRequest {
+            id: Uuid::new_v4(),
+            code: code.to_string(),
+            runtime_hint: Some(PythonRuntimeType::Wasm),
+            trust_level: TrustLevel::Low,
+            timeout_ms: 10_000,
+            memory_limit_mb: 64,
+            environment: HashMap::new(),
+            requirements: Vec::new(),
+            stdin: None,
+            virtual_time: None,
+            log_limits: Default::default(),
+            process_limits: Default::default(),
+        }
+    }
+
+    /// Message of the instantiation error `result` failed with.
+    fn instantiation_error(result: &Result<PythonExecutionResult>) -> Option<String> {
+        match result.as_ref().err()?.downcast_ref::<RuntimeError>()? {
+            RuntimeError::InstantiationError(message) => Some(message.clone()),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stdin_is_rejected() {
+        let runtime = WasmPythonRuntime::new().await.unwrap();
+        let mut with_stdin = request("print(input())");
+        with_stdin.stdin = Some("hello\n".to_string());
+        let error = instantiation_error(&runtime.execute(with_stdin).await).unwrap();
+        assert!(error.contains("cannot read stdin"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_package_images_are_rejected() {
+        let runtime = WasmPythonRuntime::new().await.unwrap();
+        let root = std::env::temp_dir().join(format!("next-rc-image-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("next-rc-packages.json"), r#"{"requirements": []}"#).unwrap();
+        let image = PackageImage::open(&root).unwrap();
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(runtime.set_package_image(Some(image)).is_err());
+        assert!(runtime.set_package_image(None).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_memory_limit_below_the_interpreter_fails_to_start() {
+        let runtime = WasmPythonRuntime::new().await.unwrap();
+        // The interpreter starts with 16 pages, 1 MiB
+        let mut limited = request("print(1)");
+        limited.memory_limit_mb = 0;
+        let error = instantiation_error(&runtime.execute(limited).await).unwrap();
+        assert!(error.contains("growing memory to 1048576 bytes"), "{}", error);
+    }
+}
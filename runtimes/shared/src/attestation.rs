@@ -0,0 +1,271 @@
+use anyhow::{anyhow, Result};
+use parking_lot::Mutex;
+use ring::rand::SystemRandom;
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::{ExecutionResult, RuntimeType};
+
+/// Version stamped into attestations as the runtime version.
+pub const RUNTIME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// What a node vouches for when it signs a result.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttestationClaims {
+    /// SHA-256 of the code as the runtime compiled it, after transforms.
+    pub code_sha256: String,
+    /// SHA-256 of the inputs and sandbox configuration the code ran with.
+    pub input_sha256: String,
+    /// SHA-256 of the result, e.g. `ExecutionResult::digest`.
+    pub output_sha256: String,
+    pub runtime: RuntimeType,
+    pub runtime_version: String,
+    /// Milliseconds since the Unix epoch.
+    pub signed_at: u64,
+}
+
+/// Ed25519 signature by a node over `claims`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    pub claims: AttestationClaims,
+    /// Hex-encoded public key of the signing node.
+    pub public_key: String,
+    /// Hex-encoded signature over the JSON encoding of `claims`.
+    pub signature: String,
+}
+
+impl Attestation {
+    /// Checks that the attestation was signed by `public_key`.
+    pub fn verify(&self, public_key: &[u8]) -> Result<()> {
+        if hex::decode(&self.public_key)? != public_key {
+            return Err(anyhow!("Attestation was signed by a different key"));
+        }
+        let signature = hex::decode(&self.signature)?;
+        UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(&serde_json::to_vec(&self.claims)?, &signature)
+            .map_err(|_| anyhow!("Attestation signature mismatch"))
+    }
+}
+
+/// A node's signing key.
+pub struct NodeKey {
+    key_pair: Ed25519KeyPair,
+}
+
+impl NodeKey {
+    /// Generates a key, returning it with its PKCS#8 encoding for storage.
+    pub fn generate() -> Result<(Self, Vec<u8>)> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+            .map_err(|_| anyhow!("Failed to generate node key"))?;
+        let key = Self::from_pkcs8(pkcs8.as_ref())?;
+        Ok((key, pkcs8.as_ref().to_vec()))
+    }
+
+    pub fn from_pkcs8(pkcs8: &[u8]) -> Result<Self> {
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8)
+            .map_err(|e| anyhow!("Invalid node key: {}", e))?;
+        Ok(Self { key_pair })
+    }
+
+    pub fn public_key(&self) -> &[u8] {
+        self.key_pair.public_key().as_ref()
+    }
+
+    /// Signs claims about a run; `signed_at` is set to the current time.
+    pub fn attest(
+        &self,
+        code_sha256: String,
+        input_sha256: String,
+        output_sha256: String,
+        runtime: RuntimeType,
+    ) -> Result<Attestation> {
+        let claims = AttestationClaims {
+            code_sha256,
+            input_sha256,
+            output_sha256,
+            runtime,
+            runtime_version: RUNTIME_VERSION.to_string(),
+            signed_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_millis() as u64)
+                .unwrap_or(0),
+        };
+        let signature = self.key_pair.sign(&serde_json::to_vec(&claims)?);
+        Ok(Attestation {
+            claims,
+            public_key: hex::encode(self.public_key()),
+            signature: hex::encode(signature.as_ref()),
+        })
+    }
+}
+
+impl std::fmt::Debug for NodeKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NodeKey")
+            .field("public_key", &hex::encode(self.public_key()))
+            .finish()
+    }
+}
+
+impl ExecutionResult {
+    /// SHA-256 over the outcome, output and error, ignoring timings.
+    pub fn digest(&self) -> String {
+        sha256_hex(&serde_json::to_vec(&(self.success, &self.output, &self.error)).unwrap_or_default())
+    }
+
+    /// Checks that the result carries an attestation signed by `public_key`
+    /// that covers this result's contents.
+    pub fn verify_attestation(&self, public_key: &[u8]) -> Result<()> {
+        let attestation = self
+            .attestation
+            .as_ref()
+            .ok_or_else(|| anyhow!("Result is not attested"))?;
+        attestation.verify(public_key)?;
+        if attestation.claims.output_sha256 != self.digest() {
+            return Err(anyhow!("Result does not match its attestation"));
+        }
+        Ok(())
+    }
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Hashes everything read through it, for code that is streamed to a
+/// runtime rather than buffered.
+pub struct DigestReader<R> {
+    inner: R,
+    hasher: Arc<Mutex<Sha256>>,
+}
+
+impl<R> DigestReader<R> {
+    /// Wraps `inner`; the returned handle yields the hex digest of whatever
+    /// was read so far.
+    pub fn new(inner: R) -> (Self, DigestHandle) {
+        let hasher = Arc::new(Mutex::new(Sha256::new()));
+        let handle = DigestHandle(hasher.clone());
+        (Self { inner, hasher }, handle)
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for DigestReader<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        let before = buf.filled().len();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(())) = poll {
+            this.hasher.lock().update(&buf.filled()[before..]);
+        }
+        poll
+    }
+}
+
+pub struct DigestHandle(Arc<Mutex<Sha256>>);
+
+impl DigestHandle {
+    pub fn hex(&self) -> String {
+        hex::encode(self.0.lock().clone().finalize())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tokio::io::AsyncReadExt;
+
+    fn result(output: &[u8]) -> ExecutionResult {
+        ExecutionResult {
+            success: true,
+            output: Some(output.to_vec()),
+            error: None,
+            execution_time: Duration::from_millis(3),
+            memory_used: 1024,
+            logs: Default::default(),
+            attestation: None,
+            environment_fingerprint: None,
+            timing: None,
+        }
+    }
+
+    fn attested(key: &NodeKey, output: &[u8]) -> ExecutionResult {
+        let mut result = result(output);
+        result.attestation = Some(
+            key.attest(sha256_hex(b"code"), sha256_hex(b"input"), result.digest(), RuntimeType::Wasm)
+                .unwrap(),
+        );
+        result
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let (key, pkcs8) = NodeKey::generate().unwrap();
+        let result = attested(&key, b"42");
+
+        result.verify_attestation(key.public_key()).unwrap();
+        let claims = &result.attestation.as_ref().unwrap().claims;
+        assert_eq!(claims.runtime_version, RUNTIME_VERSION);
+        assert_eq!(claims.code_sha256, sha256_hex(b"code"));
+
+        // The stored key signs the same way after a restart
+        let restored = NodeKey::from_pkcs8(&pkcs8).unwrap();
+        assert_eq!(restored.public_key(), key.public_key());
+        result.verify_attestation(restored.public_key()).unwrap();
+    }
+
+    #[test]
+    fn test_tampered_output_is_rejected() {
+        let (key, _) = NodeKey::generate().unwrap();
+
+        let mut result = attested(&key, b"42");
+        result.output = Some(b"43".to_vec());
+        let error = result.verify_attestation(key.public_key()).unwrap_err();
+        assert_eq!(error.to_string(), "Result does not match its attestation");
+
+        // Rewriting the claims to match breaks the signature instead
+        let digest = result.digest();
+        result.attestation.as_mut().unwrap().claims.output_sha256 = digest;
+        let error = result.verify_attestation(key.public_key()).unwrap_err();
+        assert_eq!(error.to_string(), "Attestation signature mismatch");
+    }
+
+    #[test]
+    fn test_other_keys_are_rejected() {
+        let (key, _) = NodeKey::generate().unwrap();
+        let (other, _) = NodeKey::generate().unwrap();
+
+        let result = attested(&key, b"42");
+        let error = result.verify_attestation(other.public_key()).unwrap_err();
+        assert_eq!(error.to_string(), "Attestation was signed by a different key");
+
+        let error = self::result(b"42").verify_attestation(key.public_key()).unwrap_err();
+        assert_eq!(error.to_string(), "Result is not attested");
+    }
+
+    #[test]
+    fn test_digest_ignores_timings() {
+        let mut slower = result(b"42");
+        slower.execution_time = Duration::from_secs(1);
+        slower.memory_used = 0;
+        assert_eq!(slower.digest(), result(b"42").digest());
+        assert_ne!(result(b"43").digest(), result(b"42").digest());
+    }
+
+    #[tokio::test]
+    async fn test_digest_reader() {
+        let code = vec![7u8; 100_000];
+        let (mut reader, handle) = DigestReader::new(&code[..]);
+
+        let mut read = Vec::new();
+        reader.read_to_end(&mut read).await.unwrap();
+        assert_eq!(read, code);
+        assert_eq!(handle.hex(), sha256_hex(&code));
+    }
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Virtual wall-clock settings for a guest. Sleeps return immediately and
+/// move the clock forward instead, so executions are reproducible and sleep
+/// loops can be fast-forwarded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VirtualTime {
+    /// Unix time, in milliseconds, the guest sees when it starts.
+    pub start_unix_ms: u64,
+    /// Nanoseconds every clock read advances the clock by, so busy-wait loops
+    /// still terminate. Zero freezes the clock between sleeps.
+    #[serde(default)]
+    pub tick_ns: u64,
+}
+
+impl VirtualTime {
+    pub fn starting_at(start_unix_ms: u64) -> Self {
+        Self {
+            start_unix_ms,
+            tick_ns: 0,
+        }
+    }
+}
+
+/// Per-execution clock driven by a `VirtualTime`.
+#[derive(Debug, Clone)]
+pub struct VirtualClock {
+    start_unix_ns: u64,
+    tick_ns: u64,
+    elapsed_ns: u64,
+}
+
+impl VirtualClock {
+    pub fn new(time: VirtualTime) -> Self {
+        Self {
+            start_unix_ns: time.start_unix_ms.saturating_mul(1_000_000),
+            tick_ns: time.tick_ns,
+            elapsed_ns: 0,
+        }
+    }
+
+    /// Nanoseconds since the execution started.
+    pub fn monotonic_ns(&mut self) -> u64 {
+        self.elapsed_ns = self.elapsed_ns.saturating_add(self.tick_ns);
+        self.elapsed_ns
+    }
+
+    /// Nanoseconds since the Unix epoch.
+    pub fn realtime_ns(&mut self) -> u64 {
+        self.start_unix_ns.saturating_add(self.monotonic_ns())
+    }
+
+    pub fn sleep(&mut self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        self.elapsed_ns = self.elapsed_ns.saturating_add(nanos);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_nanos(self.elapsed_ns)
+    }
+}
@@ -0,0 +1,116 @@
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::RuntimeError;
+
+/// Leads an Arrow IPC file.
+const ARROW_FILE_MAGIC: &[u8] = b"ARROW1";
+/// Leads every message of an Arrow IPC stream.
+const ARROW_CONTINUATION: &[u8] = &[0xff, 0xff, 0xff, 0xff];
+
+/// Form the caller wants an execution's output in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum OutputEncoding {
+    /// The bytes the guest wrote, unchanged.
+    #[default]
+    Bytes,
+    /// Text; output that isn't valid UTF-8 is rejected rather than mangled.
+    Utf8,
+    /// The bytes as standard, padded base64 text.
+    Base64,
+    /// A single JSON document, re-serialized compactly.
+    Json,
+    /// An Arrow IPC file or stream, passed through once its framing checks out.
+    Arrow,
+}
+
+impl OutputEncoding {
+    /// Converts `output` into this encoding, or explains why it can't be.
+    pub fn encode(&self, output: Vec<u8>) -> Result<Vec<u8>, RuntimeError> {
+        match self {
+            OutputEncoding::Bytes => Ok(output),
+            OutputEncoding::Utf8 => match String::from_utf8(output) {
+                Ok(text) => Ok(text.into_bytes()),
+                Err(e) => Err(RuntimeError::EncodingError(format!(
+                    "output is not valid UTF-8 ({}); request base64 or bytes instead",
+                    e.utf8_error()
+                ))),
+            },
+            OutputEncoding::Base64 => Ok(base64::engine::general_purpose::STANDARD.encode(output).into_bytes()),
+            OutputEncoding::Json => serde_json::from_slice::<serde_json::Value>(&output)
+                .and_then(|value| serde_json::to_vec(&value))
+                .map_err(|e| RuntimeError::EncodingError(format!("output is not a JSON document: {}", e))),
+            OutputEncoding::Arrow if output.starts_with(ARROW_FILE_MAGIC) || output.starts_with(ARROW_CONTINUATION) => {
+                Ok(output)
+            }
+            OutputEncoding::Arrow => Err(RuntimeError::EncodingError(
+                "output is not an Arrow IPC file or stream".to_string(),
+            )),
+        }
+    }
+
+    /// Whether encoded output is text a caller can hold as a string.
+    pub fn is_text(&self) -> bool {
+        matches!(self, OutputEncoding::Utf8 | OutputEncoding::Base64 | OutputEncoding::Json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoding_error(encoding: OutputEncoding, output: &[u8]) -> String {
+        match encoding.encode(output.to_vec()) {
+            Err(RuntimeError::EncodingError(message)) => message,
+            other => panic!("expected an encoding error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_bytes() {
+        let output = vec![0xff, 0x00, b'a'];
+        assert_eq!(OutputEncoding::Bytes.encode(output.clone()).unwrap(), output);
+        assert_eq!(OutputEncoding::default(), OutputEncoding::Bytes);
+    }
+
+    #[test]
+    fn test_utf8() {
+        assert_eq!(OutputEncoding::Utf8.encode("héllo".into()).unwrap(), "héllo".as_bytes());
+        assert!(encoding_error(OutputEncoding::Utf8, &[b'a', 0xff]).starts_with("output is not valid UTF-8"));
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(OutputEncoding::Base64.encode(vec![0xff, 0x00, b'a']).unwrap(), b"/wBh");
+        assert_eq!(OutputEncoding::Base64.encode(b"ab".to_vec()).unwrap(), b"YWI=");
+        assert_eq!(OutputEncoding::Base64.encode(Vec::new()).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_json() {
+        let output = b"{ \"answer\" : [ 4, 2 ] }\n".to_vec();
+        assert_eq!(OutputEncoding::Json.encode(output).unwrap(), br#"{"answer":[4,2]}"#);
+        assert!(encoding_error(OutputEncoding::Json, b"{\"answer\":").starts_with("output is not a JSON document"));
+        assert!(encoding_error(OutputEncoding::Json, b"1 2").starts_with("output is not a JSON document"));
+    }
+
+    #[test]
+    fn test_arrow() {
+        let file = b"ARROW1\0\0rest of the file".to_vec();
+        assert_eq!(OutputEncoding::Arrow.encode(file.clone()).unwrap(), file);
+
+        let stream = [ARROW_CONTINUATION, b"\x10\0\0\0message"].concat();
+        assert_eq!(OutputEncoding::Arrow.encode(stream.clone()).unwrap(), stream);
+
+        assert_eq!(encoding_error(OutputEncoding::Arrow, b"PAR1"), "output is not an Arrow IPC file or stream");
+    }
+
+    #[test]
+    fn test_text_encodings() {
+        assert!(OutputEncoding::Utf8.is_text());
+        assert!(OutputEncoding::Base64.is_text());
+        assert!(OutputEncoding::Json.is_text());
+        assert!(!OutputEncoding::Bytes.is_text());
+        assert!(!OutputEncoding::Arrow.is_text());
+    }
+}
@@ -1,5 +1,8 @@
+use std::time::Duration;
 use thiserror::Error;
 
+use crate::JobPhase;
+
 #[derive(Error, Debug)]
 pub enum RuntimeError {
     #[error("Compilation failed: {0}")]
@@ -20,6 +23,9 @@ pub enum RuntimeError {
     #[error("Timeout exceeded")]
     TimeoutError,
     
+    #[error("Timed out in {phase} phase after {budget:?}")]
+    PhaseTimeout { phase: JobPhase, budget: Duration },
+    
     #[error("Module not found: {0}")]
     ModuleNotFound(String),
     
@@ -32,6 +38,9 @@ pub enum RuntimeError {
     #[error("Resource limit exceeded: {0}")]
     ResourceLimitExceeded(String),
     
+    #[error("Output encoding failed: {0}")]
+    EncodingError(String),
+    
     #[error("Internal error: {0}")]
     InternalError(String),
 }
\ No newline at end of file
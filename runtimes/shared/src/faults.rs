@@ -0,0 +1,144 @@
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{JobPhase, RuntimeError};
+
+/// Faults to inject for resilience testing. Rates are probabilities from
+/// 0.0 (never) to 1.0 (every operation).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FaultConfig {
+    /// Compilations failed with `RuntimeError::CompilationError`.
+    #[serde(default)]
+    pub compile_failure_rate: f64,
+    /// Instantiations failed as if the instance pool were exhausted, with
+    /// `RuntimeError::MemoryError`.
+    #[serde(default)]
+    pub pool_exhaustion_rate: f64,
+    /// Executions whose sandbox fails to activate, with
+    /// `RuntimeError::SecurityError`.
+    #[serde(default)]
+    pub sandbox_failure_rate: f64,
+    #[serde(default)]
+    pub latency: Option<LatencyFault>,
+    /// Seeds the generator so a run's faults can be replayed.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+/// Delay added at the start of job phases, drawn uniformly from `min..=max`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyFault {
+    pub rate: f64,
+    pub min: Duration,
+    pub max: Duration,
+    /// Phases delayed; every phase when empty.
+    #[serde(default)]
+    pub phases: Vec<JobPhase>,
+}
+
+/// Faults injected so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FaultCounts {
+    pub compile_failures: u64,
+    pub pool_exhaustions: u64,
+    pub sandbox_failures: u64,
+    pub delays: u64,
+}
+
+/// Decides which operations fail or stall. Share one injector between the
+/// orchestrator and its runtimes; `set_config` changes the faults of a
+/// running system.
+pub struct FaultInjector {
+    state: Mutex<FaultState>,
+}
+
+struct FaultState {
+    config: FaultConfig,
+    rng: fastrand::Rng,
+    counts: FaultCounts,
+}
+
+impl FaultInjector {
+    pub fn new(config: FaultConfig) -> Self {
+        Self {
+            state: Mutex::new(FaultState {
+                rng: Self::rng(&config),
+                config,
+                counts: FaultCounts::default(),
+            }),
+        }
+    }
+
+    fn rng(config: &FaultConfig) -> fastrand::Rng {
+        config.seed.map_or_else(fastrand::Rng::new, fastrand::Rng::with_seed)
+    }
+
+    /// Replaces the faults to inject, reseeding the generator if the new
+    /// config has a seed.
+    pub fn set_config(&self, config: FaultConfig) {
+        let mut state = self.state.lock();
+        state.rng = Self::rng(&config);
+        state.config = config;
+    }
+
+    pub fn config(&self) -> FaultConfig {
+        self.state.lock().config.clone()
+    }
+
+    pub fn counts(&self) -> FaultCounts {
+        self.state.lock().counts
+    }
+
+    /// Sleeps for any delay due at the start of `phase`, then returns the
+    /// fault due there, if any. Queue waits are only ever delayed.
+    pub async fn inject(&self, phase: JobPhase) -> Result<(), RuntimeError> {
+        let (delay, fault) = self.roll(phase);
+        if let Some(delay) = delay {
+            tokio::time::sleep(delay).await;
+        }
+        fault.map_or(Ok(()), Err)
+    }
+
+    fn roll(&self, phase: JobPhase) -> (Option<Duration>, Option<RuntimeError>) {
+        let state = &mut *self.state.lock();
+        let FaultState { config, rng, counts } = state;
+
+        let delay = config
+            .latency
+            .as_ref()
+            .filter(|latency| latency.phases.is_empty() || latency.phases.contains(&phase))
+            .filter(|latency| rng.f64() < latency.rate)
+            .map(|latency| {
+                counts.delays += 1;
+                let min = latency.min.as_nanos() as u64;
+                let max = (latency.max.as_nanos() as u64).max(min);
+                Duration::from_nanos(rng.u64(min..=max))
+            });
+
+        let (rate, count, fault) = match phase {
+            JobPhase::QueueWait => return (delay, None),
+            JobPhase::Compile => (
+                config.compile_failure_rate,
+                &mut counts.compile_failures,
+                RuntimeError::CompilationError("Injected fault".to_string()),
+            ),
+            JobPhase::Instantiate => (
+                config.pool_exhaustion_rate,
+                &mut counts.pool_exhaustions,
+                RuntimeError::MemoryError("Injected fault: no available memory slots".to_string()),
+            ),
+            JobPhase::Execute => (
+                config.sandbox_failure_rate,
+                &mut counts.sandbox_failures,
+                RuntimeError::SecurityError("Injected fault: sandbox activation failed".to_string()),
+            ),
+        };
+        let fault = (rng.f64() < rate).then(|| {
+            *count += 1;
+            fault
+        });
+
+        (delay, fault)
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::{sha256_hex, ExecutionConfig, RuntimeType, RUNTIME_VERSION};
+
+/// Engine details a runtime reports for the fingerprints of its executions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeEnvironment {
+    /// Engine name and version, such as `wasmtime 41`.
+    pub engine: String,
+    /// Settings the engine was built with, by name.
+    pub engine_config: BTreeMap<String, String>,
+    /// Versions of the packages available to guests, by package name.
+    pub packages: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModuleDigest {
+    /// Name within a bundle, or `None` for a lone module.
+    pub name: Option<String>,
+    pub sha256: String,
+}
+
+/// What an execution ran with, recorded so it can be re-run under
+/// identical conditions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvironmentFingerprint {
+    pub runtime: RuntimeType,
+    pub runtime_version: String,
+    pub engine: String,
+    pub engine_config: BTreeMap<String, String>,
+    pub packages: BTreeMap<String, String>,
+    /// See `ExecutionConfig::security_policy_sha256`.
+    pub security_policy_sha256: String,
+    /// Code the runtime compiled, in bundle order; empty when unknown.
+    pub modules: Vec<ModuleDigest>,
+}
+
+impl EnvironmentFingerprint {
+    pub fn new(
+        runtime: RuntimeType,
+        environment: RuntimeEnvironment,
+        security_policy_sha256: String,
+        modules: Vec<ModuleDigest>,
+    ) -> Self {
+        Self {
+            runtime,
+            runtime_version: RUNTIME_VERSION.to_string(),
+            engine: environment.engine,
+            engine_config: environment.engine_config,
+            packages: environment.packages,
+            security_policy_sha256,
+            modules,
+        }
+    }
+
+    /// SHA-256 over the whole fingerprint; executions share it exactly when
+    /// they ran under identical conditions.
+    pub fn digest(&self) -> String {
+        sha256_hex(&serde_json::to_vec(self).unwrap_or_default())
+    }
+}
+
+impl ExecutionConfig {
+    /// SHA-256 of the trust level, capabilities and limits the execution is
+    /// confined by. Tenant, tags and logging settings don't contribute.
+    pub fn security_policy_sha256(&self) -> String {
+        let mut capabilities: Vec<String> = self
+            .permissions
+            .capabilities
+            .iter()
+            .map(|capability| format!("{:?}", capability))
+            .collect();
+        capabilities.sort();
+
+        let policy = (
+            self.permissions.trust_level,
+            capabilities,
+            self.timeout,
+            self.memory_limit,
+            self.process_limits,
+        );
+        sha256_hex(&serde_json::to_vec(&policy).unwrap_or_default())
+    }
+}
@@ -0,0 +1,148 @@
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{InstanceId, ModuleId};
+
+/// An ID string that isn't a UUID in any of the accepted forms.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum IdError {
+    #[error("Invalid module ID {id:?}: {reason}")]
+    InvalidModuleId { id: String, reason: String },
+
+    #[error("Invalid instance ID {id:?}: {reason}")]
+    InvalidInstanceId { id: String, reason: String },
+}
+
+impl IdError {
+    /// Stable code for callers that branch on the error rather than its
+    /// message.
+    pub fn code(&self) -> &'static str {
+        match self {
+            IdError::InvalidModuleId { .. } => "INVALID_MODULE_ID",
+            IdError::InvalidInstanceId { .. } => "INVALID_INSTANCE_ID",
+        }
+    }
+}
+
+impl ModuleId {
+    /// The UUID as 32 hex digits without hyphens, which `parse` accepts
+    /// back.
+    pub fn short(&self) -> String {
+        self.0.simple().to_string()
+    }
+}
+
+impl InstanceId {
+    /// The UUID as 32 hex digits without hyphens, which `parse` accepts
+    /// back.
+    pub fn short(&self) -> String {
+        self.0.simple().to_string()
+    }
+}
+
+impl fmt::Display for ModuleId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.hyphenated().fmt(f)
+    }
+}
+
+impl fmt::Display for InstanceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.hyphenated().fmt(f)
+    }
+}
+
+/// Accepts the hyphenated, short, braced and URN forms of the UUID.
+impl FromStr for ModuleId {
+    type Err = IdError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(id.trim()).map(Self).map_err(|e| IdError::InvalidModuleId {
+            id: id.to_string(),
+            reason: e.to_string(),
+        })
+    }
+}
+
+/// Accepts the hyphenated, short, braced and URN forms of the UUID.
+impl FromStr for InstanceId {
+    type Err = IdError;
+
+    fn from_str(id: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(id.trim()).map(Self).map_err(|e| IdError::InvalidInstanceId {
+            id: id.to_string(),
+            reason: e.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+
+    #[test]
+    fn test_accepted_forms() {
+        let expected = ModuleId(Uuid::parse_str(ID).unwrap());
+        for form in [
+            ID,
+            "67e5504410b1426f9247bb680e5fe0c8",
+            "{67e55044-10b1-426f-9247-bb680e5fe0c8}",
+            "urn:uuid:67e55044-10b1-426f-9247-bb680e5fe0c8",
+            "  67E55044-10B1-426F-9247-BB680E5FE0C8\n",
+        ] {
+            assert_eq!(form.parse::<ModuleId>().unwrap(), expected, "{:?}", form);
+            assert_eq!(form.parse::<InstanceId>().unwrap().0, expected.0, "{:?}", form);
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let module_id = ModuleId(Uuid::new_v4());
+        assert_eq!(module_id.to_string().parse::<ModuleId>().unwrap(), module_id);
+        assert_eq!(module_id.short().parse::<ModuleId>().unwrap(), module_id);
+
+        let instance_id = InstanceId(Uuid::new_v4());
+        assert_eq!(instance_id.to_string().parse::<InstanceId>().unwrap(), instance_id);
+        assert_eq!(instance_id.short().parse::<InstanceId>().unwrap(), instance_id);
+    }
+
+    #[test]
+    fn test_formats() {
+        let module_id: ModuleId = ID.parse().unwrap();
+        assert_eq!(module_id.to_string(), ID);
+        assert_eq!(module_id.short(), "67e5504410b1426f9247bb680e5fe0c8");
+
+        let instance_id: InstanceId = ID.parse().unwrap();
+        assert_eq!(instance_id.to_string(), ID);
+        assert_eq!(instance_id.short(), "67e5504410b1426f9247bb680e5fe0c8");
+    }
+
+    #[test]
+    fn test_malformed_ids() {
+        for id in [
+            "",
+            "not-a-uuid",
+            "67e55044-10b1-426f-9247",
+            "67e55044-10b1-426f-9247-bb680e5fe0c8ff",
+            "67e55044-10b1-426f-9247-bb680e5fe0cz",
+        ] {
+            let module_error = id.parse::<ModuleId>().unwrap_err();
+            assert_eq!(module_error.code(), "INVALID_MODULE_ID");
+            assert!(matches!(&module_error, IdError::InvalidModuleId { id: bad, .. } if bad == id));
+
+            let instance_error = id.parse::<InstanceId>().unwrap_err();
+            assert_eq!(instance_error.code(), "INVALID_INSTANCE_ID");
+            assert!(matches!(&instance_error, IdError::InvalidInstanceId { id: bad, .. } if bad == id));
+        }
+    }
+
+    #[test]
+    fn test_error_messages() {
+        let error = "nope".parse::<InstanceId>().unwrap_err();
+        assert!(error.to_string().starts_with("Invalid instance ID \"nope\": "));
+    }
+}
@@ -1,16 +1,61 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
 use uuid::Uuid;
 
+pub mod attestation;
+pub mod clock;
+pub mod encoding;
 pub mod errors;
+#[cfg(feature = "fault-injection")]
+pub mod faults;
+pub mod fingerprint;
+pub mod ids;
+pub mod limits;
+pub mod load;
+pub mod logging;
 pub mod memory;
+pub mod profiles;
+pub mod redaction;
+pub mod reuse;
+pub mod scaling;
 pub mod security;
+pub mod source_map;
+pub mod timeouts;
+pub mod timing;
+pub mod toolchain;
+pub mod transform;
+pub mod validation;
+pub mod vector;
+pub mod webhooks;
 
+pub use attestation::*;
+pub use clock::*;
+pub use encoding::*;
 pub use errors::*;
+#[cfg(feature = "fault-injection")]
+pub use faults::*;
+pub use fingerprint::*;
+pub use ids::*;
+pub use limits::*;
+pub use load::*;
+pub use logging::*;
 pub use memory::*;
+pub use profiles::*;
+pub use redaction::*;
+pub use reuse::*;
+pub use scaling::*;
 pub use security::*;
+pub use source_map::*;
+pub use timeouts::*;
+pub use timing::*;
+pub use toolchain::*;
+pub use transform::*;
+pub use validation::*;
+pub use vector::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct ModuleId(pub Uuid);
@@ -23,6 +68,56 @@ pub struct ExecutionConfig {
     pub timeout: Duration,
     pub memory_limit: usize,
     pub permissions: Permissions,
+    /// Tenant the execution is billed and audited against.
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+    /// Caller-supplied labels recorded alongside the execution.
+    #[serde(default)]
+    pub tags: HashMap<String, String>,
+    /// Runs the guest against a virtual clock instead of the host's.
+    #[serde(default)]
+    pub virtual_time: Option<VirtualTime>,
+    /// Bounds on the log records the guest may emit.
+    #[serde(default)]
+    pub log_limits: LogLimits,
+    /// Caps on the descriptors and processes the guest may hold.
+    #[serde(default)]
+    pub process_limits: ProcessLimits,
+    /// Bytes handed to the guest: packet data for eBPF programs, stdin for
+    /// Python and `env.read_input` for WASM modules.
+    #[serde(default)]
+    pub input: Option<Vec<u8>>,
+    /// Names of state the execution wants warm, such as a session or a
+    /// dataset, most specific first. Executions sharing a key are routed to
+    /// the same instance while it lives.
+    #[serde(default)]
+    pub affinity_keys: Vec<String>,
+    /// Form the output is returned in.
+    #[serde(default)]
+    pub output_encoding: OutputEncoding,
+    /// How much guest logging and runtime tracing is returned with the
+    /// result.
+    #[serde(default)]
+    pub trace_verbosity: TraceVerbosity,
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30),
+            memory_limit: 64 * 1024 * 1024,
+            permissions: Permissions::new(TrustLevel::default()),
+            tenant_id: None,
+            tags: HashMap::new(),
+            virtual_time: None,
+            log_limits: LogLimits::default(),
+            process_limits: ProcessLimits::default(),
+            input: None,
+            affinity_keys: Vec::new(),
+            output_encoding: OutputEncoding::default(),
+            trace_verbosity: TraceVerbosity::default(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,6 +127,30 @@ pub struct ExecutionResult {
     pub error: Option<String>,
     pub execution_time: Duration,
     pub memory_used: usize,
+    /// Records the guest logged through the host logging API.
+    #[serde(default)]
+    pub logs: ExecutionLogs,
+    /// Signature by the executing node, when result signing is enabled.
+    #[serde(default)]
+    pub attestation: Option<Attestation>,
+    /// Runtime, engine, policy and code the execution ran with.
+    #[serde(default)]
+    pub environment_fingerprint: Option<EnvironmentFingerprint>,
+    /// Time spent in each phase of the request, when the runtime reports it.
+    #[serde(default)]
+    pub timing: Option<TimingBreakdown>,
+}
+
+/// Outcome of pre-provisioning warm instances ahead of expected traffic.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvisionReport {
+    pub requested: usize,
+    /// Instances created by this call.
+    pub ready: usize,
+    /// Idle instances now waiting in the pool, including earlier ones.
+    pub pooled: usize,
+    pub failures: Vec<String>,
+    pub elapsed: Duration,
 }
 
 #[async_trait]
@@ -40,9 +159,39 @@ pub trait Runtime: Send + Sync {
     async fn instantiate(&self, module_id: ModuleId) -> Result<InstanceId>;
     async fn execute(&self, instance_id: InstanceId, config: ExecutionConfig) -> Result<ExecutionResult>;
     async fn destroy(&self, instance_id: InstanceId) -> Result<()>;
+    /// Runs compilation and verification checks without caching or executing.
+    async fn validate(&self, code: &[u8], language: Language, trust_level: TrustLevel) -> Result<ValidationReport>;
+    /// Compiles code read from `reader`. Runtimes that can't compile
+    /// incrementally buffer the whole stream and defer to `compile`.
+    async fn compile_stream(&self, mut reader: Box<dyn AsyncRead + Send + Unpin>, language: Language) -> Result<ModuleId> {
+        let mut code = Vec::new();
+        reader.read_to_end(&mut code).await?;
+        self.compile(&code, language).await
+    }
+    /// Engine details recorded in the fingerprint of every execution.
+    fn environment(&self) -> RuntimeEnvironment {
+        RuntimeEnvironment::default()
+    }
+    /// Languages and toolchains available to `compile`, probed when the
+    /// runtime started.
+    fn capabilities(&self) -> RuntimeCapabilities {
+        RuntimeCapabilities::default()
+    }
+    /// Executions running and waiting on this runtime.
+    fn current_load(&self) -> LoadReport {
+        LoadReport::default()
+    }
+    /// Compiles a main module (the first entry) whose imports resolve against
+    /// the other named modules in the bundle.
+    async fn compile_bundle(&self, modules: Vec<(String, Vec<u8>)>, language: Language) -> Result<ModuleId> {
+        match modules.as_slice() {
+            [(_, code)] => self.compile(code, language).await,
+            _ => Err(anyhow::anyhow!("Module bundles are not supported by this runtime")),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum Language {
     Rust,
     JavaScript,
@@ -52,14 +201,17 @@ pub enum Language {
     C,
     Cpp,
     Wasm,
+    /// Raw eBPF bytecode
+    Ebpf,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum RuntimeType {
     Wasm,
     Ebpf,
     V8Isolate,
     Firecracker,
+    Python,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
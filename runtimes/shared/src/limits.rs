@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+use crate::RuntimeError;
+
+/// Guest errors raised when a descriptor table is full.
+const FILE_LIMIT_ERRORS: &[&str] = &["EMFILE", "Too many open files"];
+
+/// Guest errors raised when a process or thread can't be created.
+const PROCESS_LIMIT_ERRORS: &[&str] = &["can't start new thread", "Resource temporarily unavailable"];
+
+/// Caps on the descriptors and processes a guest may hold at once. `None`
+/// leaves the backend's own limit in place.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProcessLimits {
+    pub max_open_files: Option<u64>,
+    /// Processes and threads, counted together as the kernel does.
+    pub max_processes: Option<u64>,
+}
+
+impl ProcessLimits {
+    pub fn is_unlimited(&self) -> bool {
+        self.max_open_files.is_none() && self.max_processes.is_none()
+    }
+
+    /// The limit a guest failure with message `error` ran into, so callers
+    /// see `ResourceLimitExceeded` instead of a bare EMFILE or EAGAIN.
+    pub fn violation(&self, error: &str) -> Option<RuntimeError> {
+        if let Some(max) = self.max_open_files {
+            if FILE_LIMIT_ERRORS.iter().any(|pattern| error.contains(pattern)) {
+                return Some(RuntimeError::ResourceLimitExceeded(format!("open file limit of {} reached", max)));
+            }
+        }
+        if let Some(max) = self.max_processes {
+            if PROCESS_LIMIT_ERRORS.iter().any(|pattern| error.contains(pattern)) {
+                return Some(RuntimeError::ResourceLimitExceeded(format!("process limit of {} reached", max)));
+            }
+        }
+        None
+    }
+}
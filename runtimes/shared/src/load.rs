@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// How busy a runtime is right now, so embedders can shed load before
+/// queueing more work onto it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LoadReport {
+    /// Executions running now.
+    pub in_flight: usize,
+    /// Executions waiting for a slot.
+    pub queued: usize,
+    /// Slots free right now; `None` when executions aren't limited.
+    pub available_slots: Option<usize>,
+    /// Executions allowed to run at once; `None` when unlimited.
+    pub capacity: Option<usize>,
+    /// How long a new execution would wait for a slot, judged from recent
+    /// execution times.
+    pub estimated_wait: Duration,
+}
+
+impl LoadReport {
+    /// Whether a new execution would have to queue.
+    pub fn saturated(&self) -> bool {
+        self.available_slots == Some(0)
+    }
+}
+
+/// Counts queued and running executions and keeps a moving average of how
+/// long they run, for building `LoadReport`s.
+#[derive(Debug, Default)]
+pub struct LoadTracker {
+    in_flight: AtomicUsize,
+    queued: AtomicUsize,
+    average_run_micros: AtomicU64,
+}
+
+impl LoadTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts the caller as queued until the guard drops.
+    pub fn queue(&self) -> QueuedGuard<'_> {
+        self.queued.fetch_add(1, Ordering::SeqCst);
+        QueuedGuard { tracker: self }
+    }
+
+    /// Counts the caller as running until the guard drops, then folds its
+    /// run time into the average.
+    pub fn run(&self) -> RunningGuard<'_> {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        RunningGuard {
+            tracker: self,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn average_run_time(&self) -> Duration {
+        Duration::from_micros(self.average_run_micros.load(Ordering::Relaxed))
+    }
+
+    /// Load given the slots of the semaphore or pool the executions wait on.
+    /// A full runtime estimates the wait as one average run per round of
+    /// `capacity` executions ahead of the caller.
+    pub fn report(&self, available_slots: Option<usize>, capacity: Option<usize>) -> LoadReport {
+        let queued = self.queued.load(Ordering::SeqCst);
+        let estimated_wait = match (available_slots, capacity) {
+            (Some(0), Some(capacity)) if capacity > 0 => self.average_run_time() * (queued / capacity + 1) as u32,
+            _ => Duration::ZERO,
+        };
+        LoadReport {
+            in_flight: self.in_flight.load(Ordering::SeqCst),
+            queued,
+            available_slots,
+            capacity,
+            estimated_wait,
+        }
+    }
+
+    fn record_run(&self, elapsed: Duration) {
+        let sample = elapsed.as_micros().min(u64::MAX as u128) as u64;
+        let _ = self
+            .average_run_micros
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| match average {
+                0 => Some(sample),
+                average => Some(average - average / 8 + sample / 8),
+            });
+    }
+}
+
+pub struct QueuedGuard<'a> {
+    tracker: &'a LoadTracker,
+}
+
+impl Drop for QueuedGuard<'_> {
+    fn drop(&mut self) {
+        self.tracker.queued.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub struct RunningGuard<'a> {
+    tracker: &'a LoadTracker,
+    started: Instant,
+}
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        self.tracker.in_flight.fetch_sub(1, Ordering::SeqCst);
+        self.tracker.record_run(self.started.elapsed());
+    }
+}
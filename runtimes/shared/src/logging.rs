@@ -0,0 +1,213 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum LogLevel {
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl LogLevel {
+    /// Level passed to the WASM `env.log` import: 0 (trace) through 4 (error).
+    pub fn from_guest(level: i32) -> Option<Self> {
+        Some(match level {
+            0 => LogLevel::Trace,
+            1 => LogLevel::Debug,
+            2 => LogLevel::Info,
+            3 => LogLevel::Warn,
+            4 => LogLevel::Error,
+            _ => return None,
+        })
+    }
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LogLevel::Trace => "trace",
+            LogLevel::Debug => "debug",
+            LogLevel::Info => "info",
+            LogLevel::Warn => "warn",
+            LogLevel::Error => "error",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    pub message: String,
+    /// Time since the execution started.
+    pub elapsed: Duration,
+}
+
+/// Bounds on what a single execution may log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogLimits {
+    /// Records below this level are discarded without counting as dropped.
+    pub min_level: LogLevel,
+    pub max_records: usize,
+    /// Records accepted per second of execution; the excess is dropped.
+    pub max_per_second: u32,
+    /// Longer messages are truncated.
+    pub max_message_bytes: usize,
+}
+
+impl Default for LogLimits {
+    fn default() -> Self {
+        Self {
+            min_level: LogLevel::Debug,
+            max_records: 1000,
+            max_per_second: 100,
+            max_message_bytes: 4096,
+        }
+    }
+}
+
+/// How much guest logging and runtime tracing one execution captures and
+/// returns, whatever the host's own tracing filter lets through.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum TraceVerbosity {
+    /// Neither guest logs nor trace events are kept.
+    Off,
+    /// Guest logs within the execution's `LogLimits` and the main trace
+    /// events.
+    #[default]
+    Basic,
+    /// Guest logs down to trace level and every trace event.
+    Verbose,
+}
+
+impl TraceVerbosity {
+    /// `limits` as they apply at this verbosity.
+    pub fn log_limits(&self, limits: LogLimits) -> LogLimits {
+        match self {
+            TraceVerbosity::Off => LogLimits {
+                max_records: 0,
+                ..limits
+            },
+            TraceVerbosity::Basic => limits,
+            TraceVerbosity::Verbose => LogLimits {
+                min_level: LogLevel::Trace,
+                ..limits
+            },
+        }
+    }
+}
+
+/// Guest log records captured during one execution.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionLogs {
+    pub records: Vec<LogRecord>,
+    /// Records discarded by `LogLimits`.
+    pub dropped: u64,
+    /// Runtime events recorded for the execution at its `TraceVerbosity`.
+    #[serde(default)]
+    pub trace: Vec<LogRecord>,
+}
+
+/// Records runtime events for one execution at its `TraceVerbosity`.
+/// Messages are only built for events that are kept.
+#[derive(Debug, Clone)]
+pub struct TraceCapture {
+    verbosity: TraceVerbosity,
+    started: Instant,
+    records: Vec<LogRecord>,
+}
+
+impl TraceCapture {
+    pub fn new(verbosity: TraceVerbosity) -> Self {
+        Self {
+            verbosity,
+            started: Instant::now(),
+            records: Vec::new(),
+        }
+    }
+
+    /// Records an event kept at `Basic` verbosity and above.
+    pub fn basic(&mut self, message: impl FnOnce() -> String) {
+        if self.verbosity >= TraceVerbosity::Basic {
+            self.push(LogLevel::Info, message());
+        }
+    }
+
+    /// Records a detail kept only at `Verbose`.
+    pub fn verbose(&mut self, message: impl FnOnce() -> String) {
+        if self.verbosity == TraceVerbosity::Verbose {
+            self.push(LogLevel::Debug, message());
+        }
+    }
+
+    fn push(&mut self, level: LogLevel, message: String) {
+        self.records.push(LogRecord {
+            level,
+            message,
+            elapsed: self.started.elapsed(),
+        });
+    }
+
+    pub fn finish(self) -> Vec<LogRecord> {
+        self.records
+    }
+}
+
+/// Collects guest log records for one execution, enforcing `LogLimits`.
+#[derive(Debug, Clone)]
+pub struct LogCapture {
+    limits: LogLimits,
+    started: Instant,
+    window: u64,
+    window_count: u32,
+    logs: ExecutionLogs,
+}
+
+impl LogCapture {
+    pub fn new(limits: LogLimits) -> Self {
+        Self {
+            limits,
+            started: Instant::now(),
+            window: 0,
+            window_count: 0,
+            logs: ExecutionLogs::default(),
+        }
+    }
+
+    /// Records `message`, returning whether it was kept.
+    pub fn push(&mut self, level: LogLevel, message: &str) -> bool {
+        if level < self.limits.min_level {
+            return false;
+        }
+
+        let elapsed = self.started.elapsed();
+        let window = elapsed.as_secs();
+        if window != self.window {
+            self.window = window;
+            self.window_count = 0;
+        }
+        if self.logs.records.len() >= self.limits.max_records || self.window_count >= self.limits.max_per_second {
+            self.logs.dropped += 1;
+            return false;
+        }
+        self.window_count += 1;
+
+        let mut end = message.len().min(self.limits.max_message_bytes);
+        while !message.is_char_boundary(end) {
+            end -= 1;
+        }
+        self.logs.records.push(LogRecord {
+            level,
+            message: message[..end].to_string(),
+            elapsed,
+        });
+        true
+    }
+
+    pub fn finish(self) -> ExecutionLogs {
+        self.logs
+    }
+}
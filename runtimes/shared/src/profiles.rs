@@ -0,0 +1,154 @@
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{Capability, ExecutionConfig, Permissions, RuntimeType, TrustLevel};
+
+/// Named execution defaults for a class of caller, so configs for the same
+/// kind of workload stay consistent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Profile {
+    pub name: String,
+    pub timeout: Duration,
+    pub memory_limit: usize,
+    pub permissions: Permissions,
+    /// Runtimes to use, most preferred first, when the caller doesn't pick one.
+    pub runtime_preference: Vec<RuntimeType>,
+}
+
+impl Profile {
+    pub const UNTRUSTED: &'static str = "untrusted";
+    pub const INTERNAL: &'static str = "internal";
+    pub const BATCH: &'static str = "batch";
+
+    /// Short, isolated runs of code from unknown users.
+    pub fn untrusted() -> Self {
+        Self {
+            name: Self::UNTRUSTED.to_string(),
+            timeout: Duration::from_secs(5),
+            memory_limit: 32 * 1024 * 1024,
+            permissions: Permissions::new(TrustLevel::Low),
+            runtime_preference: vec![RuntimeType::Wasm, RuntimeType::Ebpf],
+        }
+    }
+
+    /// Code written by the operator's own teams.
+    pub fn internal() -> Self {
+        Self {
+            name: Self::INTERNAL.to_string(),
+            timeout: Duration::from_secs(60),
+            memory_limit: 256 * 1024 * 1024,
+            permissions: Permissions::new(TrustLevel::Medium),
+            runtime_preference: vec![RuntimeType::Wasm, RuntimeType::Python],
+        }
+    }
+
+    /// Long-running trusted jobs such as data processing.
+    pub fn batch() -> Self {
+        let mut permissions = Permissions::new(TrustLevel::High);
+        permissions.capabilities.insert(Capability::CpuIntensive);
+
+        Self {
+            name: Self::BATCH.to_string(),
+            timeout: Duration::from_secs(600),
+            memory_limit: 1024 * 1024 * 1024,
+            permissions,
+            runtime_preference: vec![RuntimeType::Python, RuntimeType::Wasm],
+        }
+    }
+
+    /// Built-in profile called `name`.
+    pub fn by_name(name: &str) -> Option<Self> {
+        match name {
+            Self::UNTRUSTED => Some(Self::untrusted()),
+            Self::INTERNAL => Some(Self::internal()),
+            Self::BATCH => Some(Self::batch()),
+            _ => None,
+        }
+    }
+
+    pub fn builtin() -> Vec<Self> {
+        vec![Self::untrusted(), Self::internal(), Self::batch()]
+    }
+
+    pub fn execution_config(&self) -> ExecutionConfig {
+        ExecutionConfig {
+            timeout: self.timeout,
+            memory_limit: self.memory_limit,
+            permissions: self.permissions.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Most preferred runtime among `available`.
+    pub fn preferred_runtime(&self, available: &[RuntimeType]) -> Option<RuntimeType> {
+        self.runtime_preference
+            .iter()
+            .copied()
+            .find(|runtime| available.contains(runtime))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_untrusted() {
+        let profile = Profile::by_name("untrusted").unwrap();
+        assert_eq!(profile.name, Profile::UNTRUSTED);
+        assert_eq!(profile.timeout, Duration::from_secs(5));
+        assert_eq!(profile.memory_limit, 32 * 1024 * 1024);
+        assert_eq!(profile.permissions.trust_level, TrustLevel::Low);
+        assert!(profile.permissions.capabilities.is_empty());
+        assert_eq!(profile.runtime_preference, [RuntimeType::Wasm, RuntimeType::Ebpf]);
+    }
+
+    #[test]
+    fn test_internal() {
+        let profile = Profile::by_name("internal").unwrap();
+        assert_eq!(profile.name, Profile::INTERNAL);
+        assert_eq!(profile.timeout, Duration::from_secs(60));
+        assert_eq!(profile.memory_limit, 256 * 1024 * 1024);
+        assert_eq!(profile.permissions.trust_level, TrustLevel::Medium);
+        assert_eq!(profile.permissions.capabilities, Permissions::new(TrustLevel::Medium).capabilities);
+        assert_eq!(profile.runtime_preference, [RuntimeType::Wasm, RuntimeType::Python]);
+    }
+
+    #[test]
+    fn test_batch() {
+        let profile = Profile::by_name("batch").unwrap();
+        assert_eq!(profile.name, Profile::BATCH);
+        assert_eq!(profile.timeout, Duration::from_secs(600));
+        assert_eq!(profile.memory_limit, 1024 * 1024 * 1024);
+        assert_eq!(profile.permissions.trust_level, TrustLevel::High);
+        assert!(profile.permissions.capabilities.contains(&Capability::CpuIntensive));
+        assert!(profile.permissions.capabilities.is_superset(&Permissions::new(TrustLevel::High).capabilities));
+        assert_eq!(profile.runtime_preference, [RuntimeType::Python, RuntimeType::Wasm]);
+    }
+
+    #[test]
+    fn test_unknown_names() {
+        assert!(Profile::by_name("").is_none());
+        assert!(Profile::by_name("Untrusted").is_none());
+        assert!(Profile::by_name("admin").is_none());
+
+        let names: Vec<_> = Profile::builtin().into_iter().map(|profile| profile.name).collect();
+        assert_eq!(names, [Profile::UNTRUSTED, Profile::INTERNAL, Profile::BATCH]);
+    }
+
+    #[test]
+    fn test_execution_config() {
+        let config = Profile::internal().execution_config();
+        assert_eq!(config.timeout, Duration::from_secs(60));
+        assert_eq!(config.memory_limit, 256 * 1024 * 1024);
+        assert_eq!(config.permissions.trust_level, TrustLevel::Medium);
+    }
+
+    #[test]
+    fn test_preferred_runtime() {
+        let batch = Profile::batch();
+        assert_eq!(batch.preferred_runtime(&[RuntimeType::Wasm, RuntimeType::Python]), Some(RuntimeType::Python));
+        assert_eq!(batch.preferred_runtime(&[RuntimeType::Ebpf, RuntimeType::Wasm]), Some(RuntimeType::Wasm));
+        assert_eq!(batch.preferred_runtime(&[RuntimeType::Ebpf]), None);
+    }
+}
@@ -0,0 +1,340 @@
+use base64::Engine;
+use regex::bytes::Regex;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::Arc;
+
+use crate::{ExecutionResult, OutputEncoding};
+
+/// Finds one kind of PII in captured output.
+pub trait PiiDetector: Send + Sync {
+    /// Names what was found in redaction markers, e.g. `email`.
+    fn name(&self) -> &str;
+
+    /// Byte ranges of `haystack` that hold PII.
+    fn find(&self, haystack: &[u8]) -> Vec<Range<usize>>;
+}
+
+/// Flags every match of a regular expression.
+pub struct RegexDetector {
+    name: String,
+    pattern: Regex,
+}
+
+impl RegexDetector {
+    pub fn new(name: impl Into<String>, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            name: name.into(),
+            pattern: Regex::new(pattern)?,
+        })
+    }
+}
+
+impl PiiDetector for RegexDetector {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn find(&self, haystack: &[u8]) -> Vec<Range<usize>> {
+        self.pattern.find_iter(haystack).map(|m| m.range()).collect()
+    }
+}
+
+/// Flags runs of 13 to 19 digits, optionally split by spaces or dashes,
+/// that pass the Luhn check.
+pub struct CardNumberDetector {
+    candidates: Regex,
+}
+
+impl CardNumberDetector {
+    pub fn new() -> Self {
+        Self {
+            candidates: Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").expect("card number pattern is valid"),
+        }
+    }
+
+    fn luhn_valid(digits: &[u8]) -> bool {
+        let sum: u32 = digits
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, &digit)| {
+                let digit = u32::from(digit - b'0');
+                match i % 2 {
+                    0 => digit,
+                    _ if digit > 4 => digit * 2 - 9,
+                    _ => digit * 2,
+                }
+            })
+            .sum();
+        sum.is_multiple_of(10)
+    }
+}
+
+impl Default for CardNumberDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PiiDetector for CardNumberDetector {
+    fn name(&self) -> &str {
+        "card_number"
+    }
+
+    fn find(&self, haystack: &[u8]) -> Vec<Range<usize>> {
+        self.candidates
+            .find_iter(haystack)
+            .filter(|m| {
+                let digits: Vec<u8> = m.as_bytes().iter().copied().filter(u8::is_ascii_digit).collect();
+                Self::luhn_valid(&digits)
+            })
+            .map(|m| m.range())
+            .collect()
+    }
+}
+
+/// Scrubs PII out of what an execution returns: its output, its error and
+/// the messages it logged.
+#[derive(Clone, Default)]
+pub struct Redactor {
+    detectors: Vec<Arc<dyn PiiDetector>>,
+}
+
+impl Redactor {
+    /// A redactor with no detectors, which leaves everything as is.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Detects email addresses, US social security numbers, payment card
+    /// numbers and IPv4 addresses.
+    pub fn with_builtin_detectors() -> Self {
+        let builtin = |name, pattern| RegexDetector::new(name, pattern).expect("built-in pattern is valid");
+        Self::new()
+            .with_detector(Arc::new(builtin("email", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}")))
+            .with_detector(Arc::new(builtin("ssn", r"\b\d{3}-\d{2}-\d{4}\b")))
+            .with_detector(Arc::new(CardNumberDetector::new()))
+            .with_detector(Arc::new(builtin(
+                "ipv4",
+                r"\b(?:(?:25[0-5]|2[0-4]\d|1?\d?\d)\.){3}(?:25[0-5]|2[0-4]\d|1?\d?\d)\b",
+            )))
+    }
+
+    pub fn with_detector(mut self, detector: Arc<dyn PiiDetector>) -> Self {
+        self.detectors.push(detector);
+        self
+    }
+
+    /// Adds a detector that flags every match of `pattern`.
+    pub fn with_pattern(self, name: impl Into<String>, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(self.with_detector(Arc::new(RegexDetector::new(name, pattern)?)))
+    }
+
+    /// Replaces PII in `text` with `[REDACTED:<detector>]` markers,
+    /// returning the redacted text and how many spans were replaced.
+    pub fn redact_text(&self, text: &str) -> (String, usize) {
+        let (redacted, count) = self.redact(text.as_bytes(), false);
+        let redacted = String::from_utf8(redacted).unwrap_or_else(|e| String::from_utf8_lossy(e.as_bytes()).into_owned());
+        (redacted, count)
+    }
+
+    /// Redacts output already in `encoding`. Base64 output is decoded
+    /// first; Arrow output, and raw bytes that aren't text, are masked in
+    /// place so their length and framing survive.
+    pub fn redact_output(&self, output: Vec<u8>, encoding: OutputEncoding) -> (Vec<u8>, usize) {
+        match encoding {
+            OutputEncoding::Utf8 | OutputEncoding::Json => self.redact(&output, false),
+            OutputEncoding::Bytes => self.redact(&output, std::str::from_utf8(&output).is_err()),
+            OutputEncoding::Arrow => self.redact(&output, true),
+            OutputEncoding::Base64 => match base64::engine::general_purpose::STANDARD.decode(&output) {
+                Ok(decoded) => {
+                    let (redacted, count) = self.redact(&decoded, true);
+                    (base64::engine::general_purpose::STANDARD.encode(redacted).into_bytes(), count)
+                }
+                Err(_) => self.redact(&output, false),
+            },
+        }
+    }
+
+    /// Redacts `result`'s output, error and log messages in place,
+    /// returning how many spans were replaced.
+    pub fn redact_result(&self, result: &mut ExecutionResult, encoding: OutputEncoding) -> usize {
+        let mut count = 0;
+        if let Some(output) = result.output.take() {
+            let (redacted, found) = self.redact_output(output, encoding);
+            result.output = Some(redacted);
+            count += found;
+        }
+        let texts = result.error.iter_mut().chain(result.logs.records.iter_mut().map(|record| &mut record.message));
+        for text in texts {
+            let (redacted, found) = self.redact_text(text);
+            *text = redacted;
+            count += found;
+        }
+        count
+    }
+
+    /// Replaces every detected span, merging overlapping ones under the
+    /// name of the detector that found the earliest. Masking keeps the
+    /// length by overwriting with `*`.
+    fn redact(&self, haystack: &[u8], mask: bool) -> (Vec<u8>, usize) {
+        let mut spans: Vec<(Range<usize>, &str)> = self
+            .detectors
+            .iter()
+            .flat_map(|detector| detector.find(haystack).into_iter().map(move |range| (range, detector.name())))
+            .filter(|(range, _)| !range.is_empty() && range.end <= haystack.len())
+            .collect();
+        if spans.is_empty() {
+            return (haystack.to_vec(), 0);
+        }
+        spans.sort_by_key(|(range, _)| (range.start, std::cmp::Reverse(range.end)));
+
+        let mut redacted = Vec::with_capacity(haystack.len());
+        let mut count = 0;
+        let mut cursor = 0;
+        for (range, name) in spans {
+            if range.end <= cursor {
+                continue;
+            }
+            let start = range.start.max(cursor);
+            redacted.extend_from_slice(&haystack[cursor..start]);
+            if mask {
+                redacted.resize(redacted.len() + (range.end - start), b'*');
+            } else if start == range.start {
+                redacted.extend_from_slice(format!("[REDACTED:{}]", name).as_bytes());
+            }
+            if start == range.start {
+                count += 1;
+            }
+            cursor = range.end;
+        }
+        redacted.extend_from_slice(&haystack[cursor..]);
+        (redacted, count)
+    }
+}
+
+/// Which redactor scrubs each tenant's executions. Runtimes and bridges
+/// pass every result through it before returning it.
+#[derive(Clone, Default)]
+pub struct RedactionPolicy {
+    pub redactors: HashMap<String, Arc<Redactor>>,
+    /// Redacts executions of tenants without their own redactor, and those
+    /// without a tenant. Nothing is redacted for them when unset.
+    pub default_redactor: Option<Arc<Redactor>>,
+}
+
+impl RedactionPolicy {
+    /// Redactor for `tenant`'s executions, if any.
+    pub fn redactor(&self, tenant: Option<&str>) -> Option<&Arc<Redactor>> {
+        tenant
+            .and_then(|tenant| self.redactors.get(tenant))
+            .or(self.default_redactor.as_ref())
+    }
+
+    /// Redacts `result` with `tenant`'s redactor, returning how many spans
+    /// were replaced.
+    pub fn redact_result(&self, result: &mut ExecutionResult, tenant: Option<&str>, encoding: OutputEncoding) -> usize {
+        self.redactor(tenant).map_or(0, |redactor| redactor.redact_result(result, encoding))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_luhn() {
+        assert!(CardNumberDetector::luhn_valid(b"4111111111111111"));
+        assert!(CardNumberDetector::luhn_valid(b"79927398713"));
+        assert!(!CardNumberDetector::luhn_valid(b"4111111111111112"));
+        assert!(!CardNumberDetector::luhn_valid(b"79927398710"));
+    }
+
+    #[test]
+    fn test_card_numbers() {
+        let detector = CardNumberDetector::new();
+        let text = b"paid with 4111 1111 1111 1111, order 4111-1111-1111-1112";
+
+        let found = detector.find(text);
+        assert_eq!(found.len(), 1);
+        assert_eq!(&text[found[0].clone()], b"4111 1111 1111 1111");
+    }
+
+    #[test]
+    fn test_emails() {
+        let redactor = Redactor::with_builtin_detectors();
+
+        let (redacted, count) = redactor.redact_text("mail jane.doe+ops@example.co.uk or bob@example.com");
+        assert_eq!(redacted, "mail [REDACTED:email] or [REDACTED:email]");
+        assert_eq!(count, 2);
+
+        let (redacted, count) = redactor.redact_text("nobody@localhost is not an address");
+        assert_eq!(redacted, "nobody@localhost is not an address");
+        assert_eq!(count, 0);
+    }
+
+    #[test]
+    fn test_tenant_rules() {
+        let tenant = Redactor::with_builtin_detectors().with_pattern("employee_id", r"EMP-\d{6}").unwrap();
+        let text = "EMP-123456 called from 10.0.0.1";
+
+        assert_eq!(tenant.redact_text(text).0, "[REDACTED:employee_id] called from [REDACTED:ipv4]");
+        assert_eq!(Redactor::with_builtin_detectors().redact_text(text).0, "EMP-123456 called from [REDACTED:ipv4]");
+        assert_eq!(Redactor::new().redact_text(text), (text.to_string(), 0));
+        assert!(Redactor::new().with_pattern("broken", "(").is_err());
+    }
+
+    #[test]
+    fn test_encoded_output() {
+        let redactor = Redactor::with_builtin_detectors();
+
+        // Binary output keeps its length
+        let binary = b"\xff\x00ssn 123-45-6789".to_vec();
+        let (redacted, count) = redactor.redact_output(binary, OutputEncoding::Bytes);
+        assert_eq!(redacted, b"\xff\x00ssn ***********".to_vec());
+        assert_eq!(count, 1);
+
+        let encoded = base64::engine::general_purpose::STANDARD.encode("to bob@example.com");
+        let (redacted, count) = redactor.redact_output(encoded.into_bytes(), OutputEncoding::Base64);
+        let decoded = base64::engine::general_purpose::STANDARD.decode(redacted).unwrap();
+        assert_eq!(decoded, b"to ***************");
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn test_policy_picks_the_tenant_redactor() {
+        let policy = RedactionPolicy {
+            redactors: HashMap::from([(
+                "acme".to_string(),
+                Arc::new(Redactor::new().with_pattern("employee_id", r"EMP-\d{6}").unwrap()),
+            )]),
+            default_redactor: Some(Arc::new(Redactor::with_builtin_detectors())),
+        };
+        let result = || ExecutionResult {
+            success: true,
+            output: Some(b"EMP-123456 bob@example.com".to_vec()),
+            error: None,
+            execution_time: Default::default(),
+            memory_used: 0,
+            logs: Default::default(),
+            attestation: None,
+            environment_fingerprint: None,
+            timing: None,
+        };
+
+        let mut acme = result();
+        assert_eq!(policy.redact_result(&mut acme, Some("acme"), OutputEncoding::Utf8), 1);
+        assert_eq!(acme.output.unwrap(), b"[REDACTED:employee_id] bob@example.com");
+
+        for tenant in [Some("other"), None] {
+            let mut other = result();
+            assert_eq!(policy.redact_result(&mut other, tenant, OutputEncoding::Utf8), 1);
+            assert_eq!(other.output.unwrap(), b"EMP-123456 [REDACTED:email]");
+        }
+
+        let mut unredacted = result();
+        assert_eq!(RedactionPolicy::default().redact_result(&mut unredacted, Some("acme"), OutputEncoding::Utf8), 0);
+        assert_eq!(unredacted.output, result().output);
+    }
+}
@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+
+use crate::TrustLevel;
+
+/// When an instance that has already run code may run another execution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ReusePolicy {
+    /// Any execution may reuse the instance.
+    Always,
+    /// Only executions of the tenant that last used the instance may reuse it.
+    /// Executions without a tenant count as one anonymous tenant.
+    WithinTenant,
+    /// Every execution gets an instance that has never run code.
+    Never,
+}
+
+impl ReusePolicy {
+    /// Low trust always runs fresh; otherwise instances never cross tenants.
+    pub fn for_trust_level(trust_level: TrustLevel) -> Self {
+        match trust_level {
+            TrustLevel::Low => ReusePolicy::Never,
+            TrustLevel::Medium | TrustLevel::High => ReusePolicy::WithinTenant,
+        }
+    }
+
+    /// Whether an instance last used by `last_tenant` may run for `tenant`.
+    pub fn allows(&self, last_tenant: Option<&str>, tenant: Option<&str>) -> bool {
+        match self {
+            ReusePolicy::Always => true,
+            ReusePolicy::WithinTenant => last_tenant == tenant,
+            ReusePolicy::Never => false,
+        }
+    }
+}
+
+/// How often executions reused an instance versus being given a fresh one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReuseMetrics {
+    /// Executions on an instance's first use.
+    pub first_use: u64,
+    /// Executions that reused an instance that had already run code.
+    pub reused: u64,
+    /// Executions the policy moved to a fresh instance instead of reusing one.
+    pub fresh: u64,
+}
+
+impl ReuseMetrics {
+    /// Share of executions on already-used instances that reused them.
+    pub fn reuse_rate(&self) -> f64 {
+        let candidates = self.reused + self.fresh;
+        if candidates == 0 {
+            0.0
+        } else {
+            self.reused as f64 / candidates as f64
+        }
+    }
+}
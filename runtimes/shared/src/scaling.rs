@@ -0,0 +1,15 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// Warm capacity held ahead of demand, such as pooled instances,
+/// interpreters or memory slots, that an autoscaler may grow and shrink.
+#[async_trait]
+pub trait ScalablePool: Send + Sync {
+    /// Names the pool in scaling decisions.
+    fn name(&self) -> String;
+    /// Units currently held.
+    fn size(&self) -> usize;
+    /// Grows or shrinks toward `target` and returns the size reached. Units
+    /// in use are never reclaimed, so a shrink may stop short.
+    async fn resize(&self, target: usize) -> Result<usize>;
+}
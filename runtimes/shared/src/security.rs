@@ -18,6 +18,8 @@ pub enum Capability {
     SharedMemory,
     CpuIntensive,
     GpuAccess,
+    /// Querying the host's vector store through the retrieval host API.
+    VectorSearch,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -41,6 +43,7 @@ impl Permissions {
                 let mut caps = HashSet::new();
                 caps.insert(Capability::SystemTime);
                 caps.insert(Capability::FileSystemRead);
+                caps.insert(Capability::VectorSearch);
                 caps
             }
             TrustLevel::High => {
@@ -51,6 +54,7 @@ impl Permissions {
                 caps.insert(Capability::SystemTime);
                 caps.insert(Capability::EnvironmentVariables);
                 caps.insert(Capability::SharedMemory);
+                caps.insert(Capability::VectorSearch);
                 caps
             }
         };
@@ -0,0 +1,147 @@
+use anyhow::{anyhow, bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Deserialize)]
+struct RawSourceMap {
+    version: u32,
+    sources: Vec<String>,
+    mappings: String,
+}
+
+/// Position in the original (pre-transpilation) source. Line and column are
+/// 1-based.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OriginalPosition {
+    pub source: String,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl fmt::Display for OriginalPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.source, self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    generated_column: u32,
+    source: u32,
+    line: u32,
+    column: u32,
+}
+
+/// Decoded source map (revision 3). For WASM targets the generated column is
+/// the byte offset into the module binary.
+#[derive(Debug, Clone)]
+pub struct SourceMap {
+    sources: Vec<String>,
+    /// Segments per generated line, sorted by generated column.
+    lines: Vec<Vec<Segment>>,
+}
+
+impl SourceMap {
+    pub fn from_json(json: &[u8]) -> Result<Self> {
+        let raw: RawSourceMap = serde_json::from_slice(json)?;
+        if raw.version != 3 {
+            bail!("Unsupported source map version {}", raw.version);
+        }
+
+        let mut lines = Vec::new();
+        let (mut source, mut line, mut column) = (0i64, 0i64, 0i64);
+
+        for encoded_line in raw.mappings.split(';') {
+            let mut segments = Vec::new();
+            let mut generated_column = 0i64;
+
+            for encoded_segment in encoded_line.split(',').filter(|s| !s.is_empty()) {
+                let fields = decode_vlq(encoded_segment)?;
+                generated_column += fields[0];
+                // Single-field segments map to nothing in the original source
+                if fields.len() < 4 {
+                    continue;
+                }
+                source += fields[1];
+                line += fields[2];
+                column += fields[3];
+
+                if generated_column < 0 || source < 0 || line < 0 || column < 0 {
+                    bail!("Source map segment `{}` points before the start of a file", encoded_segment);
+                }
+                if source as usize >= raw.sources.len() {
+                    bail!("Source map references unknown source index {}", source);
+                }
+                segments.push(Segment {
+                    generated_column: generated_column as u32,
+                    source: source as u32,
+                    line: line as u32,
+                    column: column as u32,
+                });
+            }
+
+            segments.sort_by_key(|segment| segment.generated_column);
+            lines.push(segments);
+        }
+
+        Ok(Self {
+            sources: raw.sources,
+            lines,
+        })
+    }
+
+    /// Original position for a 0-based generated line and column.
+    pub fn lookup(&self, line: u32, column: u32) -> Option<OriginalPosition> {
+        let segments = self.lines.get(line as usize)?;
+        let index = segments.partition_point(|segment| segment.generated_column <= column);
+        let segment = segments.get(index.checked_sub(1)?)?;
+
+        Some(OriginalPosition {
+            source: self.sources[segment.source as usize].clone(),
+            line: segment.line + 1,
+            column: segment.column + 1,
+        })
+    }
+
+    /// Original position of a byte offset in a WASM module.
+    pub fn lookup_offset(&self, offset: usize) -> Option<OriginalPosition> {
+        self.lookup(0, u32::try_from(offset).ok()?)
+    }
+}
+
+fn decode_vlq(segment: &str) -> Result<Vec<i64>> {
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0i64, 0u32);
+
+    for byte in segment.bytes() {
+        let digit = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return Err(anyhow!("Invalid base64 character `{}` in source map", byte as char)),
+        } as i64;
+        if shift > 32 {
+            bail!("Source map value in `{}` is too large", segment);
+        }
+
+        value += (digit & 0x1f) << shift;
+        if digit & 0x20 != 0 {
+            shift += 5;
+            continue;
+        }
+
+        values.push(if value & 1 == 1 { -(value >> 1) } else { value >> 1 });
+        value = 0;
+        shift = 0;
+    }
+
+    if shift != 0 {
+        bail!("Truncated source map segment `{}`", segment);
+    }
+    if values.is_empty() || values.len() > 5 {
+        bail!("Malformed source map segment `{}`", segment);
+    }
+    Ok(values)
+}
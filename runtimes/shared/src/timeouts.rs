@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+use crate::TrustLevel;
+
+/// Stage of a job, each with its own time budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum JobPhase {
+    Compile,
+    QueueWait,
+    Instantiate,
+    Execute,
+}
+
+impl fmt::Display for JobPhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            JobPhase::Compile => "compile",
+            JobPhase::QueueWait => "queue wait",
+            JobPhase::Instantiate => "instantiate",
+            JobPhase::Execute => "execute",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseTimeouts {
+    pub compile: Duration,
+    /// Time spent waiting for a free execution slot.
+    pub queue_wait: Duration,
+    pub instantiate: Duration,
+    /// Upper bound on `ExecutionConfig::timeout`.
+    pub execute: Duration,
+}
+
+impl PhaseTimeouts {
+    /// Lower trust levels get tighter budgets.
+    pub fn for_trust_level(trust_level: TrustLevel) -> Self {
+        match trust_level {
+            TrustLevel::Low => Self {
+                compile: Duration::from_secs(10),
+                queue_wait: Duration::from_secs(5),
+                instantiate: Duration::from_secs(1),
+                execute: Duration::from_secs(30),
+            },
+            TrustLevel::Medium => Self {
+                compile: Duration::from_secs(30),
+                queue_wait: Duration::from_secs(15),
+                instantiate: Duration::from_secs(5),
+                execute: Duration::from_secs(120),
+            },
+            TrustLevel::High => Self {
+                compile: Duration::from_secs(120),
+                queue_wait: Duration::from_secs(60),
+                instantiate: Duration::from_secs(10),
+                execute: Duration::from_secs(600),
+            },
+        }
+    }
+
+    pub fn budget(&self, phase: JobPhase) -> Duration {
+        match phase {
+            JobPhase::Compile => self.compile,
+            JobPhase::QueueWait => self.queue_wait,
+            JobPhase::Instantiate => self.instantiate,
+            JobPhase::Execute => self.execute,
+        }
+    }
+}
+
+impl Default for PhaseTimeouts {
+    fn default() -> Self {
+        Self::for_trust_level(TrustLevel::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHASES: [JobPhase; 4] = [JobPhase::Compile, JobPhase::QueueWait, JobPhase::Instantiate, JobPhase::Execute];
+
+    #[test]
+    fn test_budget_table() {
+        let secs = |trust_level| {
+            let timeouts = PhaseTimeouts::for_trust_level(trust_level);
+            PHASES.map(|phase| timeouts.budget(phase).as_secs())
+        };
+        assert_eq!(secs(TrustLevel::Low), [10, 5, 1, 30]);
+        assert_eq!(secs(TrustLevel::Medium), [30, 15, 5, 120]);
+        assert_eq!(secs(TrustLevel::High), [120, 60, 10, 600]);
+    }
+
+    #[test]
+    fn test_lower_trust_gets_tighter_budgets() {
+        let low = PhaseTimeouts::for_trust_level(TrustLevel::Low);
+        let medium = PhaseTimeouts::for_trust_level(TrustLevel::Medium);
+        let high = PhaseTimeouts::for_trust_level(TrustLevel::High);
+        for phase in PHASES {
+            assert!(low.budget(phase) < medium.budget(phase), "{}", phase);
+            assert!(medium.budget(phase) < high.budget(phase), "{}", phase);
+        }
+    }
+
+    #[test]
+    fn test_default_is_low_trust() {
+        assert_eq!(PhaseTimeouts::default(), PhaseTimeouts::for_trust_level(TrustLevel::Low));
+    }
+
+    #[test]
+    fn test_phase_names() {
+        assert_eq!(PHASES.map(|phase| phase.to_string()), ["compile", "queue wait", "instantiate", "execute"]);
+    }
+}
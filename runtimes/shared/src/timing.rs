@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+use std::ops::AddAssign;
+use std::time::Duration;
+
+/// Where the time of one request went. Phases the request skipped, or that
+/// nothing on its path measures, stay zero.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimingBreakdown {
+    /// Waiting for an execution slot.
+    #[serde(default)]
+    pub queue_wait: Duration,
+    /// Choosing the runtime, backend or instance to run on.
+    #[serde(default)]
+    pub scheduling: Duration,
+    /// Preparing the sandbox for this execution: resetting limits, clocks
+    /// and I/O buffers, or handing out an interpreter.
+    #[serde(default)]
+    pub sandbox_setup: Duration,
+    /// Compiling the code, or finding it in a cache, when the request did.
+    #[serde(default)]
+    pub compile: Duration,
+    /// Creating an instance for this request alone.
+    #[serde(default)]
+    pub instantiate: Duration,
+    /// Running the guest.
+    #[serde(default)]
+    pub execute: Duration,
+    /// Destroying single-use instances and returning their resources.
+    #[serde(default)]
+    pub teardown: Duration,
+}
+
+impl TimingBreakdown {
+    pub fn total(&self) -> Duration {
+        self.queue_wait
+            + self.scheduling
+            + self.sandbox_setup
+            + self.compile
+            + self.instantiate
+            + self.execute
+            + self.teardown
+    }
+}
+
+/// Adds phase by phase, for layers that each measure part of a request.
+impl AddAssign for TimingBreakdown {
+    fn add_assign(&mut self, other: Self) {
+        self.queue_wait += other.queue_wait;
+        self.scheduling += other.scheduling;
+        self.sandbox_setup += other.sandbox_setup;
+        self.compile += other.compile;
+        self.instantiate += other.instantiate;
+        self.execute += other.execute;
+        self.teardown += other.teardown;
+    }
+}
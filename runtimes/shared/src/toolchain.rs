@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::{Language, RuntimeError};
+
+/// External compiler a runtime shells out to, as found when the runtime
+/// started.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolchainStatus {
+    pub name: String,
+    /// Program run to use it; `None` when it wasn't found.
+    pub path: Option<PathBuf>,
+    /// First line of its version output.
+    pub version: Option<String>,
+    /// What to do when it's missing, such as `install wasi-sdk or disable
+    /// C support`.
+    pub install_hint: String,
+}
+
+impl ToolchainStatus {
+    /// Looks for `name` on `PATH` and asks it for its version.
+    pub fn probe(name: &str, install_hint: &str) -> Self {
+        Self::probe_command(name, name, &["--version"], install_hint)
+    }
+
+    /// Looks for `program` on `PATH` and runs it with `args`, which must
+    /// succeed for the toolchain to count as available.
+    pub fn probe_command(name: &str, program: &str, args: &[&str], install_hint: &str) -> Self {
+        let found = find_on_path(program).and_then(|path| {
+            let output = Command::new(&path).args(args).output().ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            let version = [&output.stdout, &output.stderr]
+                .into_iter()
+                .filter_map(|stream| String::from_utf8_lossy(stream).lines().next().map(str::to_string))
+                .find(|line| !line.trim().is_empty());
+            Some((path, version))
+        });
+
+        let (path, version) = found.unzip();
+        Self {
+            name: name.to_string(),
+            path,
+            version: version.flatten(),
+            install_hint: install_hint.to_string(),
+        }
+    }
+
+    pub fn available(&self) -> bool {
+        self.path.is_some()
+    }
+
+    /// The program to run, or a `CompilationError` saying how to get it.
+    pub fn require(&self) -> Result<&Path, RuntimeError> {
+        self.path.as_deref().ok_or_else(|| {
+            RuntimeError::CompilationError(format!("toolchain '{}' not found, {}", self.name, self.install_hint))
+        })
+    }
+
+    /// Writes `source` to `input` in a scratch directory, runs the toolchain
+    /// there with `args` and returns what it wrote to `output`. A failed run
+    /// is a `CompilationError` carrying the toolchain's diagnostics. Blocks
+    /// until the toolchain exits.
+    pub fn run(&self, source: &[u8], input: &str, output: &str, args: &[&str]) -> Result<Vec<u8>, RuntimeError> {
+        let program = self.require()?;
+        let scratch = tempfile::tempdir().map_err(|e| RuntimeError::CompilationError(e.to_string()))?;
+        std::fs::write(scratch.path().join(input), source).map_err(|e| RuntimeError::CompilationError(e.to_string()))?;
+
+        let result = Command::new(program)
+            .args(args)
+            .current_dir(scratch.path())
+            .output()
+            .map_err(|e| RuntimeError::CompilationError(format!("Failed to run {}: {}", self.name, e)))?;
+        if !result.status.success() {
+            return Err(RuntimeError::CompilationError(format!(
+                "{} failed: {}",
+                self.name,
+                String::from_utf8_lossy(&result.stderr).trim()
+            )));
+        }
+
+        std::fs::read(scratch.path().join(output))
+            .map_err(|e| RuntimeError::CompilationError(format!("{} produced no {}: {}", self.name, output, e)))
+    }
+}
+
+/// What a runtime can compile on this host.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RuntimeCapabilities {
+    /// Languages `compile` accepts, leaving out those whose toolchain is
+    /// missing.
+    pub languages: Vec<Language>,
+    /// Toolchains the runtime shells out to, whether found or not.
+    pub toolchains: Vec<ToolchainStatus>,
+}
+
+fn find_on_path(program: &str) -> Option<PathBuf> {
+    let path = Path::new(program);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
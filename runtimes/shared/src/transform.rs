@@ -0,0 +1,123 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::{Language, RuntimeType};
+
+/// Where the code being transformed is headed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransformContext {
+    pub runtime: RuntimeType,
+    pub language: Language,
+}
+
+/// A pass that rewrites submitted code before it is compiled, e.g. to inject
+/// instrumentation or strip sections the sandbox doesn't need.
+#[async_trait]
+pub trait CodeTransform: Send + Sync {
+    /// Identifies the pass in metrics and errors.
+    fn name(&self) -> &str;
+
+    /// Whether the pass runs for this submission. Defaults to every submission.
+    fn applies_to(&self, _context: &TransformContext) -> bool {
+        true
+    }
+
+    async fn transform(&self, code: Vec<u8>, context: &TransformContext) -> Result<Vec<u8>>;
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransformMetrics {
+    pub runs: u64,
+    pub failures: u64,
+    pub total_duration: Duration,
+    pub last_duration: Duration,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+}
+
+/// Context attached to a failing pass's error, so callers can tell transform
+/// failures from compile failures.
+#[derive(Debug, Clone)]
+pub struct TransformError {
+    pub pass: String,
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Transform pass `{}` failed", self.pass)
+    }
+}
+
+/// Ordered set of `CodeTransform` passes. Each pass receives the previous
+/// pass's output; the first failure aborts the pipeline.
+#[derive(Default)]
+pub struct TransformPipeline {
+    passes: RwLock<Vec<Arc<dyn CodeTransform>>>,
+    metrics: Mutex<HashMap<String, TransformMetrics>>,
+}
+
+impl TransformPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends `pass`, so it runs after every pass registered before it.
+    pub fn register(&self, pass: Arc<dyn CodeTransform>) {
+        self.passes.write().unwrap().push(pass);
+    }
+
+    pub fn passes(&self) -> Vec<String> {
+        self.passes.read().unwrap().iter().map(|pass| pass.name().to_string()).collect()
+    }
+
+    /// Whether any registered pass applies to `context`.
+    pub fn applies_to(&self, context: &TransformContext) -> bool {
+        self.passes.read().unwrap().iter().any(|pass| pass.applies_to(context))
+    }
+
+    pub async fn apply(&self, mut code: Vec<u8>, context: &TransformContext) -> Result<Vec<u8>> {
+        let passes: Vec<_> = self
+            .passes
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|pass| pass.applies_to(context))
+            .cloned()
+            .collect();
+
+        for pass in passes {
+            let bytes_in = code.len() as u64;
+            let started = Instant::now();
+            let result = pass.transform(code, context).await;
+            let elapsed = started.elapsed();
+
+            let mut metrics = self.metrics.lock().unwrap();
+            let entry = metrics.entry(pass.name().to_string()).or_default();
+            entry.runs += 1;
+            entry.total_duration += elapsed;
+            entry.last_duration = elapsed;
+            entry.bytes_in += bytes_in;
+            match &result {
+                Ok(output) => entry.bytes_out += output.len() as u64,
+                Err(_) => entry.failures += 1,
+            }
+            drop(metrics);
+
+            code = result.with_context(|| TransformError {
+                pass: pass.name().to_string(),
+            })?;
+        }
+
+        Ok(code)
+    }
+
+    /// Per-pass counters and timings, keyed by pass name.
+    pub fn metrics(&self) -> HashMap<String, TransformMetrics> {
+        self.metrics.lock().unwrap().clone()
+    }
+}
@@ -0,0 +1,158 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[cfg(feature = "vector-pgvector")]
+mod pgvector;
+#[cfg(feature = "vector-qdrant")]
+mod qdrant;
+
+#[cfg(feature = "vector-pgvector")]
+pub use self::pgvector::PgVectorStore;
+#[cfg(feature = "vector-qdrant")]
+pub use self::qdrant::QdrantVectorStore;
+
+/// Most matches a guest may ask for in one search.
+pub const MAX_VECTOR_SEARCH_RESULTS: usize = 100;
+
+/// Longest query vector a guest may send.
+pub const MAX_VECTOR_DIMENSIONS: usize = 65_536;
+
+const DEFAULT_SEARCH_LIMIT: usize = 5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorPoint {
+    pub id: String,
+    pub vector: Vec<f32>,
+    #[serde(default)]
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorMatch {
+    pub id: String,
+    /// Cosine similarity to the query; higher is closer.
+    pub score: f32,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VectorQuery {
+    pub collection: String,
+    pub vector: Vec<f32>,
+    #[serde(default = "default_search_limit")]
+    pub limit: usize,
+}
+
+fn default_search_limit() -> usize {
+    DEFAULT_SEARCH_LIMIT
+}
+
+impl VectorQuery {
+    /// Rejects queries a guest shouldn't be able to send to a store. The
+    /// collection ends up in URLs and SQL, so only `[A-Za-z0-9_-]` is allowed.
+    pub fn validate(&self) -> Result<()> {
+        let collection_ok = !self.collection.is_empty()
+            && self.collection.len() <= 255
+            && self.collection.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if !collection_ok {
+            return Err(anyhow!("Invalid collection name {:?}", self.collection));
+        }
+        if self.vector.is_empty() || self.vector.len() > MAX_VECTOR_DIMENSIONS {
+            return Err(anyhow!("Query vectors must have 1 to {} dimensions", MAX_VECTOR_DIMENSIONS));
+        }
+        if !self.vector.iter().all(|x| x.is_finite()) {
+            return Err(anyhow!("Query vector contains a non-finite value"));
+        }
+        if self.limit == 0 || self.limit > MAX_VECTOR_SEARCH_RESULTS {
+            return Err(anyhow!("Search limit must be between 1 and {}", MAX_VECTOR_SEARCH_RESULTS));
+        }
+        Ok(())
+    }
+}
+
+/// Similarity search over named collections of embeddings, serving the
+/// retrieval host APIs so guests never need network access of their own.
+#[async_trait]
+pub trait VectorStore: Send + Sync {
+    /// Closest points in `query.collection`, best match first.
+    async fn search(&self, query: &VectorQuery) -> Result<Vec<VectorMatch>>;
+    /// Inserts `points`, replacing any already stored under the same id.
+    async fn upsert(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()>;
+}
+
+/// Runs a guest's JSON-encoded `VectorQuery` and returns the JSON-encoded
+/// matches, for host APIs that exchange bytes with the sandbox.
+pub async fn guest_vector_search(store: &dyn VectorStore, request: &[u8]) -> Result<Vec<u8>> {
+    let query: VectorQuery = serde_json::from_slice(request)?;
+    query.validate()?;
+    let matches = store.search(&query).await?;
+    Ok(serde_json::to_vec(&matches)?)
+}
+
+/// Process-local store for tests and single-node deployments. Searches are
+/// exhaustive, so it suits small collections only.
+#[derive(Default)]
+pub struct InMemoryVectorStore {
+    collections: RwLock<HashMap<String, HashMap<String, VectorPoint>>>,
+}
+
+impl InMemoryVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl VectorStore for InMemoryVectorStore {
+    async fn search(&self, query: &VectorQuery) -> Result<Vec<VectorMatch>> {
+        let collections = self.collections.read();
+        let Some(points) = collections.get(&query.collection) else {
+            return Err(anyhow!("Collection not found: {}", query.collection));
+        };
+
+        let mut matches = points
+            .values()
+            .map(|point| {
+                if point.vector.len() != query.vector.len() {
+                    return Err(anyhow!(
+                        "Query has {} dimensions but collection {} stores {}",
+                        query.vector.len(),
+                        query.collection,
+                        point.vector.len()
+                    ));
+                }
+                Ok(VectorMatch {
+                    id: point.id.clone(),
+                    score: cosine_similarity(&query.vector, &point.vector),
+                    payload: point.payload.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+        matches.truncate(query.limit);
+        Ok(matches)
+    }
+
+    async fn upsert(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()> {
+        let mut collections = self.collections.write();
+        let stored = collections.entry(collection.to_string()).or_default();
+        for point in points {
+            stored.insert(point.id.clone(), point);
+        }
+        Ok(())
+    }
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norms = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
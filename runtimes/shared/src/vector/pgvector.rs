@@ -0,0 +1,93 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::Mutex;
+use tokio_postgres::{Client, NoTls};
+use tracing::error;
+
+use super::{VectorMatch, VectorPoint, VectorQuery, VectorStore};
+
+const SCHEMA: &str = "
+    CREATE EXTENSION IF NOT EXISTS vector;
+    CREATE TABLE IF NOT EXISTS vector_points (
+        collection TEXT NOT NULL,
+        id TEXT NOT NULL,
+        embedding vector NOT NULL,
+        payload JSONB NOT NULL DEFAULT 'null',
+        PRIMARY KEY (collection, id)
+    );
+";
+
+/// Store backed by Postgres with the pgvector extension, keeping every
+/// collection in one `vector_points` table.
+pub struct PgVectorStore {
+    client: Mutex<Client>,
+}
+
+impl PgVectorStore {
+    /// Connects using a libpq-style connection string and ensures the schema exists.
+    pub async fn connect(config: &str) -> Result<Self> {
+        let (client, connection) = tokio_postgres::connect(config, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                error!("Vector store connection closed: {}", e);
+            }
+        });
+
+        client.batch_execute(SCHEMA).await?;
+
+        Ok(Self {
+            client: Mutex::new(client),
+        })
+    }
+}
+
+/// pgvector's text form, `[1,2,3]`, so vectors can be bound as text and cast.
+fn vector_literal(vector: &[f32]) -> String {
+    let values: Vec<String> = vector.iter().map(|x| x.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+#[async_trait]
+impl VectorStore for PgVectorStore {
+    async fn search(&self, query: &VectorQuery) -> Result<Vec<VectorMatch>> {
+        let client = self.client.lock().await;
+        let rows = client
+            .query(
+                "SELECT id, 1 - (embedding <=> $2::text::vector), payload::text
+                 FROM vector_points
+                 WHERE collection = $1
+                 ORDER BY embedding <=> $2::text::vector
+                 LIMIT $3",
+                &[&query.collection, &vector_literal(&query.vector), &(query.limit as i64)],
+            )
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(VectorMatch {
+                    id: row.get(0),
+                    score: row.get::<_, f64>(1) as f32,
+                    payload: serde_json::from_str(row.get(2))?,
+                })
+            })
+            .collect()
+    }
+
+    async fn upsert(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()> {
+        let mut client = self.client.lock().await;
+        let transaction = client.transaction().await?;
+        for point in points {
+            transaction
+                .execute(
+                    "INSERT INTO vector_points (collection, id, embedding, payload)
+                     VALUES ($1, $2, $3::text::vector, $4::text::jsonb)
+                     ON CONFLICT (collection, id)
+                     DO UPDATE SET embedding = EXCLUDED.embedding, payload = EXCLUDED.payload",
+                    &[&collection, &point.id, &vector_literal(&point.vector), &point.payload.to_string()],
+                )
+                .await?;
+        }
+        transaction.commit().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use super::{VectorMatch, VectorPoint, VectorQuery, VectorStore};
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Store backed by a Qdrant server's REST API. Collections must already
+/// exist with the cosine distance.
+pub struct QdrantVectorStore {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    result: T,
+}
+
+#[derive(Deserialize)]
+struct ScoredPoint {
+    id: Value,
+    score: f32,
+    #[serde(default)]
+    payload: Option<Value>,
+}
+
+impl QdrantVectorStore {
+    /// `base_url` is the server's HTTP endpoint, such as `http://localhost:6333`.
+    pub fn new(base_url: &str, api_key: Option<String>) -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key,
+        })
+    }
+
+    async fn send<T: DeserializeOwned>(&self, method: reqwest::Method, path: &str, body: Value) -> Result<T> {
+        let mut request = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path))
+            .header("Content-Type", "application/json")
+            .body(serde_json::to_vec(&body)?);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("api-key", api_key.as_str());
+        }
+
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.bytes().await?;
+        if !status.is_success() {
+            return Err(anyhow!("Qdrant returned {}: {}", status, String::from_utf8_lossy(&body)));
+        }
+        Ok(serde_json::from_slice::<Response<T>>(&body)?.result)
+    }
+}
+
+/// Qdrant ids are unsigned integers or UUIDs; anything numeric is sent as one.
+fn point_id(id: &str) -> Value {
+    match id.parse::<u64>() {
+        Ok(number) => json!(number),
+        Err(_) => json!(id),
+    }
+}
+
+#[async_trait]
+impl VectorStore for QdrantVectorStore {
+    async fn search(&self, query: &VectorQuery) -> Result<Vec<VectorMatch>> {
+        let points: Vec<ScoredPoint> = self
+            .send(
+                reqwest::Method::POST,
+                &format!("/collections/{}/points/search", query.collection),
+                json!({
+                    "vector": query.vector,
+                    "limit": query.limit,
+                    "with_payload": true,
+                }),
+            )
+            .await?;
+
+        Ok(points
+            .into_iter()
+            .map(|point| VectorMatch {
+                id: match point.id {
+                    Value::String(id) => id,
+                    id => id.to_string(),
+                },
+                score: point.score,
+                payload: point.payload.unwrap_or(Value::Null),
+            })
+            .collect())
+    }
+
+    async fn upsert(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()> {
+        let points: Vec<Value> = points
+            .into_iter()
+            .map(|point| json!({
+                "id": point_id(&point.id),
+                "vector": point.vector,
+                "payload": point.payload,
+            }))
+            .collect();
+
+        let _: Value = self
+            .send(
+                reqwest::Method::PUT,
+                &format!("/collections/{}/points?wait=true", collection),
+                json!({ "points": points }),
+            )
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,468 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+pub const SIGNATURE_HEADER: &str = "X-NextRC-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-NextRC-Timestamp";
+pub const EVENT_HEADER: &str = "X-NextRC-Event";
+pub const DELIVERY_HEADER: &str = "X-NextRC-Delivery";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEventKind {
+    ExecutionCompleted,
+    ExecutionFailed,
+    QuotaExceeded,
+    ApprovalNeeded,
+}
+
+impl WebhookEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEventKind::ExecutionCompleted => "execution_completed",
+            WebhookEventKind::ExecutionFailed => "execution_failed",
+            WebhookEventKind::QuotaExceeded => "quota_exceeded",
+            WebhookEventKind::ApprovalNeeded => "approval_needed",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEvent {
+    pub id: Uuid,
+    pub kind: WebhookEventKind,
+    pub tenant_id: Option<String>,
+    pub timestamp_ms: u64,
+    pub data: serde_json::Value,
+}
+
+impl WebhookEvent {
+    pub fn new(kind: WebhookEventKind, tenant_id: Option<String>, data: serde_json::Value) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            kind,
+            tenant_id,
+            timestamp_ms: unix_millis(),
+            data,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    /// Shared secret used for the HMAC-SHA256 signature header.
+    pub secret: String,
+    /// Event kinds delivered to this endpoint; empty means all of them.
+    #[serde(default)]
+    pub events: HashSet<WebhookEventKind>,
+    /// Restricts delivery to events raised for a single tenant.
+    #[serde(default)]
+    pub tenant_id: Option<String>,
+}
+
+impl WebhookEndpoint {
+    pub fn accepts(&self, event: &WebhookEvent) -> bool {
+        let kind_matches = self.events.is_empty() || self.events.contains(&event.kind);
+        let tenant_matches = self.tenant_id.is_none() || self.tenant_id == event.tenant_id;
+        kind_matches && tenant_matches
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `attempt` (1-based), doubling each time.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 1u32.checked_shl(attempt.saturating_sub(1)).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .checked_mul(factor)
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub endpoints: Vec<WebhookEndpoint>,
+    pub retry: RetryPolicy,
+    pub request_timeout: Duration,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        Self {
+            endpoints: Vec::new(),
+            retry: RetryPolicy::default(),
+            request_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+#[async_trait]
+pub trait WebhookTransport: Send + Sync {
+    /// Sends a POST request and returns the HTTP status code.
+    async fn post(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<u16>;
+}
+
+#[cfg(feature = "webhooks-http")]
+pub struct HttpWebhookTransport {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "webhooks-http")]
+impl HttpWebhookTransport {
+    pub fn new() -> Result<Self> {
+        Ok(Self {
+            client: reqwest::Client::builder().build()?,
+        })
+    }
+}
+
+#[cfg(feature = "webhooks-http")]
+#[async_trait]
+impl WebhookTransport for HttpWebhookTransport {
+    async fn post(
+        &self,
+        url: &str,
+        headers: &[(String, String)],
+        body: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<u16> {
+        let mut request = self
+            .client
+            .post(url)
+            .timeout(timeout)
+            .header("Content-Type", "application/json")
+            .body(body);
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        Ok(request.send().await?.status().as_u16())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DeliveryReport {
+    pub url: String,
+    pub attempts: u32,
+    pub status: Option<u16>,
+    pub delivered: bool,
+}
+
+/// Fans lifecycle events out to the configured endpoints. Deliveries run in
+/// background tasks so callers are never blocked on slow receivers.
+pub struct WebhookDispatcher {
+    config: WebhookConfig,
+    transport: Arc<dyn WebhookTransport>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(config: WebhookConfig, transport: Arc<dyn WebhookTransport>) -> Self {
+        Self { config, transport }
+    }
+
+    #[cfg(feature = "webhooks-http")]
+    pub fn with_http(config: WebhookConfig) -> Result<Self> {
+        Ok(Self::new(config, Arc::new(HttpWebhookTransport::new()?)))
+    }
+
+    pub fn config(&self) -> &WebhookConfig {
+        &self.config
+    }
+
+    /// Queues `event` for every matching endpoint and returns immediately.
+    pub fn dispatch(self: &Arc<Self>, event: WebhookEvent) {
+        for endpoint in self.config.endpoints.iter().filter(|e| e.accepts(&event)) {
+            let dispatcher = self.clone();
+            let endpoint = endpoint.clone();
+            let event = event.clone();
+            tokio::spawn(async move {
+                let report = dispatcher.deliver(&endpoint, &event).await;
+                if !report.delivered {
+                    warn!(
+                        "Webhook {} for event {} dropped after {} attempts",
+                        report.url, event.id, report.attempts
+                    );
+                }
+            });
+        }
+    }
+
+    /// Delivers `event` to one endpoint, retrying on transport errors,
+    /// 429 and 5xx responses according to the retry policy.
+    pub async fn deliver(&self, endpoint: &WebhookEndpoint, event: &WebhookEvent) -> DeliveryReport {
+        let body = match serde_json::to_vec(event) {
+            Ok(body) => body,
+            Err(e) => {
+                warn!("Failed to serialize webhook event {}: {}", event.id, e);
+                return DeliveryReport {
+                    url: endpoint.url.clone(),
+                    attempts: 0,
+                    status: None,
+                    delivered: false,
+                };
+            }
+        };
+
+        let mut attempts = 0;
+        let mut last_status = None;
+        let max_attempts = self.config.retry.max_attempts.max(1);
+
+        while attempts < max_attempts {
+            if attempts > 0 {
+                tokio::time::sleep(self.config.retry.backoff(attempts)).await;
+            }
+            attempts += 1;
+
+            let timestamp = unix_millis().to_string();
+            let headers = vec![
+                (EVENT_HEADER.to_string(), event.kind.as_str().to_string()),
+                (DELIVERY_HEADER.to_string(), event.id.to_string()),
+                (TIMESTAMP_HEADER.to_string(), timestamp.clone()),
+                (
+                    SIGNATURE_HEADER.to_string(),
+                    sign_payload(&endpoint.secret, &timestamp, &body),
+                ),
+            ];
+
+            match self
+                .transport
+                .post(&endpoint.url, &headers, body.clone(), self.config.request_timeout)
+                .await
+            {
+                Ok(status) if (200..300).contains(&status) => {
+                    debug!("Delivered webhook {} to {} (attempt {})", event.id, endpoint.url, attempts);
+                    return DeliveryReport {
+                        url: endpoint.url.clone(),
+                        attempts,
+                        status: Some(status),
+                        delivered: true,
+                    };
+                }
+                Ok(status) if status == 429 || status >= 500 => {
+                    debug!("Webhook {} returned {}, retrying", endpoint.url, status);
+                    last_status = Some(status);
+                }
+                Ok(status) => {
+                    warn!("Webhook {} rejected event {} with {}", endpoint.url, event.id, status);
+                    last_status = Some(status);
+                    break;
+                }
+                Err(e) => {
+                    debug!("Webhook {} delivery failed: {}", endpoint.url, e);
+                }
+            }
+        }
+
+        DeliveryReport {
+            url: endpoint.url.clone(),
+            attempts,
+            status: last_status,
+            delivered: false,
+        }
+    }
+}
+
+/// Computes the `sha256=<hex>` signature over `"{timestamp}.{body}"`.
+pub fn sign_payload(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Checks a signature produced by `sign_payload` in constant time.
+pub fn verify_signature(secret: &str, timestamp: &str, body: &[u8], signature: &str) -> Result<()> {
+    let digest = signature
+        .strip_prefix("sha256=")
+        .ok_or_else(|| anyhow!("Unsupported signature scheme"))?;
+    let expected = hex::decode(digest)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac.verify_slice(&expected)
+        .map_err(|_| anyhow!("Webhook signature mismatch"))
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parking_lot::Mutex;
+
+    /// Answers each POST with the next scripted status, recording the
+    /// headers it was sent.
+    struct ScriptedTransport {
+        statuses: Mutex<Vec<u16>>,
+        requests: Mutex<Vec<Vec<(String, String)>>>,
+    }
+
+    #[async_trait]
+    impl WebhookTransport for ScriptedTransport {
+        async fn post(&self, _url: &str, headers: &[(String, String)], _body: Vec<u8>, _timeout: Duration) -> Result<u16> {
+            self.requests.lock().push(headers.to_vec());
+            let mut statuses = self.statuses.lock();
+            if statuses.is_empty() {
+                return Err(anyhow!("connection refused"));
+            }
+            Ok(statuses.remove(0))
+        }
+    }
+
+    fn scripted(statuses: Vec<u16>) -> (WebhookDispatcher, Arc<ScriptedTransport>) {
+        let transport = Arc::new(ScriptedTransport {
+            statuses: Mutex::new(statuses),
+            requests: Mutex::new(Vec::new()),
+        });
+        let config = WebhookConfig {
+            retry: RetryPolicy {
+                max_attempts: 3,
+                initial_backoff: Duration::from_millis(1),
+                max_backoff: Duration::from_millis(2),
+            },
+            ..Default::default()
+        };
+        (WebhookDispatcher::new(config, transport.clone()), transport)
+    }
+
+    fn endpoint() -> WebhookEndpoint {
+        WebhookEndpoint {
+            url: "https://hooks.example.com/next-rc".to_string(),
+            secret: "s3cret".to_string(),
+            events: HashSet::new(),
+            tenant_id: None,
+        }
+    }
+
+    #[test]
+    fn test_signature_round_trip() {
+        let body = br#"{"kind":"execution_failed"}"#;
+        let signature = sign_payload("s3cret", "1700000000000", body);
+        assert!(signature.starts_with("sha256="));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+
+        verify_signature("s3cret", "1700000000000", body, &signature).unwrap();
+    }
+
+    #[test]
+    fn test_signature_mismatch() {
+        let body = br#"{"kind":"execution_failed"}"#;
+        let signature = sign_payload("s3cret", "1700000000000", body);
+
+        let mismatch = |secret, timestamp, body: &[u8]| {
+            verify_signature(secret, timestamp, body, &signature).unwrap_err().to_string()
+        };
+        assert_eq!(mismatch("other", "1700000000000", body), "Webhook signature mismatch");
+        assert_eq!(mismatch("s3cret", "1700000000001", body), "Webhook signature mismatch");
+        assert_eq!(mismatch("s3cret", "1700000000000", b"{}"), "Webhook signature mismatch");
+
+        let digest = signature.strip_prefix("sha256=").unwrap();
+        let error = verify_signature("s3cret", "1700000000000", body, digest).unwrap_err();
+        assert_eq!(error.to_string(), "Unsupported signature scheme");
+        assert!(verify_signature("s3cret", "1700000000000", body, "sha256=zz").is_err());
+    }
+
+    #[test]
+    fn test_backoff_schedule() {
+        let retry = RetryPolicy::default();
+        let schedule: Vec<_> = (1..=8).map(|attempt| retry.backoff(attempt).as_millis()).collect();
+        assert_eq!(schedule, [500, 1_000, 2_000, 4_000, 8_000, 16_000, 30_000, 30_000]);
+
+        // Doubling past the range of the factor stays at the cap
+        assert_eq!(retry.backoff(40), retry.max_backoff);
+        assert_eq!(retry.backoff(u32::MAX), retry.max_backoff);
+    }
+
+    #[test]
+    fn test_endpoint_filters() {
+        let event = WebhookEvent::new(WebhookEventKind::QuotaExceeded, Some("acme".to_string()), serde_json::Value::Null);
+        assert!(endpoint().accepts(&event));
+
+        let failures_only = WebhookEndpoint {
+            events: HashSet::from([WebhookEventKind::ExecutionFailed]),
+            ..endpoint()
+        };
+        assert!(!failures_only.accepts(&event));
+
+        let other_tenant = WebhookEndpoint {
+            tenant_id: Some("globex".to_string()),
+            ..endpoint()
+        };
+        assert!(!other_tenant.accepts(&event));
+    }
+
+    #[tokio::test]
+    async fn test_delivery_retries() {
+        let event = WebhookEvent::new(WebhookEventKind::ExecutionFailed, None, serde_json::json!({ "error": "boom" }));
+
+        let (dispatcher, transport) = scripted(vec![503, 429, 204]);
+        let report = dispatcher.deliver(&endpoint(), &event).await;
+        assert!(report.delivered);
+        assert_eq!((report.attempts, report.status), (3, Some(204)));
+
+        let requests = transport.requests.lock();
+        let header = |name| requests[2].iter().find(|(header, _)| header == name).unwrap().1.clone();
+        assert_eq!(header(EVENT_HEADER), "execution_failed");
+        assert_eq!(header(DELIVERY_HEADER), event.id.to_string());
+        let body = serde_json::to_vec(&event).unwrap();
+        verify_signature("s3cret", &header(TIMESTAMP_HEADER), &body, &header(SIGNATURE_HEADER)).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delivery_gives_up() {
+        let event = WebhookEvent::new(WebhookEventKind::ExecutionFailed, None, serde_json::Value::Null);
+
+        // Transport errors and 5xx are retried up to the attempt limit
+        let (dispatcher, _) = scripted(vec![500]);
+        let report = dispatcher.deliver(&endpoint(), &event).await;
+        assert!(!report.delivered);
+        assert_eq!((report.attempts, report.status), (3, Some(500)));
+
+        // Other 4xx responses are final
+        let (dispatcher, _) = scripted(vec![410, 204]);
+        let report = dispatcher.deliver(&endpoint(), &event).await;
+        assert!(!report.delivered);
+        assert_eq!((report.attempts, report.status), (1, Some(410)));
+    }
+}
@@ -72,6 +72,7 @@ fn benchmark_execution(c: &mut Criterion) {
             timeout: Duration::from_secs(1),
             memory_limit: 1024 * 1024,
             permissions: Permissions::new(TrustLevel::Low),
+            ..Default::default()
         };
         
         c.bench_function("lucet_execution", |b| {
@@ -0,0 +1,126 @@
+use next_rc_shared::{CompileError, Diagnostic};
+use std::collections::HashMap;
+use wasmtime::Module;
+
+use crate::instance::HOST_IMPORTS;
+
+/// Tags compile diagnostics with the bundle module they came from.
+pub fn attribute(error: anyhow::Error, name: &str) -> anyhow::Error {
+    let diagnostics = CompileError::diagnostics_of(&error)
+        .into_iter()
+        .map(|diagnostic| match diagnostic.file {
+            Some(_) => diagnostic,
+            None => diagnostic.in_file(name),
+        })
+        .collect();
+    CompileError::new(diagnostics).into()
+}
+
+/// Resolves the modules the main module (index 0) links against, returning
+/// their indices in instantiation order, dependencies first.
+pub fn link_order(components: &[(&str, &Module)]) -> Result<Vec<usize>, CompileError> {
+    let mut diagnostics = Vec::new();
+    let mut by_name = HashMap::new();
+
+    for (index, (name, _)) in components.iter().enumerate() {
+        if HOST_IMPORTS.iter().any(|(module, _)| module == name) {
+            diagnostics.push(Diagnostic::error(format!("Module name `{}` is reserved for host functions", name)).in_file(*name));
+        }
+        if by_name.insert(*name, index).is_some() {
+            diagnostics.push(Diagnostic::error(format!("Duplicate module name `{}` in bundle", name)).in_file(*name));
+        }
+    }
+
+    let mut dependencies = vec![Vec::new(); components.len()];
+    for (index, (name, module)) in components.iter().enumerate() {
+        for import in module.imports() {
+            if HOST_IMPORTS.contains(&(import.module(), import.name())) {
+                continue;
+            }
+            match by_name.get(import.module()) {
+                Some(&target) if target != index => {
+                    if components[target].1.get_export(import.name()).is_none() {
+                        diagnostics.push(Diagnostic::error(format!(
+                            "Unresolved import {}::{}: `{}` has no such export",
+                            import.module(),
+                            import.name(),
+                            import.module()
+                        )).in_file(*name));
+                    }
+                    if !dependencies[index].contains(&target) {
+                        dependencies[index].push(target);
+                    }
+                }
+                _ => diagnostics.push(Diagnostic::error(format!(
+                    "Unresolved import {}::{}",
+                    import.module(),
+                    import.name()
+                )).in_file(*name)),
+            }
+        }
+    }
+
+    if !diagnostics.is_empty() {
+        return Err(CompileError::new(diagnostics));
+    }
+
+    let mut order = Vec::new();
+    let mut visiting = Vec::new();
+    visit(0, components, &dependencies, &mut visiting, &mut order)?;
+    order.pop();
+    Ok(order)
+}
+
+fn visit(
+    index: usize,
+    components: &[(&str, &Module)],
+    dependencies: &[Vec<usize>],
+    visiting: &mut Vec<usize>,
+    order: &mut Vec<usize>,
+) -> Result<(), CompileError> {
+    if order.contains(&index) {
+        return Ok(());
+    }
+    if let Some(position) = visiting.iter().position(|&i| i == index) {
+        let cycle: Vec<&str> = visiting[position..]
+            .iter()
+            .chain(std::iter::once(&index))
+            .map(|&i| components[i].0)
+            .collect();
+        return Err(Diagnostic::error(format!("Import cycle: {}", cycle.join(" -> "))).into());
+    }
+
+    visiting.push(index);
+    for &dependency in &dependencies[index] {
+        visit(dependency, components, dependencies, visiting, order)?;
+    }
+    visiting.pop();
+    order.push(index);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime::Engine;
+
+    #[test]
+    fn test_link_order() {
+        let engine = Engine::default();
+        let module = |wat: &str| Module::new(&engine, wat).unwrap();
+
+        let app = module(r#"(module (import "math" "double" (func (param i32) (result i32))))"#);
+        let math = module(r#"(module (import "util" "one" (func (result i32))) (func (export "double") (param i32) (result i32) local.get 0))"#);
+        let util = module(r#"(module (func (export "one") (result i32) i32.const 1))"#);
+        let order = link_order(&[("app", &app), ("util", &util), ("math", &math)]).unwrap();
+        assert_eq!(order, vec![1, 2]);
+
+        let err = link_order(&[("app", &app)]).unwrap_err();
+        assert_eq!(err.diagnostics[0].file.as_deref(), Some("app"));
+
+        let cyclic = module(r#"(module (import "app" "f" (func)) (func (export "double") (param i32) (result i32) local.get 0))"#);
+        let app = module(r#"(module (import "math" "double" (func (param i32) (result i32))) (func (export "f")))"#);
+        let err = link_order(&[("app", &app), ("math", &cyclic)]).unwrap_err();
+        assert_eq!(err.to_string(), "Import cycle: app -> math -> app");
+    }
+}
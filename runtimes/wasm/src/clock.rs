@@ -0,0 +1,139 @@
+use anyhow::Result;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use wasmtime::{Caller, Extern, Linker, Memory};
+
+use crate::instance::StoreData;
+
+/// WASI module the clock shim is provided under.
+pub const WASI_MODULE: &str = "wasi_snapshot_preview1";
+
+const CLOCK_REALTIME: i32 = 0;
+const CLOCK_THREAD_CPUTIME: i32 = 3;
+
+const ERRNO_SUCCESS: i32 = 0;
+const ERRNO_FAULT: i32 = 21;
+const ERRNO_INVAL: i32 = 28;
+const ERRNO_NOTSUP: i32 = 58;
+
+const SUBSCRIPTION_SIZE: usize = 48;
+const EVENT_SIZE: usize = 32;
+const EVENTTYPE_CLOCK: u8 = 0;
+const SUBCLOCKFLAGS_ABSTIME: u16 = 1;
+
+/// Adds `clock_time_get`, `clock_res_get` and `poll_oneoff`. Guests running
+/// with `ExecutionConfig::virtual_time` read the virtual clock and their
+/// sleeps advance it instead of waiting.
+pub fn add_to_linker(linker: &mut Linker<StoreData>) -> Result<()> {
+    linker.func_wrap(WASI_MODULE, "clock_time_get", |mut caller: Caller<'_, StoreData>, clock_id: i32, _precision: i64, time_ptr: i32| {
+        let Some(now) = now_ns(caller.data_mut(), clock_id) else {
+            return ERRNO_INVAL;
+        };
+        write_bytes(&mut caller, time_ptr, &now.to_le_bytes())
+    })?;
+
+    linker.func_wrap(WASI_MODULE, "clock_res_get", |mut caller: Caller<'_, StoreData>, clock_id: i32, resolution_ptr: i32| {
+        if !(CLOCK_REALTIME..=CLOCK_THREAD_CPUTIME).contains(&clock_id) {
+            return ERRNO_INVAL;
+        }
+        write_bytes(&mut caller, resolution_ptr, &1u64.to_le_bytes())
+    })?;
+
+    linker.func_wrap_async(WASI_MODULE, "poll_oneoff", |mut caller: Caller<'_, StoreData>, (in_ptr, out_ptr, subscriptions, events_ptr): (i32, i32, i32, i32)| {
+        Box::new(async move { poll_oneoff(&mut caller, in_ptr, out_ptr, subscriptions, events_ptr).await })
+    })?;
+
+    Ok(())
+}
+
+fn now_ns(data: &mut StoreData, clock_id: i32) -> Option<u64> {
+    if !(CLOCK_REALTIME..=CLOCK_THREAD_CPUTIME).contains(&clock_id) {
+        return None;
+    }
+
+    Some(match (&mut data.clock, clock_id) {
+        (Some(clock), CLOCK_REALTIME) => clock.realtime_ns(),
+        (Some(clock), _) => clock.monotonic_ns(),
+        (None, CLOCK_REALTIME) => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64),
+        (None, _) => data.start_time.elapsed().as_nanos() as u64,
+    })
+}
+
+/// Only clock subscriptions are supported: the call sleeps until the earliest
+/// one fires and reports every subscription due by then. Other subscription
+/// types complete immediately with `ENOTSUP`.
+async fn poll_oneoff(caller: &mut Caller<'_, StoreData>, in_ptr: i32, out_ptr: i32, subscriptions: i32, events_ptr: i32) -> i32 {
+    let Some(memory) = guest_memory(caller) else {
+        return ERRNO_FAULT;
+    };
+    if subscriptions <= 0 {
+        return ERRNO_INVAL;
+    }
+
+    let mut input = vec![0u8; subscriptions as usize * SUBSCRIPTION_SIZE];
+    if memory.read(&*caller, in_ptr as u32 as usize, &mut input).is_err() {
+        return ERRNO_FAULT;
+    }
+
+    // (userdata, event type, errno, timeout) per subscription
+    let mut pending = Vec::new();
+    for subscription in input.chunks_exact(SUBSCRIPTION_SIZE) {
+        let userdata = u64::from_le_bytes(subscription[0..8].try_into().unwrap());
+        let tag = subscription[8];
+        if tag != EVENTTYPE_CLOCK {
+            pending.push((userdata, tag, ERRNO_NOTSUP, Duration::ZERO));
+            continue;
+        }
+
+        let clock_id = u32::from_le_bytes(subscription[16..20].try_into().unwrap()) as i32;
+        let timeout = u64::from_le_bytes(subscription[24..32].try_into().unwrap());
+        let flags = u16::from_le_bytes(subscription[40..42].try_into().unwrap());
+        let Some(now) = now_ns(caller.data_mut(), clock_id) else {
+            pending.push((userdata, tag, ERRNO_INVAL, Duration::ZERO));
+            continue;
+        };
+        let wait = if flags & SUBCLOCKFLAGS_ABSTIME != 0 { timeout.saturating_sub(now) } else { timeout };
+        pending.push((userdata, tag, ERRNO_SUCCESS, Duration::from_nanos(wait)));
+    }
+
+    let wait = pending.iter().map(|(_, _, _, wait)| *wait).min().unwrap_or_default();
+    let data = caller.data_mut();
+    match &mut data.clock {
+        Some(clock) => clock.sleep(wait),
+        None => {
+            // Wake at the deadline so the timeout trap still fires
+            let remaining = data.deadline.map_or(wait, |deadline| deadline.saturating_duration_since(Instant::now()));
+            tokio::time::sleep(wait.min(remaining)).await;
+        }
+    }
+
+    let mut output = Vec::new();
+    for (userdata, tag, errno, _) in pending.iter().filter(|(_, _, _, due)| *due <= wait) {
+        let mut event = [0u8; EVENT_SIZE];
+        event[0..8].copy_from_slice(&userdata.to_le_bytes());
+        event[8..10].copy_from_slice(&(*errno as u16).to_le_bytes());
+        event[10] = *tag;
+        output.extend_from_slice(&event);
+    }
+
+    let count = (output.len() / EVENT_SIZE) as u32;
+    if memory.write(&mut *caller, out_ptr as u32 as usize, &output).is_err() {
+        return ERRNO_FAULT;
+    }
+    write_bytes(caller, events_ptr, &count.to_le_bytes())
+}
+
+pub(crate) fn guest_memory(caller: &mut Caller<'_, StoreData>) -> Option<Memory> {
+    match caller.get_export("memory") {
+        Some(Extern::Memory(memory)) => Some(memory),
+        _ => None,
+    }
+}
+
+fn write_bytes(caller: &mut Caller<'_, StoreData>, ptr: i32, bytes: &[u8]) -> i32 {
+    match guest_memory(caller) {
+        Some(memory) if memory.write(&mut *caller, ptr as u32 as usize, bytes).is_ok() => ERRNO_SUCCESS,
+        _ => ERRNO_FAULT,
+    }
+}
\ No newline at end of file
@@ -1,16 +1,82 @@
 use anyhow::{anyhow, Result};
 use cranelift_codegen::settings::{self, Configurable};
-use next_rc_shared::{Language, ModuleId};
-use std::sync::Arc;
+use next_rc_shared::{CompileError, Diagnostic, Language, ModuleId, RuntimeCapabilities, ToolchainStatus, TrustLevel};
+use std::collections::BTreeMap;
+use std::sync::{Arc, OnceLock};
+use tokio::io::{AsyncRead, AsyncReadExt};
 use uuid::Uuid;
-use wasmtime::{Config, Engine, OptLevel};
+use wasmparser::{BinaryReaderError, Chunk, Parser, ValidPayload, Validator, WasmFeatures};
+use wasmtime::{Config, Engine, ExternType, Module, OptLevel, ValType};
+
+use crate::instance::HOST_IMPORTS;
+use crate::manifest::WasmManifest;
+use crate::runtime::WasmConfig;
+
+/// Engine named in execution fingerprints, following the `wasmtime` dependency.
+pub const ENGINE: &str = "wasmtime 41";
+
+/// Post-MVP proposals that are only enabled for the trust levels
+/// `WasmConfig` lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Proposal {
+    ExceptionHandling,
+    Gc,
+}
+
+impl Proposal {
+    pub fn name(self) -> &'static str {
+        match self {
+            Proposal::ExceptionHandling => "exception-handling",
+            Proposal::Gc => "GC",
+        }
+    }
+    
+    fn features(self) -> WasmFeatures {
+        match self {
+            Proposal::ExceptionHandling => WasmFeatures::EXCEPTIONS | WasmFeatures::LEGACY_EXCEPTIONS,
+            Proposal::Gc => WasmFeatures::GC,
+        }
+    }
+}
 
 pub struct WasmCompiler {
     engine: Arc<Engine>,
+    toolchains: &'static Toolchains,
+    /// Trust levels allowed to run modules using exception handling.
+    exception_handling: Vec<TrustLevel>,
+    /// Trust levels allowed to run modules using GC.
+    gc: Vec<TrustLevel>,
+}
+
+/// Compilers for the source languages, probed once per process when the
+/// first `WasmCompiler` is created.
+struct Toolchains {
+    rustc: ToolchainStatus,
+    clang: ToolchainStatus,
+    tinygo: ToolchainStatus,
+}
+
+impl Toolchains {
+    fn get() -> &'static Self {
+        static TOOLCHAINS: OnceLock<Toolchains> = OnceLock::new();
+        TOOLCHAINS.get_or_init(|| Self {
+            rustc: ToolchainStatus::probe(
+                "rustc",
+                "install Rust with the wasm32-unknown-unknown target or disable Rust support",
+            ),
+            clang: ToolchainStatus::probe("clang", "install wasi-sdk or disable C support"),
+            tinygo: ToolchainStatus::probe("tinygo", "install TinyGo or disable Go support"),
+        })
+    }
 }
 
 impl WasmCompiler {
     pub fn new() -> Result<Self> {
+        Self::with_config(&WasmConfig::default())
+    }
+    
+    /// Enables the gated proposals `wasm_config` allows for any trust level.
+    pub fn with_config(wasm_config: &WasmConfig) -> Result<Self> {
         let mut config = Config::new();
         
         // Optimize for fast instantiation
@@ -28,68 +94,335 @@ impl WasmCompiler {
         config.wasm_threads(false);
         config.wasm_multi_memory(false);
         
+        // Proposals gated by trust level; checked per execution
+        let gc = !wasm_config.gc.is_empty();
+        config.wasm_exceptions(!wasm_config.exception_handling.is_empty());
+        config.wasm_function_references(gc);
+        config.wasm_gc(gc);
+        
         // Memory configuration for fast allocation
-        config.static_memory_maximum_size(4 * 1024 * 1024); // 4MB
-        config.static_memory_guard_size(64 * 1024); // 64KB guard pages
-        config.dynamic_memory_guard_size(64 * 1024);
+        config.memory_reservation(4 * 1024 * 1024); // 4MB
+        config.memory_guard_size(64 * 1024); // 64KB guard pages
         
         // Enable memory protection keys if available
         config.memory_init_cow(true);
         
+        // Guests run as futures and yield back to the executor on epoch ticks
+        config.async_support(true);
+        config.epoch_interruption(true);
+        
         let engine = Engine::new(&config)?;
         
         Ok(Self {
             engine: Arc::new(engine),
+            toolchains: Toolchains::get(),
+            exception_handling: wasm_config.exception_handling.clone(),
+            gc: wasm_config.gc.clone(),
         })
     }
     
+    /// Languages `compile` accepts on this host: WASM and WAT always, source
+    /// languages only when their toolchain was found.
+    pub fn capabilities(&self) -> RuntimeCapabilities {
+        let Toolchains { rustc, clang, tinygo } = self.toolchains;
+        let mut languages = vec![Language::Wasm];
+        if rustc.available() {
+            languages.push(Language::Rust);
+        }
+        if clang.available() {
+            languages.extend([Language::C, Language::Cpp]);
+        }
+        if tinygo.available() {
+            languages.push(Language::Go);
+        }
+        
+        RuntimeCapabilities {
+            languages,
+            toolchains: vec![rustc.clone(), clang.clone(), tinygo.clone()],
+        }
+    }
+    
     pub fn get_engine(&self) -> Arc<Engine> {
         self.engine.clone()
     }
     
+    /// The `Config` settings `new` builds the engine with; keep the two in
+    /// sync so fingerprints describe the engine that actually ran.
+    pub fn settings(&self) -> BTreeMap<String, String> {
+        let enabled = |levels: &[TrustLevel]| if levels.is_empty() { "false" } else { "true" };
+        [
+            ("cranelift_opt_level", "speed"),
+            ("cranelift_nan_canonicalization", "false"),
+            ("wasm_simd", "true"),
+            ("wasm_bulk_memory", "true"),
+            ("wasm_multi_value", "true"),
+            ("wasm_reference_types", "true"),
+            ("wasm_threads", "false"),
+            ("wasm_multi_memory", "false"),
+            ("wasm_exceptions", enabled(&self.exception_handling)),
+            ("wasm_function_references", enabled(&self.gc)),
+            ("wasm_gc", enabled(&self.gc)),
+            ("memory_reservation", "4194304"),
+            ("memory_guard_size", "65536"),
+            ("memory_init_cow", "true"),
+            ("async_support", "true"),
+            ("epoch_interruption", "true"),
+        ]
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+    }
+    
     pub fn compile(&self, code: &[u8], language: Language) -> Result<(ModuleId, Vec<u8>)> {
-        let wasm_bytes = match language {
-            Language::Wasm => code.to_vec(),
-            Language::Rust => self.compile_rust_to_wasm(code)?,
-            Language::C | Language::Cpp => self.compile_c_to_wasm(code)?,
-            _ => return Err(anyhow!("Unsupported language for WASM compilation: {:?}", language)),
-        };
+        let wasm_bytes = self.to_wasm_bytes(code, language)?;
         
         // Pre-compile and validate
-        let _ = wasmtime::Module::new(&self.engine, &wasm_bytes)?;
+        Module::new(&self.engine, &wasm_bytes).map_err(|e| self.module_error(e, &wasm_bytes))?;
         
         let module_id = ModuleId(Uuid::new_v4());
         Ok((module_id, wasm_bytes))
     }
     
-    fn compile_rust_to_wasm(&self, _code: &[u8]) -> Result<Vec<u8>> {
-        // In a real implementation, this would invoke rustc with wasm32-unknown-unknown target
-        // For now, return a simple test module
-        let wat = r#"
-            (module
-                (memory (export "memory") 1)
-                (func (export "_start")
-                    nop
-                )
-            )
-        "#;
+    /// Reads a module from `reader`, validating binaries section by section
+    /// as they arrive so malformed uploads are rejected without reading the
+    /// rest. Text formats can't be parsed incrementally and are read in full.
+    pub async fn compile_stream<R>(&self, mut reader: R, language: Language) -> Result<Vec<u8>>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut buffer = Vec::new();
+        let mut chunk = vec![0u8; 64 * 1024];
+        
+        while buffer.len() < 4 {
+            let read = reader.read(&mut chunk).await?;
+            if read == 0 {
+                break;
+            }
+            buffer.extend_from_slice(&chunk[..read]);
+        }
+        
+        if language != Language::Wasm || !buffer.starts_with(b"\0asm") {
+            reader.read_to_end(&mut buffer).await?;
+            return self.to_wasm_bytes(&buffer, language);
+        }
+        
+        let mut parser = Parser::new(0);
+        let mut validator = Validator::new();
+        let mut offset = 0;
+        let mut eof = false;
+        
+        loop {
+            let (consumed, payload) = match parser.parse(&buffer[offset..], eof).map_err(Self::binary_error)? {
+                Chunk::NeedMoreData(_) => {
+                    let read = reader.read(&mut chunk).await?;
+                    eof = read == 0;
+                    buffer.extend_from_slice(&chunk[..read]);
+                    continue;
+                }
+                Chunk::Parsed { consumed, payload } => (consumed, payload),
+            };
+            
+            match validator.payload(&payload).map_err(Self::binary_error)? {
+                ValidPayload::Func(func, body) => {
+                    func.into_validator(Default::default())
+                        .validate(&body)
+                        .map_err(Self::binary_error)?;
+                }
+                ValidPayload::Parser(_) => {
+                    return Err(CompileError::from(Diagnostic::error("Nested modules are not supported")).into());
+                }
+                ValidPayload::End(_) => break,
+                ValidPayload::Ok => {}
+            }
+            offset += consumed;
+        }
         
-        wat::parse_str(wat).map_err(|e| anyhow!("Failed to parse WAT: {}", e))
+        Ok(buffer)
     }
     
-    fn compile_c_to_wasm(&self, _code: &[u8]) -> Result<Vec<u8>> {
-        // In a real implementation, this would invoke clang with wasm32 target
-        // For now, return a simple test module
-        let wat = r#"
-            (module
-                (memory (export "memory") 1)
-                (func (export "main") (result i32)
-                    i32.const 0
-                )
-            )
-        "#;
+    /// Explains engine rejections caused by proposals that are disabled, or
+    /// by the legacy exception-handling encoding wasmtime never runs, instead
+    /// of a bare validation error.
+    pub fn module_error(&self, e: anyhow::Error, wasm_bytes: &[u8]) -> CompileError {
+        if Self::uses_legacy_exceptions(wasm_bytes) {
+            return Diagnostic::error(
+                "Module uses the legacy exception-handling encoding (`try`/`catch`), which this engine \
+                 does not run; rebuild it with `try_table`",
+            ).into();
+        }
+        
+        let disabled: Vec<&str> = Self::used_proposals(wasm_bytes)
+            .into_iter()
+            .filter(|&proposal| self.allowed_levels(proposal).is_empty())
+            .map(Proposal::name)
+            .collect();
+        if disabled.is_empty() {
+            return Diagnostic::error(format!("Invalid WASM module: {:#}", e)).into();
+        }
+        Diagnostic::error(format!(
+            "Module uses the {} proposal, which is disabled in this runtime's WasmConfig",
+            disabled.join(" and ")
+        )).into()
+    }
+    
+    /// Gated proposals the module needs. Modules that don't validate with
+    /// every proposal enabled report none.
+    pub fn used_proposals(wasm_bytes: &[u8]) -> Vec<Proposal> {
+        let full = WasmFeatures::default() | WasmFeatures::LEGACY_EXCEPTIONS;
+        if !Self::validates(wasm_bytes, full) {
+            return Vec::new();
+        }
         
-        wat::parse_str(wat).map_err(|e| anyhow!("Failed to parse WAT: {}", e))
+        [Proposal::ExceptionHandling, Proposal::Gc]
+            .into_iter()
+            .filter(|proposal| !Self::validates(wasm_bytes, full - proposal.features()))
+            .collect()
+    }
+    
+    fn uses_legacy_exceptions(wasm_bytes: &[u8]) -> bool {
+        let full = WasmFeatures::default() | WasmFeatures::LEGACY_EXCEPTIONS;
+        Self::validates(wasm_bytes, full) && !Self::validates(wasm_bytes, full - WasmFeatures::LEGACY_EXCEPTIONS)
+    }
+    
+    fn validates(wasm_bytes: &[u8], features: WasmFeatures) -> bool {
+        Validator::new_with_features(features).validate_all(wasm_bytes).is_ok()
+    }
+    
+    /// Trust levels allowed to run modules that use `proposal`.
+    pub fn allowed_levels(&self, proposal: Proposal) -> &[TrustLevel] {
+        match proposal {
+            Proposal::ExceptionHandling => &self.exception_handling,
+            Proposal::Gc => &self.gc,
+        }
+    }
+    
+    /// An error for each of `proposals` that `trust_level` may not use.
+    pub fn check_proposals(&self, proposals: &[Proposal], trust_level: TrustLevel) -> Vec<Diagnostic> {
+        proposals
+            .iter()
+            .filter(|&&proposal| !self.allowed_levels(proposal).contains(&trust_level))
+            .map(|proposal| {
+                Diagnostic::error(format!(
+                    "Module uses the {} proposal, which is not enabled for {:?} trust",
+                    proposal.name(),
+                    trust_level
+                ))
+            })
+            .collect()
+    }
+    
+    fn binary_error(e: BinaryReaderError) -> CompileError {
+        Diagnostic::error(format!("{} (at offset {:#x})", e.message(), e.offset())).into()
+    }
+    
+    /// Compiles the module and checks it against the host environment
+    /// without caching anything.
+    pub fn validate(&self, code: &[u8], language: Language, trust_level: TrustLevel) -> Vec<Diagnostic> {
+        let wasm_bytes = match self.to_wasm_bytes(code, language) {
+            Ok(bytes) => bytes,
+            Err(e) => return CompileError::diagnostics_of(&e),
+        };
+        
+        let module = match Module::new(&self.engine, &wasm_bytes) {
+            Ok(module) => module,
+            Err(e) => return self.module_error(e, &wasm_bytes).diagnostics,
+        };
+        
+        let mut diagnostics = self.check_proposals(&Self::used_proposals(&wasm_bytes), trust_level);
+        
+        for import in module.imports() {
+            if !HOST_IMPORTS.contains(&(import.module(), import.name())) {
+                diagnostics.push(Diagnostic::error(format!(
+                    "Unresolved import {}::{}",
+                    import.module(),
+                    import.name()
+                )));
+            }
+        }
+        
+        let manifest = match WasmManifest::from_module(&module, &wasm_bytes) {
+            Ok(manifest) => manifest,
+            Err(e) => {
+                diagnostics.extend(e.diagnostics);
+                return diagnostics;
+            }
+        };
+        if let Some(manifest) = &manifest {
+            diagnostics.extend(manifest.check_trust(trust_level));
+        }
+        
+        let declares_entry = manifest.as_ref().is_some_and(|manifest| !manifest.entry_points.is_empty());
+        let entry_ok = declares_entry || match module.get_export("_start") {
+            Some(ExternType::Func(func)) => {
+                let results: Vec<ValType> = func.results().collect();
+                func.params().len() == 0 && results.len() == 1 && matches!(results[0], ValType::I32)
+            }
+            _ => false,
+        };
+        if !entry_ok {
+            diagnostics.push(Diagnostic::warning(
+                "No `_start` export of type () -> i32; execution will report no entry point",
+            ));
+        }
+        
+        diagnostics
+    }
+    
+    fn to_wasm_bytes(&self, code: &[u8], language: Language) -> Result<Vec<u8>> {
+        match language {
+            Language::Wasm if code.starts_with(b"\0asm") => Ok(code.to_vec()),
+            Language::Wasm => {
+                let text = std::str::from_utf8(code)
+                    .map_err(|_| CompileError::from(Diagnostic::error("Module is neither WASM binary nor UTF-8 WAT text")))?;
+                Ok(Self::parse_wat(text)?)
+            }
+            Language::Rust => self.compile_rust_to_wasm(code),
+            Language::C | Language::Cpp => self.compile_c_to_wasm(code, language),
+            Language::Go => self.compile_go_to_wasm(code),
+            _ => Err(anyhow!("Unsupported language for WASM compilation: {:?}", language)),
+        }
+    }
+    
+    /// Parses WAT text, reporting failures with their source position.
+    fn parse_wat(text: &str) -> std::result::Result<Vec<u8>, CompileError> {
+        let to_diagnostic = |e: wast::Error| {
+            let (line, column) = e.span().linecol_in(text);
+            let diagnostic = Diagnostic::error(e.message()).at(line as u32 + 1, column as u32 + 1);
+            match text.lines().nth(line) {
+                Some(snippet) => CompileError::from(diagnostic.with_snippet(snippet)),
+                None => CompileError::from(diagnostic),
+            }
+        };
+        
+        let buffer = wast::parser::ParseBuffer::new(text).map_err(to_diagnostic)?;
+        let mut module = wast::parser::parse::<wast::Wat>(&buffer).map_err(to_diagnostic)?;
+        module.encode().map_err(to_diagnostic)
+    }
+    
+    /// Builds a `cdylib` whose `#[no_mangle] extern "C" fn _start() -> i32`
+    /// is the entry point.
+    fn compile_rust_to_wasm(&self, code: &[u8]) -> Result<Vec<u8>> {
+        let args = [
+            "--target", "wasm32-unknown-unknown", "--crate-type", "cdylib", "-C", "opt-level=2",
+            "-o", "module.wasm", "lib.rs",
+        ];
+        Ok(self.toolchains.rustc.run(code, "lib.rs", "module.wasm", &args)?)
+    }
+    
+    /// Builds with the wasi-sdk sysroot, without a libc entry point, so the
+    /// guest's exported functions are the entry points.
+    fn compile_c_to_wasm(&self, code: &[u8], language: Language) -> Result<Vec<u8>> {
+        let source = if language == Language::Cpp { "main.cpp" } else { "main.c" };
+        let args = [
+            "--target=wasm32-wasi", "-O2", "-nostartfiles", "-Wl,--no-entry", "-Wl,--export-dynamic",
+            "-o", "module.wasm", source,
+        ];
+        Ok(self.toolchains.clang.run(code, source, "module.wasm", &args)?)
+    }
+    
+    fn compile_go_to_wasm(&self, code: &[u8]) -> Result<Vec<u8>> {
+        let args = ["build", "-target=wasm-unknown", "-opt=2", "-o", "module.wasm", "main.go"];
+        Ok(self.toolchains.tinygo.run(code, "main.go", "module.wasm", &args)?)
     }
     
     pub fn create_optimized_cranelift_flags() -> settings::Flags {
@@ -117,16 +450,17 @@ impl WasmCompiler {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use next_rc_shared::Severity;
     
     #[test]
     fn test_compiler_creation() {
-        let compiler = LucetCompiler::new().unwrap();
+        let compiler = WasmCompiler::new().unwrap();
         assert!(Arc::strong_count(&compiler.engine) == 1);
     }
     
     #[test]
     fn test_wasm_compilation() {
-        let compiler = LucetCompiler::new().unwrap();
+        let compiler = WasmCompiler::new().unwrap();
         
         let wat = r#"
             (module
@@ -142,4 +476,139 @@ mod tests {
         assert!(!compiled_bytes.is_empty());
         assert_ne!(module_id.0, Uuid::nil());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_validate_clean_module() {
+        let compiler = WasmCompiler::new().unwrap();
+        
+        let wat = r#"
+            (module
+                (import "env" "write_output" (func $write_output (param i32 i32)))
+                (memory (export "memory") 1)
+                (func (export "_start") (result i32)
+                    i32.const 0
+                )
+            )
+        "#;
+        assert!(compiler.validate(wat.as_bytes(), Language::Wasm, TrustLevel::Low).is_empty());
+    }
+    
+    #[test]
+    fn test_validate_unresolved_import() {
+        let compiler = WasmCompiler::new().unwrap();
+        
+        let wat = r#"
+            (module
+                (import "env" "open_socket" (func (result i32)))
+                (func (export "_start") (result i32)
+                    i32.const 0
+                )
+            )
+        "#;
+        let diagnostics = compiler.validate(wat.as_bytes(), Language::Wasm, TrustLevel::Low);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].message, "Unresolved import env::open_socket");
+    }
+    
+    #[test]
+    fn test_validate_missing_start() {
+        let compiler = WasmCompiler::new().unwrap();
+        
+        let wat = r#"
+            (module
+                (func (export "main") (param i32) (result i32)
+                    local.get 0
+                )
+            )
+        "#;
+        let diagnostics = compiler.validate(wat.as_bytes(), Language::Wasm, TrustLevel::Low);
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert!(diagnostics[0].message.starts_with("No `_start` export"), "{:?}", diagnostics);
+    }
+    
+    const EH_MODULE: &str = r#"
+        (module
+            (tag $oops)
+            (func (export "_start") (result i32)
+                (block $caught
+                    (try_table (catch $oops $caught)
+                        throw $oops
+                    )
+                )
+                i32.const 0
+            )
+        )
+    "#;
+    
+    const GC_MODULE: &str = r#"
+        (module
+            (type $point (struct (field i32)))
+            (func (export "_start") (result i32)
+                (drop (struct.new $point (i32.const 1)))
+                i32.const 0
+            )
+        )
+    "#;
+    
+    #[test]
+    fn test_gated_proposals_compile_and_are_detected() {
+        let compiler = WasmCompiler::new().unwrap();
+        
+        let (_, eh) = compiler.compile(EH_MODULE.as_bytes(), Language::Wasm).unwrap();
+        assert_eq!(WasmCompiler::used_proposals(&eh), vec![Proposal::ExceptionHandling]);
+        
+        let (_, gc) = compiler.compile(GC_MODULE.as_bytes(), Language::Wasm).unwrap();
+        assert_eq!(WasmCompiler::used_proposals(&gc), vec![Proposal::Gc]);
+    }
+    
+    #[test]
+    fn test_proposals_are_gated_by_trust_level() {
+        let compiler = WasmCompiler::new().unwrap();
+        let proposals = [Proposal::ExceptionHandling, Proposal::Gc];
+        
+        assert_eq!(compiler.check_proposals(&proposals, TrustLevel::Low).len(), 2);
+        assert!(compiler.check_proposals(&proposals, TrustLevel::Medium).is_empty());
+        
+        let diagnostics = compiler.validate(GC_MODULE.as_bytes(), Language::Wasm, TrustLevel::Low);
+        assert!(diagnostics[0].message.contains("GC proposal, which is not enabled for Low trust"), "{:?}", diagnostics);
+        assert!(compiler.validate(GC_MODULE.as_bytes(), Language::Wasm, TrustLevel::High).is_empty());
+    }
+    
+    #[test]
+    fn test_disabled_proposals_are_named() {
+        let compiler = WasmCompiler::with_config(&WasmConfig {
+            exception_handling: Vec::new(),
+            gc: Vec::new(),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(compiler.settings()["wasm_gc"], "false");
+        
+        let err = compiler.compile(EH_MODULE.as_bytes(), Language::Wasm).unwrap_err();
+        assert!(err.to_string().contains("exception-handling proposal, which is disabled"), "{}", err);
+        
+        let err = compiler.compile(GC_MODULE.as_bytes(), Language::Wasm).unwrap_err();
+        assert!(err.to_string().contains("GC proposal, which is disabled"), "{}", err);
+    }
+    
+    #[test]
+    fn test_legacy_exceptions_are_named() {
+        let compiler = WasmCompiler::new().unwrap();
+        
+        let legacy = r#"
+            (module
+                (tag $oops)
+                (func (export "_start") (result i32)
+                    try
+                        throw $oops
+                    catch $oops
+                    end
+                    i32.const 0
+                )
+            )
+        "#;
+        let err = compiler.compile(legacy.as_bytes(), Language::Wasm).unwrap_err();
+        assert!(err.to_string().contains("legacy exception-handling encoding"), "{}", err);
+    }
+}
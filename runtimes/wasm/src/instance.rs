@@ -1,10 +1,40 @@
 use anyhow::{anyhow, Result};
-use next_rc_shared::{ExecutionConfig, ExecutionResult, InstanceId, MemorySlot, ModuleId};
-use std::sync::Arc;
+use next_rc_shared::{
+    Capability, ExecutionConfig, ExecutionLogs, ExecutionResult, InstanceId, LogCapture, LogLimits,
+    MemorySlot, ModuleId, RuntimeError, SourceMap, TimingBreakdown, TrustLevel, VectorStore, VirtualClock,
+};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
 use std::time::{Duration, Instant};
 use tokio::sync::oneshot;
 use tokio::time::timeout;
-use wasmtime::{Engine, Linker, Module, Store, TypedFunc};
+use tracing::debug;
+use wasmtime::{Engine, Linker, Module, Store, TypedFunc, UpdateDeadline, WasmBacktrace};
+
+use crate::clock::{self, WASI_MODULE};
+use crate::compiler::Proposal;
+use crate::io;
+use crate::limits::{self, StoreLimits};
+use crate::logging;
+use crate::manifest::WasmManifest;
+use crate::module_cache::CompiledModule;
+use crate::vector;
+
+/// Host functions provided by `create_linker`, as (module, name) pairs.
+pub const HOST_IMPORTS: &[(&str, &str)] = &[
+    ("env", "print"),
+    ("env", "log"),
+    ("env", "vector_search"),
+    ("env", "input_len"),
+    ("env", "read_input"),
+    ("env", "write_output"),
+    (WASI_MODULE, "clock_time_get"),
+    (WASI_MODULE, "clock_res_get"),
+    (WASI_MODULE, "poll_oneoff"),
+];
+
+/// Interval at which the engine epoch is incremented.
+pub const EPOCH_TICK: Duration = Duration::from_millis(1);
 
 pub struct Instance {
     pub id: InstanceId,
@@ -12,53 +42,181 @@ pub struct Instance {
     pub memory_slot: MemorySlot,
     pub store: Store<StoreData>,
     pub entry_func: Option<TypedFunc<(), i32>>,
+    pub module: Arc<Module>,
+    pub manifest: Option<WasmManifest>,
+    pub source_map: Option<Arc<SourceMap>>,
+    /// Trust-gated proposals the module uses.
+    pub proposals: Vec<Proposal>,
 }
 
 pub struct StoreData {
     pub memory_used: usize,
     pub start_time: Instant,
+    /// Epoch ticks a guest may run before yielding to the executor.
+    pub slice_ticks: u64,
+    /// Guest is trapped at the first yield point past this instant.
+    pub deadline: Option<Instant>,
+    pub yields: Arc<AtomicU64>,
+    /// Clock the guest sees instead of the host's, if virtualized.
+    pub clock: Option<VirtualClock>,
+    /// Records the guest emitted through `env.log` during the current execution.
+    pub logs: LogCapture,
+    /// Table and instance caps for the current trust level.
+    pub limits: StoreLimits,
+    /// Instances in the store, including bundle dependencies.
+    pub instances: usize,
+    /// Tables defined across every instance in the store.
+    pub tables: usize,
+    /// Elements across every table in the store.
+    pub table_elements: u64,
+    /// Growth last allowed by `table_growing`, undone if the growth fails.
+    pending_table_growth: u64,
+    /// Store behind `env.vector_search`, set only for executions granted
+    /// `Capability::VectorSearch`.
+    pub vector_store: Option<Arc<dyn VectorStore>>,
+    /// Bytes served by `env.read_input` during the current execution.
+    pub input: Vec<u8>,
+    /// Bytes written through `env.write_output` during the current execution.
+    pub output: Option<Vec<u8>>,
 }
 
 pub struct InstanceManager {
     engine: Arc<Engine>,
-    instances: parking_lot::RwLock<std::collections::HashMap<InstanceId, Arc<parking_lot::Mutex<Instance>>>>,
+    max_consecutive_slice_ms: AtomicU64,
+    instances: parking_lot::RwLock<std::collections::HashMap<InstanceId, Arc<tokio::sync::Mutex<Instance>>>>,
+    yields: parking_lot::RwLock<std::collections::HashMap<InstanceId, Arc<AtomicU64>>>,
+    vector_store: parking_lot::RwLock<Option<Arc<dyn VectorStore>>>,
 }
 
 impl InstanceManager {
-    pub fn new(engine: Arc<Engine>) -> Self {
+    pub fn new(engine: Arc<Engine>, max_consecutive_slice_ms: u64) -> Self {
+        Self::spawn_epoch_ticker(Arc::downgrade(&engine));
+        
         Self {
             engine,
+            max_consecutive_slice_ms: AtomicU64::new(max_consecutive_slice_ms),
             instances: parking_lot::RwLock::new(std::collections::HashMap::new()),
+            yields: parking_lot::RwLock::new(std::collections::HashMap::new()),
+            vector_store: parking_lot::RwLock::new(None),
         }
     }
     
-    pub fn create_instance(
+    /// Drives epoch-based preemption until the engine is dropped.
+    fn spawn_epoch_ticker(engine: Weak<Engine>) {
+        std::thread::Builder::new()
+            .name("wasm-epoch-ticker".to_string())
+            .spawn(move || {
+                while let Some(engine) = engine.upgrade() {
+                    engine.increment_epoch();
+                    drop(engine);
+                    std::thread::sleep(EPOCH_TICK);
+                }
+                debug!("Engine dropped, stopping epoch ticker");
+            })
+            .expect("failed to spawn epoch ticker thread");
+    }
+    
+    pub fn max_consecutive_slice_ms(&self) -> u64 {
+        self.max_consecutive_slice_ms.load(Ordering::Relaxed)
+    }
+    
+    /// Applies to executions started after the call.
+    pub fn set_max_consecutive_slice_ms(&self, slice_ms: u64) {
+        self.max_consecutive_slice_ms.store(slice_ms, Ordering::Relaxed);
+    }
+    
+    /// Applies to executions started after the call.
+    pub fn set_vector_store(&self, store: Option<Arc<dyn VectorStore>>) {
+        *self.vector_store.write() = store;
+    }
+    
+    fn slice_ticks(&self) -> u64 {
+        (self.max_consecutive_slice_ms() / EPOCH_TICK.as_millis() as u64).max(1)
+    }
+    
+    /// Number of times the instance has been preempted so far.
+    pub fn yield_count(&self, id: &InstanceId) -> Option<u64> {
+        self.yields.read().get(id).map(|yields| yields.load(Ordering::Relaxed))
+    }
+    
+    pub async fn create_instance(
         &self,
         id: InstanceId,
         module_id: ModuleId,
-        module: Arc<Module>,
+        compiled: CompiledModule,
+        dependencies: Vec<(String, Arc<Module>)>,
         memory_slot: MemorySlot,
-    ) -> Result<Arc<parking_lot::Mutex<Instance>>> {
+    ) -> Result<Arc<tokio::sync::Mutex<Instance>>> {
+        let manifest = compiled.metadata.manifest;
+        let yields = Arc::new(AtomicU64::new(0));
         let mut store = Store::new(
             &self.engine,
             StoreData {
                 memory_used: 0,
                 start_time: Instant::now(),
+                slice_ticks: self.slice_ticks(),
+                deadline: None,
+                yields: yields.clone(),
+                clock: None,
+                logs: LogCapture::new(LogLimits::default()),
+                limits: StoreLimits::for_trust_level(TrustLevel::High),
+                instances: dependencies.len() + 1,
+                tables: dependencies
+                    .iter()
+                    .map(|(_, dependency)| dependency.as_ref())
+                    .chain([compiled.module.as_ref()])
+                    .map(|module| module.resources_required().num_tables as usize)
+                    .sum(),
+                table_elements: 0,
+                pending_table_growth: 0,
+                vector_store: None,
+                input: Vec::new(),
+                output: None,
             },
         );
         
+        let data = store.data();
+        if let Some(violation) = data.limits.check_store(data.instances, data.tables, 0) {
+            return Err(violation.into());
+        }
+        
         // Configure store limits
         store.limiter(|data| data as &mut dyn wasmtime::ResourceLimiter);
         
+        // Yield to the executor at the end of every slice, trapping once the
+        // execution deadline has passed
+        store.epoch_deadline_callback(|context| {
+            let data = context.data();
+            if data.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return Err(anyhow!("Execution timeout"));
+            }
+            data.yields.fetch_add(1, Ordering::Relaxed);
+            Ok(UpdateDeadline::Yield(data.slice_ticks))
+        });
+        store.set_epoch_deadline(store.data().slice_ticks);
+        
         // Create linker with host functions
-        let linker = self.create_linker()?;
+        let mut linker = self.create_linker()?;
+        
+        // Link bundle dependencies into the same store under their names
+        for (name, dependency) in &dependencies {
+            let dependency_instance = linker
+                .instantiate_async(&mut store, dependency)
+                .await
+                .map_err(Self::limit_error)?;
+            linker.instance(&mut store, name, dependency_instance)?;
+        }
         
         // Instantiate the module
-        let instance = linker.instantiate(&mut store, &module)?;
+        let instance = linker
+            .instantiate_async(&mut store, &compiled.module)
+            .await
+            .map_err(Self::limit_error)?;
         
         // Get entry point function
+        let entry_point = manifest.as_ref().map_or("_start", |manifest| manifest.default_entry_point());
         let entry_func = instance
-            .get_typed_func::<(), i32>(&mut store, "_start")
+            .get_typed_func::<(), i32>(&mut store, entry_point)
             .ok();
         
         let instance = Instance {
@@ -67,37 +225,45 @@ impl InstanceManager {
             memory_slot,
             store,
             entry_func,
+            module: compiled.module,
+            manifest,
+            source_map: compiled.metadata.source_map,
+            proposals: compiled.metadata.proposals,
         };
         
-        let instance_arc = Arc::new(parking_lot::Mutex::new(instance));
+        let instance_arc = Arc::new(tokio::sync::Mutex::new(instance));
         
+        self.yields.write().insert(id.clone(), yields);
         let mut instances = self.instances.write();
         instances.insert(id, instance_arc.clone());
         
         Ok(instance_arc)
     }
     
-    pub fn get_instance(&self, id: &InstanceId) -> Option<Arc<parking_lot::Mutex<Instance>>> {
+    pub fn get_instance(&self, id: &InstanceId) -> Option<Arc<tokio::sync::Mutex<Instance>>> {
         let instances = self.instances.read();
         instances.get(id).cloned()
     }
     
-    pub fn remove_instance(&self, id: &InstanceId) -> Option<Arc<parking_lot::Mutex<Instance>>> {
+    pub fn remove_instance(&self, id: &InstanceId) -> Option<Arc<tokio::sync::Mutex<Instance>>> {
+        self.yields.write().remove(id);
         let mut instances = self.instances.write();
         instances.remove(id)
     }
     
     pub async fn execute_instance(
         &self,
-        instance: Arc<parking_lot::Mutex<Instance>>,
+        instance: Arc<tokio::sync::Mutex<Instance>>,
         config: ExecutionConfig,
     ) -> Result<ExecutionResult> {
         let (tx, rx) = oneshot::channel();
         
         // Execute in a separate task with timeout
         let config_clone = config.clone();
+        let slice_ticks = self.slice_ticks();
+        let vector_store = self.vector_store.read().clone();
         tokio::spawn(async move {
-            let result = Self::execute_with_config(instance, config_clone).await;
+            let result = Self::execute_with_config(instance, config_clone, slice_ticks, vector_store).await;
             let _ = tx.send(result);
         });
         
@@ -110,36 +276,89 @@ impl InstanceManager {
                 error: Some("Execution timeout".to_string()),
                 execution_time: config.timeout,
                 memory_used: 0,
+                logs: ExecutionLogs::default(),
+                attestation: None,
+                environment_fingerprint: None,
+                timing: None,
             }),
         }
     }
     
     async fn execute_with_config(
-        instance: Arc<parking_lot::Mutex<Instance>>,
-        _config: ExecutionConfig,
+        instance: Arc<tokio::sync::Mutex<Instance>>,
+        config: ExecutionConfig,
+        slice_ticks: u64,
+        vector_store: Option<Arc<dyn VectorStore>>,
     ) -> Result<ExecutionResult> {
         let start_time = Instant::now();
         
-        let mut instance_guard = instance.lock();
+        let mut instance_guard = instance.lock().await;
+        
+        // Refuse to run modules whose declared capabilities aren't granted
+        if let Some(manifest) = &instance_guard.manifest {
+            let missing = manifest.missing_capabilities(&config.permissions);
+            if !missing.is_empty() {
+                return Err(RuntimeError::SecurityError(format!(
+                    "Module requires capabilities that were not granted: {:?}",
+                    missing
+                )).into());
+            }
+        }
         
         // Set resource limits
-        instance_guard.store.data_mut().memory_used = 0;
+        let data = instance_guard.store.data_mut();
+        data.memory_used = 0;
+        data.slice_ticks = slice_ticks;
+        data.deadline = Some(start_time + config.timeout);
+        data.start_time = start_time;
+        data.clock = config.virtual_time.map(VirtualClock::new);
+        data.logs = LogCapture::new(config.trace_verbosity.log_limits(config.log_limits));
+        data.limits = StoreLimits::for_trust_level(config.permissions.trust_level);
+        data.vector_store = vector_store.filter(|_| config.permissions.has_capability(Capability::VectorSearch));
+        data.input = config.input.clone().unwrap_or_default();
+        data.output = None;
+        if let Some(violation) = data.limits.check_store(data.instances, data.tables, data.table_elements) {
+            return Err(violation.into());
+        }
+        if let Some(violation) = limits::check_process_limits(&config.process_limits) {
+            return Err(violation.into());
+        }
+        instance_guard.store.set_epoch_deadline(slice_ticks);
+        let setup_done = Instant::now();
         
-        let result = if let Some(entry_func) = instance_guard.entry_func {
-            match entry_func.call(&mut instance_guard.store, ()) {
+        let mut result = if let Some(entry_func) = instance_guard.entry_func.clone() {
+            match entry_func.call_async(&mut instance_guard.store, ()).await {
                 Ok(return_value) => ExecutionResult {
                     success: true,
-                    output: Some(return_value.to_string().into_bytes()), // Return the actual value
+                    output: Some(
+                        instance_guard
+                            .store
+                            .data_mut()
+                            .output
+                            .take()
+                            .unwrap_or_else(|| return_value.to_string().into_bytes()),
+                    ),
                     error: None,
                     execution_time: start_time.elapsed(),
                     memory_used: instance_guard.store.data().memory_used,
+                    logs: ExecutionLogs::default(),
+                    attestation: None,
+                    environment_fingerprint: None,
+                    timing: None,
                 },
+                Err(e) if matches!(e.downcast_ref::<RuntimeError>(), Some(RuntimeError::ResourceLimitExceeded(_))) => {
+                    return Err(e);
+                }
                 Err(e) => ExecutionResult {
                     success: false,
                     output: None,
-                    error: Some(format!("Execution error: {}", e)),
+                    error: Some(Self::describe_error(&e, &instance_guard)),
                     execution_time: start_time.elapsed(),
                     memory_used: instance_guard.store.data().memory_used,
+                    logs: ExecutionLogs::default(),
+                    attestation: None,
+                    environment_fingerprint: None,
+                    timing: None,
                 },
             }
         } else {
@@ -149,12 +368,54 @@ impl InstanceManager {
                 error: Some("No entry point found".to_string()),
                 execution_time: start_time.elapsed(),
                 memory_used: 0,
+                logs: ExecutionLogs::default(),
+                attestation: None,
+                environment_fingerprint: None,
+                timing: None,
             }
         };
         
+        let logs = std::mem::replace(&mut instance_guard.store.data_mut().logs, LogCapture::new(LogLimits::default()));
+        result.logs = logs.finish();
+        result.timing = Some(TimingBreakdown {
+            sandbox_setup: setup_done - start_time,
+            execute: setup_done.elapsed(),
+            ..Default::default()
+        });
+        
         Ok(result)
     }
     
+    /// Reports wasmtime's own store limit errors (instance, table and memory
+    /// counts) as `ResourceLimitExceeded`.
+    fn limit_error(e: anyhow::Error) -> anyhow::Error {
+        let message = e.root_cause().to_string();
+        match message.strip_prefix("resource limit exceeded: ") {
+            Some(limit) => RuntimeError::ResourceLimitExceeded(limit.to_string()).into(),
+            None => e,
+        }
+    }
+    
+    /// Root cause of a failed call, followed by the original source positions
+    /// of the module's trap frames when it ships a source map.
+    fn describe_error(e: &anyhow::Error, instance: &Instance) -> String {
+        let mut message = format!("Execution error: {}", e.root_cause());
+        
+        if let (Some(source_map), Some(backtrace)) = (&instance.source_map, e.downcast_ref::<WasmBacktrace>()) {
+            let image = instance.module.image_range();
+            let positions = backtrace
+                .frames()
+                .iter()
+                .filter(|frame| frame.module().image_range() == image)
+                .filter_map(|frame| source_map.lookup_offset(frame.module_offset()?));
+            for position in positions {
+                message.push_str(&format!("\n    at {}", position));
+            }
+        }
+        
+        message
+    }
+    
     fn create_linker(&self) -> Result<Linker<StoreData>> {
         let mut linker = Linker::new(&self.engine);
         
@@ -164,6 +425,11 @@ impl InstanceManager {
             println!("WASM print: ptr={}, len={}", ptr, len);
         })?;
         
+        clock::add_to_linker(&mut linker)?;
+        logging::add_to_linker(&mut linker)?;
+        vector::add_to_linker(&mut linker)?;
+        io::add_to_linker(&mut linker)?;
+        
         Ok(linker)
     }
 }
@@ -177,27 +443,47 @@ impl wasmtime::ResourceLimiter for StoreData {
         Ok(desired <= 128 * 1024 * 1024)
     }
     
-    fn table_growing(&mut self, _current: u32, _desired: u32, _maximum: Option<u32>) -> Result<bool> {
+    fn table_growing(&mut self, current: usize, desired: usize, _maximum: Option<usize>) -> Result<bool> {
+        let current = u32::try_from(current).unwrap_or(u32::MAX);
+        let desired = u32::try_from(desired).unwrap_or(u32::MAX);
+        if let Some(violation) = self.limits.check_table_growth(current, desired, self.table_elements) {
+            return Err(violation.into());
+        }
+        self.pending_table_growth = u64::from(desired.saturating_sub(current));
+        self.table_elements += self.pending_table_growth;
         Ok(true)
     }
+    
+    fn table_grow_failed(&mut self, _error: anyhow::Error) -> Result<()> {
+        self.table_elements -= std::mem::take(&mut self.pending_table_growth);
+        Ok(())
+    }
+    
+    fn instances(&self) -> usize {
+        self.limits.max_instances
+    }
+    
+    fn tables(&self) -> usize {
+        self.limits.max_tables
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::compiler::LucetCompiler;
-    use crate::memory_pool::LucetMemoryPool;
+    use crate::compiler::WasmCompiler;
+    use crate::memory_pool::WasmMemoryPool;
     use crate::module_cache::ModuleCache;
-    use next_rc_shared::{Language, Permissions, TrustLevel};
+    use next_rc_shared::{MemoryPool, Permissions, ProcessLimits, TrustLevel};
     use uuid::Uuid;
     
     #[tokio::test]
     async fn test_instance_creation_and_execution() {
-        let compiler = LucetCompiler::new().unwrap();
+        let compiler = WasmCompiler::new().unwrap();
         let engine = compiler.get_engine();
         let cache = ModuleCache::new(engine.clone());
-        let pool = LucetMemoryPool::new(10, 1024 * 1024).unwrap();
-        let manager = InstanceManager::new(engine);
+        let pool = WasmMemoryPool::new(10, 1024 * 1024).unwrap();
+        let manager = InstanceManager::new(engine, 10);
         
         // Compile a simple WASM module
         let wat = r#"
@@ -219,19 +505,95 @@ mod tests {
         let instance = manager.create_instance(
             instance_id.clone(),
             module_id,
-            compiled.module,
+            compiled,
+            Vec::new(),
             memory_slot,
-        ).unwrap();
+        ).await.unwrap();
         
         // Execute instance
         let config = ExecutionConfig {
             timeout: Duration::from_secs(5),
             memory_limit: 1024 * 1024,
             permissions: Permissions::new(TrustLevel::Low),
+            ..Default::default()
         };
         
         let result = manager.execute_instance(instance, config).await.unwrap();
         assert!(result.success);
         assert_eq!(result.error, None);
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_store_limits_follow_the_executing_trust_level() {
+        let compiler = WasmCompiler::new().unwrap();
+        let engine = compiler.get_engine();
+        let cache = ModuleCache::new(engine.clone());
+        let pool = WasmMemoryPool::new(10, 1024 * 1024).unwrap();
+        let manager = InstanceManager::new(engine, 10);
+        
+        // More tables than Low trust allows, fewer than Medium
+        let tables = "(table 1 funcref)".repeat(9);
+        let wat = format!(r#"(module {} (func (export "_start") (result i32) i32.const 0))"#, tables);
+        
+        let wasm_bytes = wat::parse_str(&wat).unwrap();
+        let module_id = ModuleId(Uuid::new_v4());
+        let compiled = cache.compile_and_cache(module_id.clone(), &wasm_bytes).unwrap();
+        
+        let instance = manager.create_instance(
+            InstanceId(Uuid::new_v4()),
+            module_id,
+            compiled,
+            Vec::new(),
+            pool.allocate().unwrap(),
+        ).await.unwrap();
+        
+        let execute = |trust_level| ExecutionConfig {
+            permissions: Permissions::new(trust_level),
+            ..Default::default()
+        };
+        
+        let err = manager.execute_instance(instance.clone(), execute(TrustLevel::Low)).await.unwrap_err();
+        assert!(err.to_string().contains("9 tables exceed the store limit of 8"), "{}", err);
+        
+        let result = manager.execute_instance(instance, execute(TrustLevel::Medium)).await.unwrap();
+        assert!(result.success);
+    }
+    
+    #[tokio::test]
+    async fn test_process_limits_below_the_guest_streams_are_exceeded() {
+        let compiler = WasmCompiler::new().unwrap();
+        let engine = compiler.get_engine();
+        let cache = ModuleCache::new(engine.clone());
+        let pool = WasmMemoryPool::new(10, 1024 * 1024).unwrap();
+        let manager = InstanceManager::new(engine, 10);
+        
+        let wasm_bytes = wat::parse_str(r#"(module (func (export "_start") (result i32) i32.const 0))"#).unwrap();
+        let module_id = ModuleId(Uuid::new_v4());
+        let compiled = cache.compile_and_cache(module_id.clone(), &wasm_bytes).unwrap();
+        
+        let instance = manager.create_instance(
+            InstanceId(Uuid::new_v4()),
+            module_id,
+            compiled,
+            Vec::new(),
+            pool.allocate().unwrap(),
+        ).await.unwrap();
+        
+        let execute = |max_open_files, max_processes| ExecutionConfig {
+            process_limits: ProcessLimits { max_open_files, max_processes },
+            ..Default::default()
+        };
+        
+        for config in [execute(Some(2), None), execute(None, Some(0))] {
+            let err = manager.execute_instance(instance.clone(), config).await.unwrap_err();
+            assert!(
+                matches!(err.downcast_ref::<RuntimeError>(), Some(RuntimeError::ResourceLimitExceeded(_))),
+                "{}",
+                err
+            );
+        }
+        
+        let result = manager.execute_instance(instance, execute(Some(3), Some(1))).await.unwrap();
+        assert!(result.success);
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::{anyhow, Result};
+use wasmtime::{Caller, Linker};
+
+use crate::clock::guest_memory;
+use crate::instance::StoreData;
+
+/// Most bytes a guest may write through `env.write_output` in one execution.
+pub const MAX_GUEST_OUTPUT: usize = 16 * 1024 * 1024;
+
+/// Adds the execution's input and output streams:
+///
+/// - `env.input_len() -> i32` is the length of the input, 0 without one.
+/// - `env.read_input(ptr, len) -> i32` copies up to `len` bytes from the
+///   start of the input to `ptr` and returns how many were copied.
+/// - `env.write_output(ptr, len)` appends to the output. A guest that writes
+///   any output has it returned in place of its entry point's return value.
+pub fn add_to_linker(linker: &mut Linker<StoreData>) -> Result<()> {
+    linker.func_wrap("env", "input_len", |caller: Caller<'_, StoreData>| -> i32 {
+        caller.data().input.len() as i32
+    })?;
+
+    linker.func_wrap("env", "read_input", |mut caller: Caller<'_, StoreData>, ptr: i32, len: i32| -> Result<i32> {
+        let memory = guest_memory(&mut caller).ok_or_else(|| anyhow!("Module does not export memory"))?;
+        let (memory, data) = memory.data_and_store_mut(&mut caller);
+        let count = data.input.len().min(len.max(0) as usize);
+
+        let start = ptr as u32 as usize;
+        memory
+            .get_mut(start..start.saturating_add(count))
+            .ok_or_else(|| anyhow!("Input buffer out of bounds"))?
+            .copy_from_slice(&data.input[..count]);
+        Ok(count as i32)
+    })?;
+
+    linker.func_wrap("env", "write_output", |mut caller: Caller<'_, StoreData>, ptr: i32, len: i32| -> Result<()> {
+        let memory = guest_memory(&mut caller).ok_or_else(|| anyhow!("Module does not export memory"))?;
+        let (memory, data) = memory.data_and_store_mut(&mut caller);
+
+        let start = ptr as u32 as usize;
+        let bytes = memory
+            .get(start..start.saturating_add(len as u32 as usize))
+            .ok_or_else(|| anyhow!("Output out of bounds"))?;
+        let output = data.output.get_or_insert_with(Vec::new);
+        if output.len() + bytes.len() > MAX_GUEST_OUTPUT {
+            return Err(anyhow!("Output exceeds {} bytes", MAX_GUEST_OUTPUT));
+        }
+        output.extend_from_slice(bytes);
+        Ok(())
+    })?;
+
+    Ok(())
+}
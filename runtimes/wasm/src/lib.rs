@@ -1,12 +1,21 @@
+pub mod bundle;
+pub mod clock;
 pub mod compiler;
 pub mod context;
 pub mod instance;
+pub mod io;
+pub mod limits;
+pub mod logging;
+pub mod manifest;
 pub mod memory_pool;
 pub mod module_cache;
 pub mod runtime;
+pub mod transform;
+pub mod vector;
 
 pub use runtime::WasmRuntime;
 pub use runtime::WasmConfig;
+pub use transform::StripDebugSections;
 
 #[cfg(test)]
 mod tests;
\ No newline at end of file
@@ -0,0 +1,171 @@
+use next_rc_shared::{ProcessLimits, RuntimeError, TrustLevel};
+
+/// Streams every guest holds in place of WASI's stdin, stdout and stderr:
+/// `env.read_input`, `env.write_output` and `env.log`.
+pub const GUEST_STREAMS: u64 = 3;
+
+/// Caps on the tables and instances a store may hold. Instantiation isn't
+/// tied to a caller, so it only enforces the most permissive trust level's
+/// limits; each execution then checks the store against the caller's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StoreLimits {
+    /// Elements a single table may grow to.
+    pub max_table_elements: u32,
+    /// Elements across every table in the store.
+    pub max_total_table_elements: u64,
+    pub max_tables: usize,
+    /// Instances in the store, including bundle dependencies.
+    pub max_instances: usize,
+}
+
+impl StoreLimits {
+    /// Lower trust levels get smaller tables and fewer instances.
+    pub fn for_trust_level(trust_level: TrustLevel) -> Self {
+        match trust_level {
+            TrustLevel::Low => Self {
+                max_table_elements: 10_000,
+                max_total_table_elements: 20_000,
+                max_tables: 8,
+                max_instances: 8,
+            },
+            TrustLevel::Medium => Self {
+                max_table_elements: 100_000,
+                max_total_table_elements: 500_000,
+                max_tables: 32,
+                max_instances: 32,
+            },
+            TrustLevel::High => Self {
+                max_table_elements: 1_000_000,
+                max_total_table_elements: 5_000_000,
+                max_tables: 128,
+                max_instances: 128,
+            },
+        }
+    }
+
+    /// Error for growing a table from `current` to `desired` elements while
+    /// the store's tables hold `total` elements, if that breaks a limit.
+    pub fn check_table_growth(&self, current: u32, desired: u32, total: u64) -> Option<RuntimeError> {
+        if desired > self.max_table_elements {
+            return Some(RuntimeError::ResourceLimitExceeded(format!(
+                "table of {} elements exceeds the limit of {}",
+                desired, self.max_table_elements
+            )));
+        }
+        let total = total + u64::from(desired.saturating_sub(current));
+        if total > self.max_total_table_elements {
+            return Some(RuntimeError::ResourceLimitExceeded(format!(
+                "{} table elements exceed the store limit of {}",
+                total, self.max_total_table_elements
+            )));
+        }
+        None
+    }
+
+    /// Error for a store already holding `instances` instances and `tables`
+    /// tables of `total_table_elements` elements, if that breaks a limit.
+    pub fn check_store(&self, instances: usize, tables: usize, total_table_elements: u64) -> Option<RuntimeError> {
+        if instances > self.max_instances {
+            return Some(RuntimeError::ResourceLimitExceeded(format!(
+                "{} instances exceed the store limit of {}",
+                instances, self.max_instances
+            )));
+        }
+        if tables > self.max_tables {
+            return Some(RuntimeError::ResourceLimitExceeded(format!(
+                "{} tables exceed the store limit of {}",
+                tables, self.max_tables
+            )));
+        }
+        if total_table_elements > self.max_total_table_elements {
+            return Some(RuntimeError::ResourceLimitExceeded(format!(
+                "{} table elements exceed the store limit of {}",
+                total_table_elements, self.max_total_table_elements
+            )));
+        }
+        None
+    }
+}
+
+/// Error for process limits no guest can run within. Guests hold no
+/// descriptors beyond their streams and can't start threads or processes,
+/// so only limits below that are ever exceeded.
+pub fn check_process_limits(limits: &ProcessLimits) -> Option<RuntimeError> {
+    if let Some(max) = limits.max_open_files.filter(|max| *max < GUEST_STREAMS) {
+        return Some(RuntimeError::ResourceLimitExceeded(format!(
+            "open file limit of {} is below the {} streams every guest holds",
+            max, GUEST_STREAMS
+        )));
+    }
+    if limits.max_processes == Some(0) {
+        return Some(RuntimeError::ResourceLimitExceeded(
+            "process limit of 0 leaves no thread to run on".to_string(),
+        ));
+    }
+    None
+}
+
+impl Default for StoreLimits {
+    fn default() -> Self {
+        Self::for_trust_level(TrustLevel::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_growth_limits() {
+        let low = StoreLimits::for_trust_level(TrustLevel::Low);
+
+        assert!(low.check_table_growth(0, 10_000, 0).is_none());
+        assert!(matches!(
+            low.check_table_growth(0, 10_001, 0),
+            Some(RuntimeError::ResourceLimitExceeded(_))
+        ));
+
+        // Only the growth counts towards the store total
+        assert!(low.check_table_growth(5_000, 10_000, 15_000).is_none());
+        assert!(low.check_table_growth(5_000, 10_000, 15_001).is_some());
+
+        let high = StoreLimits::for_trust_level(TrustLevel::High);
+        assert!(high.check_table_growth(0, 10_001, 0).is_none());
+    }
+
+    #[test]
+    fn test_store_limits() {
+        let low = StoreLimits::for_trust_level(TrustLevel::Low);
+
+        assert!(low.check_store(8, 8, 20_000).is_none());
+        for (instances, tables, elements) in [(9, 1, 0), (1, 9, 0), (1, 1, 20_001)] {
+            assert!(
+                matches!(low.check_store(instances, tables, elements), Some(RuntimeError::ResourceLimitExceeded(_))),
+                "{} instances, {} tables, {} elements",
+                instances,
+                tables,
+                elements
+            );
+        }
+
+        let medium = StoreLimits::for_trust_level(TrustLevel::Medium);
+        assert!(medium.check_store(9, 9, 20_001).is_none());
+    }
+
+    #[test]
+    fn test_process_limits() {
+        assert!(check_process_limits(&ProcessLimits::default()).is_none());
+        assert!(check_process_limits(&ProcessLimits { max_open_files: Some(3), max_processes: Some(1) }).is_none());
+
+        for limits in [
+            ProcessLimits { max_open_files: Some(2), max_processes: None },
+            ProcessLimits { max_open_files: None, max_processes: Some(0) },
+        ] {
+            assert!(
+                matches!(check_process_limits(&limits), Some(RuntimeError::ResourceLimitExceeded(_))),
+                "{:?}",
+                limits
+            );
+        }
+    }
+}
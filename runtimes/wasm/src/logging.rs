@@ -0,0 +1,28 @@
+use anyhow::{anyhow, Result};
+use next_rc_shared::LogLevel;
+use wasmtime::{Caller, Linker};
+
+use crate::clock::guest_memory;
+use crate::instance::StoreData;
+
+/// Adds `env.log(level, ptr, len)`, which records a UTF-8 message at `level`
+/// (0 = trace through 4 = error) in the execution's log capture. Records past
+/// the execution's `LogLimits` are dropped rather than failing the guest.
+pub fn add_to_linker(linker: &mut Linker<StoreData>) -> Result<()> {
+    linker.func_wrap("env", "log", |mut caller: Caller<'_, StoreData>, level: i32, ptr: i32, len: i32| -> Result<()> {
+        let level = LogLevel::from_guest(level).ok_or_else(|| anyhow!("Invalid log level {}", level))?;
+        let memory = guest_memory(&mut caller).ok_or_else(|| anyhow!("Module does not export memory"))?;
+
+        let start = ptr as u32 as usize;
+        let message = memory
+            .data(&caller)
+            .get(start..start.saturating_add(len as u32 as usize))
+            .ok_or_else(|| anyhow!("Log message out of bounds"))?;
+        let message = String::from_utf8_lossy(message).into_owned();
+
+        caller.data_mut().logs.push(level, &message);
+        Ok(())
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,136 @@
+use next_rc_shared::{Capability, CompileError, Diagnostic, Permissions, TrustLevel};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use wasmparser::{Parser, Payload};
+use wasmtime::{ExternType, Module, ValType};
+
+/// Custom section carrying the embedder manifest as JSON.
+pub const MANIFEST_SECTION: &str = "next-rc.manifest";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WasmManifest {
+    /// Exported `() -> i32` functions callers may run; the first is the default.
+    pub entry_points: Vec<String>,
+    /// Capabilities the module needs at runtime.
+    pub capabilities: HashSet<Capability>,
+    pub resources: ResourceHints,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceHints {
+    pub memory_limit: Option<usize>,
+    pub timeout_ms: Option<u64>,
+}
+
+impl WasmManifest {
+    /// Extracts and checks the manifest section of an already validated module.
+    pub fn from_module(module: &Module, wasm_bytes: &[u8]) -> Result<Option<Self>, CompileError> {
+        let Some(data) = custom_section(wasm_bytes, MANIFEST_SECTION)? else {
+            return Ok(None);
+        };
+
+        let manifest: Self = serde_json::from_slice(data).map_err(|e| {
+            CompileError::from(Self::error(format!("Invalid manifest: {}", e)).at(e.line() as u32, e.column() as u32))
+        })?;
+
+        let diagnostics: Vec<Diagnostic> = manifest
+            .entry_points
+            .iter()
+            .filter(|name| !Self::is_entry_point(module, name))
+            .map(|name| Self::error(format!("Declared entry point `{}` is not an exported () -> i32 function", name)))
+            .collect();
+        if !diagnostics.is_empty() {
+            return Err(CompileError::new(diagnostics));
+        }
+
+        Ok(Some(manifest))
+    }
+
+    /// Entry point run when the caller doesn't name one.
+    pub fn default_entry_point(&self) -> &str {
+        self.entry_points.first().map(String::as_str).unwrap_or("_start")
+    }
+
+    /// Declared capabilities `permissions` doesn't grant.
+    pub fn missing_capabilities(&self, permissions: &Permissions) -> Vec<Capability> {
+        self.capabilities
+            .iter()
+            .filter(|capability| !permissions.has_capability(**capability))
+            .copied()
+            .collect()
+    }
+
+    /// One error per declared capability that `trust_level` doesn't allow.
+    pub fn check_trust(&self, trust_level: TrustLevel) -> Vec<Diagnostic> {
+        self.missing_capabilities(&Permissions::new(trust_level))
+            .into_iter()
+            .map(|capability| {
+                Self::error(format!(
+                    "Capability {:?} is not allowed at trust level {:?}",
+                    capability, trust_level
+                ))
+            })
+            .collect()
+    }
+
+    fn is_entry_point(module: &Module, name: &str) -> bool {
+        match module.get_export(name) {
+            Some(ExternType::Func(func)) => {
+                let results: Vec<ValType> = func.results().collect();
+                func.params().len() == 0 && results.len() == 1 && matches!(results[0], ValType::I32)
+            }
+            _ => false,
+        }
+    }
+
+    fn error(message: String) -> Diagnostic {
+        Diagnostic::error(message).in_file(MANIFEST_SECTION)
+    }
+}
+
+/// Contents of the custom section called `name`, which may appear at most once.
+pub fn custom_section<'a>(wasm_bytes: &'a [u8], name: &str) -> Result<Option<&'a [u8]>, CompileError> {
+    let mut data = None;
+    for payload in Parser::new(0).parse_all(wasm_bytes) {
+        let payload = payload.map_err(|e| CompileError::from(Diagnostic::error(e.message())))?;
+        if let Payload::CustomSection(reader) = payload {
+            if reader.name() == name {
+                if data.is_some() {
+                    return Err(Diagnostic::error(format!("Duplicate {} section", name)).in_file(name).into());
+                }
+                data = Some(reader.data());
+            }
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasmtime::Engine;
+
+    #[test]
+    fn test_manifest_parsing_and_trust() {
+        let engine = Engine::default();
+        let wat = r#"(module
+            (@custom "next-rc.manifest" "{\"entry_points\": [\"run\"], \"capabilities\": [\"NetworkAccess\"], \"resources\": {\"timeout_ms\": 500}}")
+            (func (export "run") (result i32) i32.const 1))"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let module = Module::new(&engine, &wasm).unwrap();
+
+        let manifest = WasmManifest::from_module(&module, &wasm).unwrap().unwrap();
+        assert_eq!(manifest.default_entry_point(), "run");
+        assert_eq!(manifest.resources.timeout_ms, Some(500));
+        assert_eq!(manifest.check_trust(TrustLevel::Low).len(), 1);
+        assert!(manifest.check_trust(TrustLevel::High).is_empty());
+
+        let wat = r#"(module (@custom "next-rc.manifest" "{\"entry_points\": [\"missing\"]}"))"#;
+        let wasm = wat::parse_str(wat).unwrap();
+        let module = Module::new(&engine, &wasm).unwrap();
+        let err = WasmManifest::from_module(&module, &wasm).unwrap_err();
+        assert_eq!(err.diagnostics[0].file.as_deref(), Some(MANIFEST_SECTION));
+    }
+}
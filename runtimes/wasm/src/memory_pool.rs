@@ -1,8 +1,9 @@
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
 use memmap2::{MmapMut, MmapOptions};
-use next_rc_shared::{MemoryPool as MemoryPoolTrait, MemorySlot};
+use next_rc_shared::{MemoryPool as MemoryPoolTrait, MemorySlot, ScalablePool};
 use parking_lot::Mutex;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::ptr::NonNull;
 use std::sync::atomic::{AtomicUsize, Ordering};
 
@@ -11,21 +12,33 @@ const DEFAULT_POOL_SIZE: usize = 100; // 100 slots = 400MB total
 
 pub struct WasmMemoryPool {
     slots: Mutex<VecDeque<MemorySlot>>,
-    total_slots: usize,
+    total_slots: AtomicUsize,
     slot_size: usize,
     available_count: AtomicUsize,
-    mmaps: Mutex<Vec<MmapMut>>,
+    /// Mappings backing each slot, by slot ID.
+    mmaps: Mutex<HashMap<usize, MmapMut>>,
+    next_slot_id: AtomicUsize,
 }
 
 impl WasmMemoryPool {
     pub fn new(total_slots: usize, slot_size: usize) -> Result<Self> {
-        let mut slots = VecDeque::with_capacity(total_slots);
-        let mut mmaps = Vec::with_capacity(total_slots);
-        
-        // Pre-allocate all memory slots
-        for slot_id in 0..total_slots {
+        let pool = Self {
+            slots: Mutex::new(VecDeque::with_capacity(total_slots)),
+            total_slots: AtomicUsize::new(0),
+            slot_size,
+            available_count: AtomicUsize::new(0),
+            mmaps: Mutex::new(HashMap::with_capacity(total_slots)),
+            next_slot_id: AtomicUsize::new(0),
+        };
+        pool.grow(total_slots)?;
+        Ok(pool)
+    }
+    
+    /// Maps `count` more slots.
+    fn grow(&self, count: usize) -> Result<()> {
+        for _ in 0..count {
             let mut mmap = MmapOptions::new()
-                .len(slot_size)
+                .len(self.slot_size)
                 .map_anon()?;
             
             // Pre-fault pages to avoid page faults during execution
@@ -33,28 +46,47 @@ impl WasmMemoryPool {
             
             let ptr = NonNull::new(mmap.as_mut_ptr())
                 .ok_or_else(|| anyhow!("Failed to create non-null pointer"))?;
+            let slot_id = self.next_slot_id.fetch_add(1, Ordering::SeqCst);
             
-            slots.push_back(MemorySlot {
+            self.mmaps.lock().insert(slot_id, mmap);
+            self.slots.lock().push_back(MemorySlot {
                 ptr,
-                size: slot_size,
+                size: self.slot_size,
                 slot_id,
             });
-            
-            mmaps.push(mmap);
+            self.total_slots.fetch_add(1, Ordering::SeqCst);
+            self.available_count.fetch_add(1, Ordering::SeqCst);
         }
-        
-        Ok(Self {
-            slots: Mutex::new(slots),
-            total_slots,
-            slot_size,
-            available_count: AtomicUsize::new(total_slots),
-            mmaps: Mutex::new(mmaps),
-        })
+        Ok(())
+    }
+    
+    /// Maps or unmaps slots until the pool holds `total_slots`, returning the
+    /// number it holds. Only free slots are unmapped, so the pool never
+    /// shrinks below the slots in use.
+    pub fn resize(&self, total_slots: usize) -> Result<usize> {
+        let current = self.total_slots();
+        if total_slots > current {
+            self.grow(total_slots - current)?;
+        } else {
+            let mut slots = self.slots.lock();
+            let mut mmaps = self.mmaps.lock();
+            for _ in total_slots..current {
+                let Some(slot) = slots.pop_back() else { break };
+                mmaps.remove(&slot.slot_id);
+                self.total_slots.fetch_sub(1, Ordering::SeqCst);
+                self.available_count.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+        Ok(self.total_slots())
     }
     
     pub fn with_defaults() -> Result<Self> {
         Self::new(DEFAULT_POOL_SIZE, DEFAULT_SLOT_SIZE)
     }
+    
+    pub fn slot_size(&self) -> usize {
+        self.slot_size
+    }
 }
 
 impl MemoryPoolTrait for WasmMemoryPool {
@@ -85,7 +117,7 @@ impl MemoryPoolTrait for WasmMemoryPool {
     }
     
     fn total_slots(&self) -> usize {
-        self.total_slots
+        self.total_slots.load(Ordering::SeqCst)
     }
     
     fn available_slots(&self) -> usize {
@@ -93,6 +125,21 @@ impl MemoryPoolTrait for WasmMemoryPool {
     }
 }
 
+#[async_trait]
+impl ScalablePool for WasmMemoryPool {
+    fn name(&self) -> String {
+        "wasm:memory_slots".to_string()
+    }
+    
+    fn size(&self) -> usize {
+        self.total_slots()
+    }
+    
+    async fn resize(&self, target: usize) -> Result<usize> {
+        WasmMemoryPool::resize(self, target)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,4 +172,20 @@ mod tests {
         
         pool.release(slot2);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_memory_pool_resize() {
+        let pool = WasmMemoryPool::new(2, 1024).unwrap();
+        let slot = pool.allocate().unwrap();
+        
+        assert_eq!(pool.resize(4).unwrap(), 4);
+        assert_eq!(pool.available_slots(), 3);
+        
+        // The allocated slot stays mapped
+        assert_eq!(pool.resize(0).unwrap(), 1);
+        assert_eq!(pool.available_slots(), 0);
+        
+        pool.release(slot);
+        assert_eq!(pool.resize(0).unwrap(), 0);
+    }
+}
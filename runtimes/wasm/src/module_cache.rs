@@ -1,14 +1,23 @@
 use anyhow::Result;
-use next_rc_shared::ModuleId;
+use next_rc_shared::{CompileError, Diagnostic, ModuleId, SourceMap};
 use parking_lot::RwLock;
 use std::collections::HashMap;
 use std::sync::Arc;
 use wasmtime::{Engine, Module};
 
+use crate::compiler::{Proposal, WasmCompiler};
+use crate::manifest::{custom_section, WasmManifest};
+
+/// Custom section carrying a source map (JSON, revision 3) whose columns are
+/// byte offsets into the module.
+pub const SOURCE_MAP_SECTION: &str = "next-rc.source_map";
+
 #[derive(Clone)]
 pub struct CompiledModule {
     pub module: Arc<Module>,
     pub metadata: ModuleMetadata,
+    /// Bundle modules linked in before this one, as (import name, module).
+    pub dependencies: Vec<(String, ModuleId)>,
 }
 
 #[derive(Clone, Debug)]
@@ -17,6 +26,12 @@ pub struct ModuleMetadata {
     pub memory_pages: u32,
     pub exports: Vec<String>,
     pub imports: Vec<String>,
+    /// Parsed `next-rc.manifest` custom section, if the module has one.
+    pub manifest: Option<WasmManifest>,
+    /// Maps trap offsets back to the sources the module was built from.
+    pub source_map: Option<Arc<SourceMap>>,
+    /// Trust-gated proposals the module uses.
+    pub proposals: Vec<Proposal>,
 }
 
 pub struct ModuleCache {
@@ -52,6 +67,15 @@ impl ModuleCache {
         cache.clear();
     }
     
+    pub fn set_dependencies(&self, id: &ModuleId, dependencies: Vec<(String, ModuleId)>) -> Result<()> {
+        let mut cache = self.cache.write();
+        let compiled = cache
+            .get_mut(id)
+            .ok_or_else(|| anyhow::anyhow!("Module not found: {}", id.0))?;
+        compiled.dependencies = dependencies;
+        Ok(())
+    }
+    
     pub fn size(&self) -> usize {
         let cache = self.cache.read();
         cache.len()
@@ -62,18 +86,19 @@ impl ModuleCache {
         let module = Module::new(&self.engine, wasm_bytes)?;
         
         // Extract metadata
-        let metadata = self.extract_metadata(&module)?;
+        let metadata = self.extract_metadata(&module, wasm_bytes)?;
         
         let compiled = CompiledModule {
             module: Arc::new(module),
             metadata,
+            dependencies: Vec::new(),
         };
         
         self.insert(id.clone(), compiled.clone());
         Ok(compiled)
     }
     
-    fn extract_metadata(&self, module: &Module) -> Result<ModuleMetadata> {
+    fn extract_metadata(&self, module: &Module, wasm_bytes: &[u8]) -> Result<ModuleMetadata> {
         let exports: Vec<String> = module.exports()
             .map(|e| e.name().to_string())
             .collect();
@@ -88,16 +113,30 @@ impl ModuleCache {
             .and_then(|_| Some(1)) // Default to 1 page if memory is exported
             .unwrap_or(0);
         
-        // Look for _start or main as entry point
-        let entry_point = exports.iter()
-            .find(|&name| name == "_start" || name == "main")
-            .cloned();
+        let manifest = WasmManifest::from_module(module, wasm_bytes)?;
+        let source_map = match custom_section(wasm_bytes, SOURCE_MAP_SECTION)? {
+            Some(json) => Some(Arc::new(SourceMap::from_json(json).map_err(|e| {
+                CompileError::from(Diagnostic::error(format!("Invalid source map: {:#}", e)).in_file(SOURCE_MAP_SECTION))
+            })?)),
+            None => None,
+        };
+        
+        // Prefer the manifest's entry point, then _start or main
+        let entry_point = match &manifest {
+            Some(manifest) => Some(manifest.default_entry_point().to_string()),
+            None => exports.iter()
+                .find(|&name| name == "_start" || name == "main")
+                .cloned(),
+        };
         
         Ok(ModuleMetadata {
             entry_point,
             memory_pages,
             exports,
             imports,
+            manifest,
+            source_map,
+            proposals: WasmCompiler::used_proposals(wasm_bytes),
         })
     }
 }
@@ -1,18 +1,24 @@
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use next_rc_shared::{
-    ExecutionConfig, ExecutionResult, InstanceId, Language, ModuleId, Runtime as RuntimeTrait,
-    MemoryPool,
+    CompileError, ExecutionConfig, ExecutionResult, InstanceId, JobPhase, Language, LoadReport,
+    LoadTracker, ModuleId, Runtime as RuntimeTrait, MemoryPool, RuntimeCapabilities, RuntimeEnvironment,
+    RuntimeError, TrustLevel, ValidationReport, VectorStore,
 };
+#[cfg(feature = "fault-injection")]
+use next_rc_shared::FaultInjector;
 use std::sync::Arc;
 use std::time::Instant;
+use tokio::io::AsyncRead;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
 use crate::{
-    compiler::WasmCompiler,
+    bundle,
+    compiler::{WasmCompiler, ENGINE},
     context::ContextSwitcher,
     instance::InstanceManager,
+    manifest::WasmManifest,
     memory_pool::WasmMemoryPool,
     module_cache::ModuleCache,
 };
@@ -21,6 +27,15 @@ use crate::{
 pub struct WasmConfig {
     pub total_slots: usize,
     pub slot_size: usize,
+    /// Longest a guest runs before yielding its worker thread back to the
+    /// executor.
+    pub max_consecutive_slice_ms: u64,
+    /// Trust levels allowed to run modules that use the exception-handling
+    /// proposal; empty disables the proposal.
+    pub exception_handling: Vec<TrustLevel>,
+    /// Trust levels allowed to run modules that use the GC proposal; empty
+    /// disables the proposal.
+    pub gc: Vec<TrustLevel>,
 }
 
 impl Default for WasmConfig {
@@ -28,6 +43,9 @@ impl Default for WasmConfig {
         Self {
             total_slots: 100,
             slot_size: 64 * 1024 * 1024, // 64MB per slot
+            max_consecutive_slice_ms: 10,
+            exception_handling: vec![TrustLevel::Medium, TrustLevel::High],
+            gc: vec![TrustLevel::Medium, TrustLevel::High],
         }
     }
 }
@@ -38,23 +56,25 @@ pub struct WasmRuntime {
     module_cache: Arc<ModuleCache>,
     context_switcher: Arc<ContextSwitcher>,
     instance_manager: Arc<InstanceManager>,
+    load: LoadTracker,
+    #[cfg(feature = "fault-injection")]
+    faults: parking_lot::RwLock<Option<Arc<FaultInjector>>>,
 }
 
 impl WasmRuntime {
     pub fn new(config: WasmConfig) -> Result<Self> {
-        Self::with_config(config.total_slots, config.slot_size)
-    }
-    
-    pub fn new_default() -> Result<Self> {
-        info!("Initializing WASM runtime");
+        info!(
+            "Initializing WASM runtime with {} slots of {} bytes",
+            config.total_slots, config.slot_size
+        );
         
-        let compiler = WasmCompiler::new()?;
+        let compiler = WasmCompiler::with_config(&config)?;
         let engine = compiler.get_engine();
         
-        let memory_pool = Arc::new(WasmMemoryPool::with_defaults()?);
+        let memory_pool = Arc::new(WasmMemoryPool::new(config.total_slots, config.slot_size)?);
         let module_cache = Arc::new(ModuleCache::new(engine.clone()));
-        let context_switcher = Arc::new(ContextSwitcher::new(100));
-        let instance_manager = Arc::new(InstanceManager::new(engine));
+        let context_switcher = Arc::new(ContextSwitcher::new(config.total_slots));
+        let instance_manager = Arc::new(InstanceManager::new(engine, config.max_consecutive_slice_ms));
         
         Ok(Self {
             compiler,
@@ -62,22 +82,25 @@ impl WasmRuntime {
             module_cache,
             context_switcher,
             instance_manager,
+            load: LoadTracker::new(),
+            #[cfg(feature = "fault-injection")]
+            faults: parking_lot::RwLock::new(None),
         })
     }
     
-    pub fn with_config(total_slots: usize, slot_size: usize) -> Result<Self> {
-        info!(
-            "Initializing WASM runtime with {} slots of {} bytes",
-            total_slots, slot_size
-        );
+    pub fn new_default() -> Result<Self> {
+        info!("Initializing WASM runtime");
         
         let compiler = WasmCompiler::new()?;
         let engine = compiler.get_engine();
         
-        let memory_pool = Arc::new(WasmMemoryPool::new(total_slots, slot_size)?);
+        let memory_pool = Arc::new(WasmMemoryPool::with_defaults()?);
         let module_cache = Arc::new(ModuleCache::new(engine.clone()));
-        let context_switcher = Arc::new(ContextSwitcher::new(total_slots));
-        let instance_manager = Arc::new(InstanceManager::new(engine));
+        let context_switcher = Arc::new(ContextSwitcher::new(100));
+        let instance_manager = Arc::new(InstanceManager::new(
+            engine,
+            WasmConfig::default().max_consecutive_slice_ms,
+        ));
         
         Ok(Self {
             compiler,
@@ -85,6 +108,17 @@ impl WasmRuntime {
             module_cache,
             context_switcher,
             instance_manager,
+            load: LoadTracker::new(),
+            #[cfg(feature = "fault-injection")]
+            faults: parking_lot::RwLock::new(None),
+        })
+    }
+    
+    pub fn with_config(total_slots: usize, slot_size: usize) -> Result<Self> {
+        Self::new(WasmConfig {
+            total_slots,
+            slot_size,
+            ..Default::default()
         })
     }
     
@@ -95,12 +129,78 @@ impl WasmRuntime {
             cached_modules: self.module_cache.size(),
         }
     }
+    
+    /// Slots instances are given memory from, for sizing by an autoscaler.
+    pub fn memory_pool(&self) -> Arc<WasmMemoryPool> {
+        self.memory_pool.clone()
+    }
+    
+    /// Compiles and caches every bundle module into `components`, then
+    /// resolves the main module's link order.
+    fn link_bundle(
+        &self,
+        modules: &[(String, Vec<u8>)],
+        language: Language,
+        components: &mut Vec<(String, ModuleId, Arc<wasmtime::Module>)>,
+    ) -> Result<Vec<usize>> {
+        for (name, code) in modules {
+            let (module_id, wasm_bytes) = self.compiler
+                .compile(code, language)
+                .map_err(|e| bundle::attribute(e, name))?;
+            let compiled = self.module_cache.compile_and_cache(module_id.clone(), &wasm_bytes)?;
+            components.push((name.clone(), module_id, compiled.module));
+        }
+        
+        let modules: Vec<(&str, &wasmtime::Module)> = components
+            .iter()
+            .map(|(name, _, module)| (name.as_str(), module.as_ref()))
+            .collect();
+        Ok(bundle::link_order(&modules)?)
+    }
+    
+    /// Manifest declared by the module's `next-rc.manifest` section.
+    pub fn manifest(&self, module_id: &ModuleId) -> Option<WasmManifest> {
+        self.module_cache.get(module_id)?.metadata.manifest
+    }
+    
+    /// Number of times the instance has yielded to the executor.
+    pub fn instance_yields(&self, instance_id: &InstanceId) -> Option<u64> {
+        self.instance_manager.yield_count(instance_id)
+    }
+    
+    /// Serves `env.vector_search` for executions granted
+    /// `Capability::VectorSearch`; `None` denies every search.
+    pub fn set_vector_store(&self, store: Option<Arc<dyn VectorStore>>) {
+        self.instance_manager.set_vector_store(store);
+    }
+    
+    /// Injects failures and latency into compilations, instantiations and
+    /// executions, for resilience testing; `None` turns injection off.
+    #[cfg(feature = "fault-injection")]
+    pub fn set_fault_injector(&self, faults: Option<Arc<FaultInjector>>) {
+        *self.faults.write() = faults;
+    }
+    
+    /// Delays or fails the start of `phase` as the fault injector decides.
+    async fn inject_fault(&self, phase: JobPhase) -> Result<()> {
+        #[cfg(feature = "fault-injection")]
+        {
+            let faults = self.faults.read().clone();
+            if let Some(faults) = faults {
+                faults.inject(phase).await?;
+            }
+        }
+        #[cfg(not(feature = "fault-injection"))]
+        let _ = phase;
+        Ok(())
+    }
 }
 
 #[async_trait]
 impl RuntimeTrait for WasmRuntime {
     async fn compile(&self, code: &[u8], language: Language) -> Result<ModuleId> {
         debug!("Compiling {:?} code ({} bytes)", language, code.len());
+        self.inject_fault(JobPhase::Compile).await?;
         let start = Instant::now();
         
         let (module_id, wasm_bytes) = self.compiler.compile(code, language)?;
@@ -123,7 +223,18 @@ impl RuntimeTrait for WasmRuntime {
             .get(&module_id)
             .ok_or_else(|| anyhow!("Module not found: {}", module_id.0))?;
         
+        let dependencies = compiled.dependencies
+            .iter()
+            .map(|(name, id)| {
+                self.module_cache
+                    .get(id)
+                    .map(|dependency| (name.clone(), dependency.module))
+                    .ok_or_else(|| anyhow!("Bundle dependency {} missing for module {}", name, module_id.0))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        
         // Allocate memory slot (this should be ~0 time due to pre-allocation)
+        self.inject_fault(JobPhase::Instantiate).await?;
         let memory_slot = self.memory_pool.allocate()?;
         
         // Create instance
@@ -131,9 +242,10 @@ impl RuntimeTrait for WasmRuntime {
         self.instance_manager.create_instance(
             instance_id.clone(),
             module_id,
-            compiled.module,
+            compiled,
+            dependencies,
             memory_slot,
-        )?;
+        ).await?;
         
         let elapsed = start.elapsed();
         info!("Instantiated instance {} in {:?}", instance_id.0, elapsed);
@@ -152,7 +264,19 @@ impl RuntimeTrait for WasmRuntime {
             .get_instance(&instance_id)
             .ok_or_else(|| anyhow!("Instance not found: {}", instance_id.0))?;
         
-        let result = self.instance_manager.execute_instance(instance, config).await?;
+        // Refuse to run modules using proposals the caller's trust level
+        // doesn't allow
+        let proposals = instance.lock().await.proposals.clone();
+        let denied = self.compiler.check_proposals(&proposals, config.permissions.trust_level);
+        if let Some(diagnostic) = denied.into_iter().next() {
+            return Err(RuntimeError::SecurityError(diagnostic.message).into());
+        }
+        
+        self.inject_fault(JobPhase::Execute).await?;
+        let _running = self.load.run();
+        let encoding = config.output_encoding;
+        let mut result = self.instance_manager.execute_instance(instance, config).await?;
+        result.output = result.output.map(|output| encoding.encode(output)).transpose()?;
         
         if result.success {
             info!(
@@ -175,7 +299,7 @@ impl RuntimeTrait for WasmRuntime {
         if let Some(instance) = self.instance_manager.remove_instance(&instance_id) {
             // Get memory slot to release
             let memory_slot = {
-                let guard = instance.lock();
+                let guard = instance.lock().await;
                 guard.memory_slot.clone()
             };
             
@@ -188,6 +312,97 @@ impl RuntimeTrait for WasmRuntime {
             Err(anyhow!("Instance not found: {}", instance_id.0))
         }
     }
+    
+    async fn validate(
+        &self,
+        code: &[u8],
+        language: Language,
+        trust_level: TrustLevel,
+    ) -> Result<ValidationReport> {
+        debug!("Validating {:?} code ({} bytes)", language, code.len());
+        Ok(ValidationReport::from_diagnostics(self.compiler.validate(code, language, trust_level)))
+    }
+    
+    /// The first module is the main module; the rest are importable under
+    /// their bundle names. Each module is cached under its own id.
+    async fn compile_bundle(&self, modules: Vec<(String, Vec<u8>)>, language: Language) -> Result<ModuleId> {
+        if modules.is_empty() {
+            return Err(anyhow!("Bundle contains no modules"));
+        }
+        debug!("Compiling bundle of {} {:?} modules", modules.len(), language);
+        self.inject_fault(JobPhase::Compile).await?;
+        let start = Instant::now();
+        
+        let mut components = Vec::with_capacity(modules.len());
+        let linked = self.link_bundle(&modules, language, &mut components);
+        if linked.is_err() {
+            for (_, module_id, _) in &components {
+                self.module_cache.remove(module_id);
+            }
+        }
+        let order = linked?;
+        
+        let main_id = components[0].1.clone();
+        let dependencies = order
+            .into_iter()
+            .map(|i| (components[i].0.clone(), components[i].1.clone()))
+            .collect();
+        self.module_cache.set_dependencies(&main_id, dependencies)?;
+        
+        info!("Compiled bundle {} ({} modules) in {:?}", main_id.0, components.len(), start.elapsed());
+        
+        Ok(main_id)
+    }
+    
+    async fn compile_stream(
+        &self,
+        reader: Box<dyn AsyncRead + Send + Unpin>,
+        language: Language,
+    ) -> Result<ModuleId> {
+        debug!("Compiling streamed {:?} code", language);
+        self.inject_fault(JobPhase::Compile).await?;
+        let start = Instant::now();
+        
+        let wasm_bytes = self.compiler.compile_stream(reader, language).await?;
+        
+        let module_id = ModuleId(Uuid::new_v4());
+        self.module_cache
+            .compile_and_cache(module_id.clone(), &wasm_bytes)
+            .map_err(|e| match e.downcast::<CompileError>() {
+                Ok(compile_error) => compile_error,
+                Err(e) => self.compiler.module_error(e, &wasm_bytes),
+            })?;
+        
+        info!("Compiled streamed module {} ({} bytes) in {:?}", module_id.0, wasm_bytes.len(), start.elapsed());
+        
+        Ok(module_id)
+    }
+    
+    fn environment(&self) -> RuntimeEnvironment {
+        let mut engine_config = self.compiler.settings();
+        engine_config.insert("memory_slots".to_string(), self.memory_pool.total_slots().to_string());
+        engine_config.insert("slot_size".to_string(), self.memory_pool.slot_size().to_string());
+        engine_config.insert(
+            "max_consecutive_slice_ms".to_string(),
+            self.instance_manager.max_consecutive_slice_ms().to_string(),
+        );
+        
+        RuntimeEnvironment {
+            engine: ENGINE.to_string(),
+            engine_config,
+            packages: Default::default(),
+        }
+    }
+    
+    fn capabilities(&self) -> RuntimeCapabilities {
+        self.compiler.capabilities()
+    }
+    
+    /// Executions aren't limited beyond the instances the memory pool has
+    /// slots for, so nothing queues here.
+    fn current_load(&self) -> LoadReport {
+        self.load.report(None, None)
+    }
 }
 
 #[derive(Debug)]
@@ -200,7 +415,7 @@ pub struct RuntimeMetrics {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use next_rc_shared::{Permissions, TrustLevel};
+    use next_rc_shared::{LogLevel, LogLimits, Permissions, TrustLevel, VirtualTime};
     use std::time::Duration;
     
     #[tokio::test]
@@ -227,6 +442,7 @@ mod tests {
             timeout: Duration::from_secs(1),
             memory_limit: 1024 * 1024,
             permissions: Permissions::new(TrustLevel::Low),
+            ..Default::default()
         };
         
         let result = runtime.execute(instance_id.clone(), config).await.unwrap();
@@ -236,9 +452,192 @@ mod tests {
         runtime.destroy(instance_id).await.unwrap();
     }
     
+    #[tokio::test]
+    async fn test_gated_proposal_refused_below_allowed_trust() {
+        let runtime = WasmRuntime::with_config(2, 1024 * 1024).unwrap();
+        
+        let wat = r#"
+            (module
+                (type $point (struct (field i32)))
+                (func (export "_start") (result i32)
+                    (struct.get $point 0 (struct.new $point (i32.const 7)))
+                )
+            )
+        "#;
+        
+        let module_id = runtime.compile(wat.as_bytes(), Language::Wasm).await.unwrap();
+        let instance_id = runtime.instantiate(module_id).await.unwrap();
+        
+        let low = ExecutionConfig {
+            permissions: Permissions::new(TrustLevel::Low),
+            ..Default::default()
+        };
+        let err = runtime.execute(instance_id.clone(), low).await.unwrap_err();
+        assert!(err.to_string().contains("GC proposal"), "{}", err);
+        
+        let medium = ExecutionConfig {
+            permissions: Permissions::new(TrustLevel::Medium),
+            ..Default::default()
+        };
+        let result = runtime.execute(instance_id, medium).await.unwrap();
+        assert_eq!(result.output, Some(b"7".to_vec()));
+    }
+    
+    #[tokio::test]
+    async fn test_long_running_guest_yields() {
+        let runtime = WasmRuntime::new(WasmConfig {
+            total_slots: 2,
+            slot_size: 1024 * 1024,
+            max_consecutive_slice_ms: 1,
+            ..Default::default()
+        }).unwrap();
+        
+        let wat = r#"
+            (module
+                (func (export "_start") (result i32)
+                    (local $i i32)
+                    (local.set $i (i32.const 200000000))
+                    (loop $spin
+                        (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+                        (br_if $spin (local.get $i)))
+                    i32.const 7
+                )
+            )
+        "#;
+        
+        let module_id = runtime.compile(wat.as_bytes(), Language::Wasm).await.unwrap();
+        let instance_id = runtime.instantiate(module_id).await.unwrap();
+        assert_eq!(runtime.instance_yields(&instance_id), Some(0));
+        
+        let config = ExecutionConfig {
+            timeout: Duration::from_secs(10),
+            ..Default::default()
+        };
+        let result = runtime.execute(instance_id.clone(), config).await.unwrap();
+        assert!(result.success);
+        assert!(runtime.instance_yields(&instance_id).unwrap() > 0);
+        
+        runtime.destroy(instance_id.clone()).await.unwrap();
+        assert_eq!(runtime.instance_yields(&instance_id), None);
+    }
+    
+    fn vlq(value: i64) -> String {
+        const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut rest = if value < 0 { (-value << 1) | 1 } else { value << 1 };
+        let mut encoded = String::new();
+        loop {
+            let digit = rest & 0x1f;
+            rest >>= 5;
+            encoded.push(BASE64[(digit | if rest > 0 { 0x20 } else { 0 }) as usize] as char);
+            if rest == 0 {
+                return encoded;
+            }
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_virtual_time_fast_forwards_sleeps() {
+        let runtime = WasmRuntime::new_default().unwrap();
+        
+        // Sleeps 5s, then returns the wall-clock seconds * 10 + events fired
+        let wat = r#"
+            (module
+                (import "wasi_snapshot_preview1" "poll_oneoff" (func $poll (param i32 i32 i32 i32) (result i32)))
+                (import "wasi_snapshot_preview1" "clock_time_get" (func $time (param i32 i64 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 16) "\01")
+                (func (export "_start") (result i32)
+                    (i64.store (i32.const 24) (i64.const 5000000000))
+                    (drop (call $poll (i32.const 0) (i32.const 64) (i32.const 1) (i32.const 128)))
+                    (drop (call $time (i32.const 0) (i64.const 1) (i32.const 256)))
+                    (i32.add
+                        (i32.mul (i32.wrap_i64 (i64.div_u (i64.load (i32.const 256)) (i64.const 1000000000))) (i32.const 10))
+                        (i32.load (i32.const 128))))
+            )
+        "#;
+        
+        let module_id = runtime.compile(wat.as_bytes(), Language::Wasm).await.unwrap();
+        let instance_id = runtime.instantiate(module_id).await.unwrap();
+        let config = ExecutionConfig {
+            timeout: Duration::from_secs(1),
+            virtual_time: Some(VirtualTime::starting_at(1_000)),
+            ..Default::default()
+        };
+        let result = runtime.execute(instance_id, config).await.unwrap();
+        
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.output.as_deref(), Some(&b"61"[..]));
+        assert!(result.execution_time < Duration::from_secs(1));
+    }
+    
+    #[tokio::test]
+    async fn test_guest_logs_are_captured() {
+        let runtime = WasmRuntime::new_default().unwrap();
+        
+        let wat = r#"
+            (module
+                (import "env" "log" (func $log (param i32 i32 i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "started")
+                (data (i32.const 16) "tick")
+                (func (export "_start") (result i32)
+                    (local $i i32)
+                    (call $log (i32.const 2) (i32.const 0) (i32.const 7))
+                    (call $log (i32.const 0) (i32.const 16) (i32.const 4))
+                    (loop $again
+                        (call $log (i32.const 3) (i32.const 16) (i32.const 4))
+                        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                        (br_if $again (i32.lt_u (local.get $i) (i32.const 4))))
+                    (i32.const 0))
+            )
+        "#;
+        
+        let module_id = runtime.compile(wat.as_bytes(), Language::Wasm).await.unwrap();
+        let instance_id = runtime.instantiate(module_id).await.unwrap();
+        let config = ExecutionConfig {
+            log_limits: LogLimits {
+                max_records: 3,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let result = runtime.execute(instance_id, config).await.unwrap();
+        
+        assert!(result.success, "{:?}", result.error);
+        let records: Vec<_> = result.logs.records.iter().map(|r| (r.level, r.message.as_str())).collect();
+        assert_eq!(records, [(LogLevel::Info, "started"), (LogLevel::Warn, "tick"), (LogLevel::Warn, "tick")]);
+        assert_eq!(result.logs.dropped, 2);
+    }
+    
+    #[tokio::test]
+    async fn test_traps_are_mapped_to_original_source() {
+        let runtime = WasmRuntime::new_default().unwrap();
+        
+        let mut wasm = wat::parse_str(r#"(module (func (export "_start") (result i32) nop unreachable))"#).unwrap();
+        let trap_offset = wasm.windows(3).position(|w| w == [0x01, 0x00, 0x0b]).unwrap() + 1;
+        
+        // Offset 0 maps to app.ts:1:1, the trapping instruction to app.ts:3:5
+        let mappings = format!("AAAA,{}{}{}{}", vlq(trap_offset as i64), vlq(0), vlq(2), vlq(4));
+        let source_map = format!(r#"{{"version": 3, "sources": ["app.ts"], "mappings": "{}"}}"#, mappings);
+        let name = crate::module_cache::SOURCE_MAP_SECTION;
+        let payload_len = 1 + name.len() + source_map.len();
+        wasm.push(0);
+        wasm.extend([(payload_len & 0x7f) as u8 | 0x80, (payload_len >> 7) as u8]);
+        wasm.push(name.len() as u8);
+        wasm.extend(name.as_bytes());
+        wasm.extend(source_map.as_bytes());
+        
+        let module_id = runtime.compile(&wasm, Language::Wasm).await.unwrap();
+        let instance_id = runtime.instantiate(module_id).await.unwrap();
+        let result = runtime.execute(instance_id, ExecutionConfig::default()).await.unwrap();
+        
+        let error = result.error.unwrap();
+        assert!(error.ends_with("\n    at app.ts:3:5"), "{}", error);
+    }
+    
     #[tokio::test]
     async fn test_runtime_metrics() {
-        let runtime = LucetInspiredRuntime::with_config(10, 1024 * 1024).unwrap();
+        let runtime = WasmRuntime::with_config(10, 1024 * 1024).unwrap();
         
         let metrics = runtime.get_metrics();
         assert_eq!(metrics.total_slots, 10);
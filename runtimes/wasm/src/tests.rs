@@ -44,7 +44,7 @@ mod integration_tests {
     
     #[tokio::test]
     async fn test_concurrent_execution() {
-        let runtime = LucetInspiredRuntime::with_config(50, 1024 * 1024).unwrap();
+        let runtime = WasmRuntime::with_config(50, 1024 * 1024).unwrap();
         
         // Compile a simple counter module
         let wat = r#"
@@ -78,6 +78,7 @@ mod integration_tests {
                     timeout: Duration::from_secs(1),
                     memory_limit: 1024 * 1024,
                     permissions: Permissions::new(TrustLevel::Low),
+                    ..Default::default()
                 };
                 
                 let result = runtime_clone.execute(instance_id.clone(), config).await.unwrap();
@@ -89,12 +90,9 @@ mod integration_tests {
             handles.push(handle);
         }
         
-        // Wait for all executions
-        let results: Vec<_> = futures::future::join_all(handles).await;
-        
-        // All should succeed
-        for result in results {
-            assert!(result.unwrap().success);
+        // Wait for all executions; all should succeed
+        for handle in handles {
+            assert!(handle.await.unwrap().success);
         }
     }
     
@@ -129,6 +127,7 @@ mod integration_tests {
             timeout: Duration::from_secs(1),
             memory_limit: 1024 * 1024,
             permissions: Permissions::new(TrustLevel::Low),
+            ..Default::default()
         };
         
         // Execute both
@@ -168,6 +167,7 @@ mod integration_tests {
             timeout: Duration::from_secs(1),
             memory_limit: 4 * 1024 * 1024, // 4MB limit
             permissions: Permissions::new(TrustLevel::Low),
+            ..Default::default()
         };
         
         let result = runtime.execute(instance_id.clone(), config).await.unwrap();
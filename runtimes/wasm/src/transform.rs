@@ -0,0 +1,85 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use next_rc_shared::{CodeTransform, RuntimeType, TransformContext};
+use wasmparser::{Parser, Payload};
+
+const WASM_MAGIC: &[u8] = b"\0asm";
+
+/// Drops DWARF (`.debug_*`) custom sections from binary modules. They are
+/// often larger than the code itself and the sandbox never reads them; trap
+/// locations come from the `next-rc.source_map` section, which is kept. WAT
+/// text passes through unchanged.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripDebugSections;
+
+impl StripDebugSections {
+    pub fn strip(wasm_bytes: &[u8]) -> Result<Vec<u8>> {
+        if !wasm_bytes.starts_with(WASM_MAGIC) {
+            return Ok(wasm_bytes.to_vec());
+        }
+
+        let mut stripped = Vec::with_capacity(wasm_bytes.len());
+        // Sections are contiguous, so each one starts where the previous ended
+        let mut section_start = 0;
+        for payload in Parser::new(0).parse_all(wasm_bytes) {
+            let payload = payload.map_err(|e| anyhow!("Invalid WASM binary: {}", e.message()))?;
+            let debug = matches!(&payload, Payload::CustomSection(reader) if reader.name().starts_with(".debug_"));
+            let section_end = match &payload {
+                Payload::Version { range, .. } => range.end,
+                payload => match payload.as_section() {
+                    Some((_, range)) => range.end,
+                    None => continue,
+                },
+            };
+            if !debug {
+                stripped.extend_from_slice(&wasm_bytes[section_start..section_end]);
+            }
+            section_start = section_end;
+        }
+
+        Ok(stripped)
+    }
+}
+
+#[async_trait]
+impl CodeTransform for StripDebugSections {
+    fn name(&self) -> &str {
+        "wasm-strip-debug"
+    }
+
+    fn applies_to(&self, context: &TransformContext) -> bool {
+        context.runtime == RuntimeType::Wasm
+    }
+
+    async fn transform(&self, code: Vec<u8>, _context: &TransformContext) -> Result<Vec<u8>> {
+        Self::strip(&code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::manifest::custom_section;
+
+    #[test]
+    fn test_strips_only_debug_sections() {
+        let wasm = wat::parse_str(
+            r#"(module
+                (@custom ".debug_info" "dwarf")
+                (func (export "_start") (result i32) i32.const 7)
+                (@custom "next-rc.source_map" "{}")
+                (@custom ".debug_line" "lines"))"#,
+        )
+        .unwrap();
+
+        let stripped = StripDebugSections::strip(&wasm).unwrap();
+        assert!(stripped.len() < wasm.len());
+        assert!(custom_section(&stripped, ".debug_info").unwrap().is_none());
+        assert!(custom_section(&stripped, ".debug_line").unwrap().is_none());
+        assert_eq!(custom_section(&stripped, "next-rc.source_map").unwrap(), Some(&b"{}"[..]));
+        wasmparser::validate(&stripped).unwrap();
+
+        let wat = r#"(module (func (export "_start") (result i32) i32.const 7))"#;
+        assert_eq!(StripDebugSections::strip(wat.as_bytes()).unwrap(), wat.as_bytes());
+    }
+}
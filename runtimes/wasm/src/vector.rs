@@ -0,0 +1,60 @@
+use anyhow::{anyhow, Result};
+use next_rc_shared::guest_vector_search;
+use tracing::debug;
+use wasmtime::{Caller, Linker};
+
+use crate::clock::guest_memory;
+use crate::instance::StoreData;
+
+/// Returned by `env.vector_search` when the execution lacks
+/// `Capability::VectorSearch` or the runtime has no vector store.
+pub const VECTOR_SEARCH_DENIED: i32 = -1;
+
+/// Returned by `env.vector_search` when the query is malformed or the store fails.
+pub const VECTOR_SEARCH_FAILED: i32 = -2;
+
+/// Adds `env.vector_search(query_ptr, query_len, out_ptr, out_len) -> i32`,
+/// which runs the JSON `VectorQuery` at `query_ptr` against the runtime's
+/// vector store and writes the matches to `out_ptr` as a JSON array. Returns
+/// the length of the matches; if that exceeds `out_len` nothing is written and
+/// the guest may retry with a larger buffer. Negative results are errors.
+pub fn add_to_linker(linker: &mut Linker<StoreData>) -> Result<()> {
+    linker.func_wrap_async("env", "vector_search", |mut caller: Caller<'_, StoreData>, (query_ptr, query_len, out_ptr, out_len): (i32, i32, i32, i32)| {
+        Box::new(async move { vector_search(&mut caller, query_ptr, query_len, out_ptr, out_len).await })
+    })?;
+
+    Ok(())
+}
+
+async fn vector_search(caller: &mut Caller<'_, StoreData>, query_ptr: i32, query_len: i32, out_ptr: i32, out_len: i32) -> Result<i32> {
+    let Some(store) = caller.data().vector_store.clone() else {
+        return Ok(VECTOR_SEARCH_DENIED);
+    };
+    let memory = guest_memory(caller).ok_or_else(|| anyhow!("Module does not export memory"))?;
+
+    let start = query_ptr as u32 as usize;
+    let query = memory
+        .data(&caller)
+        .get(start..start.saturating_add(query_len as u32 as usize))
+        .ok_or_else(|| anyhow!("Vector query out of bounds"))?
+        .to_vec();
+
+    let matches = match guest_vector_search(store.as_ref(), &query).await {
+        Ok(matches) => matches,
+        Err(e) => {
+            debug!("Guest vector search failed: {}", e);
+            return Ok(VECTOR_SEARCH_FAILED);
+        }
+    };
+    if matches.len() > out_len as u32 as usize {
+        return Ok(matches.len() as i32);
+    }
+
+    let start = out_ptr as u32 as usize;
+    memory
+        .data_mut(&mut *caller)
+        .get_mut(start..start.saturating_add(matches.len()))
+        .ok_or_else(|| anyhow!("Vector search output out of bounds"))?
+        .copy_from_slice(&matches);
+    Ok(matches.len() as i32)
+}